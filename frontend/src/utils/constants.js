@@ -4,6 +4,7 @@ export const CSRF_TOKEN = 'csrf_token';
 export const ACCESS_TOKEN = 'access_token';
 export const ID_TOKEN = 'id_token';
 export const PROVIDER_TOKEN = 'provider_token';
+export const DEVICE_USER_CODE = 'device_user_code';
 
 export const AUTH_ENDPOINT = '/auth/v1/oidc/authorize';
 export const CLIENT_ID = 'rauthy';
@@ -11,6 +12,7 @@ export const LOGOUT_URL = '/auth/v1/oidc/logout';
 export const REDIRECT_URI = '/auth/v1/oidc/callback';
 export const REDIRECT_URI_SUCCESS = '/auth/v1/admin';
 export const REDIRECT_URI_SUCCESS_ACC = '/auth/v1/account';
+export const REDIRECT_URI_SUCCESS_DEVICE = '/auth/v1/oidc/device';
 export const POST_LOGOUT_REDIRECT_URI = '/auth/v1/';
 
 export const REGEX_BIRTHDATE = /^[0-9]{4}-[0-9]{2}-[0-9]{2}$/gm;
@@ -19,6 +21,8 @@ export const REGEX_STREET = /^[a-zA-Z0-9À-ÿ-.\s]{0,48}$/gm;
 export const REGEX_CITY = /^[a-zA-Z0-9À-ÿ-]{0,48}$/gm;
 export const REGEX_API_KEY = /^[a-zA-Z0-9_/-]{2,24}$/gm;
 export const REGEX_NAME = /^[\w\sÀ-ÿ\-]{0,32}$/gm;
+export const REGEX_AUTHORIZATION_DETAILS_TYPE = /^[a-zA-Z0-9\-_.:/]{1,128}$/gm;
+export const REGEX_TLS_SUBJECT_DN = /^[a-zA-Z0-9À-ÿ\s,.:/_\-=+@*'()]{1,256}$/m;
 export const REGEX_ATTR_DESC = /^[a-zA-Z0-9\-_/\s]{0,128}$/gm;
 export const REGEX_ATTR_KEY = /^[a-zA-Z0-9\-_/]{2,32}$/gm;
 export const REGEX_CLIENT_ID = /^[a-zA-Z0-9\-_/]{2,128}$/gm;
@@ -28,6 +32,9 @@ export const REGEX_PROVIDER_SCOPE = /^[a-z0-9-_\/:\s]{0,128}$/gm;
 export const REGEX_PEM = /^(-----BEGIN CERTIFICATE-----)[a-zA-Z0-9+/=\n]+(-----END CERTIFICATE-----)$/gm;
 export const REGEX_ROLES = /^[a-z0-9\-_/]{2,128}$/gm;
 export const REGEX_URI = /^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%]+$/gm;
+export const REGEX_USER_CODE = /^[BCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz]+([\-\s][BCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz]+)*$/m;
+export const REGEX_TOTP = /^[0-9]{6}$/;
+export const REGEX_RECOVERY_CODE = /^[a-zA-Z2-7]{4}(-?[a-zA-Z2-7]{4}){3}$/;
 export const REGEX_URI_SPACE = /^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%\s]+$/m;
 export const REGEX_IP_V4 = /^(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]\d|\d)(?:\.(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]\d|\d)){3}$/gm;
 
@@ -53,6 +60,8 @@ export const EVENT_TYPES = [
     'RauthyStarted',
     'RauthyHealthy',
     'RauthyUnhealthy',
+    'RecoveryCodeUsed',
+    'RefreshTokenReuse',
     'SecretsMigrated',
     'UserEmailChange',
     'UserPasswordReset',
@@ -63,8 +72,21 @@ export const TOKEN_ALGS = [
     'RS256',
     'RS384',
     'RS512',
-    'EdDSA'
+    'EdDSA',
+    'ES256',
+    'ES384'
 ]
+// the first entry returns plain JSON
+export const USERINFO_SIGNING_ALGS = ['-', ...TOKEN_ALGS];
+// the first entry disables encryption
+export const JWE_ALGS = ['-', 'RSA-OAEP-256', 'ECDH-ES'];
+export const JWE_ENCS = ['A128CBC-HS256', 'A256CBC-HS512', 'A128GCM', 'A256GCM'];
+// the first entry accepts the client secret with basic auth or in the POST body
+export const CLIENT_AUTH_METHODS = [
+    '-', 'client_secret_basic', 'client_secret_post', 'client_secret_jwt', 'private_key_jwt',
+];
+// the first entry authenticates with the client secret
+export const TLS_CLIENT_AUTH_METHODS = ['-', 'tls_client_auth', 'self_signed_tls_client_auth'];
 
 export const FLOWS = [{
     label: 'authorization_code',
@@ -82,6 +104,28 @@ export const FLOWS = [{
         label: 'refresh_token',
         value: false,
     },
+    {
+        label: 'urn:ietf:params:oauth:grant-type:device_code',
+        value: false,
+    },
+    {
+        label: 'urn:ietf:params:oauth:grant-type:token-exchange',
+        value: false,
+    },
+];
+
+export const TOKEN_EXCHANGE_SUBJECT_TYPES = [{
+    label: 'access_token',
+    value: false,
+},
+    {
+        label: 'id_token',
+        value: false,
+    },
+    {
+        label: 'jwt',
+        value: false,
+    },
 ];
 
 export const PKCE_CHALLENGES = [{
@@ -32,6 +32,28 @@ export async function authorize(data) {
     return res;
 }
 
+export async function authorizeEmail(data) {
+    return await fetch('/auth/v1/oidc/authorize/email', {
+        method: 'POST',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+}
+
+export async function authorizeEmailFinish(data) {
+    const res = await fetch('/auth/v1/oidc/authorize/email/finish', {
+        method: 'POST',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+
+    if (res.status === 202) {
+        window.location.replace(res.headers.get('location'));
+    }
+
+    return res;
+}
+
 export async function authorizeRefresh(data) {
     const res = await fetch('/auth/v1/oidc/authorize/refresh', {
         method: 'POST',
@@ -46,6 +68,28 @@ export async function authorizeRefresh(data) {
     return res;
 }
 
+export async function postConsent(data) {
+    const res = await fetch('/auth/v1/oidc/consent', {
+        method: 'POST',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+
+    if (res.status === 202) {
+        window.location.replace(res.headers.get('location'));
+    }
+
+    return res;
+}
+
+export async function postDeviceVerify(data) {
+    return await fetch('/auth/v1/oidc/device/verify', {
+        method: 'POST',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+}
+
 
 export async function getAppVersion() {
     return await fetch('/auth/v1/version', {
@@ -86,11 +130,10 @@ export async function getSessionInfoXsrf(accessToken) {
     });
 }
 
-export async function logout(data) {
+export async function logout() {
     return await fetch('/auth/v1/oidc/logout', {
         method: 'POST',
         headers: getCsrfHeaders(),
-        body: data,
     });
 }
 
@@ -282,3 +325,50 @@ export async function webauthnDelete(id, name) {
         headers: getCsrfHeaders(),
     });
 }
+
+export async function postUserTotp(id) {
+    return await fetch(`/auth/v1/users/${id}/totp`, {
+        method: 'POST',
+        headers: getCsrfHeaders(),
+    });
+}
+
+export async function putUserTotp(id, data) {
+    return await fetch(`/auth/v1/users/${id}/totp`, {
+        method: 'PUT',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+}
+
+export async function postUserTotpAuth(id, data) {
+    return await fetch(`/auth/v1/users/${id}/totp/auth`, {
+        method: 'POST',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+}
+
+export async function deleteUserTotp(id, data) {
+    return await fetch(`/auth/v1/users/${id}/totp`, {
+        method: 'DELETE',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+}
+
+export async function postUserRecoveryCodes(id, data) {
+    return await fetch(`/auth/v1/users/${id}/recovery_codes`, {
+        method: 'POST',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+}
+
+export async function postUserRecoveryCodesAuth(id, data) {
+    return await fetch(`/auth/v1/users/${id}/recovery_codes/auth`, {
+        method: 'POST',
+        headers: getCsrfHeaders(),
+        body: JSON.stringify(data),
+    });
+}
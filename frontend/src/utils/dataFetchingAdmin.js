@@ -326,8 +326,9 @@ export async function deleteSessionsForUser(uid) {
     return await checkRedirectForbidden(res);
 }
 
-export async function getUsers() {
-    const res = await fetch('/auth/v1/users', {
+export async function getUsers(params = {}) {
+    const query = new URLSearchParams(params).toString();
+    const res = await fetch(`/auth/v1/users${query ? `?${query}` : ''}`, {
         method: 'GET',
         headers: HEADERS,
     });
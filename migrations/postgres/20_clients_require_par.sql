@@ -0,0 +1,7 @@
+-- add 'require_par' to clients for pushed authorization requests
+
+alter table clients
+    add require_par bool default false not null;
+
+alter table clients
+    alter column require_par drop default;
@@ -0,0 +1,10 @@
+-- add the Token Exchange policy to clients
+
+alter table clients
+    add token_exchange_audiences varchar;
+
+alter table clients
+    add token_exchange_scopes varchar;
+
+alter table clients
+    add token_exchange_subject_types varchar;
@@ -0,0 +1,22 @@
+-- add the 'backchannel_logout_uri' to clients and track which clients a session has been used for
+
+alter table clients
+    add backchannel_logout_uri varchar;
+
+create table sessions_clients
+(
+    session_id varchar not null
+        constraint sessions_clients_sessions_id_fk
+            references sessions
+            on update cascade on delete cascade,
+    client_id  varchar not null
+        constraint sessions_clients_clients_id_fk
+            references clients
+            on update cascade on delete cascade,
+    user_id    varchar not null,
+    constraint sessions_clients_pk
+        primary key (session_id, client_id)
+);
+
+create index sessions_clients_user_id_index
+    on sessions_clients (user_id);
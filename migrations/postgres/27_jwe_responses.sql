@@ -0,0 +1,13 @@
+-- add the JWE settings for encrypted ID tokens and userinfo responses to clients
+
+alter table clients
+    add id_token_encrypted_response_alg varchar;
+
+alter table clients
+    add id_token_encrypted_response_enc varchar;
+
+alter table clients
+    add userinfo_encrypted_response_alg varchar;
+
+alter table clients
+    add userinfo_encrypted_response_enc varchar;
@@ -0,0 +1,13 @@
+-- add the mutual TLS client authentication and certificate-bound access tokens (RFC 8705)
+
+alter table clients
+    add tls_client_auth_method varchar;
+
+alter table clients
+    add tls_client_auth_subject_dn varchar;
+
+alter table clients
+    add tls_client_certificate_bound_access_tokens bool default false not null;
+
+alter table clients
+    alter column tls_client_certificate_bound_access_tokens drop default;
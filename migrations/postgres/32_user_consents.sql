@@ -0,0 +1,23 @@
+-- add 'require_consent' to clients and the persistent user consents
+
+alter table clients
+    add require_consent bool default false not null;
+
+alter table clients
+    alter column require_consent drop default;
+
+-- the scopes a user has granted to a client
+-- no foreign key for the client, because ephemeral clients only exist in the cache
+create table user_consents
+(
+    user_id    varchar not null
+        constraint user_consents_users_id_fk
+            references users
+            on update cascade on delete cascade,
+    client_id  varchar not null,
+    scopes     varchar not null,
+    created_at bigint  not null,
+    updated_at bigint  not null,
+    constraint user_consents_pk
+        primary key (user_id, client_id)
+);
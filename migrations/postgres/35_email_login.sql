@@ -0,0 +1,7 @@
+-- add 'allow_email_login' to clients for passwordless logins with magic links or codes
+
+alter table clients
+    add allow_email_login bool default false not null;
+
+alter table clients
+    alter column allow_email_login drop default;
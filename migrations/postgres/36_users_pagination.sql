@@ -0,0 +1,29 @@
+-- indexes for the server-side sorting, filtering and prefix search of users
+
+create index users_created_at_index
+    on users (created_at);
+
+create index users_last_login_index
+    on users (last_login);
+
+create index users_given_name_index
+    on users (given_name);
+
+create index users_family_name_index
+    on users (family_name);
+
+create index users_user_expires_index
+    on users (user_expires);
+
+create index users_auth_provider_id_index
+    on users (auth_provider_id);
+
+-- the search only matches the beginning of the values
+create index users_email_lower_index
+    on users (lower(email) text_pattern_ops);
+
+create index users_given_name_lower_index
+    on users (lower(given_name) text_pattern_ops);
+
+create index users_family_name_lower_index
+    on users (lower(family_name) text_pattern_ops);
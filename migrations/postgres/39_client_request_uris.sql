@@ -0,0 +1,4 @@
+-- the only 'request_uris' as CSV, from which signed request objects are fetched for a client
+
+alter table clients
+    add request_uris varchar;
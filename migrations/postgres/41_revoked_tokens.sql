@@ -0,0 +1,9 @@
+-- the 'jti' of revoked access tokens, kept until the token would have expired anyway
+
+create table revoked_tokens
+(
+    jti varchar not null
+        constraint revoked_tokens_pk
+            primary key,
+    exp bigint  not null
+);
@@ -0,0 +1,4 @@
+-- add 'require_par' to clients for pushed authorization requests
+
+alter table clients
+    add require_par bool default false not null;
@@ -0,0 +1,10 @@
+-- add the client keys and 'require_request_object' to clients for signed request objects
+
+alter table clients
+    add jwks varchar;
+
+alter table clients
+    add jwks_uri varchar;
+
+alter table clients
+    add require_request_object bool default false not null;
@@ -0,0 +1,4 @@
+-- add the 'frontchannel_logout_uri' to clients for OIDC Front-Channel Logout
+
+alter table clients
+    add frontchannel_logout_uri varchar;
@@ -0,0 +1,16 @@
+-- bind refresh tokens to their client and track rotation families for reuse detection
+
+alter table refresh_tokens
+    add client_id varchar;
+
+alter table refresh_tokens
+    add family_id varchar;
+
+alter table refresh_tokens
+    add rotated_at bigint;
+
+alter table refresh_tokens
+    add replayed_at bigint;
+
+create index refresh_tokens_family_id_index
+    on refresh_tokens (family_id);
@@ -0,0 +1,19 @@
+-- add the 'subject_type' and 'sector_identifier_uri' to clients and map pairwise subjects to users
+
+alter table clients
+    add subject_type varchar;
+
+alter table clients
+    add sector_identifier_uri varchar;
+
+create table pairwise_subjects
+(
+    sub               varchar not null
+        constraint pairwise_subjects_pk
+            primary key,
+    sector_identifier varchar not null,
+    user_id           varchar not null
+        constraint pairwise_subjects_users_id_fk
+            references users
+            on update cascade on delete cascade
+);
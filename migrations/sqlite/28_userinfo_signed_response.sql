@@ -0,0 +1,4 @@
+-- add the 'userinfo_signed_response_alg' to clients for signed userinfo responses
+
+alter table clients
+    add userinfo_signed_response_alg varchar;
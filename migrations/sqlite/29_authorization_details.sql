@@ -0,0 +1,4 @@
+-- add the 'authorization_details_types' to clients for Rich Authorization Requests
+
+alter table clients
+    add authorization_details_types varchar;
@@ -0,0 +1,13 @@
+-- protected resources for RFC 8707 resource indicators
+
+create table resources
+(
+    id     varchar not null
+        constraint resources_pk
+            primary key,
+    name   varchar not null,
+    uri    varchar not null
+        constraint resources_uri_uindex
+            unique,
+    scopes varchar not null
+);
@@ -0,0 +1,4 @@
+-- the encrypted secret of a registered TOTP authenticator app
+
+alter table users
+    add totp_secret blob;
@@ -0,0 +1,14 @@
+-- single-use MFA recovery codes, hashed with argon2id
+
+create table recovery_codes
+(
+    user_id varchar not null
+        constraint recovery_codes_users_id_fk
+            references users
+            on update cascade on delete cascade,
+    id      varchar not null,
+    hash    varchar not null,
+    created bigint  not null,
+    constraint recovery_codes_pk
+        primary key (user_id, id)
+);
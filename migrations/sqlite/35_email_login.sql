@@ -0,0 +1,4 @@
+-- add 'allow_email_login' to clients for passwordless logins with magic links or codes
+
+alter table clients
+    add allow_email_login bool default false not null;
@@ -0,0 +1,14 @@
+-- the registered client authentication method, so that other methods can be rejected
+
+alter table clients
+    add token_endpoint_auth_method varchar;
+
+-- dynamic clients have the method saved already
+update clients
+set token_endpoint_auth_method = (select token_endpoint_auth_method
+                                  from clients_dyn
+                                  where clients_dyn.id = clients.id)
+where id in (select id
+             from clients_dyn
+             where token_endpoint_auth_method in
+                   ('client_secret_basic', 'client_secret_post', 'client_secret_jwt', 'private_key_jwt'));
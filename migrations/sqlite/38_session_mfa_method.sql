@@ -0,0 +1,4 @@
+-- the second factor, which has actually been used for the MFA login of a session
+
+alter table sessions
+    add mfa_method varchar;
@@ -0,0 +1,12 @@
+-- the time steps of already used TOTP codes, which must never be accepted a second time
+
+create table totp_used_steps
+(
+    user_id varchar not null
+        constraint totp_used_steps_users_id_fk
+            references users
+            on update cascade on delete cascade,
+    step    bigint  not null,
+    constraint totp_used_steps_pk
+        primary key (user_id, step)
+);
@@ -52,6 +52,8 @@ pub(crate) enum JwkKeyPairAlg {
     RS384,
     RS512,
     EdDSA,
+    ES256,
+    ES384,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -59,6 +61,7 @@ pub(crate) enum JwkKeyPairAlg {
 pub(crate) enum JwkKeyPairType {
     RSA,
     OKP,
+    EC,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -67,10 +70,11 @@ pub(crate) struct JwkPublicKey {
     pub kty: JwkKeyPairType,
     pub alg: JwkKeyPairAlg,
     pub kid: String,
-    pub crv: Option<String>,      // Ed25519
+    pub crv: Option<String>,      // Ed25519, P-256, P-384
     pub n: Option<String>,        // RSA
     pub e: Option<String>,        // RSA
-    pub x: Option<String>,        // OCT
+    pub x: Option<String>,        // OCT, EC
+    pub y: Option<String>,        // EC
     pub x_bytes: Option<Vec<u8>>, // pre-decoded base64 string to speed up checking
 }
 
@@ -161,6 +165,23 @@ pub(crate) async fn jwks_handler() {
                                         }
                                     }
                                 }
+                            } else if matches!(key.alg, JwkKeyPairAlg::ES256 | JwkKeyPairAlg::ES384) {
+                                // pre-decode into the uncompressed point: 0x04 || x || y
+                                if let (Some(x), Some(y)) = (&key.x, &key.y) {
+                                    match (base64_url_no_pad_decode(x), base64_url_no_pad_decode(y)) {
+                                        (Ok(x), Ok(y)) => {
+                                            let mut point = Vec::with_capacity(1 + x.len() + y.len());
+                                            point.push(0x04);
+                                            point.extend_from_slice(&x);
+                                            point.extend_from_slice(&y);
+                                            key.x_bytes = Some(point)
+                                        }
+                                        _ => {
+                                            error!("Error pre-decoding given EC 'x' / 'y' pub key bytes");
+                                            return None;
+                                        }
+                                    }
+                                }
                             }
                             Some(key)
                         })
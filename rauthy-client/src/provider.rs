@@ -179,6 +179,8 @@ pub enum Algorithm {
     RS384,
     RS512,
     EdDSA,
+    ES256,
+    ES384,
 }
 
 /// Rauthy-supported PKCE challenges
@@ -1,7 +1,9 @@
 use crate::jwks::{JwkKeyPairAlg, JwkPublicKey};
 use crate::provider::OidcProvider;
 use crate::validate_jwt;
-use jwt_simple::algorithms::{EdDSAPublicKeyLike, RSAPublicKeyLike};
+use jwt_simple::algorithms::{
+    ECDSAP256PublicKeyLike, ECDSAP384PublicKeyLike, EdDSAPublicKeyLike, RSAPublicKeyLike,
+};
 use jwt_simple::claims;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -27,7 +29,11 @@ pub enum JwtTokenType {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JktClaim {
-    pub jkt: String,
+    /// Set for DPoP bound tokens
+    pub jkt: Option<String>,
+    /// Set for tokens, which are bound to a client certificate
+    #[serde(rename = "x5t#S256")]
+    pub x5t_s256: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +51,7 @@ pub struct JwtAccessClaims {
     pub roles: Option<Vec<String>>,
     pub groups: Option<Vec<String>>,
     pub cnf: Option<JktClaim>,
+    pub authorization_details: Option<Vec<serde_json::Value>>,
     pub custom: Option<HashMap<String, serde_json::Value>>,
 }
 
@@ -84,6 +91,7 @@ pub struct JwtIdClaims {
     pub azp: String,
     pub typ: JwtTokenType,
     pub amr: Vec<String>,
+    pub acr: Option<String>,
     pub auth_time: i64,
     pub preferred_username: String,
     pub email: Option<String>,
@@ -228,6 +236,36 @@ macro_rules! validate_jwt {
                     })?;
                 pk.verify_token::<$type>($token, Some($options))
             }
+
+            JwkKeyPairAlg::ES256 => {
+                let bytes = $pub_key
+                    .x_bytes
+                    .as_ref()
+                    .ok_or_else(|| anyhow::Error::msg("Invalid 'x' / 'y' for ES256 key"))?;
+                let pk = jwt_simple::algorithms::ES256PublicKey::from_bytes(bytes.as_slice())
+                    .map_err(|err| {
+                        anyhow::Error::msg(format!(
+                            "Cannot build ES256 key from public key bytes: {:?}",
+                            err,
+                        ))
+                    })?;
+                pk.verify_token::<$type>($token, Some($options))
+            }
+
+            JwkKeyPairAlg::ES384 => {
+                let bytes = $pub_key
+                    .x_bytes
+                    .as_ref()
+                    .ok_or_else(|| anyhow::Error::msg("Invalid 'x' / 'y' for ES384 key"))?;
+                let pk = jwt_simple::algorithms::ES384PublicKey::from_bytes(bytes.as_slice())
+                    .map_err(|err| {
+                        anyhow::Error::msg(format!(
+                            "Cannot build ES384 key from public key bytes: {:?}",
+                            err,
+                        ))
+                    })?;
+                pk.verify_token::<$type>($token, Some($options))
+            }
         }
         .map_err(|_| anyhow::Error::msg("Invalid Token"))
     };
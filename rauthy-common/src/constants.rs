@@ -13,13 +13,18 @@ pub const HEADER_DPOP_NONCE: &str = "DPoP-Nonce";
 pub const HEADER_HTML: (&str, &str) = ("content-type", "text/html;charset=utf-8");
 pub const HEADER_JSON: (&str, &str) = ("content-type", "application/json");
 pub const HEADER_RETRY_NOT_BEFORE: &str = "x-retry-not-before";
+pub const HEADER_TOTAL_COUNT: &str = "x-total-count";
 pub const APPLICATION_JSON: &str = "application/json";
+pub const APPLICATION_JWT: &str = "application/jwt";
+pub const TEXT_CSV: &str = "text/csv";
 pub const TEXT_TURTLE: &str = "text/turtle";
 
 pub const TOKEN_API_KEY: &str = "API-Key";
 pub const TOKEN_BEARER: &str = "Bearer";
 pub const TOKEN_DPOP: &str = "DPoP";
 pub const TOKEN_DPOP_NONCE: &str = "DPoP-nonce";
+// the JWT `typ` header of RFC 9068 access tokens
+pub const JWT_TYP_ACCESS_TOKEN: &str = "at+jwt";
 pub const COOKIE_SESSION: &str = "rauthy-session";
 pub const COOKIE_MFA: &str = "rauthy-mfa";
 pub const COOKIE_LOCALE: &str = "locale";
@@ -33,17 +38,46 @@ pub const ARGON2ID_M_COST_MIN: u32 = 32768;
 pub const ARGON2ID_T_COST_MIN: u32 = 1;
 pub const API_KEY_LENGTH: usize = 64;
 pub const EVENTS_LATEST_LIMIT: u16 = 100;
+pub const USERS_IMPORT_MAX_BYTES: usize = 64 * 1024 * 1024;
+pub const USERS_PAGE_SIZE_DEFAULT: u32 = 100;
+pub const USERS_PAGE_SIZE_MAX: u32 = 1000;
 pub const UPSTREAM_AUTH_CALLBACK_TIMEOUT_SECS: u16 = 300;
+pub const GRANT_TYPE_DEVICE_CODE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+pub const PAR_REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+pub const CLIENT_ASSERTION_TYPE_JWT_BEARER: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+pub const GRANT_TYPE_TOKEN_EXCHANGE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+pub const TOKEN_TYPE_ACCESS_TOKEN: &str = "urn:ietf:params:oauth:token-type:access_token";
+pub const TOKEN_TYPE_ID_TOKEN: &str = "urn:ietf:params:oauth:token-type:id_token";
+pub const TOKEN_TYPE_JWT: &str = "urn:ietf:params:oauth:token-type:jwt";
+pub const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+pub const BACKCHANNEL_LOGOUT_TOKEN_LIFETIME: u64 = 120;
+// pending consent requests include the time for a possible MFA login in front of them
+pub const CONSENT_REQ_LIFETIME: u64 = 900;
+// authorization requests resolved from a PAR or a request object, which must outlive the login
+pub const AUTH_REQUEST_LIFETIME: u64 = 900;
+// pending TOTP registrations and already used TOTP codes, which must outlive the accepted window
+pub const TOTP_CACHE_LIFETIME: u64 = 300;
+// fetched `jwks_uri`s of clients, which are used for each of their authentications or encryptions
+pub const CLIENT_JWKS_CACHE_LIFETIME: u64 = 300;
 
 pub const CACHE_NAME_12HR: &str = "12hr";
 pub const CACHE_NAME_AUTH_CODES: &str = "auth-codes";
+pub const CACHE_NAME_AUTH_REQUESTS: &str = "auth-requests";
 pub const CACHE_NAME_AUTH_PROVIDER_CALLBACK: &str = "auth-provider-callback";
+pub const CACHE_NAME_CLIENT_ASSERTIONS: &str = "client-assertions";
 pub const CACHE_NAME_CLIENTS_DYN: &str = "clients-dyn";
+pub const CACHE_NAME_CONSENT_REQ: &str = "consent-req";
+pub const CACHE_NAME_DEVICE_CODES: &str = "device-codes";
 pub const CACHE_NAME_DPOP_NONCES: &str = "dpop-nonces";
+pub const CACHE_NAME_EMAIL_LOGIN: &str = "email-login";
 pub const CACHE_NAME_EPHEMERAL_CLIENTS: &str = "ephemeral-clients";
 pub const CACHE_NAME_LOGIN_DELAY: &str = "login-dly";
+pub const CACHE_NAME_PAR: &str = "par";
 pub const CACHE_NAME_SESSIONS: &str = "sessions";
+pub const CACHE_NAME_TOTP: &str = "totp";
 pub const CACHE_NAME_POW: &str = "pow";
+pub const CACHE_NAME_REVOKED_TOKENS: &str = "revoked-tokens";
 pub const CACHE_NAME_USERS: &str = "users";
 pub const CACHE_NAME_WEBAUTHN: &str = "webauthn";
 pub const CACHE_NAME_WEBAUTHN_DATA: &str = "webauthn-data";
@@ -54,6 +88,7 @@ pub const IDX_AUTH_PROVIDER_LOGO: &str = "auth_provider_logo_";
 pub const IDX_AUTH_PROVIDER_TEMPLATE: &str = "provider_json_tpl";
 pub const IDX_CLIENTS: &str = "clients_";
 pub const IDX_CLIENT_LOGO: &str = "client_logo_";
+pub const IDX_DEVICE_USER_CODE: &str = "device_user_code_";
 pub const IDX_GROUPS: &str = "groups_";
 pub const IDX_JWK_KID: &str = "jwk_kid_";
 pub const IDX_JWK_LATEST: &str = "jwk_latest_";
@@ -62,6 +97,7 @@ pub const IDX_LOGIN_TIME: &str = "login_time_";
 pub const IDX_MFA_APP: &str = "mfa_app_";
 pub const IDX_MFA_LOGIN_REQ: &str = "mfa_login_req_";
 pub const IDX_PASSWORD_RULES: &str = "password_rules_";
+pub const IDX_RESOURCES: &str = "resources_";
 pub const IDX_ROLES: &str = "roles_";
 pub const IDX_SCOPES: &str = "scopes_";
 pub const IDX_SESSION: &str = "session_";
@@ -100,24 +136,50 @@ lazy_static! {
     pub static ref RE_BASE64: Regex = Regex::new(r"^[a-zA-Z0-9+/=]{4}$").unwrap();
     pub static ref RE_CHALLENGE: Regex = Regex::new(r"^(plain|S256)$").unwrap();
     pub static ref RE_CITY: Regex = Regex::new(r"^[a-zA-Z0-9À-ÿ-]{0,48}$").unwrap();
+    pub static ref RE_CLIENT_ASSERTION_TYPE: Regex = Regex::new(r"^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$").unwrap();
     pub static ref RE_CLIENT_ID_EPHEMERAL: Regex = Regex::new(r"^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%]{2,128}$").unwrap();
     pub static ref RE_CLIENT_NAME: Regex = Regex::new(r"^[a-zA-Z0-9À-ÿ-\s]{2,128}$").unwrap();
     pub static ref RE_CODE_CHALLENGE: Regex = Regex::new(r"^[a-zA-Z0-9-\._~]{43,128}$").unwrap();
     pub static ref RE_CODE_VERIFIER: Regex = Regex::new(r"^[a-zA-Z0-9-\._~+/=]+$").unwrap();
     pub static ref RE_DATE_STR: Regex = Regex::new(r"^[0-9]{4}-[0-9]{2}-[0-9]{2}$").unwrap();
-    pub static ref RE_FLOWS: Regex = Regex::new(r"^(authorization_code|client_credentials|password|refresh_token)$").unwrap();
-    pub static ref RE_GRANT_TYPES: Regex = Regex::new(r"^(authorization_code|client_credentials|password|refresh_token)$").unwrap();
+    pub static ref RE_FLOWS: Regex = Regex::new(r"^(authorization_code|client_credentials|password|refresh_token|urn:ietf:params:oauth:grant-type:device_code|urn:ietf:params:oauth:grant-type:token-exchange)$").unwrap();
+    pub static ref RE_GRANT_TYPE: Regex = Regex::new(r"^[a-z0-9-_/:.]{2,128}$").unwrap();
+    pub static ref RE_GRANT_TYPES: Regex = Regex::new(r"^(authorization_code|client_credentials|password|refresh_token|urn:ietf:params:oauth:grant-type:device_code)$").unwrap();
     pub static ref RE_LOWERCASE: Regex = Regex::new(r"^[a-z0-9-_/]{2,128}$").unwrap();
     pub static ref RE_LOWERCASE_SPACE: Regex = Regex::new(r"^[a-z0-9-_/\s]{2,128}$").unwrap();
     pub static ref RE_GROUPS: Regex = Regex::new(r"^[a-z0-9-_/,]{2,32}$").unwrap();
     pub static ref RE_MFA_CODE: Regex = Regex::new(r"^[a-zA-Z0-9]{48}$").unwrap();
     pub static ref RE_PEM: Regex = Regex::new(r"^(-----BEGIN CERTIFICATE-----)[a-zA-Z0-9+/=\n]+(-----END CERTIFICATE-----)$").unwrap();
     pub static ref RE_PHONE: Regex = Regex::new(r"^\+[0-9]{0,32}$").unwrap();
+    pub static ref RE_RESOURCE: Regex = Regex::new(r"^[a-zA-Z][a-zA-Z0-9+.\-]*:[a-zA-Z0-9,.:/_\-&?=~!$'()*+%]+$").unwrap();
+    pub static ref RE_SEARCH: Regex = Regex::new(r"^[a-zA-Z0-9À-ÿ@.+\-_\s]{1,128}$").unwrap();
     pub static ref RE_STREET: Regex = Regex::new(r"^[a-zA-Z0-9À-ÿ-.\s]{0,48}$").unwrap();
     pub static ref RE_URI: Regex = Regex::new(r"^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%]+$").unwrap();
+    pub static ref RE_USER_CODE: Regex = Regex::new(r"^[BCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz]+([\-\s][BCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz]+)*$").unwrap();
     pub static ref RE_USER_NAME: Regex = Regex::new(r"^[a-zA-Z0-9À-ÿ-\s]{2,32}$").unwrap();
+    pub static ref RE_TOTP: Regex = Regex::new(r"^[0-9]{6}$").unwrap();
+    pub static ref RE_RECOVERY_CODE: Regex = Regex::new(r"^[a-zA-Z2-7]{4}(-?[a-zA-Z2-7]{4}){3}$").unwrap();
     pub static ref RE_TOKEN_68: Regex = Regex::new(r"^[a-zA-Z0-9-._~+/]+=*$").unwrap();
-    pub static ref RE_TOKEN_ENDPOINT_AUTH_METHOD: Regex = Regex::new(r"^(client_secret_post|client_secret_basic|none)$").unwrap();
+    pub static ref RE_TOKEN_EXCHANGE_SUBJECT_TYPE: Regex = Regex::new(r"^(access_token|id_token|jwt)$").unwrap();
+    pub static ref RE_TOKEN_TYPE: Regex = Regex::new(r"^urn:ietf:params:oauth:token-type:(access_token|id_token|jwt)$").unwrap();
+    pub static ref RE_TOKEN_ENDPOINT_AUTH_METHOD: Regex = Regex::new(r"^(client_secret_post|client_secret_basic|client_secret_jwt|private_key_jwt|tls_client_auth|self_signed_tls_client_auth|none)$").unwrap();
+    pub static ref RE_TOKEN_TYPE_HINT: Regex = Regex::new(r"^(access_token|refresh_token)$").unwrap();
+    pub static ref RE_SUBJECT_TYPE: Regex = Regex::new(r"^(public|pairwise)$").unwrap();
+    pub static ref RE_PROMPT: Regex = Regex::new(
+        r"^(none|login|consent|select_account)( (none|login|consent|select_account))*$"
+    )
+    .unwrap();
+    pub static ref RE_AUTHORIZATION_DETAILS_TYPE: Regex =
+        Regex::new(r"^[a-zA-Z0-9\-_.:/]{1,128}$").unwrap();
+    pub static ref RE_ACR_VALUES: Regex = Regex::new(r"^[a-zA-Z0-9\-_.:/ ]{1,256}$").unwrap();
+    pub static ref RE_JWE_ALG: Regex = Regex::new(r"^(RSA-OAEP-256|ECDH-ES)$").unwrap();
+    pub static ref RE_JWE_ENC: Regex =
+        Regex::new(r"^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$").unwrap();
+    pub static ref RE_CLIENT_AUTH_METHOD: Regex =
+        Regex::new(r"^(client_secret_basic|client_secret_post|client_secret_jwt|private_key_jwt)$").unwrap();
+    pub static ref RE_TLS_CLIENT_AUTH_METHOD: Regex =
+        Regex::new(r"^(tls_client_auth|self_signed_tls_client_auth)$").unwrap();
+    pub static ref RE_TLS_SUBJECT_DN: Regex = Regex::new(r"^[a-zA-Z0-9À-ÿ\s,.:/_\-=+@*'()]{1,256}$").unwrap();
 
     pub static ref PUB_URL: String = env::var("PUB_URL").expect("PUB_URL env var is not set");
     pub static ref PUB_URL_WITH_SCHEME: String = {
@@ -184,6 +246,10 @@ lazy_static! {
         .unwrap_or_else(|_| String::from("false"))
         .parse::<bool>()
         .expect("ENABLE_EPHEMERAL_CLIENTS cannot be parsed to bool - bad format");
+    pub static ref ENABLE_SIGNED_METADATA: bool = env::var("ENABLE_SIGNED_METADATA")
+        .unwrap_or_else(|_| String::from("false"))
+        .parse::<bool>()
+        .expect("ENABLE_SIGNED_METADATA cannot be parsed to bool - bad format");
     pub static ref ENABLE_WEB_ID: bool = env::var("ENABLE_WEB_ID")
         .unwrap_or_else(|_| String::from("false"))
         .parse::<bool>()
@@ -196,6 +262,10 @@ lazy_static! {
         .unwrap_or_else(|_| String::from("false"))
         .parse::<bool>()
         .expect("EPHEMERAL_CLIENTS_FORCE_MFA cannot be parsed to bool - bad format");
+    pub static ref EPHEMERAL_CLIENTS_REQUIRE_CONSENT: bool = env::var("EPHEMERAL_CLIENTS_REQUIRE_CONSENT")
+        .unwrap_or_else(|_| String::from("false"))
+        .parse::<bool>()
+        .expect("EPHEMERAL_CLIENTS_REQUIRE_CONSENT cannot be parsed to bool - bad format");
     pub static ref EPHEMERAL_CLIENTS_ALLOWED_FLOWS: String = env::var("EPHEMERAL_CLIENTS_ALLOWED_FLOWS")
             .unwrap_or_else(|_| String::from("authorization_code"))
             .split(' ')
@@ -237,6 +307,8 @@ lazy_static! {
     };
 
     pub static ref PEER_IP_HEADER_NAME: Option<String> = env::var("PEER_IP_HEADER_NAME").ok();
+    pub static ref TLS_CLIENT_CERT_HEADER: Option<String> = env::var("TLS_CLIENT_CERT_HEADER").ok();
+    pub static ref TLS_CLIENT_VERIFY_HEADER: Option<String> = env::var("TLS_CLIENT_VERIFY_HEADER").ok();
 
     pub static ref POW_IT: u64 = env::var("POW_IT")
         .unwrap_or_else(|_| String::from("1000000"))
@@ -264,6 +336,8 @@ lazy_static! {
         .unwrap_or_else(|_| String::from("true"))
         .parse::<bool>()
         .expect("ADMIN_FORCE_MFA cannot be parsed to bool - bad format");
+    pub static ref TOTP_ISSUER: String = env::var("TOTP_ISSUER")
+        .unwrap_or_else(|_| String::from("Rauthy"));
 
     pub static ref DPOP_NONCE_EXP: u32 = env::var("DPOP_NONCE_EXP")
         .unwrap_or_else(|_| String::from("900"))
@@ -271,6 +345,49 @@ lazy_static! {
         .parse::<u32>()
         .expect("DPOP_NONCE_EXP cannot be parsed to u32 - bad format");
 
+    pub static ref DEVICE_GRANT_CODE_LIFETIME: u16 = env::var("DEVICE_GRANT_CODE_LIFETIME")
+        .unwrap_or_else(|_| String::from("300"))
+        .parse::<u16>()
+        .expect("DEVICE_GRANT_CODE_LIFETIME cannot be parsed to u16 - bad format");
+    pub static ref DEVICE_GRANT_USER_CODE_LENGTH: u8 = {
+        let len = env::var("DEVICE_GRANT_USER_CODE_LENGTH")
+            .unwrap_or_else(|_| String::from("8"))
+            .parse::<u8>()
+            .expect("DEVICE_GRANT_USER_CODE_LENGTH cannot be parsed to u8 - bad format");
+        if !(4..=16).contains(&len) {
+            panic!("DEVICE_GRANT_USER_CODE_LENGTH must be between 4 and 16");
+        }
+        len
+    };
+    pub static ref DEVICE_GRANT_POLL_INTERVAL: u8 = env::var("DEVICE_GRANT_POLL_INTERVAL")
+        .unwrap_or_else(|_| String::from("5"))
+        .parse::<u8>()
+        .expect("DEVICE_GRANT_POLL_INTERVAL cannot be parsed to u8 - bad format");
+
+    pub static ref PAR_EXP: u16 = env::var("PAR_EXP")
+        .unwrap_or_else(|_| String::from("60"))
+        .parse::<u16>()
+        .expect("PAR_EXP cannot be parsed to u16 - bad format");
+
+    pub static ref CLIENT_ASSERTION_MAX_LIFETIME: u16 = env::var("CLIENT_ASSERTION_MAX_LIFETIME")
+        .unwrap_or_else(|_| String::from("300"))
+        .parse::<u16>()
+        .expect("CLIENT_ASSERTION_MAX_LIFETIME cannot be parsed to u16 - bad format");
+
+    pub static ref BACKCHANNEL_LOGOUT_RETRY_COUNT: u16 = env::var("BACKCHANNEL_LOGOUT_RETRY_COUNT")
+        .unwrap_or_else(|_| String::from("5"))
+        .parse::<u16>()
+        .expect("BACKCHANNEL_LOGOUT_RETRY_COUNT cannot be parsed to u16 - bad format");
+
+    pub static ref PAIRWISE_SUBJECT_SECRET: Option<String> = env::var("PAIRWISE_SUBJECT_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty());
+
+    pub static ref ACCESS_TOKEN_RFC9068: bool = env::var("ACCESS_TOKEN_RFC9068")
+        .unwrap_or_else(|_| String::from("false"))
+        .parse::<bool>()
+        .expect("ACCESS_TOKEN_RFC9068 cannot be parsed to bool - bad format");
+
     pub static ref SESSION_LIFETIME: u32 = env::var("SESSION_LIFETIME")
         .unwrap_or_else(|_| String::from("14400"))
         .parse::<u32>()
@@ -293,6 +410,15 @@ lazy_static! {
         .parse::<u16>()
         .expect("SSE_KEEP_ALIVE cannot be parsed to u16 - bad format");
 
+    pub static ref EMAIL_LOGIN_RATE_LIMIT_SEC: i64 = env::var("EMAIL_LOGIN_RATE_LIMIT_SEC")
+        .unwrap_or_else(|_| String::from("60"))
+        .parse::<i64>()
+        .expect("EMAIL_LOGIN_RATE_LIMIT_SEC cannot be parsed to i64 - bad format");
+    pub static ref ML_LT_EMAIL_LOGIN: u64 = env::var("ML_LT_EMAIL_LOGIN")
+        .unwrap_or_else(|_| String::from("10"))
+        .parse::<u64>()
+        .expect("ML_LT_EMAIL_LOGIN cannot be parsed to u64 - bad format");
+
     pub static ref EMAIL_SUB_PREFIX: String = env::var("EMAIL_SUB_PREFIX")
         .unwrap_or_else(|_| String::from("Rauthy IAM"));
     pub static ref SMTP_USERNAME: String = env::var("SMTP_USERNAME")
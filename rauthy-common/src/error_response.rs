@@ -24,6 +24,11 @@ use utoipa::ToSchema;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum ErrorResponseType {
+    // The device grant specific errors must be serialized with their RFC 8628 values
+    #[serde(rename = "access_denied")]
+    AccessDenied,
+    #[serde(rename = "authorization_pending")]
+    AuthorizationPending,
     BadRequest,
     Connection,
     CSRFTokenError,
@@ -34,6 +39,8 @@ pub enum ErrorResponseType {
     // -> just not going down that rabbit hole for now
     DPoP(Option<String>),
     Encryption,
+    #[serde(rename = "expired_token")]
+    ExpiredToken,
     UseDpopNonce((Option<String>, String)),
     Forbidden,
     Internal,
@@ -45,6 +52,8 @@ pub enum ErrorResponseType {
     PasswordRefresh,
     SessionExpired,
     SessionTimeout,
+    #[serde(rename = "slow_down")]
+    SlowDown,
     TooManyRequests(i64),
     Unauthorized,
     WWWAuthenticate(String),
@@ -87,9 +96,12 @@ impl ErrorResponse {
 impl ResponseError for ErrorResponse {
     fn status_code(&self) -> StatusCode {
         match self.error {
-            ErrorResponseType::BadRequest | ErrorResponseType::UseDpopNonce(_) => {
-                StatusCode::BAD_REQUEST
-            }
+            ErrorResponseType::AccessDenied
+            | ErrorResponseType::AuthorizationPending
+            | ErrorResponseType::BadRequest
+            | ErrorResponseType::ExpiredToken
+            | ErrorResponseType::SlowDown
+            | ErrorResponseType::UseDpopNonce(_) => StatusCode::BAD_REQUEST,
             ErrorResponseType::Forbidden => StatusCode::FORBIDDEN,
             ErrorResponseType::MfaRequired => StatusCode::NOT_ACCEPTABLE,
             ErrorResponseType::NotFound => StatusCode::NOT_FOUND,
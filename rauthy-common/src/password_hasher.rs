@@ -1,13 +1,29 @@
 use crate::error_response::{ErrorResponse, ErrorResponseType};
 use actix_web::web;
 use argon2::password_hash::SaltString;
-use argon2::{Algorithm, Argon2, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use argon2::{Algorithm, Argon2, PasswordHash, PasswordHasher, Version};
 use once_cell::sync::Lazy;
+use pbkdf2::Pbkdf2;
 use rand_core::OsRng;
+use scrypt::Scrypt;
+use std::str::FromStr;
 use std::{env, thread};
 use tokio::time::Instant;
 use tracing::{debug, error, warn};
 
+// Upper bounds for the cost of imported hashes. A login with a more expensive hash would block
+// one of the hashing threads for way too long.
+const IMPORT_BCRYPT_COST_MAX: u32 = 14;
+const IMPORT_PBKDF2_ROUNDS_MAX: u32 = 1_000_000;
+const IMPORT_PBKDF2_LEN_MAX: u32 = 64;
+// in bytes: 128 * r * 2^ln
+const IMPORT_SCRYPT_MEM_MAX: u64 = 256 * 1024 * 1024;
+const IMPORT_SCRYPT_P_MAX: u32 = 4;
+// in KiB
+const IMPORT_ARGON2_M_COST_MAX: u32 = 256 * 1024;
+const IMPORT_ARGON2_T_COST_MAX: u32 = 10;
+const IMPORT_ARGON2_P_COST_MAX: u32 = 16;
+
 static ARGON2_PARAMS: Lazy<argon2::Params> = Lazy::new(|| {
     let mut argon2_m_cost = env::var("ARGON2_M_COST")
         .unwrap_or_else(|_| String::from("32768"))
@@ -171,21 +187,30 @@ fn hash_password(msg: HashPassword) {
 fn compare_passwords(msg: ComparePasswords) {
     debug!("Starting password compare on {:?}", thread::current());
 
-    let mut is_match = false;
-
-    match PasswordHash::new(&msg.hash) {
-        Ok(parsed_hash) => {
-            if Argon2::default()
-                .verify_password(msg.plain_text.as_bytes(), &parsed_hash)
-                .is_ok()
-            {
-                is_match = true;
+    let is_match = if is_bcrypt_hash(&msg.hash) {
+        match bcrypt::verify(msg.plain_text.as_bytes(), &msg.hash) {
+            Ok(is_match) => is_match,
+            Err(err) => {
+                error!("Error verifying the bcrypt password hash: {}", err);
+                false
             }
         }
-        Err(err) => {
-            error!("Error parsing the original password hash: {}", err);
+    } else {
+        match PasswordHash::new(&msg.hash) {
+            // Only Argon2ID hashes are created, but imported users may still have one of the
+            // other formats until their next successful login.
+            Ok(parsed_hash) => parsed_hash
+                .verify_password(
+                    &[&Argon2::default(), &Pbkdf2, &Scrypt],
+                    msg.plain_text.as_bytes(),
+                )
+                .is_ok(),
+            Err(err) => {
+                error!("Error parsing the original password hash: {}", err);
+                false
+            }
         }
-    }
+    };
 
     if let Err(err) = msg.tx.send(is_match) {
         error!("{}", err);
@@ -194,6 +219,52 @@ fn compare_passwords(msg: ComparePasswords) {
     debug!("Finished with password compare on {:?}", thread::current());
 }
 
+#[inline]
+fn is_bcrypt_hash(hash: &str) -> bool {
+    hash.starts_with("$2a$") || hash.starts_with("$2b$") || hash.starts_with("$2y$")
+}
+
+/// Returns `true`, if the given hash is in a format, which can be verified on login.
+/// Apart from Argon2, this accepts bcrypt hashes and PBKDF2-SHA256 / -SHA512 or scrypt in the
+/// PHC string format. Hashes with a cost above the import limits are rejected.
+pub fn is_supported_hash(hash: &str) -> bool {
+    if is_bcrypt_hash(hash) {
+        return match bcrypt::HashParts::from_str(hash) {
+            Ok(parts) => parts.get_cost() <= IMPORT_BCRYPT_COST_MAX,
+            Err(_) => false,
+        };
+    }
+
+    let parsed_hash = match PasswordHash::new(hash) {
+        Ok(parsed_hash) => parsed_hash,
+        Err(_) => return false,
+    };
+    // missing params fall back to the defaults of each algorithm
+    let param = |name: &'static str, default: u32| -> u32 {
+        parsed_hash.params.get_decimal(name).unwrap_or(default)
+    };
+
+    match parsed_hash.algorithm.as_str() {
+        // hashes from Rauthy itself must be accepted with the configured params in any case
+        "argon2d" | "argon2i" | "argon2id" => {
+            param("m", 0) <= IMPORT_ARGON2_M_COST_MAX.max(ARGON2_PARAMS.m_cost())
+                && param("t", 0) <= IMPORT_ARGON2_T_COST_MAX.max(ARGON2_PARAMS.t_cost())
+                && param("p", 0) <= IMPORT_ARGON2_P_COST_MAX.max(ARGON2_PARAMS.p_cost())
+        }
+        "pbkdf2-sha256" | "pbkdf2-sha512" => {
+            param("i", 0) <= IMPORT_PBKDF2_ROUNDS_MAX && param("l", 0) <= IMPORT_PBKDF2_LEN_MAX
+        }
+        "scrypt" => {
+            let mem = 1u64
+                .checked_shl(param("ln", 17))
+                .and_then(|n| n.checked_mul(128 * param("r", 8) as u64));
+            matches!(mem, Some(mem) if mem <= IMPORT_SCRYPT_MEM_MAX)
+                && param("p", 1) <= IMPORT_SCRYPT_P_MAX
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,6 +273,55 @@ mod tests {
     use std::time::{Duration, Instant};
     use tokio::time;
 
+    #[test]
+    fn test_foreign_hashes() {
+        let plain = "SuperRandom1337";
+        let hashes = [
+            "$pbkdf2-sha256$i=1000,l=32$cmF1dGh5U2FsdFZhbHVlMQ$ZyBYpBBeKJ+A80yAVlK3GxvUC7dmZs1BrXd3wR3NYvQ".to_string(),
+            "$scrypt$ln=10,r=8,p=1$cmF1dGh5U2FsdFZhbHVlMQ$h0XVtyHfKYCnt6myrWzaSijOdxrlfvUfj/SkLwzV7aA".to_string(),
+            bcrypt::hash(plain, 4).unwrap(),
+        ];
+
+        for hash in hashes {
+            assert!(is_supported_hash(&hash));
+
+            let (tx, rx) = flume::unbounded();
+            compare_passwords(ComparePasswords {
+                plain_text: plain.to_string(),
+                hash: hash.clone(),
+                tx,
+                created: tokio::time::Instant::now(),
+            });
+            assert_eq!(rx.recv().unwrap(), true);
+
+            let (tx, rx) = flume::unbounded();
+            compare_passwords(ComparePasswords {
+                plain_text: "WrongPassword".to_string(),
+                hash,
+                tx,
+                created: tokio::time::Instant::now(),
+            });
+            assert_eq!(rx.recv().unwrap(), false);
+        }
+
+        assert!(is_supported_hash(
+            "$2a$06$DCq7YPn5Rq63x1Lad4cll.TV4S6ytwfsfvkgY8jIucDrjc8deX1s."
+        ));
+        // hashes above the import limits must be rejected
+        assert!(!is_supported_hash(
+            "$2a$31$DCq7YPn5Rq63x1Lad4cll.TV4S6ytwfsfvkgY8jIucDrjc8deX1s."
+        ));
+        assert!(!is_supported_hash(
+            "$pbkdf2-sha256$i=100000000,l=32$cmF1dGh5U2FsdFZhbHVlMQ$ZyBYpBBeKJ+A80yAVlK3GxvUC7dmZs1BrXd3wR3NYvQ"
+        ));
+        assert!(!is_supported_hash(
+            "$scrypt$ln=30,r=8,p=1$cmF1dGh5U2FsdFZhbHVlMQ$h0XVtyHfKYCnt6myrWzaSijOdxrlfvUfj/SkLwzV7aA"
+        ));
+        // md5crypt and plain text must be rejected
+        assert!(!is_supported_hash("$1$saltsalt$2vnaRpHa6Jxjz5n83ok8Z0"));
+        assert!(!is_supported_hash("SuperRandom1337"));
+    }
+
     #[tokio::test]
     async fn test_limiter() {
         env::set_var("ARGON2_M_COST", "32768");
@@ -9,7 +9,7 @@ use rauthy_models::entity::api_keys::ApiKey;
 use rauthy_models::entity::principal::Principal;
 use rauthy_models::entity::sessions::Session;
 use rauthy_models::entity::webauthn::WebauthnCookie;
-use rauthy_models::response::WebauthnLoginResponse;
+use rauthy_models::response::{TotpLoginResponse, WebauthnLoginResponse};
 use rauthy_models::AuthStep;
 use rust_embed::RustEmbed;
 use tracing::error;
@@ -24,6 +24,7 @@ pub mod groups;
 pub mod middleware;
 pub mod oidc;
 pub mod openapi;
+pub mod resources;
 pub mod roles;
 pub mod scopes;
 pub mod sessions;
@@ -80,6 +81,24 @@ pub async fn map_auth_step(
 
             Ok((resp, res.has_password_been_hashed))
         }
+
+        AuthStep::AwaitTotp(res) => {
+            let body = TotpLoginResponse {
+                code: res.code,
+                user_id: res.user_id,
+                exp: res.exp,
+                totp: true,
+            };
+            let mut resp = HttpResponse::Ok()
+                .insert_header(res.header_csrf)
+                .json(&body);
+
+            if let Some((name, value)) = res.header_origin {
+                resp.headers_mut().insert(name, value);
+            }
+
+            Ok((resp, res.has_password_been_hashed))
+        }
     }
 }
 
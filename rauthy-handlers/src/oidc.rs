@@ -2,28 +2,42 @@ use crate::{map_auth_step, ReqPrincipal};
 use actix_web::cookie::time::OffsetDateTime;
 use actix_web::http::header::{HeaderValue, CONTENT_TYPE};
 use actix_web::http::{header, StatusCode};
-use actix_web::{get, post, web, HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError};
+use actix_web::{
+    get, post, routes, web, Either, HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError,
+};
 use chrono::Utc;
-use rauthy_common::constants::{APPLICATION_JSON, COOKIE_MFA, HEADER_HTML, SESSION_LIFETIME};
-use rauthy_common::error_response::ErrorResponse;
+use rauthy_common::constants::{
+    APPLICATION_JSON, APPLICATION_JWT, COOKIE_MFA, HEADER_HTML, PAR_REQUEST_URI_PREFIX,
+    SESSION_LIFETIME,
+};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
 use rauthy_common::utils::real_ip_from_req;
 use rauthy_models::app_state::AppState;
 use rauthy_models::entity::api_keys::{AccessGroup, AccessRights};
 use rauthy_models::entity::auth_providers::AuthProviderTemplate;
+use rauthy_models::entity::authorization_details::AuthorizationDetails;
 use rauthy_models::entity::colors::ColorEntity;
+use rauthy_models::entity::email_login::EmailLogin;
 use rauthy_models::entity::jwk::{JWKSPublicKey, JwkKeyPair, JWKS};
+use rauthy_models::entity::resources::Resource;
 use rauthy_models::entity::sessions::Session;
+use rauthy_models::entity::user_consents::PendingConsent;
 use rauthy_models::entity::users::User;
 use rauthy_models::entity::webauthn::WebauthnCookie;
 use rauthy_models::entity::well_known::WellKnown;
 use rauthy_models::language::Language;
 use rauthy_models::request::{
-    AuthRequest, LoginRefreshRequest, LoginRequest, LogoutRequest, TokenRequest,
-    TokenValidationRequest,
+    AuthRequest, AuthRequestUri, ConsentParams, ConsentRequest, DeviceRequest, DeviceVerifyRequest,
+    EmailLoginFinishRequest, EmailLoginParams, LoginRefreshRequest, LoginRequest, LogoutRequest,
+    ParRequest, TokenRequest, TokenRevocationRequest, TokenValidationRequest,
+};
+use rauthy_models::response::{
+    DeviceCodeResponse, EmailLoginResponse, JWKSCerts, JWKSPublicKeyCerts, ParResponse,
+    SessionInfoResponse, UserinfoResponse,
 };
-use rauthy_models::response::{JWKSCerts, JWKSPublicKeyCerts, SessionInfoResponse};
 use rauthy_models::templates::{
-    AuthorizeHtml, CallbackHtml, Error1Html, ErrorHtml, FrontendAction,
+    AuthorizeHtml, CallbackHtml, ConsentHtml, DeviceHtml, EmailLoginHtml, Error1Html, ErrorHtml,
+    FrontendAction,
 };
 use rauthy_models::JwtCommonClaims;
 use rauthy_service::auth;
@@ -34,7 +48,13 @@ use tracing::debug;
 /// OIDC Authorization HTML
 ///
 /// Starts the authorization_code flow. Log in with username / password.<br>
-/// If one does not exist, a new session will be opened with the 'Init' state and set's a cookie.
+/// If one does not exist, a new session will be opened with the 'Init' state and set's a cookie.<br>
+/// Instead of the full parameters, only the `client_id` and the `request_uri` from a former
+/// Pushed Authorization Request may be given.<br>
+/// Signed request objects (RFC 9101) are accepted by value with `request` or by reference with
+/// an `https` `request_uri`. They are validated against the registered client keys.<br>
+/// With `prompt=none`, no UI will be shown. If a login would be necessary, the user agent is
+/// redirected to the client with `error=login_required` or `error=interaction_required`.
 #[utoipa::path(
     get,
     path = "/oidc/authorize",
@@ -42,6 +62,7 @@ use tracing::debug;
     params(AuthRequest),
     responses(
         (status = 200, description = "If the params match the allowed settings, returns the pre-rendered HTML",),
+        (status = 302, description = "Error redirect to the client for `prompt=none`",),
         (status = 400, description = "If any params do not match the backend config", body = ErrorResponse),
     ),
 )]
@@ -49,14 +70,65 @@ use tracing::debug;
 pub async fn get_authorize(
     data: web::Data<AppState>,
     req: HttpRequest,
-    req_data: actix_web_validator::Query<AuthRequest>,
+    req_data: Either<
+        actix_web_validator::Query<AuthRequest>,
+        actix_web_validator::Query<AuthRequestUri>,
+    >,
     principal: ReqPrincipal,
 ) -> Result<HttpResponse, ErrorResponse> {
-    let colors = ColorEntity::find(&data, &req_data.client_id)
+    let client_id = match &req_data {
+        Either::Left(params) => &params.client_id,
+        Either::Right(params) => &params.client_id,
+    };
+    let colors = ColorEntity::find(&data, client_id)
         .await
         .unwrap_or_default();
     let lang = Language::try_from(&req).unwrap_or_default();
 
+    let resolved = match req_data {
+        Either::Left(params) => {
+            let mut params = params.into_inner();
+            match params.request.take() {
+                None => Ok((params, false, false)),
+                Some(request) => auth::resolve_request_object(&data, &params.client_id, &request)
+                    .await
+                    .map(|auth_req| (auth_req, false, true)),
+            }
+        }
+        Either::Right(params) => {
+            let params = params.into_inner();
+            match (params.request, params.request_uri) {
+                (Some(request), _) => {
+                    auth::resolve_request_object(&data, &params.client_id, &request)
+                        .await
+                        .map(|auth_req| (auth_req, false, true))
+                }
+                (None, Some(request_uri)) if request_uri.starts_with(PAR_REQUEST_URI_PREFIX) => {
+                    auth::resolve_pushed_auth_request(&data, &params.client_id, request_uri)
+                        .await
+                        .map(|auth_req| (auth_req, true, false))
+                }
+                (None, Some(request_uri)) => {
+                    auth::resolve_request_uri(&data, &params.client_id, &request_uri)
+                        .await
+                        .map(|auth_req| (auth_req, false, true))
+                }
+                (None, None) => Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    "Either 'request' or 'request_uri' must be given".to_string(),
+                )),
+            }
+        }
+    };
+    let (mut req_data, is_par, is_request_object) = match resolved {
+        Ok(res) => res,
+        Err(err) => {
+            let status = err.status_code();
+            let body = Error1Html::build(&colors, &lang, status, Some(err.message));
+            return Ok(ErrorHtml::response(body, status));
+        }
+    };
+
     let (client, origin_header) = match auth::validate_auth_req_param(
         &data,
         &req,
@@ -75,15 +147,48 @@ pub async fn get_authorize(
         }
     };
 
+    if client.require_par && !is_par {
+        let status = StatusCode::BAD_REQUEST;
+        let body = Error1Html::build(
+            &colors,
+            &lang,
+            status,
+            Some("This client only accepts Pushed Authorization Requests".to_string()),
+        );
+        return Ok(ErrorHtml::response(body, status));
+    }
+    if client.require_request_object && !is_request_object {
+        let status = StatusCode::BAD_REQUEST;
+        let body = Error1Html::build(
+            &colors,
+            &lang,
+            status,
+            Some("This client only accepts signed request objects".to_string()),
+        );
+        return Ok(ErrorHtml::response(body, status));
+    }
+    // Resolved requests are not visible in the query params and must be passed to the UI
+    let mut pass_auth_request = is_par || is_request_object;
+
+    let validated = req_data.validate_prompt().and_then(|_| {
+        AuthorizationDetails::try_for_client(req_data.authorization_details.as_deref(), &client)
+            .map(|_| ())
+    });
+    let validated = match validated {
+        Ok(_) => Resource::find_for_request(&data, req_data.resource.as_deref())
+            .await
+            .map(|_| ()),
+        Err(err) => Err(err),
+    };
+    if let Err(err) = validated {
+        let status = err.status_code();
+        let body = Error1Html::build(&colors, &lang, status, Some(err.message));
+        return Ok(ErrorHtml::response(body, status));
+    }
+
     // check prompt and max_age to possibly force a new session
-    let mut force_new_session = if req_data
-        .prompt
-        .as_ref()
-        .map(|p| p.as_str() == "login")
-        .unwrap_or(false)
-    {
-        true
-    } else if let Some(max_age) = req_data.max_age {
+    let force_login = req_data.has_prompt("login") || req_data.has_prompt("select_account");
+    let max_age_exceeded = if let Some(max_age) = req_data.max_age {
         if let Some(session) = &principal.session {
             let session_created = session.exp - *SESSION_LIFETIME as i64;
             Utc::now().timestamp() > session_created + max_age
@@ -93,6 +198,44 @@ pub async fn get_authorize(
     } else {
         false
     };
+    let mut force_new_session = force_login || max_age_exceeded;
+
+    // no UI must ever be shown with `prompt=none` -> errors go back to the client
+    if req_data.has_prompt("none") {
+        let error = match principal.validate_session_auth() {
+            Ok(session) if !force_new_session => {
+                auth::prompt_none_error(&data, session, &client, &req_data).await
+            }
+            _ => Some("login_required"),
+        };
+
+        if let Some(error) = error {
+            let loc = PendingConsent::client_location(
+                &req_data.redirect_uri,
+                ("error", error),
+                req_data.state.as_deref(),
+            )?;
+            return Ok(HttpResponse::build(StatusCode::FOUND)
+                .append_header((header::LOCATION, loc))
+                .finish());
+        }
+    }
+
+    // With `select_account`, the currently logged-in user is suggested on the login form.
+    // The UI reads the hint from the resolved request in this case.
+    if req_data.has_prompt("select_account") && req_data.login_hint.is_none() {
+        if let Some(user_id) = principal
+            .validate_session_auth()
+            .ok()
+            .and_then(|s| s.user_id.clone())
+        {
+            if let Ok(user) = User::find(&data, user_id).await {
+                req_data.login_hint = Some(user.email);
+                pass_auth_request = true;
+            }
+        }
+    }
+    let auth_request = pass_auth_request.then_some(&req_data);
 
     // check if the user needs to do the Webauthn login each time
     let mut action = FrontendAction::None;
@@ -105,36 +248,45 @@ pub async fn get_authorize(
 
                 // if the user must do another MFA login anyway, we do never force a new session creation,
                 // because the authentication happens each time anyway
-                force_new_session = false;
+                // `prompt=login` and `select_account` must always show the login form though
+                if !force_login {
+                    force_new_session = false;
+                }
             }
         }
     }
 
-    // check for no-prompt
-    if !force_new_session
-        && req_data
-            .prompt
-            .as_ref()
-            .map(|p| p.as_str() == "none")
-            .unwrap_or(false)
-    {
-        let status = StatusCode::UNAUTHORIZED;
-        let body = Error1Html::build(&colors, &lang, status, Some("login_required".to_string()));
-        return Ok(ErrorHtml::response(body, status));
-    }
-
     let auth_providers_json = AuthProviderTemplate::get_all_json_template(&data).await?;
 
     // if the user is still authenticated and everything is valid -> immediate refresh
     if !force_new_session && principal.validate_session_auth().is_ok() {
+        if is_par || is_request_object {
+            let session_id = &principal.validate_session_auth()?.id;
+            if let Err(err) = auth::save_resolved_auth_request(
+                &data,
+                session_id,
+                &req_data,
+                is_par,
+                is_request_object,
+            )
+            .await
+            {
+                let status = err.status_code();
+                let body = Error1Html::build(&colors, &lang, status, Some(err.message));
+                return Ok(ErrorHtml::response(body, status));
+            }
+        }
+
         let csrf = principal.get_session_csrf_token()?;
         let body = AuthorizeHtml::build(
             &client.name,
+            client.allow_email_login,
             csrf,
             FrontendAction::Refresh,
             &colors,
             &lang,
             auth_providers_json,
+            auth_request,
         );
 
         if let Some(o) = origin_header {
@@ -152,14 +304,31 @@ pub async fn get_authorize(
         let body = Error1Html::build(&colors, &lang, status, Some(err.message));
         return Ok(ErrorHtml::response(body, status));
     }
+    if is_par || is_request_object {
+        if let Err(err) = auth::save_resolved_auth_request(
+            &data,
+            &session.id,
+            &req_data,
+            is_par,
+            is_request_object,
+        )
+        .await
+        {
+            let status = err.status_code();
+            let body = Error1Html::build(&colors, &lang, status, Some(err.message));
+            return Ok(ErrorHtml::response(body, status));
+        }
+    }
 
     let body = AuthorizeHtml::build(
         &client.name,
+        client.allow_email_login,
         &session.csrf_token,
         action,
         &colors,
         &lang,
         auth_providers_json,
+        auth_request,
     );
 
     let cookie = session.client_cookie();
@@ -223,6 +392,88 @@ pub async fn post_authorize(
     auth::handle_login_delay(&data, ip, start, &data.caches.ha_cache_config, res).await
 }
 
+/// Request a passwordless login via E-Mail
+///
+/// Sends a magic link and a one-time code to the given E-Mail address, if the client allows the
+/// E-Mail login. The response is always the same, no matter if the user exists or not. Each
+/// address is rate-limited.<br>
+/// The login is bound to the current session and finished with
+/// [POST /oidc/authorize/email/finish](post_authorize_email_finish).
+///
+/// **Permissions**
+/// - `session-init`
+/// - `session-auth`
+#[utoipa::path(
+    post,
+    path = "/oidc/authorize/email",
+    tag = "oidc",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Ok", body = EmailLoginResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 429, description = "TooManyRequests"),
+    ),
+)]
+#[post("/oidc/authorize/email")]
+pub async fn post_authorize_email(
+    data: web::Data<AppState>,
+    req_data: actix_web_validator::Json<LoginRequest>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_session_auth_or_init()?;
+    let session = principal.get_session()?;
+
+    let res = auth::authorize_email(&data, req_data.into_inner(), session).await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Finish a passwordless login via E-Mail
+///
+/// Accepts either the one-time code or the magic link ID from the E-Mail. The responses are the
+/// same as for [POST /oidc/authorize](post_authorize): If the user has MFA configured, it will be
+/// requested afterward.
+///
+/// **Permissions**
+/// - `session-init`
+/// - `session-auth`
+#[utoipa::path(
+    post,
+    path = "/oidc/authorize/email/finish",
+    tag = "oidc",
+    request_body = EmailLoginFinishRequest,
+    responses(
+        (status = 200, description = "Correct code, but needs to continue with the MFA Login", body = WebauthnLoginResponse),
+        (status = 202, description = "Correct code and no MFA Login required, adds Location header"),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[post("/oidc/authorize/email/finish")]
+pub async fn post_authorize_email_finish(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    req_data: actix_web_validator::Json<EmailLoginFinishRequest>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_session_auth_or_init()?;
+
+    let start = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    let session = principal.get_session()?;
+    let res =
+        match auth::authorize_email_finish(&data, &req, req_data.into_inner(), session.clone())
+            .await
+        {
+            Ok(auth_step) => map_auth_step(auth_step, &req).await,
+            Err(err) => Err(err),
+        };
+
+    let ip = real_ip_from_req(&req);
+    auth::handle_login_delay(&data, ip, start, &data.caches.ha_cache_config, res).await
+}
+
 /// Immediate login refresh with valid session
 ///
 /// This endpoint is used from the login form if an authenticated and valid session still exists
@@ -326,11 +577,244 @@ pub async fn get_cert_by_kid(
     Ok(HttpResponse::Ok().json(JWKSPublicKeyCerts::from(pub_key)))
 }
 
+// Device verification HTML page
+//
+// This is the `verification_uri` for the device authorization grant, where a user can enter
+// the `user_code` and accept or decline a device authorization.
+#[get("/oidc/device")]
+pub async fn get_device_html(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ErrorResponse> {
+    let colors = ColorEntity::find_rauthy(&data).await?;
+    let lang = Language::try_from(&req).unwrap_or_default();
+    let body = DeviceHtml::build(&colors, &lang);
+
+    Ok(HttpResponse::Ok().insert_header(HEADER_HTML).body(body))
+}
+
+// Consent HTML page
+//
+// The user agent is redirected here after the login instead of the client, if the client
+// requires consent for scopes the user has not granted yet.
+#[get("/oidc/consent")]
+pub async fn get_consent_html(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    params: actix_web_validator::Query<ConsentParams>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    let colors = ColorEntity::find_rauthy(&data).await?;
+    let lang = Language::try_from(&req).unwrap_or_default();
+
+    let consent = match principal.validate_session_auth_or_init() {
+        Ok(_) => {
+            let session = principal.get_session()?;
+            auth::consent_data(&data, session, params.into_inner().id)
+                .await
+                .map(|consent| (session, consent))
+        }
+        Err(err) => Err(err),
+    };
+    match consent {
+        Ok((session, consent)) => {
+            let consent_json = serde_json::to_string(&consent)?;
+            let body = ConsentHtml::build(&session.csrf_token, &consent_json, &colors, &lang);
+            Ok(HttpResponse::Ok().insert_header(HEADER_HTML).body(body))
+        }
+        Err(err) => {
+            let status = err.status_code();
+            let body = Error1Html::build(&colors, &lang, status, Some(err.message));
+            Ok(ErrorHtml::response(body, status))
+        }
+    }
+}
+
+// E-Mail login HTML page
+//
+// The target of the magic link from the E-Mail login. It only works inside the same browser,
+// which has requested the login, because the login is bound to the session.
+#[get("/oidc/email")]
+pub async fn get_email_login_html(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    params: actix_web_validator::Query<EmailLoginParams>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    let colors = ColorEntity::find_rauthy(&data).await?;
+    let lang = Language::try_from(&req).unwrap_or_default();
+    let ml_id = params.into_inner().id;
+
+    // opening the link in another browser is the same as an unknown link
+    let mut login_session = None;
+    if principal.validate_session_auth_or_init().is_ok() {
+        let session = principal.get_session()?;
+        if let Ok(login) = EmailLogin::find(&data, &session.id).await {
+            if login.magic_link_id == ml_id {
+                login_session = Some(session);
+            }
+        }
+    }
+
+    match login_session {
+        Some(session) => {
+            let body = EmailLoginHtml::build(&session.csrf_token, &colors, &lang);
+            Ok(HttpResponse::Ok().insert_header(HEADER_HTML).body(body))
+        }
+        None => {
+            let status = StatusCode::NOT_FOUND;
+            let msg = "The login link does not exist, has expired or must be opened in the same \
+                browser it has been requested from. Please enter the code from the E-Mail on the \
+                login page instead."
+                .to_string();
+            let body = Error1Html::build(&colors, &lang, status, Some(msg));
+            Ok(ErrorHtml::response(body, status))
+        }
+    }
+}
+
+/// Accepts or declines a pending consent
+///
+/// Grants the requested scopes to the client, if accepted. Returns the redirect back to the
+/// client in the **Location** header in both cases, with an `access_denied` error if declined.
+///
+/// **Permissions**
+/// - `session-init`
+/// - `session-auth`
+#[utoipa::path(
+    post,
+    path = "/oidc/consent",
+    tag = "oidc",
+    request_body = ConsentRequest,
+    responses(
+        (status = 202, description = "Accepted, adds Location header"),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[post("/oidc/consent")]
+pub async fn post_consent(
+    data: web::Data<AppState>,
+    req_data: actix_web_validator::Json<ConsentRequest>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_session_auth_or_init()?;
+    let session = principal.get_session()?;
+
+    let loc = auth::consent(&data, session, req_data.into_inner()).await?;
+    Ok(HttpResponse::Accepted()
+        .insert_header((header::LOCATION, loc))
+        .finish())
+}
+
+/// The Pushed Authorization Request endpoint as described in RFC 9126
+///
+/// Accepts the same parameters as the [GET /oidc/authorize](get_authorize) endpoint. The client
+/// must authenticate the same way as for the token endpoint. The returned `request_uri` can then
+/// be used together with the `client_id` for the authorization request.
+#[utoipa::path(
+    post,
+    path = "/oidc/par",
+    tag = "oidc",
+    request_body(content = ParRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 201, description = "Created", body = ParResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[post("/oidc/par")]
+pub async fn post_par(
+    req_data: actix_web_validator::Form<ParRequest>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ErrorResponse> {
+    auth::pushed_auth_request(&data, req, req_data.into_inner())
+        .await
+        .map(|resp| HttpResponse::Created().json(resp))
+}
+
+/// The device authorization endpoint as described in RFC 8628
+///
+/// Starts the `urn:ietf:params:oauth:grant-type:device_code` flow and returns the `device_code`
+/// and `user_code`. The client needs to have this flow enabled.<br>
+/// The client must authenticate the same way as for the token endpoint.
+#[utoipa::path(
+    post,
+    path = "/oidc/device",
+    tag = "oidc",
+    request_body(content = DeviceRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Ok", body = DeviceCodeResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[post("/oidc/device")]
+pub async fn post_device_auth(
+    req_data: actix_web_validator::Form<DeviceRequest>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ErrorResponse> {
+    auth::device_authorization(&data, req, req_data.into_inner())
+        .await
+        .map(|resp| HttpResponse::Ok().json(resp))
+}
+
+/// Verifies a device authorization
+///
+/// Accepts or declines a pending device authorization via its `user_code` for the currently
+/// logged in user.
+///
+/// **Permissions**
+/// - authenticated user
+#[utoipa::path(
+    post,
+    path = "/oidc/device/verify",
+    tag = "oidc",
+    request_body = DeviceVerifyRequest,
+    responses(
+        (status = 200, description = "Ok"),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+        (status = 429, description = "TooManyRequests"),
+    ),
+)]
+#[post("/oidc/device/verify")]
+pub async fn post_device_verify(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    req_data: actix_web_validator::Json<DeviceVerifyRequest>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    let session = principal.validate_session_auth()?;
+
+    let start = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    // invalid user codes are throttled like invalid logins to prevent brute-forcing them
+    let res = match auth::device_verify(&data, session, req_data.into_inner()).await {
+        Ok(_) => Ok((HttpResponse::Ok().finish(), false)),
+        Err(err) => {
+            let add_login_delay = err.error == ErrorResponseType::NotFound;
+            Err((err, add_login_delay))
+        }
+    };
+
+    let ip = real_ip_from_req(&req);
+    auth::handle_login_delay(&data, ip, start, &data.caches.ha_cache_config, res).await
+}
+
 // Logout HTML page
 //
 // Returns an HTML page which can be used for logging the user out. Invalidates the session and deletes
 // all possibly existing refresh tokens from the database. Does an automatic logout if the
-// `id_token_hint` is given.
+// `id_token_hint` is given. Afterward, the `frontchannel_logout_uri`s of all clients used with
+// this session are loaded inside hidden iframes before redirecting to the
+// `post_logout_redirect_uri`.
 #[utoipa::path(
     get,
     path = "/oidc/logout",
@@ -394,20 +878,13 @@ pub async fn post_logout(
     req_data: web::Query<LogoutRequest>,
     principal: ReqPrincipal,
 ) -> Result<HttpResponse, ErrorResponse> {
+    // validate the redirect before the session is gone
+    let location = auth::post_logout_redirect(&data, &req_data).await?;
+
     let mut session = principal.get_session()?.clone();
     let cookie = session.invalidate(&data).await?;
 
-    if req_data.post_logout_redirect_uri.is_some() {
-        let state = if req_data.state.is_some() {
-            req_data.state.as_ref().unwrap().as_str()
-        } else {
-            ""
-        };
-        let loc = format!(
-            "{}?state={}",
-            req_data.post_logout_redirect_uri.as_ref().unwrap(),
-            state
-        );
+    if let Some(loc) = location {
         return Ok(HttpResponse::build(StatusCode::MOVED_PERMANENTLY)
             .append_header((header::LOCATION, loc))
             .cookie(cookie)
@@ -417,6 +894,38 @@ pub async fn post_logout(
     return Ok(HttpResponse::build(StatusCode::OK).cookie(cookie).finish());
 }
 
+/// The token revocation endpoint as described in RFC 7009
+///
+/// Revokes a refresh token or an access token. Access tokens will be blocked by their `jti` until
+/// they would have expired anyway.<br>
+/// The client must authenticate the same way as for the token endpoint. Invalid or already
+/// expired tokens will be answered with a 200 as well, as demanded by the RFC.
+#[utoipa::path(
+    post,
+    path = "/oidc/revoke",
+    tag = "oidc",
+    request_body(content = TokenRevocationRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Ok"),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[post("/oidc/revoke")]
+pub async fn post_revoke(
+    req_data: actix_web_validator::Form<TokenRevocationRequest>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ErrorResponse> {
+    let header_origin = auth::revoke_token(&data, req, req_data.into_inner()).await?;
+    let mut builder = HttpResponseBuilder::new(StatusCode::OK);
+    if let Some(h) = header_origin {
+        builder.insert_header(h);
+    }
+    Ok(builder.finish())
+}
+
 /// Rotate JWKs
 ///
 /// Rotates all currently exiting JWKs (Json Web Keys) for signing new tokens. This is a manual
@@ -672,9 +1181,12 @@ pub async fn get_userinfo(
     data: web::Data<AppState>,
     req: HttpRequest,
 ) -> Result<HttpResponse, ErrorResponse> {
-    auth::get_userinfo(&data, req)
-        .await
-        .map(|u| HttpResponse::Ok().json(u))
+    match auth::get_userinfo(&data, req).await? {
+        UserinfoResponse::Json(userinfo) => Ok(HttpResponse::Ok().json(userinfo)),
+        UserinfoResponse::Jwt(jwt) => Ok(HttpResponse::Ok()
+            .insert_header((CONTENT_TYPE, APPLICATION_JWT))
+            .body(jwt)),
+    }
 }
 
 /// The `.well-known` endpoint for OIDC Client auto discovery.
@@ -691,7 +1203,33 @@ pub async fn get_userinfo(
 )]
 #[get("/.well-known/openid-configuration")]
 pub async fn get_well_known(data: web::Data<AppState>) -> Result<HttpResponse, ErrorResponse> {
-    let wk = WellKnown::json(&data).await?;
+    well_known_response(&data).await
+}
+
+/// The OAuth 2.0 Authorization Server Metadata (RFC 8414)
+///
+/// Contains the same values as the [.well-known](get_well_known) endpoint for pure OAuth 2.0
+/// clients. Because the issuer contains the `/auth/v1` path, it is served from the path
+/// inserted `/.well-known/oauth-authorization-server/auth/v1` as well.
+#[utoipa::path(
+    get,
+    path = "/.well-known/oauth-authorization-server",
+    tag = "oidc",
+    responses(
+        (status = 200, description = "Ok", body = WellKnown),
+    ),
+)]
+#[routes]
+#[get("/.well-known/oauth-authorization-server")]
+#[get("/.well-known/oauth-authorization-server/auth/v1")]
+pub async fn get_oauth_authorization_server(
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ErrorResponse> {
+    well_known_response(&data).await
+}
+
+async fn well_known_response(data: &web::Data<AppState>) -> Result<HttpResponse, ErrorResponse> {
+    let wk = WellKnown::json(data).await?;
     Ok(HttpResponse::Ok()
         .insert_header((CONTENT_TYPE, APPLICATION_JSON))
         .insert_header((
@@ -1,5 +1,6 @@
 use crate::{
-    api_keys, blacklist, clients, events, generic, groups, oidc, roles, scopes, sessions, users,
+    api_keys, blacklist, clients, events, generic, groups, oidc, resources, roles, scopes,
+    sessions, users,
 };
 use actix_web::web;
 use rauthy_common::constants::{PROXY_MODE, RAUTHY_VERSION};
@@ -66,10 +67,17 @@ use utoipa::{openapi, OpenApi};
 
         oidc::get_authorize,
         oidc::post_authorize,
+        oidc::post_authorize_email,
+        oidc::post_authorize_email_finish,
         oidc::get_certs,
         oidc::get_cert_by_kid,
+        oidc::post_consent,
+        oidc::post_device_auth,
+        oidc::post_device_verify,
         oidc::get_logout,
         oidc::post_logout,
+        oidc::post_par,
+        oidc::post_revoke,
         oidc::rotate_jwk,
         oidc::get_session_info,
         oidc::get_session_xsrf,
@@ -78,6 +86,12 @@ use utoipa::{openapi, OpenApi};
         oidc::post_validate_token,
         oidc::get_userinfo,
         oidc::get_well_known,
+        oidc::get_oauth_authorization_server,
+
+        resources::get_resources,
+        resources::post_resource,
+        resources::put_resource,
+        resources::delete_resource,
 
         roles::get_roles,
         roles::post_role,
@@ -94,6 +108,8 @@ use utoipa::{openapi, OpenApi};
         sessions::delete_sessions_for_user,
 
         users::get_users,
+        users::get_users_export,
+        users::post_users_import,
         users::post_users,
         users::get_cust_attr,
         users::post_cust_attr,
@@ -104,6 +120,8 @@ use utoipa::{openapi, OpenApi};
         users::get_user_by_id,
         users::get_user_attr,
         users::put_user_attr,
+        users::get_user_consents,
+        users::delete_user_consent,
         users::get_user_webid,
         users::get_user_webid_data,
         users::put_user_webid_data,
@@ -113,6 +131,12 @@ use utoipa::{openapi, OpenApi};
         users::post_webauthn_auth_finish,
         users::delete_webauthn,
         users::post_webauthn_reg_start,
+        users::post_user_totp,
+        users::put_user_totp,
+        users::post_user_totp_auth,
+        users::delete_user_totp,
+        users::post_user_recovery_codes,
+        users::post_user_recovery_codes_auth,
         users::post_user_password_request_reset,
         users::get_user_by_email,
         users::put_user_by_id,
@@ -154,6 +178,10 @@ use utoipa::{openapi, OpenApi};
             request::AuthRequest,
             request::IpBlacklistRequest,
             request::ColorsRequest,
+            request::ConsentRequest,
+            request::DeviceRequest,
+            request::DeviceVerifyRequest,
+            request::EmailLoginFinishRequest,
             request::EncKeyMigrateRequest,
             request::LoginRequest,
             request::LogoutRequest,
@@ -162,6 +190,7 @@ use utoipa::{openapi, OpenApi};
             request::NewClientRequest,
             request::DynamicClientRequest,
             request::NewGroupRequest,
+            request::ParRequest,
             request::PasswordHashTimesRequest,
             request::PasswordPolicyRequest,
             request::PasswordResetRequest,
@@ -172,9 +201,16 @@ use utoipa::{openapi, OpenApi};
             request::NewUserRequest,
             request::NewUserRegistrationRequest,
             request::NewRoleRequest,
+            request::RecoveryCodeAuthRequest,
+            request::RecoveryCodesRequest,
+            request::ResourceRequest,
             request::ScopeRequest,
             request::TokenRequest,
+            request::TokenRevocationRequest,
             request::TokenValidationRequest,
+            request::TotpAuthRequest,
+            request::TotpDeleteRequest,
+            request::TotpRequest,
             request::UpdateClientRequest,
             request::UpdateUserRequest,
             request::UpdateUserSelfRequest,
@@ -182,6 +218,11 @@ use utoipa::{openapi, OpenApi};
             request::UserAttrConfigRequest,
             request::UserAttrValueRequest,
             request::UserAttrValuesUpdateRequest,
+            request::UsersSortBy,
+            request::UserImportRequest,
+            request::UsersImportRequest,
+            request::UsersExportFormat,
+            request::SortOrder,
             request::WebauthnRegStartRequest,
             request::WebauthnRegFinishRequest,
             request::WebauthnAuthStartRequest,
@@ -197,28 +238,41 @@ use utoipa::{openapi, OpenApi};
             response::BlacklistedIp,
             response::LoginTimeResponse,
             response::ClientResponse,
+            response::DeviceCodeResponse,
             response::DynamicClientResponse,
             response::ClientSecretResponse,
+            response::EmailLoginResponse,
             response::EncKeysResponse,
             response::HealthResponse,
             response::JWKSCerts,
             response::JWKSPublicKeyCerts,
             response::Argon2ParamsResponse,
+            response::ParResponse,
             response::PasswordPolicyResponse,
             response::ProviderResponse,
             response::ProviderLinkedUserResponse,
             response::ProviderLookupResponse,
+            response::RecoveryCodesResponse,
+            response::ResourceResponse,
             response::ScopeResponse,
             response::SessionResponse,
             response::SessionInfoResponse,
             response::TokenInfo,
+            response::TotpLoginResponse,
+            response::TotpRegistrationResponse,
             response::UserAttrConfigResponse,
             response::UserAttrValueResponse,
             response::UserAttrValuesResponse,
+            response::UserConsentResponse,
             response::Userinfo,
             response::UserValuesResponse,
             response::UserAccountTypeResponse,
             response::UserResponse,
+            response::UserResponseSimple,
+            response::UserExportResponse,
+            response::UsersExportResponse,
+            response::UserImportErrorResponse,
+            response::UsersImportResponse,
             response::WebauthnAuthStartResponse,
             response::WebauthnLoginFinishResponse,
             response::WebauthnLoginResponse,
@@ -239,6 +293,7 @@ use utoipa::{openapi, OpenApi};
         (name = "groups", description = "Groups endpoints"),
         (name = "roles", description = "Roles endpoints"),
         (name = "scopes", description = "Scopes endpoints"),
+        (name = "resources", description = "Protected resources endpoints"),
         (name = "events", description = "Events Stream"),
         (name = "health", description = "Ping, Health, Ready Check"),
         (name = "blacklist", description = "IP Blacklist endpoints"),
@@ -0,0 +1,128 @@
+use crate::ReqPrincipal;
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use rauthy_common::error_response::ErrorResponse;
+use rauthy_models::app_state::AppState;
+use rauthy_models::entity::api_keys::{AccessGroup, AccessRights};
+use rauthy_models::entity::resources::Resource;
+use rauthy_models::request::ResourceRequest;
+use rauthy_models::response::ResourceResponse;
+
+/// Returns all registered protected resources
+///
+/// **Permissions**
+/// - rauthy_admin
+#[utoipa::path(
+    get,
+    path = "/resources",
+    tag = "resources",
+    responses(
+        (status = 200, description = "Ok", body = [ResourceResponse]),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+    ),
+)]
+#[get("/resources")]
+pub async fn get_resources(
+    data: web::Data<AppState>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_api_key_or_admin_session(AccessGroup::Resources, AccessRights::Read)?;
+
+    Resource::find_all(&data).await.map(|res| {
+        let res = res
+            .into_iter()
+            .map(ResourceResponse::from)
+            .collect::<Vec<ResourceResponse>>();
+        HttpResponse::Ok().json(res)
+    })
+}
+
+/// Registers a new protected resource
+///
+/// Clients can request access tokens for it with the `resource` parameter (RFC 8707).
+///
+/// **Permissions**
+/// - rauthy_admin
+#[utoipa::path(
+    post,
+    path = "/resources",
+    tag = "resources",
+    request_body = ResourceRequest,
+    responses(
+        (status = 200, description = "Ok", body = ResourceResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+    ),
+)]
+#[post("/resources")]
+pub async fn post_resource(
+    data: web::Data<AppState>,
+    principal: ReqPrincipal,
+    resource_req: actix_web_validator::Json<ResourceRequest>,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_api_key_or_admin_session(AccessGroup::Resources, AccessRights::Create)?;
+
+    Resource::create(&data, resource_req.into_inner())
+        .await
+        .map(|r| HttpResponse::Ok().json(ResourceResponse::from(r)))
+}
+
+/// Modifies a protected resource
+///
+/// **Permissions**
+/// - rauthy_admin
+#[utoipa::path(
+    put,
+    path = "/resources/{id}",
+    tag = "resources",
+    request_body = ResourceRequest,
+    responses(
+        (status = 200, description = "Ok", body = ResourceResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+    ),
+)]
+#[put("/resources/{id}")]
+pub async fn put_resource(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    principal: ReqPrincipal,
+    resource_req: actix_web_validator::Json<ResourceRequest>,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_api_key_or_admin_session(AccessGroup::Resources, AccessRights::Update)?;
+
+    Resource::update(&data, path.as_str(), resource_req.into_inner())
+        .await
+        .map(|r| HttpResponse::Ok().json(ResourceResponse::from(r)))
+}
+
+/// Deletes a protected resource
+///
+/// Already issued access tokens for this resource stay valid until they expire.
+///
+/// **Permissions**
+/// - rauthy_admin
+#[utoipa::path(
+    delete,
+    path = "/resources/{id}",
+    tag = "resources",
+    responses(
+        (status = 200, description = "Ok"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+    ),
+)]
+#[delete("/resources/{id}")]
+pub async fn delete_resource(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_api_key_or_admin_session(AccessGroup::Resources, AccessRights::Delete)?;
+
+    Resource::delete(&data, path.as_str())
+        .await
+        .map(|_| HttpResponse::Ok().finish())
+}
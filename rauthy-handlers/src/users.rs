@@ -1,10 +1,11 @@
 use crate::ReqPrincipal;
-use actix_web::http::StatusCode;
+use actix_web::http::{header, StatusCode};
 use actix_web::{cookie, delete, get, post, put, web, HttpRequest, HttpResponse, ResponseError};
 use actix_web_validator::Json;
+use futures::StreamExt;
 use rauthy_common::constants::{
-    COOKIE_MFA, ENABLE_WEB_ID, HEADER_HTML, OPEN_USER_REG, PWD_RESET_COOKIE, TEXT_TURTLE,
-    USER_REG_DOMAIN_RESTRICTION,
+    COOKIE_MFA, ENABLE_WEB_ID, HEADER_HTML, HEADER_TOTAL_COUNT, OPEN_USER_REG, PWD_RESET_COOKIE,
+    TEXT_CSV, TEXT_TURTLE, USERS_IMPORT_MAX_BYTES, USER_REG_DOMAIN_RESTRICTION,
 };
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
 use rauthy_common::utils::real_ip_from_req;
@@ -13,7 +14,13 @@ use rauthy_models::entity::api_keys::{AccessGroup, AccessRights};
 use rauthy_models::entity::colors::ColorEntity;
 use rauthy_models::entity::password::PasswordPolicy;
 use rauthy_models::entity::pow::PowEntity;
+use rauthy_models::entity::recovery_codes;
+use rauthy_models::entity::recovery_codes::RecoveryCode;
+use rauthy_models::entity::refresh_tokens::RefreshToken;
+use rauthy_models::entity::totp;
+use rauthy_models::entity::totp::TotpRegistration;
 use rauthy_models::entity::user_attr::{UserAttrConfigEntity, UserAttrValueEntity};
+use rauthy_models::entity::user_consents::UserConsent;
 use rauthy_models::entity::users::User;
 use rauthy_models::entity::users_values::UserValues;
 use rauthy_models::entity::webauthn;
@@ -23,31 +30,41 @@ use rauthy_models::events::event::Event;
 use rauthy_models::language::Language;
 use rauthy_models::request::{
     MfaPurpose, NewUserRegistrationRequest, NewUserRequest, PasswordResetRequest,
-    RequestResetRequest, UpdateUserRequest, UpdateUserSelfRequest, UserAttrConfigRequest,
-    UserAttrValuesUpdateRequest, WebIdRequest, WebauthnAuthFinishRequest, WebauthnAuthStartRequest,
-    WebauthnRegFinishRequest, WebauthnRegStartRequest,
+    RecoveryCodeAuthRequest, RecoveryCodesRequest, RequestResetRequest, TotpAuthRequest,
+    TotpDeleteRequest, TotpRequest, UpdateUserRequest, UpdateUserSelfRequest,
+    UserAttrConfigRequest, UserAttrValuesUpdateRequest, UsersExportFormat, UsersExportParams,
+    UsersImportRequest, UsersQueryParams, WebIdRequest, WebauthnAuthFinishRequest,
+    WebauthnAuthStartRequest, WebauthnRegFinishRequest, WebauthnRegStartRequest,
 };
 use rauthy_models::response::{
-    PasskeyResponse, UserAttrConfigResponse, UserAttrValueResponse, UserAttrValuesResponse,
-    UserResponse, UserResponseSimple, WebIdResponse,
+    PasskeyResponse, RecoveryCodesResponse, UserAttrConfigResponse, UserAttrValueResponse,
+    UserAttrValuesResponse, UserConsentResponse, UserResponse, UserResponseSimple,
+    UsersExportResponse, UsersImportResponse, WebIdResponse,
 };
 use rauthy_models::templates::{Error1Html, Error3Html, ErrorHtml, UserRegisterHtml};
-use rauthy_service::password_reset;
+use rauthy_service::{auth, password_reset, users_bulk};
 use spow::pow::Pow;
 use std::ops::Add;
+use std::time::{SystemTime, UNIX_EPOCH};
 use time::OffsetDateTime;
 use tracing::{error, warn};
 
 /// Returns all existing users
 ///
+/// The result can be filtered, sorted and paginated with the query params. Without a
+/// `page_size`, the first 100 matching users will be returned. The `x-total-count` header always
+/// contains the total count of all users matching the filters.
+///
 /// **Permissions**
 /// - rauthy_admin
 #[utoipa::path(
     get,
     path = "/users",
     tag = "users",
+    params(UsersQueryParams),
     responses(
-        (status = 200, description = "Ok", body = [UserResponse]),
+        (status = 200, description = "Ok", body = [UserResponseSimple]),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Forbidden"),
     ),
@@ -55,17 +72,144 @@ use tracing::{error, warn};
 #[get("/users")]
 pub async fn get_users(
     data: web::Data<AppState>,
+    params: actix_web_validator::Query<UsersQueryParams>,
     principal: ReqPrincipal,
 ) -> Result<HttpResponse, ErrorResponse> {
     principal.validate_api_key_or_admin_session(AccessGroup::Users, AccessRights::Read)?;
 
-    let users = User::find_all(&data).await?;
-    let mut res = Vec::new();
-    users
+    let (users, total) = User::find_paginated(&data, &params.into_inner()).await?;
+    let res = users
         .into_iter()
         // return a simplified version to decrease payload for big deployments
-        .for_each(|u| res.push(UserResponseSimple::from(u)));
+        .map(UserResponseSimple::from)
+        .collect::<Vec<UserResponseSimple>>();
+
+    Ok(HttpResponse::Ok()
+        .insert_header((HEADER_TOTAL_COUNT, total))
+        .json(res))
+}
+
+/// Bulk export of all users
+///
+/// Exports all users with their roles, groups and custom attributes as JSON or CSV. The result
+/// has the same format as the [POST /users/import](post_users_import). Password hashes are only
+/// included with `password_hashes=true`, which needs the same `update` rights for API keys as
+/// setting new passwords.
+///
+/// **Permissions**
+/// - rauthy_admin
+#[utoipa::path(
+    get,
+    path = "/users/export",
+    tag = "users",
+    params(UsersExportParams),
+    responses(
+        (status = 200, description = "Ok", body = UsersExportResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+)]
+#[get("/users/export")]
+pub async fn get_users_export(
+    data: web::Data<AppState>,
+    params: actix_web_validator::Query<UsersExportParams>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_api_key_or_admin_session(AccessGroup::Users, AccessRights::Read)?;
 
+    let params = params.into_inner();
+    let with_password_hashes = params.password_hashes.unwrap_or(false);
+    if with_password_hashes {
+        principal.validate_api_key_or_admin_session(AccessGroup::Users, AccessRights::Update)?;
+        warn!("Exporting all users including their password hashes");
+    }
+
+    match params.format.unwrap_or_default() {
+        UsersExportFormat::Json => {
+            let res = users_bulk::export_users(&data, with_password_hashes).await?;
+            Ok(HttpResponse::Ok().json(res))
+        }
+        UsersExportFormat::Csv => {
+            let csv = users_bulk::export_users_csv(&data, with_password_hashes).await?;
+            Ok(HttpResponse::Ok()
+                .content_type(TEXT_CSV)
+                .insert_header((
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"users.csv\"",
+                ))
+                .body(csv))
+        }
+    }
+}
+
+/// Bulk import of users
+///
+/// Accepts either JSON or CSV with a `content-type: text/csv`. Password hashes from other
+/// systems can be imported in the bcrypt, PBKDF2-SHA256 / -SHA512, scrypt or Argon2 format.
+/// They will be upgraded to Argon2ID with the configured params on the next successful login.
+/// No E-Mails will be sent out to the imported users.
+///
+/// A single invalid user will not abort the whole import. All users, which could not be
+/// imported, are returned with the reason.
+///
+/// **Permissions**
+/// - rauthy_admin
+#[utoipa::path(
+    post,
+    path = "/users/import",
+    tag = "users",
+    request_body = UsersImportRequest,
+    responses(
+        (status = 200, description = "Ok", body = UsersImportResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+)]
+#[post("/users/import")]
+pub async fn post_users_import(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    principal: ReqPrincipal,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_api_key_or_admin_session(AccessGroup::Users, AccessRights::Create)?;
+
+    // the default payload limits are way too small for bigger imports
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|err| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("Error reading the payload: {}", err),
+            )
+        })?;
+        if body.len() + chunk.len() > USERS_IMPORT_MAX_BYTES {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!(
+                    "The import must not exceed {} MB",
+                    USERS_IMPORT_MAX_BYTES / 1024 / 1024
+                ),
+            ));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let is_csv = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.starts_with(TEXT_CSV))
+        .unwrap_or(false);
+    let import = if is_csv {
+        users_bulk::parse_csv(&body)?
+    } else {
+        serde_json::from_slice::<UsersImportRequest>(&body)?
+    };
+
+    let res = users_bulk::import_users(&data, import).await?;
     Ok(HttpResponse::Ok().json(res))
 }
 
@@ -499,6 +643,88 @@ pub async fn put_user_password_reset(
     })
 }
 
+/// Returns all scopes the user has granted to clients on the consent screen
+///
+/// **Permissions**
+/// - authenticated and logged in user for this very {id}
+/// - authenticated and logged in admin
+#[utoipa::path(
+    get,
+    path = "/users/{id}/consents",
+    tag = "users",
+    responses(
+        (status = 200, description = "Ok", body = [UserConsentResponse]),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+    ),
+)]
+#[get("/users/{id}/consents")]
+pub async fn get_user_consents(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    let id = id.into_inner();
+
+    if principal
+        .validate_api_key_or_admin_session(AccessGroup::Users, AccessRights::Read)
+        .is_err()
+    {
+        // make sure a non-admin can only access its own information
+        principal.validate_session_auth()?;
+        principal.is_user(&id)?;
+    }
+
+    let consents = UserConsent::find_for_user(&data, &id)
+        .await?
+        .into_iter()
+        .map(UserConsentResponse::from)
+        .collect::<Vec<UserConsentResponse>>();
+
+    Ok(HttpResponse::Ok().json(consents))
+}
+
+/// Revokes the consent of a user for a client
+///
+/// All refresh tokens of the user for this client are deleted as well. The consent screen will
+/// be shown again with the next login.
+///
+/// **Permissions**
+/// - authenticated and logged in user for this very {id}
+/// - authenticated and logged in admin
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/consents/{client_id}",
+    tag = "users",
+    responses(
+        (status = 200, description = "Ok"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[delete("/users/{id}/consents/{client_id}")]
+pub async fn delete_user_consent(
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    let (id, client_id) = path.into_inner();
+
+    if principal
+        .validate_api_key_or_admin_session(AccessGroup::Users, AccessRights::Delete)
+        .is_err()
+    {
+        principal.validate_session_auth()?;
+        principal.is_user(&id)?;
+    }
+
+    UserConsent::delete(&data, &id, &client_id).await?;
+    RefreshToken::delete_for_user_client(&data, &id, &client_id).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
 /// Get all registered Webauthn Passkeys for a user
 ///
 /// **Permissions**
@@ -713,6 +939,11 @@ pub async fn delete_webauthn(
 
         user.save(&data, None, Some(&mut txn)).await?;
         txn.commit().await?;
+
+        // recovery codes are useless without any second factor
+        if !user.has_mfa_enabled() {
+            RecoveryCode::delete_all_for_user(&data, &user.id).await?;
+        }
     } else {
         txn.commit().await?;
     }
@@ -782,6 +1013,9 @@ pub async fn post_webauthn_reg_start(
 
 /// Finishes the registration process for a new WebAuthn Device for this user
 ///
+/// If this is the first MFA enrollment, the response contains new recovery codes. They are
+/// only shown this single time.
+///
 /// **Permissions**
 /// - authenticated and logged in user for this very {id}
 #[utoipa::path(
@@ -790,7 +1024,7 @@ pub async fn post_webauthn_reg_start(
     tag = "mfa",
     request_body = WebauthnRegFinishRequest,
     responses(
-        (status = 201, description = "Created"),
+        (status = 201, description = "Created", body = RecoveryCodesResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
         (status = 403, description = "Forbidden", body = ErrorResponse),
     ),
@@ -822,11 +1056,313 @@ pub async fn post_webauthn_reg_finish(
         let id = id.into_inner();
         principal.is_user(&id)?;
 
-        webauthn::reg_finish(&data, id, req_data.into_inner()).await?;
-        Ok(HttpResponse::Created().finish())
+        webauthn::reg_finish(&data, id.clone(), req_data.into_inner()).await?;
+
+        let user = User::find(&data, id).await?;
+        let codes = RecoveryCode::generate_on_enrollment(&data, &user).await?;
+        Ok(HttpResponse::Created().json(RecoveryCodesResponse { codes }))
     }
 }
 
+/// Starts the registration of a TOTP authenticator app for this user
+///
+/// Returns the secret and the `otpauth://` URI, which can be scanned as QR code. The app is only
+/// registered after a first valid code has been sent to `PUT /users/{id}/totp`.
+///
+/// **Permissions**
+/// - authenticated and logged in user for this very {id}
+#[utoipa::path(
+    post,
+    path = "/users/{id}/totp",
+    tag = "mfa",
+    responses(
+        (status = 200, description = "Ok", body = TotpRegistrationResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+    ),
+)]
+#[post("/users/{id}/totp")]
+pub async fn post_user_totp(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+    principal: ReqPrincipal,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_session_auth()?;
+    let id = id.into_inner();
+    principal.is_user(&id)?;
+
+    let user = User::find(&data, id).await?;
+    let reg = TotpRegistration::new(user.id)?;
+    reg.save(&data).await?;
+
+    reg.as_response(&user.email)
+        .map(|res| HttpResponse::Ok().json(res))
+}
+
+/// Finishes the registration of a TOTP authenticator app for this user
+///
+/// If this is the first MFA enrollment, the response contains new recovery codes. They are
+/// only shown this single time.
+///
+/// The user must re-authenticate with either the current password, the `mfa_code` from a
+/// Webauthn request with the purpose `PasswordNew` or a code from an already registered app.
+///
+/// **Permissions**
+/// - authenticated and logged in user for this very {id}
+#[utoipa::path(
+    put,
+    path = "/users/{id}/totp",
+    tag = "mfa",
+    request_body = TotpRequest,
+    responses(
+        (status = 200, description = "Ok", body = RecoveryCodesResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[put("/users/{id}/totp")]
+pub async fn put_user_totp(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+    req: HttpRequest,
+    principal: ReqPrincipal,
+    req_data: Json<TotpRequest>,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_session_auth()?;
+    let id = id.into_inner();
+    principal.is_user(&id)?;
+
+    let req_data = req_data.into_inner();
+    let mut user = User::find(&data, id.clone()).await?;
+    validate_reauth_delayed(
+        &data,
+        &req,
+        &mut user,
+        req_data.password_current,
+        req_data.mfa_code,
+        req_data.totp_current.as_deref(),
+    )
+    .await?;
+
+    TotpRegistration::find(&data, &id)
+        .await?
+        .finish(&data, &req_data.totp)
+        .await?;
+
+    let user = User::find(&data, id).await?;
+    let codes = RecoveryCode::generate_on_enrollment(&data, &user).await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { codes }))
+}
+
+/// Finishes a login, which is waiting for a TOTP as the second factor
+///
+/// The `code` comes from the `TotpLoginResponse` of the login. Each pending login only accepts
+/// a single try.
+#[utoipa::path(
+    post,
+    path = "/users/{id}/totp/auth",
+    tag = "mfa",
+    request_body = TotpAuthRequest,
+    responses(
+        (status = 202, description = "Accepted", body = WebauthnLoginFinishResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[post("/users/{id}/totp/auth")]
+pub async fn post_user_totp_auth(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+    req_data: Json<TotpAuthRequest>,
+) -> Result<HttpResponse, ErrorResponse> {
+    // Just like for the Webauthn finish, this will simply fail, if the secret code from the
+    // password login does not exist.
+    let res = totp::login_finish(&data, id.into_inner(), req_data.into_inner()).await?;
+    Ok(res.into_response())
+}
+
+/// Deletes the TOTP authenticator app for this user
+///
+/// The user must re-authenticate with either the current password, the `mfa_code` from a
+/// Webauthn request with the purpose `PasswordNew` or a code from the registered app.
+/// An admin does not need to send a body.
+///
+/// **Permissions**
+/// - rauthy_admin
+/// - authenticated and logged in user for this very {id}
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/totp",
+    tag = "mfa",
+    request_body = TotpDeleteRequest,
+    responses(
+        (status = 200, description = "Ok"),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[delete("/users/{id}/totp")]
+pub async fn delete_user_totp(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+    req: HttpRequest,
+    principal: ReqPrincipal,
+    req_data: Option<Json<TotpDeleteRequest>>,
+) -> Result<HttpResponse, ErrorResponse> {
+    // Note: Currently, this is not allowed with an ApiKey on purpose, just like for passkeys
+    let is_admin = match principal.validate_admin_session() {
+        Ok(()) => true,
+        Err(_) => {
+            principal.validate_session_auth()?;
+            false
+        }
+    };
+
+    let id = id.into_inner();
+    if !is_admin {
+        principal.is_user(&id)?;
+        warn!("TOTP delete for user {}", id);
+    } else {
+        warn!("TOTP delete from admin for user {}", id);
+    }
+
+    let mut user = User::find(&data, id).await?;
+    if user.totp_secret.is_none() {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::NotFound,
+            "The user has no TOTP authenticator app registered".to_string(),
+        ));
+    }
+
+    if !is_admin {
+        let (password_current, mfa_code, totp_current) = match req_data {
+            Some(req_data) => {
+                let req_data = req_data.into_inner();
+                (
+                    req_data.password_current,
+                    req_data.mfa_code,
+                    req_data.totp_current,
+                )
+            }
+            None => (None, None, None),
+        };
+        validate_reauth_delayed(
+            &data,
+            &req,
+            &mut user,
+            password_current,
+            mfa_code,
+            totp_current.as_deref(),
+        )
+        .await?;
+    }
+
+    user.totp_secret = None;
+    user.save(&data, None, None).await?;
+
+    // recovery codes are useless without any second factor
+    if !user.has_mfa_enabled() {
+        RecoveryCode::delete_all_for_user(&data, &user.id).await?;
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Generates new recovery codes for this user
+///
+/// All existing codes will be replaced. The new ones are only shown this single time.
+///
+/// The user must re-authenticate with either the current password, the `mfa_code` from a
+/// Webauthn request with the purpose `PasswordNew` or a code from the registered app.
+///
+/// **Permissions**
+/// - authenticated and logged in user for this very {id}
+#[utoipa::path(
+    post,
+    path = "/users/{id}/recovery_codes",
+    tag = "mfa",
+    request_body = RecoveryCodesRequest,
+    responses(
+        (status = 200, description = "Ok", body = RecoveryCodesResponse),
+        (status = 400, description = "BadRequest", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+    ),
+)]
+#[post("/users/{id}/recovery_codes")]
+pub async fn post_user_recovery_codes(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+    req: HttpRequest,
+    principal: ReqPrincipal,
+    req_data: Json<RecoveryCodesRequest>,
+) -> Result<HttpResponse, ErrorResponse> {
+    principal.validate_session_auth()?;
+    let id = id.into_inner();
+    principal.is_user(&id)?;
+
+    let mut user = User::find(&data, id).await?;
+    if !RecoveryCode::is_applicable(&user) {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            "Recovery codes need a password or upstream login with an active second factor"
+                .to_string(),
+        ));
+    }
+
+    let req_data = req_data.into_inner();
+    validate_reauth_delayed(
+        &data,
+        &req,
+        &mut user,
+        req_data.password_current,
+        req_data.mfa_code,
+        req_data.totp_current.as_deref(),
+    )
+    .await?;
+
+    let codes = RecoveryCode::generate(&data, &user.id).await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { codes }))
+}
+
+/// Finishes a login, which is waiting for the second factor, with a recovery code instead
+///
+/// The `code` comes from the login response. Each pending login only accepts a single try and
+/// each recovery code can only be used once.
+#[utoipa::path(
+    post,
+    path = "/users/{id}/recovery_codes/auth",
+    tag = "mfa",
+    request_body = RecoveryCodeAuthRequest,
+    responses(
+        (status = 202, description = "Accepted", body = WebauthnLoginFinishResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "NotFound", body = ErrorResponse),
+    ),
+)]
+#[post("/users/{id}/recovery_codes/auth")]
+pub async fn post_user_recovery_codes_auth(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+    req: HttpRequest,
+    req_data: Json<RecoveryCodeAuthRequest>,
+) -> Result<HttpResponse, ErrorResponse> {
+    let res = recovery_codes::login_finish(
+        &data,
+        id.into_inner(),
+        req_data.into_inner(),
+        real_ip_from_req(&req),
+    )
+    .await?;
+    Ok(res.into_response())
+}
+
 /// Returns a user's webid document, if enabled
 ///
 /// Note: The way the SwaggerUI is set up currently, the path will not be correct for this single
@@ -1148,3 +1684,25 @@ pub async fn delete_user_by_id(
     user.delete(&data).await?;
     Ok(HttpResponse::NoContent().finish())
 }
+
+// Failed re-authentications get the same delay and IP blacklisting as failed logins, which
+// makes guessing the current password or TOTP codes unfeasible.
+async fn validate_reauth_delayed(
+    data: &web::Data<AppState>,
+    req: &HttpRequest,
+    user: &mut User,
+    password_current: Option<String>,
+    mfa_code: Option<String>,
+    totp_current: Option<&str>,
+) -> Result<(), ErrorResponse> {
+    let start = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let res = user
+        .validate_reauth(data, password_current, mfa_code, totp_current)
+        .await
+        .map(|_| (HttpResponse::Ok().finish(), false))
+        .map_err(|err| (err, true));
+
+    let ip = real_ip_from_req(req);
+    auth::handle_login_delay(data, ip, start, &data.caches.ha_cache_config, res).await?;
+    Ok(())
+}
@@ -8,12 +8,17 @@ use actix_web_prom::PrometheusMetricsBuilder;
 use cryptr::EncKeys;
 use prometheus::Registry;
 use rauthy_common::constants::{
-    CACHE_NAME_12HR, CACHE_NAME_AUTH_CODES, CACHE_NAME_AUTH_PROVIDER_CALLBACK,
-    CACHE_NAME_CLIENTS_DYN, CACHE_NAME_DPOP_NONCES, CACHE_NAME_EPHEMERAL_CLIENTS,
-    CACHE_NAME_LOGIN_DELAY, CACHE_NAME_POW, CACHE_NAME_SESSIONS, CACHE_NAME_USERS,
-    CACHE_NAME_WEBAUTHN, CACHE_NAME_WEBAUTHN_DATA, DPOP_NONCE_EXP, DYN_CLIENT_RATE_LIMIT_SEC,
-    DYN_CLIENT_REG_TOKEN, ENABLE_DYN_CLIENT_REG, ENABLE_WEB_ID, EPHEMERAL_CLIENTS_CACHE_LIFETIME,
-    POW_EXP, RAUTHY_VERSION, SWAGGER_UI_EXTERNAL, SWAGGER_UI_INTERNAL,
+    AUTH_REQUEST_LIFETIME, CACHE_NAME_12HR, CACHE_NAME_AUTH_CODES,
+    CACHE_NAME_AUTH_PROVIDER_CALLBACK, CACHE_NAME_AUTH_REQUESTS, CACHE_NAME_CLIENTS_DYN,
+    CACHE_NAME_CLIENT_ASSERTIONS, CACHE_NAME_CONSENT_REQ, CACHE_NAME_DEVICE_CODES,
+    CACHE_NAME_DPOP_NONCES, CACHE_NAME_EMAIL_LOGIN, CACHE_NAME_EPHEMERAL_CLIENTS,
+    CACHE_NAME_LOGIN_DELAY, CACHE_NAME_PAR, CACHE_NAME_POW, CACHE_NAME_REVOKED_TOKENS,
+    CACHE_NAME_SESSIONS, CACHE_NAME_TOTP, CACHE_NAME_USERS, CACHE_NAME_WEBAUTHN,
+    CACHE_NAME_WEBAUTHN_DATA, CLIENT_ASSERTION_MAX_LIFETIME, CONSENT_REQ_LIFETIME,
+    DEVICE_GRANT_CODE_LIFETIME, DPOP_NONCE_EXP, DYN_CLIENT_RATE_LIMIT_SEC, DYN_CLIENT_REG_TOKEN,
+    EMAIL_LOGIN_RATE_LIMIT_SEC, ENABLE_DYN_CLIENT_REG, ENABLE_WEB_ID,
+    EPHEMERAL_CLIENTS_CACHE_LIFETIME, ML_LT_EMAIL_LOGIN, PAR_EXP, POW_EXP, RAUTHY_VERSION,
+    SWAGGER_UI_EXTERNAL, SWAGGER_UI_INTERNAL, TOTP_CACHE_LIFETIME,
     UPSTREAM_AUTH_CALLBACK_TIMEOUT_SECS, WEBAUTHN_DATA_EXP, WEBAUTHN_REQ_EXP,
 };
 use rauthy_common::password_hasher;
@@ -22,11 +27,12 @@ use rauthy_handlers::middleware::logging::RauthyLoggingMiddleware;
 use rauthy_handlers::middleware::principal::RauthyPrincipalMiddleware;
 use rauthy_handlers::openapi::ApiDoc;
 use rauthy_handlers::{
-    api_keys, auth_providers, blacklist, clients, events, generic, groups, oidc, roles, scopes,
-    sessions, users,
+    api_keys, auth_providers, blacklist, clients, events, generic, groups, oidc, resources, roles,
+    scopes, sessions, users,
 };
 use rauthy_models::app_state::{AppState, Caches};
 use rauthy_models::email::EMail;
+use rauthy_models::entity::backchannel_logout;
 use rauthy_models::events::event::Event;
 use rauthy_models::events::health_watch::watch_health;
 use rauthy_models::events::listener::EventListener;
@@ -146,6 +152,48 @@ https://sebadob.github.io/rauthy/getting_started/main.html"#
         );
     }
 
+    // pending consent requests
+    cache_config.spawn_cache(
+        CACHE_NAME_CONSENT_REQ.to_string(),
+        redhac::TimedCache::with_lifespan(CONSENT_REQ_LIFETIME),
+        None,
+    );
+
+    // TOTP registrations and used codes
+    cache_config.spawn_cache(
+        CACHE_NAME_TOTP.to_string(),
+        redhac::TimedCache::with_lifespan(TOTP_CACHE_LIFETIME),
+        None,
+    );
+
+    // device codes
+    cache_config.spawn_cache(
+        CACHE_NAME_DEVICE_CODES.to_string(),
+        redhac::TimedCache::with_lifespan(*DEVICE_GRANT_CODE_LIFETIME as u64),
+        None,
+    );
+
+    // authorization requests, which have been resolved from a PAR or a request object
+    cache_config.spawn_cache(
+        CACHE_NAME_AUTH_REQUESTS.to_string(),
+        redhac::TimedCache::with_lifespan(AUTH_REQUEST_LIFETIME),
+        None,
+    );
+
+    // pushed authorization requests
+    cache_config.spawn_cache(
+        CACHE_NAME_PAR.to_string(),
+        redhac::TimedCache::with_lifespan(*PAR_EXP as u64),
+        None,
+    );
+
+    // used client assertion `jti`s for replay protection
+    cache_config.spawn_cache(
+        CACHE_NAME_CLIENT_ASSERTIONS.to_string(),
+        redhac::TimedCache::with_lifespan(*CLIENT_ASSERTION_MAX_LIFETIME as u64),
+        None,
+    );
+
     // DPoP nonces
     cache_config.spawn_cache(
         CACHE_NAME_DPOP_NONCES.to_string(),
@@ -153,6 +201,15 @@ https://sebadob.github.io/rauthy/getting_started/main.html"#
         None,
     );
 
+    // pending email logins and the rate limiting per address
+    cache_config.spawn_cache(
+        CACHE_NAME_EMAIL_LOGIN.to_string(),
+        redhac::TimedCache::with_lifespan(
+            (*ML_LT_EMAIL_LOGIN * 60).max(*EMAIL_LOGIN_RATE_LIMIT_SEC as u64),
+        ),
+        None,
+    );
+
     // ephemeral clients
     cache_config.spawn_cache(
         CACHE_NAME_EPHEMERAL_CLIENTS.to_string(),
@@ -179,6 +236,14 @@ https://sebadob.github.io/rauthy/getting_started/main.html"#
         Some(16),
     );
 
+    // revoked access tokens
+    // only a lookup cache for known revocations - the database keeps them until the tokens expire
+    cache_config.spawn_cache(
+        CACHE_NAME_REVOKED_TOKENS.to_string(),
+        redhac::TimedCache::with_lifespan(86400),
+        None,
+    );
+
     // Users
     let users_lifespan = env::var("CACHE_USERS_LIFESPAN")
         .unwrap_or_else(|_| String::from("28800"))
@@ -238,6 +303,7 @@ https://sebadob.github.io/rauthy/getting_started/main.html"#
     let (tx_events, rx_events) = flume::unbounded();
     let (tx_events_router, rx_events_router) = flume::unbounded();
     let (tx_ip_blacklist, rx_ip_blacklist) = flume::unbounded();
+    let (tx_backchannel_logout, rx_backchannel_logout) = flume::unbounded();
 
     let app_state = web::Data::new(
         AppState::new(
@@ -245,6 +311,7 @@ https://sebadob.github.io/rauthy/getting_started/main.html"#
             tx_events.clone(),
             tx_events_router.clone(),
             tx_ip_blacklist.clone(),
+            tx_backchannel_logout,
             caches,
         )
         .await?,
@@ -267,6 +334,9 @@ https://sebadob.github.io/rauthy/getting_started/main.html"#
     // spawn ip blacklist handler
     tokio::spawn(ip_blacklist_handler::run(tx_ip_blacklist, rx_ip_blacklist));
 
+    // spawn backchannel logout sender
+    tokio::spawn(backchannel_logout::sender(rx_backchannel_logout));
+
     // spawn remote cache notification service
     tokio::spawn(handle_notify(app_state.clone(), rx_notify));
 
@@ -438,6 +508,7 @@ async fn actix_main(app_state: web::Data<AppState>) -> std::io::Result<()> {
             )
             .wrap(pub_metrics.clone())
             .service(oidc::get_well_known)
+            .service(oidc::get_oauth_authorization_server)
             .service(generic::redirect)
             // Important: Do not move this middleware do need the least amount of computing
             // for blacklisted IPs -> middlewares are executed in reverse order -> this one first
@@ -494,12 +565,22 @@ async fn actix_main(app_state: web::Data<AppState>) -> std::io::Result<()> {
                             .service(generic::get_version)
                             .service(oidc::get_authorize)
                             .service(oidc::post_authorize)
+                            .service(oidc::post_authorize_email)
+                            .service(oidc::post_authorize_email_finish)
                             .service(oidc::post_authorize_refresh)
                             .service(oidc::get_callback_html)
                             .service(oidc::get_certs)
                             .service(oidc::get_cert_by_kid)
+                            .service(oidc::get_consent_html)
+                            .service(oidc::post_consent)
+                            .service(oidc::get_device_html)
+                            .service(oidc::post_device_auth)
+                            .service(oidc::post_device_verify)
+                            .service(oidc::get_email_login_html)
                             .service(oidc::get_logout)
                             .service(oidc::post_logout)
+                            .service(oidc::post_par)
+                            .service(oidc::post_revoke)
                             .service(oidc::rotate_jwk)
                             .service(oidc::get_session_info)
                             .service(oidc::get_session_xsrf)
@@ -521,6 +602,7 @@ async fn actix_main(app_state: web::Data<AppState>) -> std::io::Result<()> {
                             .service(clients::put_clients_dyn)
                             .service(generic::get_login_time)
                             .service(users::get_users)
+                            .service(users::get_users_export)
                             .service(users::get_users_register)
                             .service(users::post_users_register)
                             .service(users::get_cust_attr)
@@ -541,17 +623,26 @@ async fn actix_main(app_state: web::Data<AppState>) -> std::io::Result<()> {
                             .service(users::get_user_password_reset)
                             .service(users::put_user_password_reset)
                             .service(users::get_user_by_email)
+                            .service(users::post_users_import)
                             .service(users::post_users)
                             .service(users::put_user_by_id)
                             .service(users::put_user_self)
                             .service(users::delete_user_by_id)
                             .service(users::post_user_password_request_reset)
+                            .service(users::get_user_consents)
+                            .service(users::delete_user_consent)
                             .service(users::get_user_webauthn_passkeys)
                             .service(users::post_webauthn_reg_start)
                             .service(users::post_webauthn_reg_finish)
                             .service(users::post_webauthn_auth_start)
                             .service(users::post_webauthn_auth_finish)
                             .service(users::delete_webauthn)
+                            .service(users::post_user_totp)
+                            .service(users::put_user_totp)
+                            .service(users::post_user_totp_auth)
+                            .service(users::delete_user_totp)
+                            .service(users::post_user_recovery_codes)
+                            .service(users::post_user_recovery_codes_auth)
                             .service(generic::get_password_policy)
                             .service(generic::put_password_policy)
                             .service(generic::get_pow)
@@ -567,6 +658,10 @@ async fn actix_main(app_state: web::Data<AppState>) -> std::io::Result<()> {
                             .service(scopes::post_scope)
                             .service(scopes::put_scope)
                             .service(scopes::delete_scope)
+                            .service(resources::get_resources)
+                            .service(resources::post_resource)
+                            .service(resources::put_resource)
+                            .service(resources::delete_resource)
                             .service(oidc::post_token)
                             .service(oidc::post_token_info)
                             .service(oidc::get_userinfo)
@@ -575,6 +670,7 @@ async fn actix_main(app_state: web::Data<AppState>) -> std::io::Result<()> {
                             .service(generic::ping)
                             .service(oidc::post_validate_token)
                             .service(oidc::get_well_known)
+                            .service(oidc::get_oauth_authorization_server)
                             .service(generic::get_health)
                             .service(generic::get_ready)
                             .service(generic::whoami)
@@ -588,6 +684,8 @@ async fn actix_main(app_state: web::Data<AppState>) -> std::io::Result<()> {
 
         app
     })
+    // makes the client certificate available for mutual TLS client authentication
+    .on_connect(tls::extract_client_certificate)
     // overwrites the number of worker threads -> default == available cpu cores
     .workers(workers)
     .shutdown_timeout(10);
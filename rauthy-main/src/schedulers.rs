@@ -43,6 +43,7 @@ pub async fn scheduler_main(data: web::Data<AppState>) {
     tokio::spawn(events_cleanup(data.db.clone(), rx_health.clone()));
     tokio::spawn(magic_link_cleanup(data.db.clone(), rx_health.clone()));
     tokio::spawn(refresh_tokens_cleanup(data.db.clone(), rx_health.clone()));
+    tokio::spawn(revoked_tokens_cleanup(data.db.clone(), rx_health.clone()));
     tokio::spawn(sessions_cleanup(data.db.clone(), rx_health.clone()));
     tokio::spawn(jwks_auto_rotate(data.clone(), rx_health.clone()));
     tokio::spawn(jwks_cleanup(data.clone(), rx_health.clone()));
@@ -439,6 +440,36 @@ pub async fn refresh_tokens_cleanup(db: DbPool, rx_health: Receiver<Option<Quoru
     }
 }
 
+// Cleans up the revocations of access tokens, which have expired in the meantime
+pub async fn revoked_tokens_cleanup(db: DbPool, rx_health: Receiver<Option<QuorumHealthState>>) {
+    let mut interval = time::interval(Duration::from_secs(3600 * 3));
+
+    loop {
+        interval.tick().await;
+
+        // will return None in a non-HA deployment
+        if let Some(is_ha_leader) = is_ha_leader(&rx_health) {
+            if !is_ha_leader {
+                debug!("Running HA mode without being the leader - skipping revoked_tokens_cleanup scheduler");
+                continue;
+            }
+        }
+
+        debug!("Running revoked_tokens_cleanup scheduler");
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let res = sqlx::query("delete from revoked_tokens where exp < $1")
+            .bind(now)
+            .execute(&db)
+            .await;
+
+        match res {
+            Ok(_) => {}
+            Err(err) => error!("Revoked Token Cleanup Error: {:?}", err),
+        }
+    }
+}
+
 // Cleans up old / expired Sessions
 pub async fn sessions_cleanup(db: DbPool, rx_health: Receiver<Option<QuorumHealthState>>) {
     let mut interval = time::interval(Duration::from_secs(3595 * 2));
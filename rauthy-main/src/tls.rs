@@ -1,8 +1,20 @@
+use actix_tls::accept::rustls_0_21::TlsStream;
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use rauthy_models::entity::client_certificates::ClientCertificate;
+use rustls::server::{AllowAnyAuthenticatedClient, ClientCertVerified, ClientCertVerifier};
+use rustls::{Certificate, DistinguishedName, RootCertStore};
 use rustls_pemfile::Item;
+use std::any::Any;
 use std::io::BufReader;
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
 use std::{env, iter};
 use tokio::fs;
-use tracing::error;
+use tracing::{error, info};
+
+// Validates client certificates for `tls_client_auth`, if a `TLS_CLIENT_CA` is configured
+static CLIENT_CA_VERIFIER: OnceLock<Option<AllowAnyAuthenticatedClient>> = OnceLock::new();
 
 // Loads TLS key and cert file from disk and returns a `rustls::ServerConfig`
 pub async fn load_tls() -> rustls::ServerConfig {
@@ -26,18 +38,103 @@ pub async fn load_tls() -> rustls::ServerConfig {
         rustls::PrivateKey(key_bytes)
     };
 
-    let certs_file = fs::read(&path_cert).await.expect("Reading TLS certificate");
-    let mut certs_reader = BufReader::new(certs_file.as_slice());
-    let cert_chain = rustls_pemfile::certs(&mut certs_reader)
-        .expect("bad tls cert file")
-        .iter()
-        .map(|cert| rustls::Certificate(cert.to_owned()))
-        .collect();
+    let cert_chain = read_certs(&path_cert).await;
 
-    rustls::ServerConfig::builder()
-        .with_safe_defaults()
-        .with_no_client_auth()
+    let builder = rustls::ServerConfig::builder().with_safe_defaults();
+    let builder = if env::var("TLS_CLIENT_AUTH").as_deref() == Ok("true") {
+        load_client_ca().await;
+        info!("Optional TLS client authentication enabled");
+        builder.with_client_cert_verifier(Arc::new(OptionalClientCertVerifier))
+    } else {
+        builder.with_no_client_auth()
+    };
+
+    builder
         .with_single_cert(cert_chain, key)
         .map_err(|err| error!("Error building rustls ServerConfig: {}", err))
         .expect("bad certificate/key")
 }
+
+async fn read_certs(path: &str) -> Vec<Certificate> {
+    let certs_file = fs::read(path).await.expect("Reading TLS certificate");
+    let mut certs_reader = BufReader::new(certs_file.as_slice());
+    rustls_pemfile::certs(&mut certs_reader)
+        .expect("bad tls cert file")
+        .iter()
+        .map(|cert| Certificate(cert.to_owned()))
+        .collect()
+}
+
+async fn load_client_ca() {
+    let verifier = if let Ok(path) = env::var("TLS_CLIENT_CA") {
+        let mut roots = RootCertStore::empty();
+        for cert in read_certs(&path).await {
+            roots.add(&cert).expect("bad certificate in TLS_CLIENT_CA");
+        }
+        Some(AllowAnyAuthenticatedClient::new(roots))
+    } else {
+        None
+    };
+    let _ = CLIENT_CA_VERIFIER.set(verifier);
+}
+
+/// Extracts the client certificate from a TLS connection and makes it available for the
+/// mutual TLS client authentication (RFC 8705) via the connection data.
+pub fn extract_client_certificate(conn: &dyn Any, ext: &mut Extensions) {
+    // plain HTTP connections will never have a certificate
+    let tls = match conn.downcast_ref::<TlsStream<TcpStream>>() {
+        None => return,
+        Some(tls) => tls,
+    };
+    let (_, session) = tls.get_ref();
+    let (end_entity, intermediates) = match session
+        .peer_certificates()
+        .and_then(|certs| certs.split_first())
+    {
+        None => return,
+        Some(certs) => certs,
+    };
+
+    let ca_verified = CLIENT_CA_VERIFIER
+        .get()
+        .and_then(|verifier| verifier.as_ref())
+        .map(|verifier| {
+            verifier
+                .verify_client_cert(end_entity, intermediates, SystemTime::now())
+                .is_ok()
+        })
+        .unwrap_or(false);
+
+    ext.insert(ClientCertificate {
+        der: end_entity.0.clone(),
+        ca_verified,
+    });
+}
+
+// Requests an optional client certificate and accepts all of them, because self-signed ones
+// are valid for `self_signed_tls_client_auth`. The handshake still proves the possession of
+// the private key. The chain is validated afterward in `extract_client_certificate`.
+struct OptionalClientCertVerifier;
+
+impl ClientCertVerifier for OptionalClientCertVerifier {
+    fn offer_client_auth(&self) -> bool {
+        true
+    }
+
+    fn client_auth_mandatory(&self) -> bool {
+        false
+    }
+
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+}
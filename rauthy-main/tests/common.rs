@@ -100,6 +100,10 @@ pub async fn session_headers() -> (HeaderMap, TokenSet) {
         nonce: Some("MySuperNonce".to_string()),
         code_challenge: Some(challenge_s256),
         code_challenge_method: Some("S256".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
     };
 
     let mut res = reqwest::Client::new()
@@ -122,6 +126,18 @@ pub async fn session_headers() -> (HeaderMap, TokenSet) {
         username: None,
         password: None,
         refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
 
     let url_token = format!("{}/oidc/token", backend_url);
@@ -1,6 +1,6 @@
 use crate::common::{
     check_status, code_state_from_headers, cookie_csrf_headers_from_res, get_auth_headers,
-    get_backend_url, CLIENT_ID, CLIENT_SECRET, PASSWORD, USERNAME,
+    get_backend_url, get_issuer, get_token_set, CLIENT_ID, CLIENT_SECRET, PASSWORD, USERNAME,
 };
 use actix_web::{http, web, App, HttpResponse, HttpServer};
 use chrono::Utc;
@@ -8,22 +8,35 @@ use ed25519_compact::Noise;
 use josekit::jwk;
 use pretty_assertions::assert_eq;
 use rauthy_common::constants::{
-    APPLICATION_JSON, DPOP_TOKEN_ENDPOINT, HEADER_DPOP_NONCE, TOKEN_DPOP,
+    APPLICATION_JSON, CLIENT_ASSERTION_TYPE_JWT_BEARER, DPOP_TOKEN_ENDPOINT,
+    GRANT_TYPE_DEVICE_CODE, GRANT_TYPE_TOKEN_EXCHANGE, HEADER_DPOP_NONCE, PAR_REQUEST_URI_PREFIX,
+    TOKEN_DPOP, TOKEN_TYPE_ACCESS_TOKEN, TOKEN_TYPE_ID_TOKEN,
 };
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
-use rauthy_common::utils::{base64_url_encode, base64_url_no_pad_encode, get_rand};
+use rauthy_common::utils::{
+    base64_url_encode, base64_url_no_pad_decode, base64_url_no_pad_encode, get_rand,
+};
+use rauthy_models::entity::authorization_details::AuthorizationDetails;
 use rauthy_models::entity::dpop_proof::{DPoPClaims, DPoPHeader};
 use rauthy_models::entity::jwk::{JWKSPublicKey, JwkKeyPairAlg, JwkKeyPairType, JWKS};
 use rauthy_models::request::{
-    LoginRequest, TokenRequest, TokenValidationRequest, UpdateClientRequest,
+    ConsentRequest, DeviceRequest, DeviceVerifyRequest, EmailLoginFinishRequest, LoginRequest,
+    NewClientRequest, ParRequest, ResourceRequest, TokenRequest, TokenRevocationRequest,
+    TokenValidationRequest, UpdateClientRequest,
+};
+use rauthy_models::response::{
+    ClientSecretResponse, DeviceCodeResponse, EmailLoginResponse, ParResponse, ResourceResponse,
+    TokenInfo, UserConsentResponse,
 };
-use rauthy_models::response::TokenInfo;
 use rauthy_models::JwtTokenType;
 use rauthy_service::token_set::TokenSet;
 use ring::digest;
+use std::collections::HashMap;
+use std::env;
 use std::error::Error;
 use std::fmt::Write;
 use std::ops::Sub;
+use std::sync::mpsc;
 use std::thread;
 use std::thread::JoinHandle;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -45,7 +58,7 @@ async fn test_certs() -> Result<(), Box<dyn Error>> {
     let res = reqwest::get(&url).await?;
     assert_eq!(res.status(), 200);
     let certs = res.json::<JWKS>().await?;
-    assert_eq!(certs.keys.len(), 4);
+    assert_eq!(certs.keys.len(), 6);
 
     // for _ in 1..1000 {
     //     aw!(reqwest::get(&url)).unwrap();
@@ -63,7 +76,7 @@ async fn test_certs() -> Result<(), Box<dyn Error>> {
     let res = reqwest::get(&url).await?;
     assert_eq!(res.status(), 200);
     let new_certs = res.json::<JWKS>().await?;
-    assert_eq!(new_certs.keys.len(), 8);
+    assert_eq!(new_certs.keys.len(), 12);
 
     Ok(())
 }
@@ -102,6 +115,10 @@ async fn test_authorization_code_flow() -> Result<(), Box<dyn Error>> {
         nonce: Some(nonce.to_owned()),
         code_challenge: Some(challenge_plain.to_owned()),
         code_challenge_method: Some("plain".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
     };
     let res = reqwest::Client::new()
         .post(&url_auth)
@@ -136,6 +153,18 @@ async fn test_authorization_code_flow() -> Result<(), Box<dyn Error>> {
         username: None,
         password: None,
         refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
     let url_token = format!("{}/oidc/token", backend_url);
     let res = reqwest::Client::new()
@@ -194,6 +223,10 @@ async fn test_authorization_code_flow() -> Result<(), Box<dyn Error>> {
         .expect("'nonce' is not set in id token");
     assert_eq!(nonce_claim, nonce);
 
+    // the 'acr' mirrors the 'amr' for a password login
+    let acr = payload.claim("acr").and_then(|v| v.as_str());
+    assert_eq!(acr, Some("pwd"));
+
     // ############################################################################
     // ########################## flow with S256 pkce #############################
     // ############################################################################
@@ -278,6 +311,30 @@ async fn test_authorization_code_flow() -> Result<(), Box<dyn Error>> {
         default_scopes: vec!["openid".to_string(), "email".to_string()],
         challenges: None,
         force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
     };
     let url_client = format!("{}/clients/{}", backend_url, CLIENT_ID);
     let auth_headers = get_auth_headers().await?;
@@ -337,6 +394,88 @@ async fn test_authorization_code_flow() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_prompt_and_acr_values() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    let challenge = "oDXug9zfYqfz8ejcqMpALRPXfW8QhbKV2AVuScAt8xrLKDAmaRYQ4yRi2uqcH9ys";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let query = format!(
+        "client_id=init_client&redirect_uri={}&response_type=code&code_challenge={}&state=MyState",
+        redirect_uri, challenge
+    );
+
+    // 'none' must not be combined with other values
+    let url_auth = format!(
+        "{}/oidc/authorize?{}&prompt=none%20login",
+        backend_url, query
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    // without any session, 'prompt=none' must redirect with an error and never show the UI
+    let url_auth = format!("{}/oidc/authorize?{}&prompt=none", backend_url, query);
+    let res = client.get(&url_auth).send().await?;
+    assert_eq!(res.status(), 302);
+    let loc = res
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .unwrap()
+        .to_str()?;
+    assert_eq!(
+        loc,
+        format!("{}?error=login_required&state=MyState", redirect_uri)
+    );
+
+    // 'prompt=login' and a 'login_hint' show the login form as usual
+    let url_auth = format!(
+        "{}/oidc/authorize?{}&prompt=login&login_hint={}",
+        backend_url, query, USERNAME
+    );
+    let mut res = client.get(&url_auth).send().await?;
+    res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    // demanding MFA must fail for a user without any passkey
+    let mut req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: CLIENT_ID.to_string(),
+        redirect_uri: redirect_uri.to_owned(),
+        scopes: None,
+        state: Some("MyState".to_string()),
+        nonce: None,
+        code_challenge: Some(challenge.to_string()),
+        code_challenge_method: Some("plain".to_string()),
+        acr_values: Some("mfa".to_string()),
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+    let res = client
+        .post(&url_auth)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    check_status(res, 406).await?;
+
+    // 'pwd' as an alternative is fine
+    req_login.acr_values = Some("mfa pwd".to_string());
+    let res = client
+        .post(&url_auth)
+        .headers(headers)
+        .json(&req_login)
+        .send()
+        .await?;
+    check_status(res, 202).await?;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_client_credentials_flow() -> Result<(), Box<dyn Error>> {
     let backend_url = get_backend_url();
@@ -351,6 +490,18 @@ async fn test_client_credentials_flow() -> Result<(), Box<dyn Error>> {
         username: None,
         password: None,
         refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
     let url = format!("{}/oidc/token", backend_url);
     let client = reqwest::Client::new();
@@ -378,6 +529,163 @@ async fn test_client_credentials_flow() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_ecdsa_tokens() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "ecdsa_test";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("ECDSA Test".to_string()),
+        confidential: true,
+        redirect_uris: vec!["http://localhost:3000/oidc/callback".to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let res = reqwest::get(format!("{}/oidc/certs", backend_url)).await?;
+    let res = check_status(res, 200).await?;
+    let certs = res.json::<JWKS>().await?;
+
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    for (alg, crv) in [
+        (JwkKeyPairAlg::ES256, "P-256"),
+        (JwkKeyPairAlg::ES384, "P-384"),
+    ] {
+        let update_client = UpdateClientRequest {
+            id: client_id.to_string(),
+            name: new_client.name.clone(),
+            confidential: true,
+            redirect_uris: new_client.redirect_uris.clone(),
+            post_logout_redirect_uris: None,
+            allowed_origins: None,
+            enabled: true,
+            flows_enabled: vec!["client_credentials".to_string()],
+            access_token_alg: alg.clone(),
+            id_token_alg: alg.clone(),
+            refresh_token: false,
+            auth_code_lifetime: 60,
+            access_token_lifetime: 60,
+            scopes: vec!["openid".to_string()],
+            default_scopes: vec!["openid".to_string()],
+            challenges: None,
+            force_mfa: false,
+            require_par: false,
+            jwks: None,
+            jwks_uri: None,
+            require_request_object: false,
+            token_exchange_audiences: None,
+            token_exchange_scopes: None,
+            token_exchange_subject_types: None,
+            backchannel_logout_uri: None,
+            frontchannel_logout_uri: None,
+            subject_type: None,
+            sector_identifier_uri: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+            userinfo_signed_response_alg: None,
+            authorization_details_types: None,
+            tls_client_auth_method: None,
+            tls_client_auth_subject_dn: None,
+            tls_client_certificate_bound_access_tokens: false,
+            require_consent: false,
+            allow_email_login: false,
+            token_endpoint_auth_method: None,
+            request_uris: None,
+        };
+        let res = client
+            .put(&url_client)
+            .headers(auth_headers.clone())
+            .json(&update_client)
+            .send()
+            .await?;
+        check_status(res, 200).await?;
+
+        let res = client
+            .get(format!("{}/secret", url_client))
+            .headers(auth_headers.clone())
+            .send()
+            .await?;
+        let res = check_status(res, 200).await?;
+        let secret = res.json::<ClientSecretResponse>().await?.secret;
+
+        let body = TokenRequest {
+            grant_type: "client_credentials".to_string(),
+            code: None,
+            redirect_uri: None,
+            client_id: Some(client_id.to_string()),
+            client_secret: secret,
+            code_verifier: None,
+            username: None,
+            password: None,
+            refresh_token: None,
+            device_code: None,
+            client_assertion_type: None,
+            client_assertion: None,
+            subject_token: None,
+            subject_token_type: None,
+            actor_token: None,
+            actor_token_type: None,
+            requested_token_type: None,
+            audience: None,
+            scope: None,
+            authorization_details: None,
+            resource: None,
+        };
+        let res = client
+            .post(format!("{}/oidc/token", backend_url))
+            .form(&body)
+            .send()
+            .await?;
+        let res = check_status(res, 200).await?;
+        let ts = res.json::<TokenSet>().await?;
+
+        // the token must be signed with the client's alg and a published EC key
+        let header = ts.access_token.split('.').next().unwrap();
+        let header: serde_json::Value =
+            serde_json::from_slice(&base64_url_no_pad_decode(header).unwrap())?;
+        assert_eq!(header.get("alg").unwrap().as_str(), Some(alg.as_str()));
+        let kid = header.get("kid").unwrap().as_str().unwrap();
+        let key = certs
+            .keys
+            .iter()
+            .find(|key| key.kid.as_deref() == Some(kid))
+            .unwrap();
+        assert_eq!(key.kty.as_str(), "EC");
+        assert_eq!(key.crv.as_deref(), Some(crv));
+        assert!(key.x.is_some());
+        assert!(key.y.is_some());
+        assert!(key.validate_token_signature(&ts.access_token).is_ok());
+
+        let info = validate_token(TokenValidationRequest {
+            token: ts.access_token,
+        })
+        .await?;
+        assert!(info.active);
+    }
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
 // This test is a bit messy currently with some code reception and so one - WIP
 #[tokio::test]
 #[ignore]
@@ -416,6 +724,10 @@ async fn test_concurrent_logins() -> Result<(), Box<dyn Error>> {
         nonce: None,
         code_challenge: Some(challenge_plain.to_owned()),
         code_challenge_method: None,
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
     };
 
     let start = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
@@ -480,6 +792,18 @@ async fn test_password_flow() -> Result<(), Box<dyn Error>> {
         username: Some(USERNAME.to_string()),
         password: None,
         refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
     let client = reqwest::Client::new();
     let res = client.post(&url).form(&body).send().await?;
@@ -533,6 +857,18 @@ async fn test_password_flow() -> Result<(), Box<dyn Error>> {
         username: None,
         password: None,
         refresh_token: Some(ts.refresh_token.clone().unwrap()),
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
     let url = format!("{}/oidc/token", get_backend_url());
     let res = reqwest::Client::new().post(&url).form(&req).send().await?;
@@ -553,12 +889,8 @@ async fn test_password_flow() -> Result<(), Box<dyn Error>> {
 }
 
 #[tokio::test]
-async fn test_dpop() -> Result<(), Box<dyn Error>> {
-    let client = reqwest::Client::new();
-
+async fn test_revoke() -> Result<(), Box<dyn Error>> {
     let url = format!("{}/oidc/token", get_backend_url());
-
-    // token request itself
     let body = TokenRequest {
         grant_type: "password".to_string(),
         code: None,
@@ -569,162 +901,3140 @@ async fn test_dpop() -> Result<(), Box<dyn Error>> {
         username: Some(USERNAME.to_string()),
         password: Some(PASSWORD.to_string()),
         refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
+    let client = reqwest::Client::new();
+    let mut res = client.post(&url).form(&body).send().await?;
+    res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
 
-    // dpop header
-    let kp = ed25519_compact::KeyPair::generate();
-
-    let header = DPoPHeader {
-        typ: "dpop+jwt".to_string(),
-        alg: JwkKeyPairAlg::EdDSA,
-        jwk: JWKSPublicKey {
-            kty: JwkKeyPairType::OKP,
-            // DPoP request will not have the 'alg' here but one level higher
-            alg: None,
-            crv: Some("Ed25519".to_string()),
-            kid: None,
-            n: None,
-            e: None,
-            x: Some(base64_url_encode(kp.pk.as_slice())),
-        },
-        kid: None,
+    // rotate the refresh token once to have a successor in the same family
+    let mut body_refresh = TokenRequest {
+        grant_type: "refresh_token".to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(CLIENT_ID.to_string()),
+        client_secret: Some(CLIENT_SECRET.to_string()),
+        code_verifier: None,
+        username: None,
+        password: None,
+        refresh_token: ts.refresh_token.clone(),
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
-    let mut claims = DPoPClaims {
-        jti: "-BwC3ESc6acc2lTc".to_string(),
-        htm: http::Method::POST,
-        htu: DPOP_TOKEN_ENDPOINT.clone(),
-        iat: Utc::now().timestamp(),
-        // ath: None,
-        nonce: None,
+    let mut res = client.post(&url).form(&body_refresh).send().await?;
+    res = check_status(res, 200).await?;
+    let ts_rotated = res.json::<TokenSet>().await?;
+
+    let url_revoke = format!("{}/oidc/revoke", get_backend_url());
+    let mut req = TokenRevocationRequest {
+        token: ts.refresh_token.clone().unwrap(),
+        token_type_hint: Some("refresh_token".to_string()),
+        client_id: Some(CLIENT_ID.to_string()),
+        client_secret: None,
+        client_assertion_type: None,
+        client_assertion: None,
     };
+    let res = client.post(&url_revoke).form(&req).send().await?;
+    // should be 400 because of missing client secret
+    check_status(res, 400).await?;
 
-    let fingerprint = header.jwk.fingerprint().unwrap();
+    req.client_secret = Some(CLIENT_SECRET.to_string());
+    let res = client.post(&url_revoke).form(&req).send().await?;
+    check_status(res, 200).await?;
 
-    let header_json = serde_json::to_string(&header).unwrap();
-    let header_b64 = base64_url_no_pad_encode(header_json.as_bytes());
-    let claims_json = serde_json::to_string(&claims).unwrap();
-    let claims_b64 = base64_url_no_pad_encode(claims_json.as_bytes());
-    let mut dpop_token = format!("{}.{}", header_b64, claims_b64);
+    // revoking the rotated token must revoke its successor as well
+    body_refresh.refresh_token = ts_rotated.refresh_token;
+    let res = client.post(&url).form(&body_refresh).send().await?;
+    check_status(res, 404).await?;
 
-    let sig = kp.sk.sign(&dpop_token, Some(Noise::generate()));
-    let sig_b64 = base64_url_no_pad_encode(sig.as_ref());
-    write!(dpop_token, ".{}", sig_b64).unwrap();
-    println!("test signed token without nonce:\n{}", dpop_token);
+    // revoking it a 2nd time must not produce an error
+    let res = client.post(&url_revoke).form(&req).send().await?;
+    check_status(res, 200).await?;
 
-    let res = client
-        .post(&url)
-        .header(TOKEN_DPOP, &dpop_token)
-        .form(&body)
-        .send()
-        .await?;
-    // this should fail, because nonce is enforced, which we did not provide
-    assert_eq!(res.status(), 400);
-    let nonce = res
-        .headers()
-        .get(HEADER_DPOP_NONCE)
-        .unwrap()
-        .to_str()
-        .unwrap();
-    println!("nonce we should use: {}", nonce);
+    // revoke the access token
+    req.token = ts.access_token.clone();
+    req.token_type_hint = Some("access_token".to_string());
+    let res = client.post(&url_revoke).form(&req).send().await?;
+    check_status(res, 200).await?;
 
-    // insert the nonce and rebuild
-    claims.nonce = Some(nonce.to_string());
+    let info = validate_token(TokenValidationRequest {
+        token: ts.access_token,
+    })
+    .await?;
+    assert!(!info.active);
 
-    let claims_json = serde_json::to_string(&claims).unwrap();
-    let claims_b64 = base64_url_no_pad_encode(claims_json.as_bytes());
-    let mut dpop_token = format!("{}.{}", header_b64, claims_b64);
+    // id tokens cannot be revoked
+    req.token = ts.id_token.unwrap();
+    req.token_type_hint = None;
+    let res = client.post(&url_revoke).form(&req).send().await?;
+    check_status(res, 400).await?;
 
-    let sig = kp.sk.sign(&dpop_token, Some(Noise::generate()));
-    let sig_b64 = base64_url_no_pad_encode(sig.as_ref());
-    write!(dpop_token, ".{}", sig_b64).unwrap();
-    println!("test signed token with nonce:\n{}", dpop_token);
+    Ok(())
+}
 
-    let mut res = client
-        .post(&url)
-        .header(TOKEN_DPOP, &dpop_token)
-        .form(&body)
-        .send()
-        .await?;
-    // not it should be good
-    res = check_status(res, 200).await?;
-    // make sure nonce header is still there
-    let nonce_new = res
-        .headers()
-        .get(HEADER_DPOP_NONCE)
-        .unwrap()
-        .to_str()
-        .unwrap();
-    assert_eq!(nonce, nonce_new);
+#[tokio::test]
+async fn test_refresh_token_reuse() -> Result<(), Box<dyn Error>> {
+    let url = format!("{}/oidc/token", get_backend_url());
+    let client = reqwest::Client::new();
+    let grace_time = env::var("REFRESH_TOKEN_GRACE_TIME")
+        .map(|secs| secs.parse::<u64>().unwrap())
+        .unwrap_or(5);
 
-    let ts = res.json::<TokenSet>().await?;
-    assert_eq!(ts.token_type, JwtTokenType::DPoP);
-    let req = TokenValidationRequest {
-        token: ts.access_token.to_owned(),
-    };
-    let token_info = validate_token(req).await?;
-    assert!(token_info.cnf.is_some());
-    assert_eq!(token_info.cnf.unwrap().jkt, fingerprint);
+    // a single concurrent request within the grace time must still succeed
+    let (mut req, rotated) = rotate_refresh_token(&client, &url).await?;
+    let res = client.post(&url).form(&req).send().await?;
+    check_status(res, 200).await?;
 
-    // refresh it
-    time::sleep(Duration::from_secs(1)).await;
-    let req = TokenRequest {
-        grant_type: "refresh_token".to_string(),
+    // any other usage within the grace time must revoke the whole family
+    let res = client.post(&url).form(&req).send().await?;
+    check_status(res, 400).await?;
+    req.refresh_token = rotated;
+    let res = client.post(&url).form(&req).send().await?;
+    check_status(res, 404).await?;
+
+    // a replay after the grace time must revoke the whole family
+    let (mut req, rotated) = rotate_refresh_token(&client, &url).await?;
+    time::sleep(Duration::from_secs(grace_time + 1)).await;
+    let res = client.post(&url).form(&req).send().await?;
+    check_status(res, 400).await?;
+    req.refresh_token = rotated;
+    let res = client.post(&url).form(&req).send().await?;
+    check_status(res, 404).await?;
+
+    Ok(())
+}
+
+/// Starts a new token family and rotates it once. Returns the refresh request with the original
+/// refresh token and the rotated one.
+async fn rotate_refresh_token(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<(TokenRequest, Option<String>), Box<dyn Error>> {
+    let body = TokenRequest {
+        grant_type: "password".to_string(),
         code: None,
         redirect_uri: None,
         client_id: Some(CLIENT_ID.to_string()),
         client_secret: Some(CLIENT_SECRET.to_string()),
         code_verifier: None,
-        username: None,
-        password: None,
-        refresh_token: Some(ts.refresh_token.clone().unwrap()),
+        username: Some(USERNAME.to_string()),
+        password: Some(PASSWORD.to_string()),
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
-
-    // without DPoP header, it should fail
-    let res = reqwest::Client::new().post(&url).form(&req).send().await?;
-    assert_eq!(res.status(), 403);
-    let err = res.json::<ErrorResponse>().await.unwrap();
-    println!("{:?}", err);
-    assert_eq!(err.error, ErrorResponseType::Forbidden);
-    assert!(err.message.to_lowercase().contains("dpop"));
-
-    // now a proper refresh with DPoP header
-    let res = reqwest::Client::new()
-        .post(&url)
-        .header(TOKEN_DPOP, dpop_token)
-        .form(&req)
-        .send()
-        .await?;
-    assert_eq!(res.status(), 200);
-
+    let res = client.post(url).form(&body).send().await?;
+    let res = check_status(res, 200).await?;
     let ts = res.json::<TokenSet>().await?;
-    assert_eq!(ts.token_type, JwtTokenType::DPoP);
-    let req = TokenValidationRequest {
-        token: ts.access_token.to_owned(),
+
+    let req = TokenRequest {
+        grant_type: "refresh_token".to_string(),
+        username: None,
+        password: None,
+        refresh_token: ts.refresh_token.clone(),
+        ..body
     };
-    let token_info = validate_token(req).await?;
-    assert!(token_info.cnf.is_some());
-    assert_eq!(token_info.cnf.unwrap().jkt, fingerprint);
+    let res = client.post(url).form(&req).send().await?;
+    let res = check_status(res, 200).await?;
+    let rotated_ts = res.json::<TokenSet>().await?;
+    assert_ne!(ts.refresh_token, rotated_ts.refresh_token);
 
-    Ok(())
+    Ok((req, rotated_ts.refresh_token))
 }
 
 #[tokio::test]
-async fn test_authorization_code_flow_ephemeral_client() -> Result<(), Box<dyn Error>> {
+async fn test_par() -> Result<(), Box<dyn Error>> {
     let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
     let client = reqwest::Client::new();
 
-    // host our own ephemeral client
-    let handle = serve_ephemeral_client();
-    // make sure the http server starts and keeps running
-    tokio::time::sleep(Duration::from_millis(100)).await;
-    assert!(!handle.is_finished());
-    // our ephemeral client's id
-    let client_id = "http://127.0.0.1:10080/client";
-
-    // Step 1: GET /authorize for the CSRF token and simulate UI login
-    let challenge_plain = "oDXug9zfYqfz8ejcqMpALRPXfW8QhbKV2AVuScAt8xrLKDAmaRYQ4yRi2uqcH9ys";
-    let hash = digest::digest(&digest::SHA256, challenge_plain.as_bytes());
-    let challenge_s256 = base64_url_encode(hash.as_ref());
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "par_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("PAR Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["authorization_code".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: Some(vec!["S256".to_string()]),
+        force_mfa: false,
+        require_par: true,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    // a direct authorization request must be rejected now
+    let challenge = "oDXug9zfYqfz8ejcqMpALRPXfW8QhbKV2AVuScAt8xrLKDAmaRYQ4yRi2uqcH9ys";
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&code_challenge={}&code_challenge_method=S256",
+        backend_url, client_id, redirect_uri, challenge
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    // push the request
+    let url_par = format!("{}/oidc/par", backend_url);
+    let mut req = ParRequest {
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        redirect_uri: "http://localhost:3000/not_allowed".to_string(),
+        response_type: "code".to_string(),
+        scope: "openid email".to_string(),
+        state: Some("SomeState".to_string()),
+        code_challenge: Some(challenge.to_string()),
+        code_challenge_method: Some("S256".to_string()),
+        max_age: None,
+        prompt: None,
+        nonce: Some(get_rand(24)),
+        login_hint: None,
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client.post(&url_par).form(&req).send().await?;
+    // invalid redirect_uri
+    check_status(res, 400).await?;
+
+    req.redirect_uri = redirect_uri.to_string();
+    let mut res = client.post(&url_par).form(&req).send().await?;
+    res = check_status(res, 201).await?;
+    let par = res.json::<ParResponse>().await?;
+    assert!(par.request_uri.starts_with(PAR_REQUEST_URI_PREFIX));
+
+    // the request_uri is bound to the client
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request_uri={}",
+        backend_url, CLIENT_ID, par.request_uri
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    let mut res = client.post(&url_par).form(&req).send().await?;
+    res = check_status(res, 201).await?;
+    let par = res.json::<ParResponse>().await?;
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request_uri={}",
+        backend_url, client_id, par.request_uri
+    );
+    let mut res = client.get(&url_auth).send().await?;
+    res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    // the request_uri must only be usable once
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    // the login must use the pushed params, no matter what the browser sends afterward
+    let mut req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: None,
+        state: Some("TamperedState".to_string()),
+        nonce: None,
+        code_challenge: None,
+        code_challenge_method: None,
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+    let mut res = client
+        .post(&url_auth)
+        .headers(headers)
+        .json(&req_login)
+        .send()
+        .await?;
+    res = check_status(res, 202).await?;
+    let (_code, state) = code_state_from_headers(res)?;
+    assert_eq!(state.as_deref(), Some("SomeState"));
+
+    // a session without a PAR must not be able to log in to this client
+    let url_auth_plain = format!(
+        "{}/oidc/authorize?client_id=rauthy&redirect_uri={}/oidc/callback&response_type=code&code_challenge={}&code_challenge_method=S256",
+        backend_url, backend_url, challenge
+    );
+    let mut res = client.get(&url_auth_plain).send().await?;
+    res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+    req_login.code_challenge = Some(challenge.to_string());
+    req_login.code_challenge_method = Some("S256".to_string());
+    let res = client
+        .post(&url_auth_plain)
+        .headers(headers)
+        .json(&req_login)
+        .send()
+        .await?;
+    check_status(res, 400).await?;
+
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request_uri={}{}",
+        backend_url,
+        client_id,
+        PAR_REQUEST_URI_PREFIX,
+        get_rand(48)
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_request_object() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "jar_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("JAR Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let kp = ed25519_compact::KeyPair::generate();
+    let jwks = JWKS {
+        keys: vec![JWKSPublicKey {
+            kty: JwkKeyPairType::OKP,
+            alg: Some(JwkKeyPairAlg::EdDSA),
+            crv: Some("Ed25519".to_string()),
+            kid: Some("jar_test_key".to_string()),
+            n: None,
+            e: None,
+            x: Some(base64_url_no_pad_encode(kp.pk.as_slice())),
+            y: None,
+        }],
+    };
+
+    let mut update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["authorization_code".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: Some(vec!["S256".to_string()]),
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: true,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    // a request object cannot be required without any client keys
+    check_status(res, 400).await?;
+
+    update_client.jwks = Some(serde_json::to_string(&jwks)?);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    // a plain authorization request must be rejected now
+    let challenge = "oDXug9zfYqfz8ejcqMpALRPXfW8QhbKV2AVuScAt8xrLKDAmaRYQ4yRi2uqcH9ys";
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&code_challenge={}&code_challenge_method=S256",
+        backend_url, client_id, redirect_uri, challenge
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    let claims = serde_json::json!({
+        "iss": client_id,
+        "exp": Utc::now().timestamp() + 60,
+        "client_id": client_id,
+        "redirect_uri": redirect_uri,
+        "response_type": "code",
+        "scope": "openid email",
+        "state": "SomeState",
+        "code_challenge": challenge,
+        "code_challenge_method": "S256",
+        "nonce": get_rand(24),
+    });
+    let request = build_jwt_ed25519(&kp, "jar_test_key", &claims);
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request={}",
+        backend_url, client_id, request
+    );
+    let mut res = client.get(&url_auth).send().await?;
+    res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    // the code must be issued for the params from the request object only
+    let req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: None,
+        state: Some("TamperedState".to_string()),
+        nonce: None,
+        code_challenge: None,
+        code_challenge_method: None,
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+    let mut res = client
+        .post(&url_auth)
+        .headers(headers)
+        .json(&req_login)
+        .send()
+        .await?;
+    res = check_status(res, 202).await?;
+    let (_code, state) = code_state_from_headers(res)?;
+    assert_eq!(state.as_deref(), Some("SomeState"));
+
+    // the full params may be given additionally, but the request object takes precedence
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&request={}",
+        backend_url, client_id, redirect_uri, request
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 200).await?;
+
+    // signed with an unknown key
+    let request_invalid = build_jwt_ed25519(
+        &ed25519_compact::KeyPair::generate(),
+        "jar_test_key",
+        &claims,
+    );
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request={}",
+        backend_url, client_id, request_invalid
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    // the request object is bound to the client
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request={}",
+        backend_url, CLIENT_ID, request
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    // expired request object
+    let mut claims_expired = claims.clone();
+    claims_expired["exp"] = serde_json::json!(Utc::now().timestamp() - 10);
+    let request_expired = build_jwt_ed25519(&kp, "jar_test_key", &claims_expired);
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request={}",
+        backend_url, client_id, request_expired
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    // request objects by reference must only be fetched from registered https URIs
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request_uri=http://localhost:3000/request.jwt",
+        backend_url, client_id
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&request_uri=https://example.com/request.jwt",
+        backend_url, client_id
+    );
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    // the match must be exact
+    update_client.request_uris = Some(vec!["https://example.com/other.jwt".to_string()]);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+    let res = client.get(&url_auth).send().await?;
+    check_status(res, 400).await?;
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_assertion() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "private_key_jwt_test";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("Private Key JWT Test".to_string()),
+        confidential: true,
+        redirect_uris: vec!["http://localhost:3000/oidc/callback".to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let kp = ed25519_compact::KeyPair::generate();
+    let jwks = JWKS {
+        keys: vec![JWKSPublicKey {
+            kty: JwkKeyPairType::OKP,
+            alg: Some(JwkKeyPairAlg::EdDSA),
+            crv: Some("Ed25519".to_string()),
+            kid: Some("assertion_test_key".to_string()),
+            n: None,
+            e: None,
+            x: Some(base64_url_no_pad_encode(kp.pk.as_slice())),
+            y: None,
+        }],
+    };
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: true,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["client_credentials".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: None,
+        force_mfa: false,
+        require_par: false,
+        jwks: Some(serde_json::to_string(&jwks)?),
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: Some("private_key_jwt".to_string()),
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    // a `private_key_jwt` client must not have a secret
+    let res = client
+        .get(format!("{}/secret", url_client))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let secret = res.json::<ClientSecretResponse>().await?;
+    assert!(secret.secret.is_none());
+
+    let claims = serde_json::json!({
+        "iss": client_id,
+        "sub": client_id,
+        "aud": format!("{}/oidc/token", backend_url),
+        "exp": Utc::now().timestamp() + 60,
+        "jti": get_rand(24),
+    });
+    let mut body = TokenRequest {
+        grant_type: "client_credentials".to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: None,
+        client_secret: None,
+        code_verifier: None,
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: Some(CLIENT_ASSERTION_TYPE_JWT_BEARER.to_string()),
+        client_assertion: Some(build_jwt_ed25519(&kp, "assertion_test_key", &claims)),
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let url = format!("{}/oidc/token", backend_url);
+    let res = client.post(&url).form(&body).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    assert!(!ts.access_token.is_empty());
+    assert!(ts.refresh_token.is_none());
+
+    // each assertion must only be used once
+    let res = client.post(&url).form(&body).send().await?;
+    check_status(res, 401).await?;
+
+    // signed with an unknown key
+    let mut claims_new = claims.clone();
+    claims_new["jti"] = serde_json::json!(get_rand(24));
+    body.client_assertion = Some(build_jwt_ed25519(
+        &ed25519_compact::KeyPair::generate(),
+        "assertion_test_key",
+        &claims_new,
+    ));
+    let res = client.post(&url).form(&body).send().await?;
+    check_status(res, 401).await?;
+
+    // the audience must be this issuer
+    claims_new["aud"] = serde_json::json!("https://example.com");
+    body.client_assertion = Some(build_jwt_ed25519(&kp, "assertion_test_key", &claims_new));
+    let res = client.post(&url).form(&body).send().await?;
+    check_status(res, 401).await?;
+
+    // other auth methods than the registered one must be rejected
+    body.client_id = Some(client_id.to_string());
+    body.client_secret = Some("SomeGuessedSecret123".to_string());
+    body.client_assertion_type = None;
+    body.client_assertion = None;
+    let res = client.post(&url).form(&body).send().await?;
+    check_status(res, 401).await?;
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+fn build_jwt_ed25519(
+    kp: &ed25519_compact::KeyPair,
+    kid: &str,
+    claims: &serde_json::Value,
+) -> String {
+    let header = serde_json::json!({
+        "alg": "EdDSA",
+        "kid": kid,
+    });
+    let header_b64 = base64_url_no_pad_encode(header.to_string().as_bytes());
+    let claims_b64 = base64_url_no_pad_encode(claims.to_string().as_bytes());
+    let mut token = format!("{}.{}", header_b64, claims_b64);
+
+    let sig = kp.sk.sign(&token, Some(Noise::generate()));
+    write!(token, ".{}", base64_url_no_pad_encode(sig.as_ref())).unwrap();
+    token
+}
+
+#[tokio::test]
+async fn test_token_exchange() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "token_exchange_test";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("Token Exchange Test".to_string()),
+        confidential: true,
+        redirect_uris: vec!["http://localhost:3000/oidc/callback".to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: true,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec![
+            GRANT_TYPE_TOKEN_EXCHANGE.to_string(),
+            "password".to_string(),
+        ],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: None,
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: Some(vec!["downstream_service".to_string()]),
+        token_exchange_scopes: None,
+        token_exchange_subject_types: Some(vec!["access_token".to_string()]),
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let res = client
+        .get(format!("{}/secret", url_client))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let secret = res.json::<ClientSecretResponse>().await?.secret;
+
+    // the subject is a user access token for this client with the `openid` scope
+    let url = format!("{}/oidc/token", backend_url);
+    let subject_body = TokenRequest {
+        grant_type: "password".to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: secret.clone(),
+        code_verifier: None,
+        username: Some(USERNAME.to_string()),
+        password: Some(PASSWORD.to_string()),
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client.post(&url).form(&subject_body).send().await?;
+    let res = check_status(res, 200).await?;
+    let subject = res.json::<TokenSet>().await?;
+
+    let mut body = TokenRequest {
+        grant_type: GRANT_TYPE_TOKEN_EXCHANGE.to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: secret,
+        code_verifier: None,
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: Some(subject.access_token.clone()),
+        subject_token_type: Some(TOKEN_TYPE_ACCESS_TOKEN.to_string()),
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: Some("downstream_service".to_string()),
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client.post(&url).form(&body).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    assert_eq!(
+        ts.issued_token_type.as_deref(),
+        Some(TOKEN_TYPE_ACCESS_TOKEN)
+    );
+    assert!(ts.id_token.is_none());
+    assert!(ts.refresh_token.is_none());
+    let claims = token_claims(&ts.access_token);
+    assert_eq!(claims["aud"], "downstream_service");
+    assert_eq!(claims["azp"], client_id);
+    assert_eq!(claims["scope"], "openid");
+    assert!(claims.get("act").is_none());
+
+    // tokens of other clients cannot be exchanged
+    body.subject_token = Some(get_token_set().await.access_token);
+    let res = client.post(&url).form(&body).send().await?;
+    check_status(res, 401).await?;
+    body.subject_token = Some(subject.access_token.clone());
+
+    // the audience must be allowed by the client policy
+    body.audience = Some("other_service".to_string());
+    let res = client.post(&url).form(&body).send().await?;
+    check_status(res, 400).await?;
+
+    // scopes can only be narrowed down from the subject token
+    body.audience = None;
+    body.scope = Some("openid email".to_string());
+    let res = client.post(&url).form(&body).send().await?;
+    check_status(res, 400).await?;
+    body.scope = None;
+
+    // ID tokens are not accepted by the client policy
+    body.subject_token = subject.id_token.clone();
+    body.subject_token_type = Some(TOKEN_TYPE_ID_TOKEN.to_string());
+    let res = client.post(&url).form(&body).send().await?;
+    check_status(res, 400).await?;
+    body.subject_token = Some(subject.access_token.clone());
+    body.subject_token_type = Some(TOKEN_TYPE_ACCESS_TOKEN.to_string());
+
+    // delegation with the token of another client as the actor
+    let actor_body = TokenRequest {
+        grant_type: "client_credentials".to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(CLIENT_ID.to_string()),
+        client_secret: Some(CLIENT_SECRET.to_string()),
+        code_verifier: None,
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client.post(&url).form(&actor_body).send().await?;
+    let res = check_status(res, 200).await?;
+    let actor = res.json::<TokenSet>().await?;
+
+    body.actor_token = Some(actor.access_token);
+    let res = client.post(&url).form(&body).send().await?;
+    // the `actor_token_type` is mandatory
+    check_status(res, 400).await?;
+
+    body.actor_token_type = Some(TOKEN_TYPE_ACCESS_TOKEN.to_string());
+    let res = client.post(&url).form(&body).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    let claims = token_claims(&ts.access_token);
+    assert_eq!(claims["aud"], client_id);
+    assert_eq!(claims["act"]["sub"], CLIENT_ID);
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+fn token_claims(token: &str) -> serde_json::Value {
+    let claims = token.split('.').nth(1).unwrap();
+    let bytes = base64_url_no_pad_decode(claims).unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn test_backchannel_logout() -> Result<(), Box<dyn Error>> {
+    let (tx, rx) = mpsc::channel::<String>();
+    let handle = serve_backchannel_logout(tx);
+    time::sleep(Duration::from_secs(1)).await;
+    assert!(!handle.is_finished());
+
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "backchannel_logout_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("Backchannel Logout Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["authorization_code".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: Some(vec!["S256".to_string()]),
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: Some("http://127.0.0.1:10081/logout".to_string()),
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    // login with a new session
+    let challenge_plain = get_rand(48);
+    let hash = digest::digest(&digest::SHA256, challenge_plain.as_bytes());
+    let challenge_s256 = base64_url_encode(hash.as_ref());
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&code_challenge={}&code_challenge_method=S256",
+        backend_url, client_id, redirect_uri, challenge_s256
+    );
+    let res = reqwest::get(&url_auth).await?;
+    let res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    let req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: None,
+        state: None,
+        nonce: None,
+        code_challenge: Some(challenge_s256),
+        code_challenge_method: Some("S256".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+    let res = client
+        .post(&url_auth)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    let (code, _) = code_state_from_headers(res)?;
+
+    let req_token = TokenRequest {
+        grant_type: "authorization_code".to_string(),
+        code: Some(code),
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        code_verifier: Some(challenge_plain),
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client
+        .post(format!("{}/oidc/token", backend_url))
+        .form(&req_token)
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+
+    // the id token must be linked to the session
+    let id_claims = token_claims(ts.id_token.as_ref().unwrap());
+    let sid = id_claims.get("sid").unwrap().as_str().unwrap().to_string();
+    let sub = id_claims.get("sub").unwrap().as_str().unwrap().to_string();
+
+    // logging out the session must notify the client
+    let res = client
+        .post(format!("{}/oidc/logout", backend_url))
+        .headers(headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let logout_token = rx.recv_timeout(Duration::from_secs(10))?;
+    let claims = token_claims(&logout_token);
+    assert_eq!(
+        claims.get("iss").unwrap().as_str(),
+        Some(get_issuer().as_str())
+    );
+    assert_eq!(claims.get("aud").unwrap().as_str(), Some(client_id));
+    assert_eq!(claims.get("sub").unwrap().as_str(), Some(sub.as_str()));
+    assert_eq!(claims.get("sid").unwrap().as_str(), Some(sid.as_str()));
+    assert!(claims.get("jti").is_some());
+    assert!(claims.get("nonce").is_none());
+    assert!(claims
+        .get("events")
+        .unwrap()
+        .get("http://schemas.openid.net/event/backchannel-logout")
+        .is_some());
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_frontchannel_logout() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    // the post logout redirect must be checked without following it
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "frontchannel_logout_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("Frontchannel Logout Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: Some(vec!["http://localhost:3000/logout/*".to_string()]),
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["authorization_code".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: Some(vec!["S256".to_string()]),
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: Some("http://localhost:3000/oidc/frontchannel_logout".to_string()),
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    // login with a new session
+    let challenge_plain = get_rand(48);
+    let hash = digest::digest(&digest::SHA256, challenge_plain.as_bytes());
+    let challenge_s256 = base64_url_encode(hash.as_ref());
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&code_challenge={}&code_challenge_method=S256",
+        backend_url, client_id, redirect_uri, challenge_s256
+    );
+    let res = reqwest::get(&url_auth).await?;
+    let res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    let req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: None,
+        state: None,
+        nonce: None,
+        code_challenge: Some(challenge_s256),
+        code_challenge_method: Some("S256".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+    let res = client
+        .post(&url_auth)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    let (code, _) = code_state_from_headers(res)?;
+
+    let req_token = TokenRequest {
+        grant_type: "authorization_code".to_string(),
+        code: Some(code),
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        code_verifier: Some(challenge_plain),
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client
+        .post(format!("{}/oidc/token", backend_url))
+        .form(&req_token)
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    let id_token = ts.id_token.unwrap();
+    let sid = token_claims(&id_token)
+        .get("sid")
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // the logout page must contain the frontchannel uri and the validated redirect
+    let url_logout = format!(
+        "{}/oidc/logout?id_token_hint={}&post_logout_redirect_uri=http://localhost:3000/logout/done&state=xyz",
+        backend_url, id_token
+    );
+    let res = client
+        .get(&url_logout)
+        .headers(headers.clone())
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let body = res.text().await?;
+    assert!(body.contains("http://localhost:3000/oidc/frontchannel_logout?iss="));
+    assert!(body.contains(&format!("sid={}", sid)));
+    assert!(body.contains("http://localhost:3000/logout/done?state=xyz"));
+
+    // a redirect which is not allowed for the client must be rejected
+    let res = client
+        .post(format!(
+            "{}/oidc/logout?id_token_hint={}&post_logout_redirect_uri=http://localhost:3001/logout",
+            backend_url, id_token
+        ))
+        .headers(headers.clone())
+        .send()
+        .await?;
+    check_status(res, 400).await?;
+
+    // without an id_token_hint, no redirect is allowed at all
+    let res = client
+        .post(format!(
+            "{}/oidc/logout?post_logout_redirect_uri=http://localhost:3000/logout/done",
+            backend_url
+        ))
+        .headers(headers.clone())
+        .send()
+        .await?;
+    check_status(res, 400).await?;
+
+    let res = client
+        .post(format!("{}/oidc/logout?id_token_hint={}&post_logout_redirect_uri=http://localhost:3000/logout/done&state=xyz", backend_url, id_token))
+        .headers(headers)
+        .send()
+        .await?;
+    let res = check_status(res, 301).await?;
+    assert_eq!(
+        res.headers()
+            .get(reqwest::header::LOCATION)
+            .unwrap()
+            .to_str()?,
+        "http://localhost:3000/logout/done?state=xyz"
+    );
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pairwise_subject() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(format!("{}/.well-known/openid-configuration", backend_url))
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let well_known = res.json::<serde_json::Value>().await?;
+    assert!(well_known
+        .get("subject_types_supported")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::Value::String("pairwise".to_string())));
+
+    // the default client uses public subjects, which are the user id
+    let user_id = token_claims(get_token_set().await.id_token.as_ref().unwrap())
+        .get("sub")
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "pairwise_subject_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("Pairwise Subject Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["authorization_code".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: Some(vec!["S256".to_string()]),
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: Some("pairwise".to_string()),
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let challenge_plain = get_rand(48);
+    let hash = digest::digest(&digest::SHA256, challenge_plain.as_bytes());
+    let challenge_s256 = base64_url_encode(hash.as_ref());
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&code_challenge={}&code_challenge_method=S256",
+        backend_url, client_id, redirect_uri, challenge_s256
+    );
+    let res = reqwest::get(&url_auth).await?;
+    let res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    let req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: None,
+        state: None,
+        nonce: None,
+        code_challenge: Some(challenge_s256),
+        code_challenge_method: Some("S256".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+    let res = client
+        .post(&url_auth)
+        .headers(headers)
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    let (code, _) = code_state_from_headers(res)?;
+
+    let req_token = TokenRequest {
+        grant_type: "authorization_code".to_string(),
+        code: Some(code),
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        code_verifier: Some(challenge_plain),
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client
+        .post(format!("{}/oidc/token", backend_url))
+        .form(&req_token)
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+
+    // the client must only ever see its pairwise subject
+    let sub = token_claims(ts.id_token.as_ref().unwrap())
+        .get("sub")
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_ne!(sub, user_id);
+    assert_eq!(
+        token_claims(&ts.access_token).get("sub").unwrap().as_str(),
+        Some(sub.as_str())
+    );
+
+    // the userinfo endpoint must still find the user behind it
+    let res = client
+        .get(format!("{}/oidc/userinfo", backend_url))
+        .header(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", ts.access_token),
+        )
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let userinfo = res.json::<serde_json::Value>().await?;
+    assert_eq!(userinfo.get("sub").unwrap().as_str(), Some(sub.as_str()));
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_user_consent() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    let user_id = token_claims(get_token_set().await.id_token.as_ref().unwrap())
+        .get("sub")
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "user_consent_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("User Consent Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["authorization_code".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: Some(vec!["plain".to_string()]),
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: true,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let challenge = get_rand(48);
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&code_challenge={}&code_challenge_method=plain&state=MyState",
+        backend_url, client_id, redirect_uri, challenge
+    );
+    let mut req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: Some(vec!["openid".to_string(), "email".to_string()]),
+        state: Some("MyState".to_string()),
+        nonce: None,
+        code_challenge: Some(challenge.clone()),
+        code_challenge_method: Some("plain".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+
+    // the first login must end up on the consent page instead of the client
+    let res = client.get(&url_auth).send().await?;
+    let res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+    let res = client
+        .post(&url_auth)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    let loc = res
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .unwrap()
+        .to_str()?;
+    let (_, consent_id) = loc.split_once("/oidc/consent?id=").unwrap();
+
+    let res = client
+        .get(format!("{}/oidc/consent?id={}", backend_url, consent_id))
+        .headers(headers.clone())
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let req_consent = ConsentRequest {
+        id: consent_id.to_string(),
+        accept: true,
+    };
+    let res = client
+        .post(format!("{}/oidc/consent", backend_url))
+        .headers(headers)
+        .json(&req_consent)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    let (code, state) = code_state_from_headers(res)?;
+    assert!(!code.is_empty());
+    assert_eq!(state.as_deref(), Some("MyState"));
+
+    let url_consents = format!("{}/users/{}/consents", backend_url, user_id);
+    let res = client
+        .get(&url_consents)
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let consents = res.json::<Vec<UserConsentResponse>>().await?;
+    let consent = consents.iter().find(|c| c.client_id == client_id).unwrap();
+    assert!(consent.scopes.contains(&"openid".to_string()));
+    assert!(consent.scopes.contains(&"email".to_string()));
+
+    // already granted scopes must not ask again
+    let res = client.get(&url_auth).send().await?;
+    let res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+    let res = client
+        .post(&url_auth)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    code_state_from_headers(res)?;
+
+    // 'prompt=consent' asks in any case and a decline must be forwarded to the client
+    req_login.prompt = Some("consent".to_string());
+    let res = client
+        .post(&url_auth)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    let loc = res
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .unwrap()
+        .to_str()?;
+    let (_, consent_id) = loc.split_once("/oidc/consent?id=").unwrap();
+    let req_consent = ConsentRequest {
+        id: consent_id.to_string(),
+        accept: false,
+    };
+    let res = client
+        .post(format!("{}/oidc/consent", backend_url))
+        .headers(headers)
+        .json(&req_consent)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    assert_eq!(
+        res.headers()
+            .get(reqwest::header::LOCATION)
+            .unwrap()
+            .to_str()?,
+        format!("{}?error=access_denied&state=MyState", redirect_uri)
+    );
+
+    // revoke the grant
+    let res = client
+        .delete(format!("{}/{}", url_consents, client_id))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+    let res = client
+        .delete(format!("{}/{}", url_consents, client_id))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    check_status(res, 404).await?;
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_email_login() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "email_login_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("E-Mail Login Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let challenge = get_rand(48);
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&code_challenge={}&code_challenge_method=plain",
+        backend_url, client_id, redirect_uri, challenge
+    );
+    let url_email = format!("{}/oidc/authorize/email", backend_url);
+    let url_email_finish = format!("{}/oidc/authorize/email/finish", backend_url);
+    let req_login = LoginRequest {
+        email: "email_login_unknown@localhost.de".to_string(),
+        password: None,
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: Some(vec!["openid".to_string()]),
+        state: None,
+        nonce: None,
+        code_challenge: Some(challenge.clone()),
+        code_challenge_method: Some("plain".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+
+    let res = client.get(&url_auth).send().await?;
+    let res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    // the E-Mail login is disabled by default
+    let res = client
+        .post(&url_email)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    check_status(res, 400).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["authorization_code".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: Some(vec!["plain".to_string()]),
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: true,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    // unknown users must get the same response to prevent username enumeration
+    let res = client
+        .post(&url_email)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let body = res.json::<EmailLoginResponse>().await?;
+    assert!(body.exp > Utc::now().timestamp());
+
+    // each address is rate-limited
+    let res = client
+        .post(&url_email)
+        .headers(headers.clone())
+        .json(&req_login)
+        .send()
+        .await?;
+    check_status(res, 429).await?;
+
+    // no login is pending for an unknown user
+    let req_finish = EmailLoginFinishRequest {
+        code: Some("123456".to_string()),
+        magic_link_id: None,
+    };
+    let res = client
+        .post(&url_email_finish)
+        .headers(headers.clone())
+        .json(&req_finish)
+        .send()
+        .await?;
+    check_status(res, 404).await?;
+
+    // a link from another browser must not be accepted
+    let res = client
+        .get(format!("{}/oidc/email?id={}", backend_url, get_rand(64)))
+        .send()
+        .await?;
+    check_status(res, 404).await?;
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_jwe_responses() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(format!("{}/.well-known/openid-configuration", backend_url))
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let well_known = res.json::<serde_json::Value>().await?;
+    assert!(well_known
+        .get("id_token_encryption_alg_values_supported")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::Value::String("ECDH-ES".to_string())));
+
+    // the client's encryption keys, which will be registered with its JWKS
+    let mut ec_key = jwk::Jwk::generate_ec_key(jwk::alg::ec::EcCurve::P256)?;
+    ec_key.set_key_use("enc");
+    let mut rsa_key = jwk::Jwk::generate_rsa_key(2048)?;
+    rsa_key.set_key_use("enc");
+    let jwks = serde_json::json!({
+        "keys": [
+            serde_json::Value::Object(ec_key.to_public_key()?.as_ref().clone()),
+            serde_json::Value::Object(rsa_key.to_public_key()?.as_ref().clone()),
+        ]
+    });
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "jwe_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("JWE Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let mut update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["authorization_code".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        default_scopes: vec!["openid".to_string(), "email".to_string()],
+        challenges: Some(vec!["S256".to_string()]),
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: Some("ECDH-ES".to_string()),
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: Some("RSA-OAEP-256".to_string()),
+        userinfo_encrypted_response_enc: Some("A256GCM".to_string()),
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+
+    // encryption without any registered key must be rejected
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 400).await?;
+
+    update_client.jwks = Some(jwks.to_string());
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let challenge_plain = get_rand(48);
+    let hash = digest::digest(&digest::SHA256, challenge_plain.as_bytes());
+    let challenge_s256 = base64_url_encode(hash.as_ref());
+    let url_auth = format!(
+        "{}/oidc/authorize?client_id={}&redirect_uri={}&response_type=code&code_challenge={}&code_challenge_method=S256",
+        backend_url, client_id, redirect_uri, challenge_s256
+    );
+    let res = reqwest::get(&url_auth).await?;
+    let res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    let req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: None,
+        state: None,
+        nonce: None,
+        code_challenge: Some(challenge_s256),
+        code_challenge_method: Some("S256".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
+    };
+    let res = client
+        .post(&url_auth)
+        .headers(headers)
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    let (code, _) = code_state_from_headers(res)?;
+
+    let req_token = TokenRequest {
+        grant_type: "authorization_code".to_string(),
+        code: Some(code),
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        code_verifier: Some(challenge_plain),
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client
+        .post(format!("{}/oidc/token", backend_url))
+        .form(&req_token)
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+
+    // the id token is a nested JWT: signed first and encrypted to the client's EC key
+    let id_token = ts.id_token.unwrap();
+    assert_eq!(id_token.split('.').count(), 5);
+    let decrypter = josekit::jwe::ECDH_ES.decrypter_from_jwk(&ec_key)?;
+    let (payload, header) = josekit::jwe::deserialize_compact(&id_token, &decrypter)?;
+    assert_eq!(header.content_encryption(), Some("A128CBC-HS256"));
+    assert_eq!(header.content_type(), Some("JWT"));
+    let signed = String::from_utf8(payload)?;
+    let claims = token_claims(&signed);
+    assert_eq!(claims.get("aud").unwrap().as_str(), Some(client_id));
+    assert!(claims.get("sub").is_some());
+
+    // the userinfo is encrypted to the RSA key
+    let res = client
+        .get(format!("{}/oidc/userinfo", backend_url))
+        .header(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", ts.access_token),
+        )
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    assert_eq!(
+        res.headers().get(reqwest::header::CONTENT_TYPE).unwrap(),
+        "application/jwt"
+    );
+    let jwe = res.text().await?;
+    let decrypter = josekit::jwe::RSA_OAEP_256.decrypter_from_jwk(&rsa_key)?;
+    let (payload, header) = josekit::jwe::deserialize_compact(&jwe, &decrypter)?;
+    assert_eq!(header.content_encryption(), Some("A256GCM"));
+    let userinfo = serde_json::from_slice::<serde_json::Value>(&payload)?;
+    assert_eq!(userinfo.get("sub"), claims.get("sub"));
+    assert_eq!(userinfo.get("email").unwrap().as_str(), Some(USERNAME));
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_signed_userinfo() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(format!("{}/.well-known/openid-configuration", backend_url))
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let well_known = res.json::<serde_json::Value>().await?;
+    assert!(well_known
+        .get("userinfo_signing_alg_values_supported")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::Value::String("ES256".to_string())));
+    let issuer = well_known.get("issuer").unwrap().as_str().unwrap();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "userinfo_signed_test";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("Userinfo Signed Test".to_string()),
+        confidential: false,
+        redirect_uris: vec!["http://localhost:3000/oidc/callback".to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec!["password".to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        default_scopes: vec!["openid".to_string(), "email".to_string()],
+        challenges: None,
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: Some(JwkKeyPairAlg::ES256),
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let body = TokenRequest {
+        grant_type: "password".to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        code_verifier: None,
+        username: Some(USERNAME.to_string()),
+        password: Some(PASSWORD.to_string()),
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    let res = client
+        .post(format!("{}/oidc/token", backend_url))
+        .form(&body)
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+
+    let res = client
+        .get(format!("{}/oidc/userinfo", backend_url))
+        .header(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", ts.access_token),
+        )
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    assert_eq!(
+        res.headers().get(reqwest::header::CONTENT_TYPE).unwrap(),
+        "application/jwt"
+    );
+    let jwt = res.text().await?;
+
+    // the userinfo must be signed with the configured alg and a published key
+    let header = jwt.split('.').next().unwrap();
+    let header: serde_json::Value =
+        serde_json::from_slice(&base64_url_no_pad_decode(header).unwrap())?;
+    assert_eq!(header.get("alg").unwrap().as_str(), Some("ES256"));
+    let kid = header.get("kid").unwrap().as_str().unwrap();
+
+    let res = reqwest::get(format!("{}/oidc/certs", backend_url)).await?;
+    let res = check_status(res, 200).await?;
+    let certs = res.json::<JWKS>().await?;
+    let key = certs
+        .keys
+        .iter()
+        .find(|key| key.kid.as_deref() == Some(kid))
+        .unwrap();
+    assert!(key.validate_token_signature(&jwt).is_ok());
+
+    let claims = token_claims(&jwt);
+    assert_eq!(claims.get("iss").unwrap().as_str(), Some(issuer));
+    assert_eq!(claims.get("aud").unwrap().as_str(), Some(client_id));
+    assert_eq!(claims.get("sub"), token_claims(&ts.access_token).get("sub"));
+    assert_eq!(claims.get("email").unwrap().as_str(), Some(USERNAME));
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rich_authorization_requests() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "rar_test";
+    let redirect_uri = "http://localhost:3000/oidc/callback";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("RAR Test".to_string()),
+        confidential: false,
+        redirect_uris: vec![redirect_uri.to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec![
+            "authorization_code".to_string(),
+            "refresh_token".to_string(),
+        ],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: true,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: None,
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: Some(vec!["payment_initiation".to_string()]),
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let details = r#"[{"type":"payment_initiation","actions":["initiate"],"instructedAmount":{"currency":"EUR","amount":"123.50"}}]"#;
+    let url_auth = format!("{}/oidc/authorize", backend_url);
+    let query = |details: &str| {
+        vec![
+            ("client_id", client_id.to_string()),
+            ("redirect_uri", redirect_uri.to_string()),
+            ("response_type", "code".to_string()),
+            ("authorization_details", details.to_string()),
+        ]
+    };
+
+    // types, which are not allowed for the client, must be rejected
+    let res = client
+        .get(&url_auth)
+        .query(&query(r#"[{"type":"document_signing"}]"#))
+        .send()
+        .await?;
+    check_status(res, 400).await?;
+
+    let res = client.get(&url_auth).query(&query(details)).send().await?;
+    let res = check_status(res, 200).await?;
+    let headers = cookie_csrf_headers_from_res(res).await?;
+
+    let req_login = LoginRequest {
+        email: USERNAME.to_string(),
+        password: Some(PASSWORD.to_string()),
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes: None,
+        state: None,
+        nonce: None,
+        code_challenge: None,
+        code_challenge_method: None,
+        acr_values: None,
+        authorization_details: Some(details.to_string()),
+        resource: None,
+        prompt: None,
+    };
+    let res = client
+        .post(&url_auth)
+        .headers(headers)
+        .json(&req_login)
+        .send()
+        .await?;
+    let res = check_status(res, 202).await?;
+    let (code, _) = code_state_from_headers(res)?;
+
+    // the details can only be narrowed down at the token endpoint
+    let mut req_token = TokenRequest {
+        grant_type: "authorization_code".to_string(),
+        code: Some(code.to_string()),
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        code_verifier: None,
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: Some(r#"[{"type":"payment_initiation"}]"#.to_string()),
+        resource: None,
+    };
+    let url_token = format!("{}/oidc/token", backend_url);
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    check_status(res, 400).await?;
+
+    req_token.authorization_details = None;
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+
+    let granted = AuthorizationDetails::try_from_str(details).unwrap();
+    assert_eq!(ts.authorization_details.as_ref(), Some(&granted));
+    let claims = token_claims(&ts.access_token);
+    assert_eq!(
+        claims.get("authorization_details").unwrap(),
+        &serde_json::to_value(&granted)?
+    );
+
+    let info = validate_token(TokenValidationRequest {
+        token: ts.access_token,
+    })
+    .await?;
+    assert!(info.active);
+    assert_eq!(info.authorization_details.as_ref(), Some(&granted));
+
+    // a refresh must keep the granted details
+    req_token.grant_type = "refresh_token".to_string();
+    req_token.code = None;
+    req_token.refresh_token = ts.refresh_token;
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    assert_eq!(ts.authorization_details, Some(granted));
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resource_indicators() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    let resource_uri = "https://api.example.com/orders";
+    let req_resource = ResourceRequest {
+        name: "Orders API".to_string(),
+        uri: resource_uri.to_string(),
+        scopes: vec!["openid".to_string(), "groups".to_string()],
+    };
+    let url_resources = format!("{}/resources", backend_url);
+    let res = client
+        .post(&url_resources)
+        .headers(auth_headers.clone())
+        .json(&req_resource)
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let resource = res.json::<ResourceResponse>().await?;
+    assert_eq!(resource.uri, resource_uri);
+    assert_eq!(resource.scopes, req_resource.scopes);
+
+    // the same uri cannot be registered twice
+    let res = client
+        .post(&url_resources)
+        .headers(auth_headers.clone())
+        .json(&req_resource)
+        .send()
+        .await?;
+    check_status(res, 400).await?;
+
+    let url_token = format!("{}/oidc/token", backend_url);
+    let mut req_token = TokenRequest {
+        grant_type: "password".to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(CLIENT_ID.to_string()),
+        client_secret: Some(CLIENT_SECRET.to_string()),
+        code_verifier: None,
+        username: Some(USERNAME.to_string()),
+        password: Some(PASSWORD.to_string()),
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: Some("https://api.example.com/unknown".to_string()),
+    };
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    check_status(res, 400).await?;
+
+    req_token.resource = Some(resource_uri.to_string());
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    let claims = token_claims(&ts.access_token);
+    assert_eq!(claims.get("aud").unwrap().as_str(), Some(resource_uri));
+    assert_eq!(claims.get("scope").unwrap().as_str(), Some("openid"));
+
+    // without a resource, the refresh token issues tokens for the client itself
+    req_token.grant_type = "refresh_token".to_string();
+    req_token.username = None;
+    req_token.password = None;
+    req_token.refresh_token = ts.refresh_token;
+    req_token.resource = None;
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    let claims = token_claims(&ts.access_token);
+    assert_eq!(claims.get("aud").unwrap().as_str(), Some(CLIENT_ID));
+
+    // ... and for any registered resource on request
+    req_token.refresh_token = ts.refresh_token;
+    req_token.resource = Some(resource_uri.to_string());
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    let claims = token_claims(&ts.access_token);
+    assert_eq!(claims.get("aud").unwrap().as_str(), Some(resource_uri));
+
+    // client credentials
+    req_token.grant_type = "client_credentials".to_string();
+    req_token.refresh_token = None;
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    let res = check_status(res, 200).await?;
+    let ts = res.json::<TokenSet>().await?;
+    let claims = token_claims(&ts.access_token);
+    assert_eq!(claims.get("aud").unwrap().as_str(), Some(resource_uri));
+
+    // a resource without any matching scope cannot be requested
+    let url_resource = format!("{}/{}", url_resources, resource.id);
+    let res = client
+        .put(&url_resource)
+        .headers(auth_headers.clone())
+        .json(&ResourceRequest {
+            scopes: vec!["email".to_string()],
+            ..req_resource
+        })
+        .send()
+        .await?;
+    let res = check_status(res, 200).await?;
+    let resource = res.json::<ResourceResponse>().await?;
+    assert_eq!(resource.scopes, vec!["email".to_string()]);
+
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    check_status(res, 400).await?;
+
+    let res = client
+        .delete(&url_resource)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let res = client.post(&url_token).form(&req_token).send().await?;
+    check_status(res, 400).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_device_grant() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    // use a dedicated client to not interfere with the other tests
+    let client_id = "device_test";
+    let new_client = NewClientRequest {
+        id: client_id.to_string(),
+        secret: None,
+        name: Some("Device Test".to_string()),
+        confidential: false,
+        redirect_uris: vec!["http://localhost:3000/oidc/callback".to_string()],
+        post_logout_redirect_uris: None,
+    };
+    let res = client
+        .post(format!("{}/clients", backend_url))
+        .headers(auth_headers.clone())
+        .json(&new_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    // the device flow is not enabled yet
+    let url_device = format!("{}/oidc/device", backend_url);
+    let req = DeviceRequest {
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        scope: Some("openid email".to_string()),
+    };
+    let res = client.post(&url_device).form(&req).send().await?;
+    check_status(res, 400).await?;
+
+    let update_client = UpdateClientRequest {
+        id: client_id.to_string(),
+        name: new_client.name.clone(),
+        confidential: false,
+        redirect_uris: new_client.redirect_uris.clone(),
+        post_logout_redirect_uris: None,
+        allowed_origins: None,
+        enabled: true,
+        flows_enabled: vec![GRANT_TYPE_DEVICE_CODE.to_string()],
+        access_token_alg: JwkKeyPairAlg::EdDSA,
+        id_token_alg: JwkKeyPairAlg::EdDSA,
+        refresh_token: false,
+        auth_code_lifetime: 60,
+        access_token_lifetime: 60,
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        default_scopes: vec!["openid".to_string()],
+        challenges: None,
+        force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
+    };
+    let url_client = format!("{}/clients/{}", backend_url, client_id);
+    let res = client
+        .put(&url_client)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    let mut res = client.post(&url_device).form(&req).send().await?;
+    res = check_status(res, 200).await?;
+    let device = res.json::<DeviceCodeResponse>().await?;
+    assert_eq!(
+        device.verification_uri_complete,
+        format!("{}?user_code={}", device.verification_uri, device.user_code)
+    );
+
+    // polling before the user has verified the code
+    let url_token = format!("{}/oidc/token", backend_url);
+    let body = TokenRequest {
+        grant_type: GRANT_TYPE_DEVICE_CODE.to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(client_id.to_string()),
+        client_secret: None,
+        code_verifier: None,
+        username: None,
+        password: None,
+        refresh_token: None,
+        device_code: Some(device.device_code.clone()),
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+    time::sleep(Duration::from_secs(device.interval as u64)).await;
+    let res = client.post(&url_token).form(&body).send().await?;
+    assert_eq!(res.status(), 400);
+    let err = res.json::<ErrorResponse>().await?;
+    assert_eq!(err.error, ErrorResponseType::AuthorizationPending);
+
+    // polling too fast
+    let res = client.post(&url_token).form(&body).send().await?;
+    assert_eq!(res.status(), 400);
+    let err = res.json::<ErrorResponse>().await?;
+    assert_eq!(err.error, ErrorResponseType::SlowDown);
+
+    // each slow_down increases the interval for the device code by 5 seconds
+    time::sleep(Duration::from_secs(device.interval as u64)).await;
+    let res = client.post(&url_token).form(&body).send().await?;
+    assert_eq!(res.status(), 400);
+    let err = res.json::<ErrorResponse>().await?;
+    assert_eq!(err.error, ErrorResponseType::SlowDown);
+
+    // verifying a code needs a valid session
+    let verify = DeviceVerifyRequest {
+        user_code: device.user_code.clone(),
+        accept: true,
+    };
+    let res = client
+        .post(format!("{}/oidc/device/verify", backend_url))
+        .json(&verify)
+        .send()
+        .await?;
+    check_status(res, 401).await?;
+
+    // unknown device codes are treated as expired
+    let body = TokenRequest {
+        device_code: Some(get_rand(64)),
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        ..body
+    };
+    let res = client.post(&url_token).form(&body).send().await?;
+    assert_eq!(res.status(), 400);
+    let err = res.json::<ErrorResponse>().await?;
+    assert_eq!(err.error, ErrorResponseType::ExpiredToken);
+
+    let res = client
+        .delete(&url_client)
+        .headers(auth_headers)
+        .send()
+        .await?;
+    check_status(res, 200).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dpop() -> Result<(), Box<dyn Error>> {
+    let client = reqwest::Client::new();
+
+    let url = format!("{}/oidc/token", get_backend_url());
+
+    // token request itself
+    let body = TokenRequest {
+        grant_type: "password".to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(CLIENT_ID.to_string()),
+        client_secret: Some(CLIENT_SECRET.to_string()),
+        code_verifier: None,
+        username: Some(USERNAME.to_string()),
+        password: Some(PASSWORD.to_string()),
+        refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+
+    // dpop header
+    let kp = ed25519_compact::KeyPair::generate();
+
+    let header = DPoPHeader {
+        typ: "dpop+jwt".to_string(),
+        alg: JwkKeyPairAlg::EdDSA,
+        jwk: JWKSPublicKey {
+            kty: JwkKeyPairType::OKP,
+            // DPoP request will not have the 'alg' here but one level higher
+            alg: None,
+            crv: Some("Ed25519".to_string()),
+            kid: None,
+            n: None,
+            e: None,
+            x: Some(base64_url_encode(kp.pk.as_slice())),
+            y: None,
+        },
+        kid: None,
+    };
+    let mut claims = DPoPClaims {
+        jti: "-BwC3ESc6acc2lTc".to_string(),
+        htm: http::Method::POST,
+        htu: DPOP_TOKEN_ENDPOINT.clone(),
+        iat: Utc::now().timestamp(),
+        // ath: None,
+        nonce: None,
+    };
+
+    let fingerprint = header.jwk.fingerprint().unwrap();
+
+    let header_json = serde_json::to_string(&header).unwrap();
+    let header_b64 = base64_url_no_pad_encode(header_json.as_bytes());
+    let claims_json = serde_json::to_string(&claims).unwrap();
+    let claims_b64 = base64_url_no_pad_encode(claims_json.as_bytes());
+    let mut dpop_token = format!("{}.{}", header_b64, claims_b64);
+
+    let sig = kp.sk.sign(&dpop_token, Some(Noise::generate()));
+    let sig_b64 = base64_url_no_pad_encode(sig.as_ref());
+    write!(dpop_token, ".{}", sig_b64).unwrap();
+    println!("test signed token without nonce:\n{}", dpop_token);
+
+    let res = client
+        .post(&url)
+        .header(TOKEN_DPOP, &dpop_token)
+        .form(&body)
+        .send()
+        .await?;
+    // this should fail, because nonce is enforced, which we did not provide
+    assert_eq!(res.status(), 400);
+    let nonce = res
+        .headers()
+        .get(HEADER_DPOP_NONCE)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    println!("nonce we should use: {}", nonce);
+
+    // insert the nonce and rebuild
+    claims.nonce = Some(nonce.to_string());
+
+    let claims_json = serde_json::to_string(&claims).unwrap();
+    let claims_b64 = base64_url_no_pad_encode(claims_json.as_bytes());
+    let mut dpop_token = format!("{}.{}", header_b64, claims_b64);
+
+    let sig = kp.sk.sign(&dpop_token, Some(Noise::generate()));
+    let sig_b64 = base64_url_no_pad_encode(sig.as_ref());
+    write!(dpop_token, ".{}", sig_b64).unwrap();
+    println!("test signed token with nonce:\n{}", dpop_token);
+
+    let mut res = client
+        .post(&url)
+        .header(TOKEN_DPOP, &dpop_token)
+        .form(&body)
+        .send()
+        .await?;
+    // not it should be good
+    res = check_status(res, 200).await?;
+    // make sure nonce header is still there
+    let nonce_new = res
+        .headers()
+        .get(HEADER_DPOP_NONCE)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert_eq!(nonce, nonce_new);
+
+    let ts = res.json::<TokenSet>().await?;
+    assert_eq!(ts.token_type, JwtTokenType::DPoP);
+    let req = TokenValidationRequest {
+        token: ts.access_token.to_owned(),
+    };
+    let token_info = validate_token(req).await?;
+    assert!(token_info.cnf.is_some());
+    assert_eq!(token_info.cnf.unwrap().jkt, Some(fingerprint.clone()));
+
+    // refresh it
+    time::sleep(Duration::from_secs(1)).await;
+    let req = TokenRequest {
+        grant_type: "refresh_token".to_string(),
+        code: None,
+        redirect_uri: None,
+        client_id: Some(CLIENT_ID.to_string()),
+        client_secret: Some(CLIENT_SECRET.to_string()),
+        code_verifier: None,
+        username: None,
+        password: None,
+        refresh_token: Some(ts.refresh_token.clone().unwrap()),
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
+    };
+
+    // without DPoP header, it should fail
+    let res = reqwest::Client::new().post(&url).form(&req).send().await?;
+    assert_eq!(res.status(), 403);
+    let err = res.json::<ErrorResponse>().await.unwrap();
+    println!("{:?}", err);
+    assert_eq!(err.error, ErrorResponseType::Forbidden);
+    assert!(err.message.to_lowercase().contains("dpop"));
+
+    // now a proper refresh with DPoP header
+    let res = reqwest::Client::new()
+        .post(&url)
+        .header(TOKEN_DPOP, dpop_token)
+        .form(&req)
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+
+    let ts = res.json::<TokenSet>().await?;
+    assert_eq!(ts.token_type, JwtTokenType::DPoP);
+    let req = TokenValidationRequest {
+        token: ts.access_token.to_owned(),
+    };
+    let token_info = validate_token(req).await?;
+    assert!(token_info.cnf.is_some());
+    assert_eq!(token_info.cnf.unwrap().jkt, Some(fingerprint));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_authorization_code_flow_ephemeral_client() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let client = reqwest::Client::new();
+
+    // host our own ephemeral client
+    let handle = serve_ephemeral_client();
+    // make sure the http server starts and keeps running
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!handle.is_finished());
+    // our ephemeral client's id
+    let client_id = "http://127.0.0.1:10080/client";
+
+    // Step 1: GET /authorize for the CSRF token and simulate UI login
+    let challenge_plain = "oDXug9zfYqfz8ejcqMpALRPXfW8QhbKV2AVuScAt8xrLKDAmaRYQ4yRi2uqcH9ys";
+    let hash = digest::digest(&digest::SHA256, challenge_plain.as_bytes());
+    let challenge_s256 = base64_url_encode(hash.as_ref());
 
     let redirect_uri = "http://localhost:3000/oidc/callback";
     let query = format!(
@@ -754,6 +4064,10 @@ async fn test_authorization_code_flow_ephemeral_client() -> Result<(), Box<dyn E
         nonce: Some(nonce.to_owned()),
         code_challenge: Some(challenge_s256),
         code_challenge_method: Some("S256".to_string()),
+        acr_values: None,
+        authorization_details: None,
+        resource: None,
+        prompt: None,
     };
     let res = client
         .post(&url_auth)
@@ -777,6 +4091,18 @@ async fn test_authorization_code_flow_ephemeral_client() -> Result<(), Box<dyn E
         username: None,
         password: None,
         refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
 
     let url_token = format!("{}/oidc/token", backend_url);
@@ -801,6 +4127,18 @@ async fn test_authorization_code_flow_ephemeral_client() -> Result<(), Box<dyn E
         username: None,
         password: None,
         refresh_token: Some(ts.refresh_token.clone().unwrap()),
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
     let res = client.post(&url_token).form(&req).send().await?;
     assert!(res.status().is_success());
@@ -851,6 +4189,32 @@ fn serve_ephemeral_client() -> JoinHandle<()> {
     })
 }
 
+fn serve_backchannel_logout(tx: mpsc::Sender<String>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let actix_system = actix_web::rt::System::new();
+        actix_system.block_on(async {
+            HttpServer::new(move || {
+                App::new().app_data(web::Data::new(tx.clone())).route(
+                    "/logout",
+                    web::post().to(
+                        |tx: web::Data<mpsc::Sender<String>>,
+                         form: web::Form<HashMap<String, String>>| async move {
+                            let token = form.get("logout_token").cloned().unwrap_or_default();
+                            tx.send(token).unwrap();
+                            HttpResponse::Ok().finish()
+                        },
+                    ),
+                )
+            })
+            .bind(("127.0.0.1", 10081))
+            .expect("port 10081 to be free for testing")
+            .run()
+            .await
+            .expect("backchannel logout test http server to start")
+        })
+    })
+}
+
 async fn validate_token(req: TokenValidationRequest) -> Result<TokenInfo, Box<dyn Error>> {
     let url_valid = format!("{}/oidc/tokenInfo", get_backend_url());
     let res = reqwest::Client::new()
@@ -25,3 +25,30 @@ async fn test_get_well_known() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_get_oauth_authorization_server() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let public_url = backend_url.strip_suffix("/auth/v1").unwrap();
+
+    // RFC 8414 inserts the well-known path in front of the issuer path
+    for url in [
+        format!("{}/.well-known/oauth-authorization-server", backend_url),
+        format!(
+            "{}/.well-known/oauth-authorization-server/auth/v1",
+            public_url
+        ),
+    ] {
+        let res = reqwest::get(&url).await?;
+        assert_eq!(res.status(), 200);
+        let content = res.json::<WellKnown>().await?;
+        assert_eq!(content.issuer, get_issuer());
+        assert_eq!(content.response_modes_supported, vec!["query".to_string()]);
+        assert_eq!(
+            content.introspection_endpoint_auth_methods_supported,
+            vec!["none".to_string()]
+        );
+    }
+
+    Ok(())
+}
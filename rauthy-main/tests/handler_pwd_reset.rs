@@ -146,6 +146,18 @@ async fn test_get_pwd_reset_form() -> Result<(), Box<dyn Error>> {
         username: Some(username.to_string()),
         password: Some(req.password.to_string()),
         refresh_token: None,
+        device_code: None,
+        client_assertion_type: None,
+        client_assertion: None,
+        subject_token: None,
+        subject_token_type: None,
+        actor_token: None,
+        actor_token_type: None,
+        requested_token_type: None,
+        audience: None,
+        scope: None,
+        authorization_details: None,
+        resource: None,
     };
     let client = reqwest::Client::new();
     let res = client.post(&url).form(&body).send().await?;
@@ -1,9 +1,17 @@
-use crate::common::{get_auth_headers, get_backend_url, get_token_set};
+use crate::common::{get_auth_headers, get_backend_url, get_token_set, PASSWORD, USERNAME};
 use pretty_assertions::assert_eq;
+use rauthy_common::constants::{HEADER_TOTAL_COUNT, TEXT_CSV};
+use rauthy_common::utils::get_rand;
 use rauthy_models::language::Language;
-use rauthy_models::request::NewUserRequest;
-use rauthy_models::response::{UserResponse, UserResponseSimple};
-use reqwest::header::AUTHORIZATION;
+use rauthy_models::request::{
+    NewUserRequest, RecoveryCodeAuthRequest, RecoveryCodesRequest, TotpAuthRequest, TotpRequest,
+    UserImportRequest, UsersImportRequest,
+};
+use rauthy_models::response::{
+    TotpRegistrationResponse, UserResponse, UserResponseSimple, UsersExportResponse,
+    UsersImportResponse,
+};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
 use std::error::Error;
 
 mod common;
@@ -72,6 +80,71 @@ async fn test_users() -> Result<(), Box<dyn Error>> {
     assert_eq!(alfred.enabled, true);
     assert_eq!(alfred.email_verified, false);
 
+    // search, filter, sort and paginate
+    let res = reqwest::Client::new()
+        .get(format!("{}?search=BATM", url))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get(HEADER_TOTAL_COUNT).unwrap(), "1");
+    let users = res.json::<Vec<UserResponseSimple>>().await?;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].id, alfred.id);
+
+    // only the beginning of the values is matched
+    let res = reqwest::Client::new()
+        .get(format!("{}?search=batcave", url))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get(HEADER_TOTAL_COUNT).unwrap(), "0");
+
+    let url_page = format!(
+        "{}?role=rauthy_admin&sort_by=created_at&order=desc&page_size=2",
+        url
+    );
+    let res = reqwest::Client::new()
+        .get(format!("{}&page=1", url_page))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get(HEADER_TOTAL_COUNT).unwrap(), "3");
+    let users = res.json::<Vec<UserResponseSimple>>().await?;
+    assert_eq!(users.len(), 2);
+    // same timestamps must be ordered by id to keep the pages stable
+    assert_eq!(users[0].id, "2PYV3STNz3MN7VnPjJVcPQap");
+    assert_eq!(users[1].id, "m4PJ3TnyP32LA8hzY23deme3");
+
+    let res = reqwest::Client::new()
+        .get(format!("{}&page=2", url_page))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let users = res.json::<Vec<UserResponseSimple>>().await?;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].id, "za9UxpH7XVxqrtpEbThoqvn2");
+
+    let res = reqwest::Client::new()
+        .get(format!("{}?email_verified=false&enabled=true", url))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let users = res.json::<Vec<UserResponseSimple>>().await?;
+    assert!(users.iter().any(|u| u.id == alfred.id));
+    assert!(users.iter().all(|u| u.email != USERNAME));
+
+    let res = reqwest::Client::new()
+        .get(format!("{}?page_size=0", url))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 400);
+
     // get the new user by id
     let url_id = format!("{}/users/{}", get_backend_url(), alfred.id);
     let res = reqwest::Client::new()
@@ -113,6 +186,130 @@ async fn test_users() -> Result<(), Box<dyn Error>> {
     let users = res.json::<Vec<UserResponseSimple>>().await?;
     assert_eq!(users.len(), 3);
 
+    // bulk import with a foreign PBKDF2 hash and an already existing E-Mail
+    let url_import = format!("{}/users/import", get_backend_url());
+    let import = UsersImportRequest {
+        users: vec![
+            UserImportRequest {
+                email: "lucius@batcave.io".to_string(),
+                given_name: "Lucius".to_string(),
+                family_name: "Fox".to_string(),
+                language: Language::En,
+                roles: vec!["user".to_string()],
+                groups: None,
+                enabled: Some(true),
+                email_verified: Some(true),
+                user_expires: None,
+                password_hash: Some("$pbkdf2-sha256$i=1000,l=32$cmF1dGh5U2FsdFZhbHVlMQ$ZyBYpBBeKJ+A80yAVlK3GxvUC7dmZs1BrXd3wR3NYvQ".to_string()),
+                attributes: None,
+            },
+            UserImportRequest {
+                email: USERNAME.to_string(),
+                given_name: "Duplicate".to_string(),
+                family_name: "Admin".to_string(),
+                language: Language::En,
+                roles: vec!["user".to_string()],
+                groups: None,
+                enabled: None,
+                email_verified: None,
+                user_expires: None,
+                password_hash: None,
+                attributes: None,
+            },
+        ],
+    };
+    let res = reqwest::Client::new()
+        .post(&url_import)
+        .headers(auth_headers.clone())
+        .json(&import)
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let import_res = res.json::<UsersImportResponse>().await?;
+    assert_eq!(import_res.imported, 1);
+    assert_eq!(import_res.errors.len(), 1);
+    assert_eq!(import_res.errors[0].email, USERNAME);
+
+    // a hash format we cannot verify rejects the whole request
+    let res = reqwest::Client::new()
+        .post(&url_import)
+        .headers(auth_headers.clone())
+        .header(CONTENT_TYPE, TEXT_CSV)
+        .body("email,given_name,family_name,password_hash\nselina@batcave.io,Selina,Kyle,$1$abc$xyz\n")
+        .send()
+        .await?;
+    assert_eq!(res.status(), 400);
+
+    let res = reqwest::Client::new()
+        .post(&url_import)
+        .headers(auth_headers.clone())
+        .header(CONTENT_TYPE, TEXT_CSV)
+        .body("email,given_name,family_name,roles,enabled\nselina@batcave.io,Selina,Kyle,user,false\n")
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let import_res = res.json::<UsersImportResponse>().await?;
+    assert_eq!(import_res.imported, 1);
+    assert!(import_res.errors.is_empty());
+
+    // export as JSON with and without the password hashes
+    let url_export = format!("{}/users/export", get_backend_url());
+    let res = reqwest::Client::new()
+        .get(&url_export)
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let export = res.json::<UsersExportResponse>().await?;
+    assert_eq!(export.users.len(), 5);
+    assert!(export.users.iter().all(|u| u.password_hash.is_none()));
+
+    let res = reqwest::Client::new()
+        .get(format!("{}?password_hashes=true", url_export))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let export = res.json::<UsersExportResponse>().await?;
+    let lucius = export
+        .users
+        .iter()
+        .find(|u| u.email == "lucius@batcave.io")
+        .unwrap();
+    assert!(lucius
+        .password_hash
+        .as_ref()
+        .unwrap()
+        .starts_with("$pbkdf2-sha256$"));
+    let selina = export
+        .users
+        .iter()
+        .find(|u| u.email == "selina@batcave.io")
+        .unwrap();
+    assert!(!selina.enabled);
+    assert!(selina.password_hash.is_none());
+
+    let res = reqwest::Client::new()
+        .get(format!("{}?format=csv", url_export))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap().to_str()?, TEXT_CSV);
+    let csv = res.text().await?;
+    assert!(csv.starts_with("id,email,given_name,family_name,"));
+    assert_eq!(csv.lines().count(), 6);
+
+    // clean up the imported users
+    for id in [&lucius.id, &selina.id] {
+        let res = reqwest::Client::new()
+            .delete(format!("{}/users/{}", get_backend_url(), id))
+            .headers(auth_headers.clone())
+            .send()
+            .await?;
+        assert_eq!(res.status(), 204);
+    }
+
     Ok(())
 }
 
@@ -136,3 +333,148 @@ async fn test_userinfo() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_user_totp() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(format!("{}/users/email/{}", backend_url, USERNAME))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let user = res.json::<UserResponse>().await?;
+    assert!(!user.totp_enabled);
+
+    // start a registration - it is not finished in this test, because the other tests would
+    // need a second factor for this user afterward
+    let url_totp = format!("{}/users/{}/totp", backend_url, user.id);
+    let res = client
+        .post(&url_totp)
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let reg = res.json::<TotpRegistrationResponse>().await?;
+    assert_eq!(reg.secret.len(), 32);
+    assert!(reg.uri.starts_with("otpauth://totp/"));
+    assert!(reg.uri.contains(&format!("secret={}", reg.secret)));
+    assert!(reg.qr_svg.contains("<svg"));
+
+    // a code in a bad format must be rejected
+    let res = client
+        .put(&url_totp)
+        .headers(auth_headers.clone())
+        .json(&TotpRequest {
+            totp: "12345".to_string(),
+            password_current: Some(PASSWORD.to_string()),
+            mfa_code: None,
+            totp_current: None,
+        })
+        .send()
+        .await?;
+    assert_eq!(res.status(), 400);
+
+    // the registration needs a re-authentication of the user
+    let res = client
+        .put(&url_totp)
+        .headers(auth_headers.clone())
+        .json(&TotpRequest {
+            totp: "123456".to_string(),
+            password_current: None,
+            mfa_code: None,
+            totp_current: None,
+        })
+        .send()
+        .await?;
+    assert_eq!(res.status(), 400);
+
+    // there is nothing to delete yet
+    let res = client
+        .delete(&url_totp)
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 404);
+
+    // a login can only be finished for an existing pending login
+    let res = client
+        .post(format!("{}/auth", url_totp))
+        .headers(auth_headers.clone())
+        .json(&TotpAuthRequest {
+            code: get_rand(48),
+            totp: "123456".to_string(),
+        })
+        .send()
+        .await?;
+    assert_eq!(res.status(), 404);
+
+    let res = client
+        .get(format!("{}/users/{}", backend_url, user.id))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let user = res.json::<UserResponse>().await?;
+    assert!(!user.totp_enabled);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_user_recovery_codes() -> Result<(), Box<dyn Error>> {
+    let backend_url = get_backend_url();
+    let auth_headers = get_auth_headers().await?;
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(format!("{}/users/email/{}", backend_url, USERNAME))
+        .headers(auth_headers.clone())
+        .send()
+        .await?;
+    assert_eq!(res.status(), 200);
+    let user = res.json::<UserResponse>().await?;
+
+    // recovery codes only exist together with a second factor
+    let url_codes = format!("{}/users/{}/recovery_codes", backend_url, user.id);
+    let res = client
+        .post(&url_codes)
+        .headers(auth_headers.clone())
+        .json(&RecoveryCodesRequest {
+            password_current: Some(PASSWORD.to_string()),
+            mfa_code: None,
+            totp_current: None,
+        })
+        .send()
+        .await?;
+    assert_eq!(res.status(), 400);
+
+    // a code in a bad format must be rejected
+    let res = client
+        .post(format!("{}/auth", url_codes))
+        .headers(auth_headers.clone())
+        .json(&RecoveryCodeAuthRequest {
+            code: get_rand(48),
+            recovery_code: "ABCD-EFGH-IJKL".to_string(),
+        })
+        .send()
+        .await?;
+    assert_eq!(res.status(), 400);
+
+    // a login can only be finished for an existing pending login
+    let res = client
+        .post(format!("{}/auth", url_codes))
+        .headers(auth_headers.clone())
+        .json(&RecoveryCodeAuthRequest {
+            code: get_rand(48),
+            recovery_code: "ABCD-EFGH-IJKL-MNOP".to_string(),
+        })
+        .send()
+        .await?;
+    assert_eq!(res.status(), 404);
+
+    Ok(())
+}
@@ -126,6 +126,30 @@ async fn test_cust_attrs() -> Result<(), Box<dyn Error>> {
         default_scopes,
         challenges: c.challenges,
         force_mfa: c.force_mfa,
+        require_par: c.require_par,
+        jwks: c.jwks,
+        jwks_uri: c.jwks_uri,
+        require_request_object: c.require_request_object,
+        token_exchange_audiences: c.token_exchange_audiences,
+        token_exchange_scopes: c.token_exchange_scopes,
+        token_exchange_subject_types: c.token_exchange_subject_types,
+        backchannel_logout_uri: c.backchannel_logout_uri,
+        frontchannel_logout_uri: c.frontchannel_logout_uri,
+        subject_type: c.subject_type,
+        sector_identifier_uri: c.sector_identifier_uri,
+        id_token_encrypted_response_alg: c.id_token_encrypted_response_alg,
+        id_token_encrypted_response_enc: c.id_token_encrypted_response_enc,
+        userinfo_encrypted_response_alg: c.userinfo_encrypted_response_alg,
+        userinfo_encrypted_response_enc: c.userinfo_encrypted_response_enc,
+        userinfo_signed_response_alg: c.userinfo_signed_response_alg.map(JwkKeyPairAlg::from),
+        authorization_details_types: c.authorization_details_types,
+        tls_client_auth_method: c.tls_client_auth_method,
+        tls_client_auth_subject_dn: c.tls_client_auth_subject_dn,
+        tls_client_certificate_bound_access_tokens: c.tls_client_certificate_bound_access_tokens,
+        require_consent: c.require_consent,
+        allow_email_login: c.allow_email_login,
+        token_endpoint_auth_method: c.token_endpoint_auth_method,
+        request_uris: c.request_uris,
     };
     let res = client
         .put(&url_client)
@@ -88,7 +88,7 @@ async fn test_clients() -> Result<(), Box<dyn Error>> {
     let mut flows_enabled = client.flows_enabled;
     flows_enabled.push("password".to_string());
 
-    let update_client = UpdateClientRequest {
+    let mut update_client = UpdateClientRequest {
         id: client.id.clone(),
         name: None,
         confidential: false,
@@ -114,9 +114,47 @@ async fn test_clients() -> Result<(), Box<dyn Error>> {
         ],
         challenges: Some(vec!["S256".to_string(), "plain".to_string()]),
         force_mfa: false,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
     };
 
     let url_id = format!("{}/clients/{}", backend_url, client.id);
+
+    // mutual TLS client authentication is only possible for confidential clients
+    update_client.tls_client_auth_method = Some("tls_client_auth".to_string());
+    update_client.tls_client_auth_subject_dn = Some("CN=localhost, O=Rauthy OIDC".to_string());
+    let res = reqwest::Client::new()
+        .put(&url_id)
+        .headers(auth_headers.clone())
+        .json(&update_client)
+        .send()
+        .await?;
+    assert_eq!(res.status(), 400);
+    update_client.tls_client_auth_method = None;
+    update_client.tls_client_auth_subject_dn = None;
+
     let res = reqwest::Client::new()
         .put(&url_id)
         .headers(auth_headers.clone())
@@ -20,6 +20,19 @@ async fn test_dynamic_client() -> Result<(), Box<dyn Error>> {
         id_token_signed_response_alg: None,
         token_endpoint_auth_method: Some("none".to_string()),
         token_endpoint_auth_signing_alg: None,
+        jwks_uri: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
         post_logout_redirect_uri: None,
     };
     let res = client.post(&url).json(&payload).send().await?;
@@ -1,4 +1,5 @@
 use crate::email::EMail;
+use crate::entity::backchannel_logout::BackchannelLogout;
 use crate::entity::db_version::DbVersion;
 use crate::events::event::Event;
 use crate::events::ip_blacklist_handler::IpBlacklistReq;
@@ -29,6 +30,11 @@ pub type DbPool = sqlx::PgPool;
 #[cfg(feature = "sqlite")]
 pub type DbPool = sqlx::SqlitePool;
 
+#[cfg(not(feature = "sqlite"))]
+pub type DbType = sqlx::Postgres;
+#[cfg(feature = "sqlite")]
+pub type DbType = sqlx::Sqlite;
+
 #[cfg(not(feature = "sqlite"))]
 pub type DbTxn<'a> = sqlx::Transaction<'a, sqlx::Postgres>;
 #[cfg(feature = "sqlite")]
@@ -51,6 +57,7 @@ pub struct AppState {
     pub tx_events: flume::Sender<Event>,
     pub tx_events_router: flume::Sender<EventRouterMsg>,
     pub tx_ip_blacklist: flume::Sender<IpBlacklistReq>,
+    pub tx_backchannel_logout: flume::Sender<BackchannelLogout>,
     pub caches: Caches,
     pub webauthn: Arc<Webauthn>,
 }
@@ -61,6 +68,7 @@ impl AppState {
         tx_events: flume::Sender<Event>,
         tx_events_router: flume::Sender<EventRouterMsg>,
         tx_ip_blacklist: flume::Sender<IpBlacklistReq>,
+        tx_backchannel_logout: flume::Sender<BackchannelLogout>,
         caches: Caches,
     ) -> anyhow::Result<Self> {
         dotenvy::dotenv().ok();
@@ -186,6 +194,7 @@ impl AppState {
             tx_events_router,
             caches,
             tx_ip_blacklist,
+            tx_backchannel_logout,
             webauthn,
         })
     }
@@ -298,6 +307,10 @@ impl AppState {
             migrate_dev_data(&pool).await.expect("Migrating DEV DATA");
         }
 
+        db_migrate::migrate_ecdsa_jwks(&pool)
+            .await
+            .map_err(|err| anyhow::Error::msg(err.message))?;
+
         if let Err(err) = db_migrate::anti_lockout(&pool, issuer).await {
             error!("Error when applying anti-lockout check: {:?}", err);
         }
@@ -3,6 +3,7 @@ use crate::entity::magic_links::MagicLink;
 use crate::entity::users::User;
 use crate::i18n::email_change_info_new::I18nEmailChangeInfoNew;
 use crate::i18n::email_confirm_change::I18nEmailConfirmChange;
+use crate::i18n::email_login::I18nEmailLogin;
 use crate::i18n::email_reset::I18nEmailReset;
 use crate::i18n::email_reset_info::I18nEmailResetInfo;
 use crate::i18n::SsrJson;
@@ -94,6 +95,37 @@ pub struct EMailConfirmChangeTxt<'a> {
     pub changed_by_admin: &'a str,
 }
 
+#[derive(Default, Template)]
+#[template(path = "email/login.html")]
+pub struct EMailLoginHtml<'a> {
+    pub email_sub_prefix: &'a str,
+    pub link: &'a str,
+    pub code: &'a str,
+    pub exp: &'a str,
+    // i18n
+    pub header: &'a str,
+    pub click_link: &'a str,
+    pub enter_code: &'a str,
+    pub validity: &'a str,
+    pub expires: &'a str,
+    pub button_text: &'a str,
+}
+
+#[derive(Default, Template)]
+#[template(path = "email/login.txt")]
+pub struct EmailLoginTxt<'a> {
+    pub email_sub_prefix: &'a str,
+    pub link: &'a str,
+    pub code: &'a str,
+    pub exp: &'a str,
+    // i18n
+    pub header: &'a str,
+    pub click_link: &'a str,
+    pub enter_code: &'a str,
+    pub validity: &'a str,
+    pub expires: &'a str,
+}
+
 #[derive(Default, Template)]
 #[template(path = "email/reset.html")]
 pub struct EMailResetHtml<'a> {
@@ -291,6 +323,61 @@ pub async fn send_email_confirm_change(
     }
 }
 
+pub async fn send_email_login(
+    data: &web::Data<AppState>,
+    magic_link: &MagicLink,
+    user: &User,
+    code: &str,
+) {
+    let link = format!("{}/oidc/email?id={}", data.issuer, &magic_link.id);
+    let exp = email_ts_prettify(magic_link.exp);
+
+    let i18n = I18nEmailLogin::build(&user.language);
+    let text = EmailLoginTxt {
+        email_sub_prefix: &EMAIL_SUB_PREFIX,
+        link: &link,
+        code,
+        exp: &exp,
+        header: i18n.header,
+        click_link: i18n.click_link,
+        enter_code: i18n.enter_code,
+        validity: i18n.validity,
+        expires: i18n.expires,
+    };
+
+    let html = EMailLoginHtml {
+        email_sub_prefix: &EMAIL_SUB_PREFIX,
+        link: &link,
+        code,
+        exp: &exp,
+        header: i18n.header,
+        click_link: i18n.click_link,
+        enter_code: i18n.enter_code,
+        validity: i18n.validity,
+        expires: i18n.expires,
+        button_text: i18n.button_text,
+    };
+
+    let req = EMail {
+        address: user.email.to_string(),
+        subject: format!("{} - {}", *EMAIL_SUB_PREFIX, i18n.subject),
+        text: text.render().expect("Template rendering: EmailLoginTxt"),
+        html: Some(html.render().expect("Template rendering: EmailLoginHtml")),
+    };
+
+    let tx = &data.tx_email;
+    let res = tx.send_timeout(req, Duration::from_secs(10)).await;
+    match res {
+        Ok(_) => {}
+        Err(ref e) => {
+            error!(
+                "Error sending email login request for user '{}': {:?}",
+                user.email, e
+            );
+        }
+    }
+}
+
 pub async fn send_pwd_reset(data: &web::Data<AppState>, magic_link: &MagicLink, user: &User) {
     let link = format!(
         "{}/users/{}/reset/{}?type={}",
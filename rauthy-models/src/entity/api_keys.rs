@@ -255,6 +255,8 @@ pub enum AccessGroup {
     Scopes,
     UserAttributes,
     Users,
+    // The access is serialized with bincode -> new groups must always be appended
+    Resources,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
@@ -1,4 +1,5 @@
 use crate::app_state::AppState;
+use crate::entity::authorization_details::AuthorizationDetails;
 use actix_web::web;
 use rauthy_common::constants::CACHE_NAME_AUTH_CODES;
 use rauthy_common::error_response::ErrorResponse;
@@ -20,6 +21,8 @@ pub struct AuthCode {
     pub challenge_method: Option<String>,
     pub nonce: Option<String>,
     pub scopes: Vec<String>,
+    pub authorization_details: Option<AuthorizationDetails>,
+    pub resource: Option<String>,
 }
 
 // CRUD
@@ -74,6 +77,8 @@ impl AuthCode {
         challenge_method: Option<String>,
         nonce: Option<String>,
         scopes: Vec<String>,
+        authorization_details: Option<AuthorizationDetails>,
+        resource: Option<String>,
         lifetime_secs: i32,
     ) -> Self {
         let id = get_rand(64);
@@ -90,6 +95,8 @@ impl AuthCode {
             challenge_method,
             nonce,
             scopes,
+            authorization_details,
+            resource,
         }
     }
 }
@@ -1,7 +1,9 @@
 use crate::app_state::AppState;
 use crate::entity::auth_codes::AuthCode;
 use crate::entity::clients::Client;
+use crate::entity::resolved_auth_requests::ResolvedAuthRequest;
 use crate::entity::sessions::Session;
+use crate::entity::user_consents::PendingConsent;
 use crate::entity::users::User;
 use crate::entity::users_values::UserValues;
 use crate::entity::webauthn::WebauthnLoginReq;
@@ -11,7 +13,7 @@ use crate::request::{
     UserValuesRequest,
 };
 use crate::response::{ProviderLinkedUserResponse, ProviderLookupResponse};
-use crate::{AuthStep, AuthStepAwaitWebauthn, AuthStepLoggedIn};
+use crate::{AuthStep, AuthStepAwaitTotp, AuthStepAwaitWebauthn, AuthStepLoggedIn};
 use actix_web::cookie::Cookie;
 use actix_web::http::header;
 use actix_web::http::header::HeaderValue;
@@ -511,6 +513,72 @@ impl AuthProvider {
         })
     }
 
+    /// Validates a token from a trusted upstream provider for a Token Exchange and returns the
+    /// linked local user together with the tokens `exp`, if it exists.
+    /// The provider is found by the tokens `iss` and the token is validated at its userinfo
+    /// endpoint, which works for opaque signatures and any algorithm the provider uses.
+    pub async fn find_user_by_token(
+        data: &web::Data<AppState>,
+        token: &str,
+    ) -> Result<(User, Option<i64>), ErrorResponse> {
+        let err = || {
+            ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                "Invalid upstream token".to_string(),
+            )
+        };
+
+        let claims = token.split('.').nth(1).ok_or_else(err)?;
+        let claims = serde_json::from_slice::<AuthProviderTokenClaims>(
+            &base64_url_no_pad_decode(claims).map_err(|_| err())?,
+        )
+        .map_err(|_| err())?;
+
+        let provider = Self::find_all(data)
+            .await?
+            .into_iter()
+            .find(|p| {
+                p.enabled && p.issuer.trim_end_matches('/') == claims.iss.trim_end_matches('/')
+            })
+            .ok_or_else(|| {
+                ErrorResponse::new(
+                    ErrorResponseType::Unauthorized,
+                    format!("Issuer '{}' is not trusted", claims.iss),
+                )
+            })?;
+
+        let client = Self::build_client(
+            provider.allow_insecure_requests,
+            provider.root_pem.as_deref(),
+        )?;
+        let res = client
+            .get(&provider.userinfo_endpoint)
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header(ACCEPT, APPLICATION_JSON)
+            .send()
+            .await?;
+        if !res.status().is_success() {
+            debug!(
+                "GET /userinfo auth provider status for upstream token: {}",
+                res.status().as_u16()
+            );
+            return Err(err());
+        }
+
+        let res_bytes = res.bytes().await?;
+        let uid = AuthProviderIdClaims::try_from(res_bytes.as_bytes())?.federation_uid()?;
+        let user = User::find_by_federation(data, &provider.id, &uid)
+            .await
+            .map_err(|_| {
+                ErrorResponse::new(
+                    ErrorResponseType::Forbidden,
+                    "The upstream user is not linked to any user".to_string(),
+                )
+            })?;
+
+        Ok((user, claims.exp))
+    }
+
     fn secret_encrypted(secret: &Option<String>) -> Result<Option<Vec<u8>>, ErrorResponse> {
         if let Some(secret) = &secret {
             Ok(Some(
@@ -698,7 +766,7 @@ impl AuthProviderCallback {
         debug!("callback state is valid");
 
         // validate csrf token
-        let slf = Self::find(data, callback_id).await?;
+        let mut slf = Self::find(data, callback_id).await?;
         if slf.xsrf_token != payload.xsrf_token {
             Self::delete(data, slf.callback_id).await?;
 
@@ -826,10 +894,20 @@ impl AuthProviderCallback {
         user.check_expired()?;
 
         // validate client values
-        let client = Client::find_maybe_ephemeral(data, slf.req_client_id).await?;
+        let client = Client::find_maybe_ephemeral(data, slf.req_client_id.clone()).await?;
+        if let Some(auth_request) =
+            ResolvedAuthRequest::find_required(data, &session.id, &client).await?
+        {
+            slf.req_scopes = Some(auth_request.scope.split(' ').map(String::from).collect());
+            slf.req_redirect_uri = auth_request.redirect_uri;
+            slf.req_state = auth_request.state;
+            slf.req_nonce = auth_request.nonce;
+            slf.req_code_challenge = auth_request.code_challenge;
+            slf.req_code_challenge_method = auth_request.code_challenge_method;
+        }
         let force_mfa = client.force_mfa();
         if force_mfa {
-            if provider_mfa_login == ProviderMfaLogin::No && !user.has_webauthn_enabled() {
+            if provider_mfa_login == ProviderMfaLogin::No && !user.has_mfa_enabled() {
                 return Err(ErrorResponse::new(
                     ErrorResponseType::MfaRequired,
                     "MFA is required for this client".to_string(),
@@ -845,7 +923,7 @@ impl AuthProviderCallback {
         // all good, we can generate an auth code
 
         // authorization code
-        let code_lifetime = if force_mfa && user.has_webauthn_enabled() {
+        let code_lifetime = if force_mfa && user.has_mfa_enabled() {
             client.auth_code_lifetime + *WEBAUTHN_REQ_EXP as i32
         } else {
             client.auth_code_lifetime
@@ -859,15 +937,20 @@ impl AuthProviderCallback {
             slf.req_code_challenge_method,
             slf.req_nonce,
             scopes,
+            None,
+            None,
             code_lifetime,
         );
-        code.save(data).await?;
-
-        // location header
-        let mut loc = format!("{}?code={}", slf.req_redirect_uri, code.id);
-        if let Some(state) = slf.req_state {
-            write!(loc, "&state={}", state).expect("`write!` to succeed");
-        };
+        // points to the consent page, if the user has not granted all scopes yet
+        let loc = PendingConsent::code_location(
+            data,
+            code,
+            slf.req_redirect_uri,
+            slf.req_state,
+            client.require_consent,
+            false,
+        )
+        .await?;
 
         let auth_step = if user.has_webauthn_enabled() {
             let step = AuthStepAwaitWebauthn {
@@ -884,6 +967,7 @@ impl AuthProviderCallback {
             WebauthnLoginReq {
                 code: step.code.clone(),
                 user_id: user.id,
+                session_id: Some(step.session.id.clone()),
                 header_loc: loc,
                 header_origin: step
                     .header_origin
@@ -894,6 +978,30 @@ impl AuthProviderCallback {
             .await?;
 
             AuthStep::AwaitWebauthn(step)
+        } else if user.has_totp_enabled() {
+            let step = AuthStepAwaitTotp {
+                has_password_been_hashed: false,
+                code: get_rand(48),
+                header_csrf: Session::get_csrf_header(&session.csrf_token),
+                header_origin,
+                user_id: user.id.clone(),
+                exp: *WEBAUTHN_REQ_EXP,
+            };
+
+            WebauthnLoginReq {
+                code: step.code.clone(),
+                user_id: user.id,
+                session_id: Some(session.id.clone()),
+                header_loc: loc,
+                header_origin: step
+                    .header_origin
+                    .as_ref()
+                    .map(|h| h.1.to_str().unwrap().to_string()),
+            }
+            .save(data)
+            .await?;
+
+            AuthStep::AwaitTotp(step)
         } else {
             AuthStep::LoggedIn(AuthStepLoggedIn {
                 has_password_been_hashed: false,
@@ -996,6 +1104,13 @@ pub struct AuthProviderAddressClaims<'a> {
     pub country: Option<&'a str>,
 }
 
+/// The claims of an upstream JWT, which are needed before its validation
+#[derive(Debug, Deserialize)]
+struct AuthProviderTokenClaims {
+    iss: String,
+    exp: Option<i64>,
+}
+
 #[derive(Debug, PartialEq)]
 enum ProviderMfaLogin {
     Yes,
@@ -1082,21 +1197,8 @@ impl AuthProviderIdClaims<'_> {
         Ok(json_bytes)
     }
 
-    async fn validate_update_user(
-        &self,
-        data: &web::Data<AppState>,
-        provider: &AuthProvider,
-    ) -> Result<(User, ProviderMfaLogin), ErrorResponse> {
-        if self.email.is_none() {
-            let err = "No `email` in ID token claims. This is a mandatory claim";
-            error!("{}", err);
-            return Err(ErrorResponse::new(
-                ErrorResponseType::BadRequest,
-                err.to_string(),
-            ));
-        }
-
-        let claims_user_id = if let Some(sub) = &self.sub {
+    fn federation_uid(&self) -> Result<String, ErrorResponse> {
+        let uid = if let Some(sub) = &self.sub {
             sub
         } else if let Some(id) = &self.id {
             id
@@ -1113,6 +1215,24 @@ impl AuthProviderIdClaims<'_> {
         // We need to create a real string here, since we don't know what json type we get.
         // Any json number would become a String too, which is what we need for compatibility.
         .to_string();
+        Ok(uid)
+    }
+
+    async fn validate_update_user(
+        &self,
+        data: &web::Data<AppState>,
+        provider: &AuthProvider,
+    ) -> Result<(User, ProviderMfaLogin), ErrorResponse> {
+        if self.email.is_none() {
+            let err = "No `email` in ID token claims. This is a mandatory claim";
+            error!("{}", err);
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                err.to_string(),
+            ));
+        }
+
+        let claims_user_id = self.federation_uid()?;
 
         let user_opt = match User::find_by_federation(data, &provider.id, &claims_user_id).await {
             Ok(user) => {
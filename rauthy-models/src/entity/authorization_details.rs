@@ -0,0 +1,147 @@
+use crate::entity::clients::Client;
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::warn;
+
+/// A single entry of the `authorization_details` from RFC 9396. Only the `type` is interpreted by
+/// Rauthy. All other fields are specific to the resource server and passed into the tokens as
+/// they are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorizationDetail {
+    #[serde(rename = "type")]
+    pub typ: String,
+    #[serde(flatten)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Rich Authorization Request details (RFC 9396)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AuthorizationDetails(pub Vec<AuthorizationDetail>);
+
+impl AuthorizationDetails {
+    /// Parses the JSON array from the `authorization_details` request parameter
+    pub fn try_from_str(value: &str) -> Result<Self, ErrorResponse> {
+        let slf = serde_json::from_str::<Self>(value).map_err(|err| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("invalid_authorization_details: {}", err),
+            )
+        })?;
+
+        if slf.0.is_empty() || slf.0.iter().any(|d| d.typ.is_empty()) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "invalid_authorization_details: each entry needs a 'type'".to_string(),
+            ));
+        }
+
+        Ok(slf)
+    }
+
+    /// Parses and validates the optional `authorization_details` for the given client
+    pub fn try_for_client(
+        value: Option<&str>,
+        client: &Client,
+    ) -> Result<Option<Self>, ErrorResponse> {
+        match value {
+            None => Ok(None),
+            Some(value) => {
+                let slf = Self::try_from_str(value)?;
+                slf.validate_client(client)?;
+                Ok(Some(slf))
+            }
+        }
+    }
+
+    /// Checks, that all requested `type`s are allowed for the client
+    pub fn validate_client(&self, client: &Client) -> Result<(), ErrorResponse> {
+        let allowed = client.get_authorization_details_types().unwrap_or_default();
+        for detail in &self.0 {
+            if !allowed.contains(&detail.typ) {
+                warn!(
+                    "authorization_details type '{}' is not allowed for client '{}'",
+                    detail.typ, client.id
+                );
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    format!(
+                        "invalid_authorization_details: type '{}' is not allowed for this client",
+                        detail.typ
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// At the token endpoint, a client may only narrow down the already granted details. Each
+    /// requested entry must exactly match a granted one.
+    pub fn validate_subset_of(&self, granted: &Self) -> Result<(), ErrorResponse> {
+        if self.0.iter().all(|d| granted.0.contains(d)) {
+            Ok(())
+        } else {
+            Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "invalid_authorization_details: exceeds the granted details".to_string(),
+            ))
+        }
+    }
+}
+
+/// The `authorization_details` are a JSON encoded string as a query or form parameter, but a
+/// plain JSON array inside request objects. Both are accepted and kept as a string.
+pub fn deserialize_authorization_details<'de, D>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(s)) => Some(s),
+        Some(v) => Some(v.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization_details() {
+        let client = Client {
+            authorization_details_types: Some("payment_initiation,account_information".to_string()),
+            ..Default::default()
+        };
+
+        let value = r#"[{
+            "type": "payment_initiation",
+            "actions": ["initiate"],
+            "instructedAmount": {"currency": "EUR", "amount": "123.50"}
+        }]"#;
+        let details = AuthorizationDetails::try_for_client(Some(value), &client)
+            .unwrap()
+            .unwrap();
+        assert_eq!(details.0.len(), 1);
+        assert_eq!(details.0[0].typ, "payment_initiation");
+        assert!(details.0[0].fields.contains_key("instructedAmount"));
+
+        // the fields must survive a roundtrip into the tokens
+        let json = serde_json::to_string(&details).unwrap();
+        assert_eq!(AuthorizationDetails::try_from_str(&json).unwrap(), details);
+        assert!(details.validate_subset_of(&details).is_ok());
+
+        let other =
+            AuthorizationDetails::try_from_str(r#"[{"type": "account_information"}]"#).unwrap();
+        assert!(other.validate_client(&client).is_ok());
+        assert!(other.validate_subset_of(&details).is_err());
+
+        let forbidden = r#"[{"type": "document_signing"}]"#;
+        assert!(AuthorizationDetails::try_for_client(Some(forbidden), &client).is_err());
+        assert!(AuthorizationDetails::try_from_str("[]").is_err());
+        assert!(AuthorizationDetails::try_from_str(r#"[{"actions": []}]"#).is_err());
+        assert!(AuthorizationDetails::try_from_str(r#"{"type": "a"}"#).is_err());
+    }
+}
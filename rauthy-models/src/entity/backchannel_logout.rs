@@ -0,0 +1,280 @@
+use crate::app_state::AppState;
+use crate::entity::clients::Client;
+use crate::entity::jwk::{JwkKeyPair, JwkKeyPairAlg};
+use crate::entity::sessions::Session;
+use crate::{sign_jwt, JwtLogoutClaims};
+use actix_web::web;
+use jwt_simple::algorithms::{
+    ECDSAP256KeyPairLike, ECDSAP384KeyPairLike, EdDSAKeyPairLike, RSAKeyPairLike,
+};
+use jwt_simple::claims::Claims;
+use jwt_simple::prelude::coarsetime;
+use rauthy_common::constants::{
+    BACKCHANNEL_LOGOUT_EVENT, BACKCHANNEL_LOGOUT_RETRY_COUNT, BACKCHANNEL_LOGOUT_TOKEN_LIFETIME,
+};
+use rauthy_common::error_response::ErrorResponse;
+use rauthy_common::utils::get_rand;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+/// Links a session to each client, which has received tokens for it and has a
+/// `backchannel_logout_uri` or `frontchannel_logout_uri` configured.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SessionClient {
+    pub session_id: String,
+    pub client_id: String,
+    pub user_id: String,
+}
+
+// CRUD
+impl SessionClient {
+    pub async fn upsert(
+        data: &web::Data<AppState>,
+        session_id: &str,
+        client_id: &str,
+        user_id: &str,
+    ) -> Result<(), ErrorResponse> {
+        #[cfg(feature = "sqlite")]
+        let q = sqlx::query!(
+            r#"insert or ignore into sessions_clients (session_id, client_id, user_id)
+            values ($1, $2, $3)"#,
+            session_id,
+            client_id,
+            user_id,
+        );
+
+        #[cfg(not(feature = "sqlite"))]
+        let q = sqlx::query!(
+            r#"insert into sessions_clients (session_id, client_id, user_id)
+            values ($1, $2, $3)
+            on conflict(session_id, client_id) do nothing"#,
+            session_id,
+            client_id,
+            user_id,
+        );
+
+        q.execute(&data.db).await?;
+        Ok(())
+    }
+
+    pub async fn find_for_session(
+        data: &web::Data<AppState>,
+        session_id: &str,
+    ) -> Result<Vec<Self>, ErrorResponse> {
+        let res = sqlx::query_as!(
+            Self,
+            "select * from sessions_clients where session_id = $1",
+            session_id
+        )
+        .fetch_all(&data.db)
+        .await?;
+        Ok(res)
+    }
+
+    pub async fn find_for_user(
+        data: &web::Data<AppState>,
+        user_id: &str,
+    ) -> Result<Vec<Self>, ErrorResponse> {
+        let res = sqlx::query_as!(
+            Self,
+            "select * from sessions_clients where user_id = $1",
+            user_id
+        )
+        .fetch_all(&data.db)
+        .await?;
+        Ok(res)
+    }
+
+    pub async fn delete_for_session(
+        data: &web::Data<AppState>,
+        session_id: &str,
+    ) -> Result<(), ErrorResponse> {
+        sqlx::query!(
+            "delete from sessions_clients where session_id = $1",
+            session_id
+        )
+        .execute(&data.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_user(
+        data: &web::Data<AppState>,
+        user_id: &str,
+    ) -> Result<(), ErrorResponse> {
+        sqlx::query!("delete from sessions_clients where user_id = $1", user_id)
+            .execute(&data.db)
+            .await?;
+        Ok(())
+    }
+}
+
+/// A signed `logout_token` for OIDC Back-Channel Logout, which is waiting for its delivery.
+#[derive(Debug)]
+pub struct BackchannelLogout {
+    pub client_id: String,
+    pub uri: String,
+    pub logout_token: String,
+}
+
+impl BackchannelLogout {
+    /// Notifies all clients, which have been used with the given session, about its logout.
+    /// Errors are only logged, since they must never prevent the logout itself.
+    pub async fn notify_session(data: &web::Data<AppState>, session_id: &str) {
+        match SessionClient::find_for_session(data, session_id).await {
+            Ok(sessions) if !sessions.is_empty() => {
+                Self::notify(data, sessions).await;
+                if let Err(err) = SessionClient::delete_for_session(data, session_id).await {
+                    error!("Error cleaning up backchannel logout sessions: {}", err);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => error!("Error looking up backchannel logout sessions: {}", err),
+        }
+    }
+
+    /// Notifies all clients, which have been used with any session of the given user, about
+    /// their logout. Errors are only logged, since they must never prevent the logout itself.
+    pub async fn notify_user(data: &web::Data<AppState>, user_id: &str) {
+        match SessionClient::find_for_user(data, user_id).await {
+            Ok(sessions) if !sessions.is_empty() => {
+                Self::notify(data, sessions).await;
+                if let Err(err) = SessionClient::delete_for_user(data, user_id).await {
+                    error!("Error cleaning up backchannel logout sessions: {}", err);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => error!("Error looking up backchannel logout sessions: {}", err),
+        }
+    }
+
+    async fn notify(data: &web::Data<AppState>, sessions: Vec<SessionClient>) {
+        for sc in sessions {
+            let client = match Client::find(data, sc.client_id).await {
+                Ok(client) => client,
+                Err(err) => {
+                    error!("Error looking up client for backchannel logout: {}", err);
+                    continue;
+                }
+            };
+            // the uri may have been removed in the meantime
+            let uri = match &client.backchannel_logout_uri {
+                Some(uri) => uri.clone(),
+                None => continue,
+            };
+            if let Err(err) = Client::validate_public_uri(&uri, "backchannel_logout_uri") {
+                error!(
+                    "Skipping backchannel logout for client {}: {}",
+                    client.id, err.message
+                );
+                continue;
+            }
+
+            let sid = Session::sid_from_id(&sc.session_id);
+            let logout_token = match Self::build_logout_token(data, &client, sc.user_id, sid).await
+            {
+                Ok(token) => token,
+                Err(err) => {
+                    error!("Error building backchannel logout token: {}", err);
+                    continue;
+                }
+            };
+
+            let req = Self {
+                client_id: client.id,
+                uri,
+                logout_token,
+            };
+            if let Err(err) = data.tx_backchannel_logout.send_async(req).await {
+                error!("Error sending backchannel logout to the sender: {}", err);
+            }
+        }
+    }
+
+    /// Builds a `logout_token`, signed with the same algorithm as the client's ID tokens
+    async fn build_logout_token(
+        data: &web::Data<AppState>,
+        client: &Client,
+        user_id: String,
+        sid: String,
+    ) -> Result<String, ErrorResponse> {
+        let mut events = HashMap::with_capacity(1);
+        events.insert(
+            BACKCHANNEL_LOGOUT_EVENT.to_string(),
+            serde_json::Value::Object(serde_json::Map::new()),
+        );
+        let custom_claims = JwtLogoutClaims {
+            events,
+            sid: Some(sid),
+        };
+
+        let claims = Claims::with_custom_claims(
+            custom_claims,
+            coarsetime::Duration::from_secs(BACKCHANNEL_LOGOUT_TOKEN_LIFETIME),
+        )
+        .with_issuer(data.issuer.clone())
+        .with_audience(client.id.clone())
+        .with_subject(client.subject_for(data, &user_id).await?)
+        .with_jwt_id(get_rand(24));
+
+        let key_pair_type = JwkKeyPairAlg::from_str(&client.id_token_alg)?;
+        let kp = JwkKeyPair::find_latest(data, &client.id_token_alg, key_pair_type).await?;
+        sign_jwt!(kp, claims)
+    }
+
+    async fn deliver(self) {
+        let mut retries = 0;
+        loop {
+            let res = Client::http_client()
+                .post(&self.uri)
+                .form(&[("logout_token", &self.logout_token)])
+                .send()
+                .await;
+
+            match res {
+                Ok(resp) if resp.status().is_success() => {
+                    debug!("Backchannel logout delivered to client {}", self.client_id);
+                    return;
+                }
+                Ok(resp) => warn!(
+                    "Backchannel logout for client {} returned status {}",
+                    self.client_id,
+                    resp.status().as_u16()
+                ),
+                Err(err) => warn!(
+                    "Backchannel logout for client {} failed: {}",
+                    self.client_id, err
+                ),
+            }
+
+            if retries >= *BACKCHANNEL_LOGOUT_RETRY_COUNT {
+                error!(
+                    "Giving up backchannel logout for client {} after {} retries",
+                    self.client_id, retries
+                );
+                return;
+            }
+            retries += 1;
+
+            // exponential backoff: 2s, 4s, 8s, ... capped at ~17 minutes
+            let secs = 2u64.pow(retries.min(10) as u32);
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+        }
+    }
+}
+
+/// Delivers the `logout_token`s to the clients' `backchannel_logout_uri`s in the background.
+/// Each delivery runs independently, so a slow or unreachable client does not block the others.
+pub async fn sender(rx: flume::Receiver<BackchannelLogout>) {
+    debug!("Backchannel logout sender started");
+
+    while let Ok(req) = rx.recv_async().await {
+        tokio::spawn(req.deliver());
+    }
+
+    warn!("Backchannel logout channel closed - exiting");
+}
@@ -0,0 +1,234 @@
+use crate::app_state::AppState;
+use crate::entity::clients::Client;
+use crate::entity::jwk::{JWKSPublicKey, JwkKeyPairAlg};
+use crate::JwtAudience;
+use actix_web::web;
+use rauthy_common::constants::{CACHE_NAME_CLIENT_ASSERTIONS, CLIENT_ASSERTION_MAX_LIFETIME};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::utils::base64_url_no_pad_decode;
+use redhac::{cache_get, cache_get_from, cache_get_value, cache_put};
+use ring::hmac;
+use serde::Deserialize;
+use std::str::FromStr;
+use time::OffsetDateTime;
+use tracing::warn;
+
+/// Client authentication with a signed JWT at the token endpoint from RFC 7523.
+/// Supports `private_key_jwt` validated against the client's JWKS and `client_secret_jwt`
+/// validated with the client secret.
+#[derive(Debug)]
+pub struct ClientAssertion {
+    pub header: ClientAssertionHeader,
+    pub claims: ClientAssertionClaims,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClientAssertionHeader {
+    pub alg: String,
+    pub kid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClientAssertionClaims {
+    /// Must be the `client_id`
+    pub iss: String,
+    /// Must be the `client_id`
+    pub sub: String,
+    /// Must contain the issuer or the token endpoint of this Rauthy instance
+    pub aud: JwtAudience,
+    pub exp: i64,
+    pub nbf: Option<i64>,
+    pub jti: String,
+}
+
+impl ClientAssertion {
+    /// Extracts the `sub` without any validation to be able to look up the client, if no
+    /// `client_id` has been given with the request.
+    pub fn client_id_unverified(token: &str) -> Result<String, ErrorResponse> {
+        Ok(Self::try_from_str(token)?.claims.sub)
+    }
+
+    /// Validates the given client assertion for the given client including its signature and
+    /// all claims. Each assertion can only be used once.
+    pub async fn validate(
+        data: &web::Data<AppState>,
+        client: &Client,
+        token: &str,
+    ) -> Result<(), ErrorResponse> {
+        let slf = Self::try_from_str(token)?;
+
+        // the `alg` must match the registered auth method, otherwise a `private_key_jwt` client
+        // could be downgraded to its secret and vice versa
+        let is_hmac = matches!(slf.header.alg.as_str(), "HS256" | "HS384" | "HS512");
+        let method = client.token_endpoint_auth_method.as_deref();
+        if (is_hmac && method != Some("client_secret_jwt"))
+            || (!is_hmac && method != Some("private_key_jwt"))
+        {
+            warn!(
+                "Client assertion 'alg' {} does not match the auth method of client '{}'",
+                slf.header.alg, client.id
+            );
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                format!(
+                    "Client assertion 'alg' {} is not allowed for this client",
+                    slf.header.alg
+                ),
+            ));
+        }
+
+        let is_valid = match slf.header.alg.as_str() {
+            "HS256" | "HS384" | "HS512" => slf.validate_hmac(client, token)?,
+            alg => {
+                let alg = JwkKeyPairAlg::from_str(alg).map_err(|_| {
+                    ErrorResponse::new(
+                        ErrorResponseType::BadRequest,
+                        format!("Client assertion 'alg' {} is not supported", alg),
+                    )
+                })?;
+                let key =
+                    JWKSPublicKey::find_for_alg(client.find_jwks().await?, &slf.header.kid, alg)?;
+                key.validate_token_signature(token).is_ok()
+            }
+        };
+        if !is_valid {
+            warn!(
+                "Invalid client assertion signature for client '{}'",
+                client.id
+            );
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                "Invalid 'client_assertion'".to_string(),
+            ));
+        }
+
+        slf.validate_claims(data, client)?;
+        slf.validate_replay(data, client).await
+    }
+
+    fn try_from_str(token: &str) -> Result<Self, ErrorResponse> {
+        let err = || {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Malformed 'client_assertion'".to_string(),
+            )
+        };
+
+        let (header, rest) = token.split_once('.').ok_or_else(err)?;
+        let (claims, _signature) = rest.split_once('.').ok_or_else(err)?;
+
+        let bytes = base64_url_no_pad_decode(header)?;
+        let header = serde_json::from_slice::<ClientAssertionHeader>(&bytes).map_err(|_| err())?;
+
+        let bytes = base64_url_no_pad_decode(claims)?;
+        let claims = serde_json::from_slice::<ClientAssertionClaims>(&bytes).map_err(|_| err())?;
+
+        Ok(Self { header, claims })
+    }
+
+    fn validate_hmac(&self, client: &Client, token: &str) -> Result<bool, ErrorResponse> {
+        let secret = client.get_secret_cleartext()?.ok_or_else(|| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "'client_secret_jwt' is only possible for confidential clients".to_string(),
+            )
+        })?;
+
+        let alg = match self.header.alg.as_str() {
+            "HS256" => hmac::HMAC_SHA256,
+            "HS384" => hmac::HMAC_SHA384,
+            _ => hmac::HMAC_SHA512,
+        };
+        let (message, sig) = token.rsplit_once('.').unwrap_or_default();
+        let sig = base64_url_no_pad_decode(sig)?;
+
+        let key = hmac::Key::new(alg, secret.as_bytes());
+        Ok(hmac::verify(&key, message.as_bytes(), &sig).is_ok())
+    }
+
+    fn validate_claims(
+        &self,
+        data: &web::Data<AppState>,
+        client: &Client,
+    ) -> Result<(), ErrorResponse> {
+        let claims = &self.claims;
+
+        if claims.iss != client.id || claims.sub != client.id {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                "Client assertion 'iss' and 'sub' must be the 'client_id'".to_string(),
+            ));
+        }
+
+        let token_endpoint = format!("{}/oidc/token", data.issuer);
+        if !claims.aud.contains(&data.issuer) && !claims.aud.contains(&token_endpoint) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                "Client assertion 'aud' does not contain this issuer".to_string(),
+            ));
+        }
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        if claims.exp < now {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                "Client assertion has expired".to_string(),
+            ));
+        }
+        if claims.exp > now + *CLIENT_ASSERTION_MAX_LIFETIME as i64 {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!(
+                    "Client assertion lifetime must not exceed {} seconds",
+                    *CLIENT_ASSERTION_MAX_LIFETIME
+                ),
+            ));
+        }
+        if claims.nbf.map(|nbf| nbf > now).unwrap_or(false) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                "Client assertion is not valid yet".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // The cache lifespan is the max lifetime for an assertion, which makes sure that a `jti`
+    // is blocked as long as the assertion itself could be valid.
+    async fn validate_replay(
+        &self,
+        data: &web::Data<AppState>,
+        client: &Client,
+    ) -> Result<(), ErrorResponse> {
+        let idx = format!("{}_{}", client.id, self.claims.jti);
+
+        let used = cache_get!(
+            i64,
+            CACHE_NAME_CLIENT_ASSERTIONS.to_string(),
+            idx.clone(),
+            &data.caches.ha_cache_config,
+            true
+        )
+        .await?;
+        if used.is_some() {
+            warn!(
+                "Client assertion replay detected for client '{}' with jti '{}'",
+                client.id, self.claims.jti
+            );
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                "Client assertion 'jti' has already been used".to_string(),
+            ));
+        }
+
+        cache_put(
+            CACHE_NAME_CLIENT_ASSERTIONS.to_string(),
+            idx,
+            &data.caches.ha_cache_config,
+            &self.claims.exp,
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use crate::entity::clients::Client;
+use actix_web::HttpRequest;
+use rauthy_common::constants::{PROXY_MODE, TLS_CLIENT_CERT_HEADER, TLS_CLIENT_VERIFY_HEADER};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::utils::{base64_decode, base64_url_no_pad_encode};
+use ring::digest;
+use tracing::warn;
+
+/// The X.509 certificate a client presented for mutual TLS (RFC 8705), either directly during
+/// the TLS handshake or forwarded by a reverse proxy in `PROXY_MODE`.
+#[derive(Debug, Clone)]
+pub struct ClientCertificate {
+    /// The DER encoded end-entity certificate
+    pub der: Vec<u8>,
+    /// `true`, if the certificate chain has been validated against a trusted CA
+    pub ca_verified: bool,
+}
+
+impl ClientCertificate {
+    /// Returns the certificate of the current connection. In `PROXY_MODE`, the URL encoded PEM
+    /// certificate from the `TLS_CLIENT_CERT_HEADER` is used as a fallback.
+    pub fn from_req(req: &HttpRequest) -> Result<Option<Self>, ErrorResponse> {
+        if let Some(cert) = req.conn_data::<ClientCertificate>() {
+            return Ok(Some(cert.clone()));
+        }
+
+        if !*PROXY_MODE {
+            return Ok(None);
+        }
+        let value = match TLS_CLIENT_CERT_HEADER
+            .as_deref()
+            .and_then(|name| req.headers().get(name))
+        {
+            None => return Ok(None),
+            Some(value) => value.to_str().unwrap_or_default(),
+        };
+        // proxies usually send an empty value, if the client did not present a certificate
+        if value.is_empty() {
+            return Ok(None);
+        }
+
+        let pem = urlencoding::decode(value).map_err(|_| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "The forwarded client certificate is not URL encoded".to_string(),
+            )
+        })?;
+        // the proxy has to tell us if it validated the chain - otherwise it is self-signed only
+        let ca_verified = TLS_CLIENT_VERIFY_HEADER
+            .as_deref()
+            .and_then(|name| req.headers().get(name))
+            .map(|value| value.as_bytes() == b"SUCCESS")
+            .unwrap_or(false);
+
+        Self::from_pem(pem.as_bytes(), ca_verified).map(Some)
+    }
+
+    pub fn from_pem(pem: &[u8], ca_verified: bool) -> Result<Self, ErrorResponse> {
+        let (_, pem) = x509_parser::pem::parse_x509_pem(pem).map_err(|_| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "The client certificate is not a valid PEM".to_string(),
+            )
+        })?;
+        let slf = Self {
+            der: pem.contents,
+            ca_verified,
+        };
+        // make sure we only ever work with valid certificates
+        slf.subject_dn()?;
+
+        Ok(slf)
+    }
+
+    /// The subject distinguished name like `CN=my-service, O=My Org`
+    pub fn subject_dn(&self) -> Result<String, ErrorResponse> {
+        x509_parser::parse_x509_certificate(&self.der)
+            .map(|(_, cert)| cert.subject().to_string())
+            .map_err(|_| {
+                ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    "The client certificate cannot be parsed".to_string(),
+                )
+            })
+    }
+
+    /// The base64 url encoded SHA-256 thumbprint of the certificate for the `cnf.x5t#S256`
+    /// claim of certificate-bound access tokens.
+    pub fn thumbprint(&self) -> String {
+        let hash = digest::digest(&digest::SHA256, &self.der);
+        base64_url_no_pad_encode(hash.as_ref())
+    }
+
+    /// Authenticates the client with this certificate, depending on its
+    /// `tls_client_auth_method`.
+    pub async fn validate_client_auth(&self, client: &Client) -> Result<(), ErrorResponse> {
+        match client.tls_client_auth_method.as_deref() {
+            Some("tls_client_auth") => {
+                if !self.ca_verified {
+                    warn!(
+                        "Client certificate for '{}' is not issued by a trusted CA",
+                        client.id
+                    );
+                    return Err(ErrorResponse::new(
+                        ErrorResponseType::Unauthorized,
+                        "The client certificate is not issued by a trusted CA".to_string(),
+                    ));
+                }
+
+                let expected = client
+                    .tls_client_auth_subject_dn
+                    .as_deref()
+                    .ok_or_else(|| {
+                        ErrorResponse::new(
+                            ErrorResponseType::Internal,
+                            format!("Client '{}' has no 'tls_client_auth_subject_dn'", client.id),
+                        )
+                    })?;
+                let subject_dn = self.subject_dn()?;
+                if !Self::dn_matches(&subject_dn, expected) {
+                    warn!(
+                        "Client certificate subject '{}' does not match for '{}'",
+                        subject_dn, client.id
+                    );
+                    return Err(ErrorResponse::new(
+                        ErrorResponseType::Unauthorized,
+                        "The client certificate subject does not match".to_string(),
+                    ));
+                }
+
+                Ok(())
+            }
+
+            Some("self_signed_tls_client_auth") => {
+                // the certificate must be registered as the first `x5c` entry of a client key
+                let is_registered = client.find_jwks_raw().await?.iter().any(|key| {
+                    key.get("x5c")
+                        .and_then(|x5c| x5c.get(0))
+                        .and_then(|cert| cert.as_str())
+                        .and_then(|cert| base64_decode(cert).ok())
+                        .map(|der| der == self.der)
+                        .unwrap_or(false)
+                });
+                if !is_registered {
+                    warn!("Client certificate for '{}' is not registered", client.id);
+                    return Err(ErrorResponse::new(
+                        ErrorResponseType::Unauthorized,
+                        "The client certificate is not registered".to_string(),
+                    ));
+                }
+
+                Ok(())
+            }
+
+            _ => Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("Client '{}' does not use mutual TLS", client.id),
+            )),
+        }
+    }
+
+    // Compares the RDNs independent of their order and the whitespace, because the RFC 2253
+    // string representation lists them in reverse.
+    fn dn_matches(subject_dn: &str, expected: &str) -> bool {
+        let rdns = |dn: &str| {
+            let mut rdns = dn
+                .split(',')
+                .map(|rdn| rdn.trim().to_string())
+                .filter(|rdn| !rdn.is_empty())
+                .collect::<Vec<String>>();
+            rdns.sort();
+            rdns
+        };
+        rdns(subject_dn) == rdns(expected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_certificate() {
+        let cert = ClientCertificate::from_pem(include_bytes!("../../../tls/cert-chain.pem"), true)
+            .unwrap();
+
+        let subject_dn = cert.subject_dn().unwrap();
+        assert_eq!(subject_dn, "CN=localhost, O=Rauthy OIDC");
+        assert!(ClientCertificate::dn_matches(
+            &subject_dn,
+            "O=Rauthy OIDC,CN=localhost"
+        ));
+        assert!(!ClientCertificate::dn_matches(&subject_dn, "CN=localhost"));
+
+        assert_eq!(
+            cert.thumbprint(),
+            "EcL35XNo48bsdCIH820xGtfS9DrQqewQy1vf6I6ev3E"
+        );
+
+        assert!(ClientCertificate::from_pem(b"no certificate", true).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use crate::app_state::{AppState, DbTxn};
 use crate::entity::clients_dyn::ClientDyn;
-use crate::entity::jwk::JwkKeyPairAlg;
+use crate::entity::jwe::{JweAlg, JweEnc, JweRecipientKey};
+use crate::entity::jwk::{JWKSPublicKey, JwkKeyPairAlg};
+use crate::entity::pairwise_subjects::PairwiseSubject;
 use crate::entity::scopes::Scope;
+use crate::entity::user_consents::UserConsent;
 use crate::entity::users::User;
 use crate::request::{DynamicClientRequest, EphemeralClientRequest, NewClientRequest};
 use crate::response::DynamicClientResponse;
@@ -14,9 +17,11 @@ use cryptr::{utils, EncKeys, EncValue};
 use futures_util::StreamExt;
 use rauthy_common::constants::{
     ADMIN_FORCE_MFA, APPLICATION_JSON, CACHE_NAME_12HR, CACHE_NAME_EPHEMERAL_CLIENTS,
-    DYN_CLIENT_DEFAULT_TOKEN_LIFETIME, DYN_CLIENT_SECRET_AUTO_ROTATE, ENABLE_EPHEMERAL_CLIENTS,
-    EPHEMERAL_CLIENTS_ALLOWED_FLOWS, EPHEMERAL_CLIENTS_ALLOWED_SCOPES, EPHEMERAL_CLIENTS_FORCE_MFA,
-    IDX_CLIENTS, IDX_CLIENT_LOGO, PROXY_MODE, RAUTHY_VERSION,
+    CLIENT_JWKS_CACHE_LIFETIME, DEV_MODE, DYN_CLIENT_DEFAULT_TOKEN_LIFETIME,
+    DYN_CLIENT_SECRET_AUTO_ROTATE, ENABLE_EPHEMERAL_CLIENTS, EPHEMERAL_CLIENTS_ALLOWED_FLOWS,
+    EPHEMERAL_CLIENTS_ALLOWED_SCOPES, EPHEMERAL_CLIENTS_FORCE_MFA,
+    EPHEMERAL_CLIENTS_REQUIRE_CONSENT, IDX_CLIENTS, IDX_CLIENT_LOGO, PAIRWISE_SUBJECT_SECRET,
+    PROXY_MODE, RAUTHY_VERSION, RE_CLIENT_AUTH_METHOD, RE_TLS_CLIENT_AUTH_METHOD,
 };
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
 use rauthy_common::utils::{cache_entry_client, get_client_ip, get_rand};
@@ -24,13 +29,17 @@ use redhac::{
     cache_del, cache_get, cache_get_from, cache_get_value, cache_insert, cache_put, cache_remove,
     AckLevel,
 };
-use reqwest::header::CONTENT_TYPE;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::redirect::Policy;
 use reqwest::{tls, Url};
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, Row};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
-use std::sync::OnceLock;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tracing::{debug, error, warn};
 use utoipa::ToSchema;
 use validator::Validate;
@@ -38,6 +47,12 @@ use validator::Validate;
 const RAUTHY_DEFAULT_LOGO: &str = "data:image/svg+xml;base64,PD94bWwgdmVyc2lvbj0iMS4wIiBlbmNvZGluZz0iVVRGLTgiIHN0YW5kYWxvbmU9Im5vIj8+CjwhRE9DVFlQRSBzdmcgUFVCTElDICItLy9XM0MvL0RURCBTVkcgMS4xLy9FTiIgImh0dHA6Ly93d3cudzMub3JnL0dyYXBoaWNzL1NWRy8xLjEvRFREL3N2ZzExLmR0ZCI+Cjxzdmcgd2lkdGg9IjEwMCUiIGhlaWdodD0iMTAwJSIgdmlld0JveD0iMCAwIDUxMiAxMzgiIHZlcnNpb249IjEuMSIgeG1sbnM9Imh0dHA6Ly93d3cudzMub3JnLzIwMDAvc3ZnIiB4bWxuczp4bGluaz0iaHR0cDovL3d3dy53My5vcmcvMTk5OS94bGluayIgeG1sOnNwYWNlPSJwcmVzZXJ2ZSIgeG1sbnM6c2VyaWY9Imh0dHA6Ly93d3cuc2VyaWYuY29tLyIgc3R5bGU9ImZpbGwtcnVsZTpldmVub2RkO2NsaXAtcnVsZTpldmVub2RkO3N0cm9rZS1saW5lY2FwOnJvdW5kO3N0cm9rZS1saW5lam9pbjpyb3VuZDtzdHJva2UtbWl0ZXJsaW1pdDoxLjU7Ij4KICAgIDxnIHRyYW5zZm9ybT0ibWF0cml4KDEsMCwwLDEsMCwtMTEpIj4KICAgICAgICA8ZyB0cmFuc2Zvcm09Im1hdHJpeCgxLDAsMCwxLDAsLTE3NikiPgogICAgICAgICAgICA8ZyB0cmFuc2Zvcm09Im1hdHJpeCgwLjkyMDMyNSwwLDAsMS44NDE1MSw0NS45Mjc5LDI2LjQ1OSkiPgogICAgICAgICAgICAgICAgPHJlY3QgeD0iMjcuNzQxIiB5PSIxNTEuNTciIHdpZHRoPSIyMDAuNTE3IiBoZWlnaHQ9IjEwLjE0OCIgc3R5bGU9ImZpbGw6cmdiKDQsNywxMSk7Ii8+CiAgICAgICAgICAgIDwvZz4KICAgICAgICAgICAgPGcgdHJhbnNmb3JtPSJtYXRyaXgoMS45MzQ3MiwwLDAsMS44MjczMiw4LjM1NjE4LDI4Ljc1MzMpIj4KICAgICAgICAgICAgICAgIDxyZWN0IHg9IjMzLjMwNyIgeT0iOTcuMTUiIHdpZHRoPSI5NC42OTMiIGhlaWdodD0iNTQuNDIiIHN0eWxlPSJmaWxsOnJnYig0LDcsMTEpO3N0cm9rZTpyZ2IoNCw3LDExKTtzdHJva2Utd2lkdGg6MS4wNnB4OyIvPgogICAgICAgICAgICA8L2c+CiAgICAgICAgICAgIDxnIHRyYW5zZm9ybT0ibWF0cml4KDEuODI3MzIsMCwwLDEuODI3MzIsLTE2MC44MjIsNzAuMTgwNikiPgogICAgICAgICAgICAgICAgPGcgdHJhbnNmb3JtPSJtYXRyaXgoNzIsMCwwLDcyLDIyNy4xNzQsMTIzLjQxNykiPgogICAgICAgICAgICAgICAgPC9nPgogICAgICAgICAgICAgICAgPHRleHQgeD0iMTI4Ljk4MnB4IiB5PSIxMjMuNDE3cHgiIHN0eWxlPSJmb250LWZhbWlseTonQ2FsaWJyaS1Cb2xkJywgJ0NhbGlicmknLCBzYW5zLXNlcmlmO2ZvbnQtd2VpZ2h0OjcwMDtmb250LXNpemU6NzJweDtmaWxsOndoaXRlOyI+cjx0c3BhbiB4PSIxNTIuOTk0cHggMTg4LjUzN3B4ICIgeT0iMTIzLjQxN3B4IDEyMy40MTdweCAiPmF1PC90c3Bhbj48L3RleHQ+CiAgICAgICAgICAgIDwvZz4KICAgICAgICAgICAgPGcgdHJhbnNmb3JtPSJtYXRyaXgoMSwwLDAsMS4wMTYxNywtMS40MjEwOWUtMTQsLTUuMjQ0OTIpIj4KICAgICAgICAgICAgICAgIDxwYXRoIGQ9Ik00NDAuOTM2LDMyMi42NDNMNDM5LjIwNCwzMjQuMjY2TDI1NS40ODIsMzI0LjI2NkwyNTUuNDgyLDMwNS43MjFMNDQwLjkzNiwzMDUuNzIxTDQ0MC45MzYsMzIyLjY0M1oiIHN0eWxlPSJmaWxsOnVybCgjX0xpbmVhcjEpOyIvPgogICAgICAgICAgICA8L2c+CiAgICAgICAgICAgIDxnIHRyYW5zZm9ybT0ibWF0cml4KDAuOTIwMTkxLDAsMCwxLjg0MTIxLDQ2LjI0NjQsLTkxLjMzODMpIj4KICAgICAgICAgICAgICAgIDxyZWN0IHg9IjI3Ljc0MSIgeT0iMTUxLjU3IiB3aWR0aD0iMjAwLjUxNyIgaGVpZ2h0PSIxMC4xNDgiIHN0eWxlPSJmaWxsOnVybCgjX0xpbmVhcjIpOyIvPgogICAgICAgICAgICA8L2c+CiAgICAgICAgICAgIDxnIHRyYW5zZm9ybT0ibWF0cml4KDEuOTc1OTgsMCwwLDEuODQ2MTksMTkwLjE4NywyNi4wNjIpIj4KICAgICAgICAgICAgICAgIDxyZWN0IHg9IjMzLjMwNyIgeT0iOTcuMTUiIHdpZHRoPSI5NC42OTMiIGhlaWdodD0iNTQuNDIiIHN0eWxlPSJmaWxsOnJnYig0Myw2NSwxMDcpOyIvPgogICAgICAgICAgICA8L2c+CiAgICAgICAgICAgIDxwYXRoIGQ9Ik00MzkuMjA0LDE4Ny43MzRMNDQwLjU1NywxODkuMDA3TDQ0MC41NTcsMjA2LjI3OUwyNTYsMjA2LjI3OUwyNTYsMTg3LjczNEw0MzkuMjA0LDE4Ny43MzRaIiBzdHlsZT0iZmlsbDpyZ2IoNDMsNjUsMTA3KTsiLz4KICAgICAgICAgICAgPGcgdHJhbnNmb3JtPSJtYXRyaXgoMS44MjczMiwwLDAsMS44MjczMiwtMTU0LjY2MSw3MC4xODA2KSI+CiAgICAgICAgICAgICAgICA8ZyB0cmFuc2Zvcm09Im1hdHJpeCg3MiwwLDAsNzIsMzIzLjA0NSwxMjMuNDE3KSI+CiAgICAgICAgICAgICAgICA8L2c+CiAgICAgICAgICAgICAgICA8dGV4dCB4PSIyMjYuNjQ2cHgiIHk9IjEyMy40MTdweCIgc3R5bGU9ImZvbnQtZmFtaWx5OidDYWxpYnJpLUJvbGQnLCAnQ2FsaWJyaScsIHNhbnMtc2VyaWY7Zm9udC13ZWlnaHQ6NzAwO2ZvbnQtc2l6ZTo3MnB4O2ZpbGw6d2hpdGU7Ij50aDx0c3BhbiB4PSIyODguOTQzcHggIiB5PSIxMjMuNDE3cHggIj55PC90c3Bhbj48L3RleHQ+CiAgICAgICAgICAgIDwvZz4KICAgICAgICAgICAgPGcgdHJhbnNmb3JtPSJtYXRyaXgoMiwwLDAsMiwwLDApIj4KICAgICAgICAgICAgICAgIDxwYXRoIGQ9Ik0yMTkuNjAyLDkzLjg2N0wyNTYsMTI4TDIxOS42MDIsMTYyLjEzM0wyMTkuNjAyLDkzLjg2N1oiIHN0eWxlPSJmaWxsOnJnYig0Myw2NSwxMDcpOyIvPgogICAgICAgICAgICA8L2c+CiAgICAgICAgICAgIDxnIHRyYW5zZm9ybT0ibWF0cml4KDIsMCwwLDEuOTU3MzksMCwzLjk5OTk3KSI+CiAgICAgICAgICAgICAgICA8cGF0aCBkPSJNMzYuMzk4LDkzLjg2N0wwLDkzLjg2N0wzNS45MDgsMTI4LjUyNEwwLDE2My42MTlMMzYuMzk4LDE2My42MTkiIHN0eWxlPSJmaWxsOnJnYig0LDcsMTEpOyIvPgogICAgICAgICAgICA8L2c+CiAgICAgICAgPC9nPgogICAgPC9nPgogICAgPGRlZnM+CiAgICAgICAgPGxpbmVhckdyYWRpZW50IGlkPSJfTGluZWFyMSIgeDE9IjAiIHkxPSIwIiB4Mj0iMSIgeTI9IjAiIGdyYWRpZW50VW5pdHM9InVzZXJTcGFjZU9uVXNlIiBncmFkaWVudFRyYW5zZm9ybT0ibWF0cml4KDE4NS40NTQsMCwwLDE4LjU0NDMsMjU1LjQ4MiwzMTQuOTk0KSI+PHN0b3Agb2Zmc2V0PSIwIiBzdHlsZT0ic3RvcC1jb2xvcjpyZ2IoNCw3LDExKTtzdG9wLW9wYWNpdHk6MSIvPjxzdG9wIG9mZnNldD0iMSIgc3R5bGU9InN0b3AtY29sb3I6cmdiKDQzLDY1LDEwNyk7c3RvcC1vcGFjaXR5OjEiLz48L2xpbmVhckdyYWRpZW50PgogICAgICAgIDxsaW5lYXJHcmFkaWVudCBpZD0iX0xpbmVhcjIiIHgxPSIwIiB5MT0iMCIgeDI9IjEiIHkyPSIwIiBncmFkaWVudFVuaXRzPSJ1c2VyU3BhY2VPblVzZSIgZ3JhZGllbnRUcmFuc2Zvcm09Im1hdHJpeCgyMDAuNTE3LDAsMCwxMC4xNDgzLDI3Ljc0MTQsMTU2LjY0NSkiPjxzdG9wIG9mZnNldD0iMCIgc3R5bGU9InN0b3AtY29sb3I6cmdiKDQsNywxMSk7c3RvcC1vcGFjaXR5OjEiLz48c3RvcCBvZmZzZXQ9IjEiIHN0eWxlPSJzdG9wLWNvbG9yOnJnYig0Myw2NSwxMDcpO3N0b3Atb3BhY2l0eToxIi8+PC9saW5lYXJHcmFkaWVudD4KICAgIDwvZGVmcz4KPC9zdmc+Cg==";
 
 static HTTP_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+// jwks_uri -> (fetched at, keys)
+static CLIENT_JWKS_CACHE: OnceLock<Mutex<HashMap<String, (Instant, Vec<serde_json::Value>)>>> =
+    OnceLock::new();
+
+// The max size of any document, which is fetched from a client provided URI
+const CLIENT_FETCH_MAX_BYTES: usize = 512 * 1024;
 
 /**
 # OIDC Client
@@ -63,9 +78,9 @@ pub struct Client {
     pub post_logout_redirect_uris: Option<String>,
     pub allowed_origins: Option<String>,
     pub flows_enabled: String,
-    // Currently supported Algorithms: RS 256, 384, 512 and ES 256, 384, 512
+    // Currently supported Algorithms: RS 256, 384, 512, EdDSA and ES 256, 384
     pub access_token_alg: String,
-    // Currently supported Algorithms: RS 256, 384, 512 and ES 256, 384, 512
+    // Currently supported Algorithms: RS 256, 384, 512, EdDSA and ES 256, 384
     pub id_token_alg: String,
     pub refresh_token: bool,
     pub auth_code_lifetime: i32,
@@ -74,6 +89,47 @@ pub struct Client {
     pub default_scopes: String,
     pub challenge: Option<String>,
     pub force_mfa: bool,
+    pub require_par: bool,
+    // Public keys for the validation of signed request objects as a JSON encoded JWKS
+    pub jwks: Option<String>,
+    pub jwks_uri: Option<String>,
+    pub require_request_object: bool,
+    // Token Exchange policy as CSV values: which audiences and scopes may be requested and
+    // which subject token types are accepted
+    pub token_exchange_audiences: Option<String>,
+    pub token_exchange_scopes: Option<String>,
+    pub token_exchange_subject_types: Option<String>,
+    pub backchannel_logout_uri: Option<String>,
+    pub frontchannel_logout_uri: Option<String>,
+    // `public` if not set
+    pub subject_type: Option<String>,
+    pub sector_identifier_uri: Option<String>,
+    // JWE settings for encrypted ID tokens and userinfo responses. The public key is taken
+    // from the `jwks` / `jwks_uri`.
+    pub id_token_encrypted_response_alg: Option<String>,
+    pub id_token_encrypted_response_enc: Option<String>,
+    pub userinfo_encrypted_response_alg: Option<String>,
+    pub userinfo_encrypted_response_enc: Option<String>,
+    // Signs userinfo responses as JWTs, if set. Same algorithms as for the `id_token_alg`.
+    pub userinfo_signed_response_alg: Option<String>,
+    // The allowed `type`s of the `authorization_details` (RFC 9396) as CSV. Rich Authorization
+    // Requests are rejected for this client, if not set.
+    pub authorization_details_types: Option<String>,
+    // Mutual TLS client authentication (RFC 8705): `tls_client_auth` validates the certificate
+    // chain and matches the `tls_client_auth_subject_dn`, `self_signed_tls_client_auth` matches
+    // the certificate against the `x5c` of the registered keys. Replaces the `client_secret`.
+    pub tls_client_auth_method: Option<String>,
+    pub tls_client_auth_subject_dn: Option<String>,
+    pub tls_client_certificate_bound_access_tokens: bool,
+    // Shows the consent screen for all scopes the user has not granted to this client yet
+    pub require_consent: bool,
+    // Allows users to log in with a magic link or one-time code sent via email
+    pub allow_email_login: bool,
+    // `client_secret_basic`, `client_secret_post`, `client_secret_jwt` or `private_key_jwt`.
+    // Only the `client_secret` is accepted, if not set.
+    pub token_endpoint_auth_method: Option<String>,
+    // The only `request_uri`s as CSV, from which request objects will be fetched
+    pub request_uris: Option<String>,
 }
 
 // CRUD
@@ -100,8 +156,17 @@ impl Client {
             r#"insert into clients (id, name, enabled, confidential, secret, secret_kid,
             redirect_uris, post_logout_redirect_uris, allowed_origins, flows_enabled, access_token_alg,
             id_token_alg, refresh_token, auth_code_lifetime, access_token_lifetime, scopes, default_scopes,
-            challenge, force_mfa)
-            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)"#,
+            challenge, force_mfa, require_par, jwks, jwks_uri, require_request_object,
+            token_exchange_audiences, token_exchange_scopes, token_exchange_subject_types,
+            backchannel_logout_uri, frontchannel_logout_uri, subject_type, sector_identifier_uri,
+            id_token_encrypted_response_alg, id_token_encrypted_response_enc, userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc, userinfo_signed_response_alg,
+            authorization_details_types, tls_client_auth_method, tls_client_auth_subject_dn,
+            tls_client_certificate_bound_access_tokens, require_consent, allow_email_login,
+            token_endpoint_auth_method, request_uris)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37, $38, $39, $40,
+            $41, $42, $43)"#,
             client.id,
             client.name,
             client.enabled,
@@ -121,6 +186,30 @@ impl Client {
             client.default_scopes,
             client.challenge,
             client.force_mfa,
+            client.require_par,
+            client.jwks,
+            client.jwks_uri,
+            client.require_request_object,
+            client.token_exchange_audiences,
+            client.token_exchange_scopes,
+            client.token_exchange_subject_types,
+            client.backchannel_logout_uri,
+            client.frontchannel_logout_uri,
+            client.subject_type,
+            client.sector_identifier_uri,
+            client.id_token_encrypted_response_alg,
+            client.id_token_encrypted_response_enc,
+            client.userinfo_encrypted_response_alg,
+            client.userinfo_encrypted_response_enc,
+            client.userinfo_signed_response_alg,
+            client.authorization_details_types,
+            client.tls_client_auth_method,
+            client.tls_client_auth_subject_dn,
+            client.tls_client_certificate_bound_access_tokens,
+            client.require_consent,
+            client.allow_email_login,
+            client.token_endpoint_auth_method,
+            client.request_uris,
         )
             .execute(&data.db)
             .await?
@@ -155,6 +244,10 @@ impl Client {
             .unwrap_or_else(|| "client_secret_basic".to_string());
 
         let client = Self::try_from_dyn_reg(client_req)?;
+        client.validate_subject_type().await?;
+        client.validate_encryption().await?;
+        client.validate_tls_client_auth()?;
+        client.validate_token_endpoint_auth()?;
 
         let mut txn = data.db.begin().await?;
 
@@ -162,8 +255,17 @@ impl Client {
             r#"INSERT INTO clients (id, name, enabled, confidential, secret, secret_kid,
             redirect_uris, post_logout_redirect_uris, allowed_origins, flows_enabled, access_token_alg,
             id_token_alg, refresh_token, auth_code_lifetime, access_token_lifetime, scopes, default_scopes,
-            challenge, force_mfa)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)"#,
+            challenge, force_mfa, require_par, jwks, jwks_uri, require_request_object,
+            token_exchange_audiences, token_exchange_scopes, token_exchange_subject_types,
+            backchannel_logout_uri, frontchannel_logout_uri, subject_type, sector_identifier_uri,
+            id_token_encrypted_response_alg, id_token_encrypted_response_enc, userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc, userinfo_signed_response_alg,
+            authorization_details_types, tls_client_auth_method, tls_client_auth_subject_dn,
+            tls_client_certificate_bound_access_tokens, require_consent, allow_email_login,
+            token_endpoint_auth_method, request_uris)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37, $38, $39, $40,
+            $41, $42, $43)"#,
             client.id,
             client.name,
             client.enabled,
@@ -183,6 +285,30 @@ impl Client {
             client.default_scopes,
             client.challenge,
             client.force_mfa,
+            client.require_par,
+            client.jwks,
+            client.jwks_uri,
+            client.require_request_object,
+            client.token_exchange_audiences,
+            client.token_exchange_scopes,
+            client.token_exchange_subject_types,
+            client.backchannel_logout_uri,
+            client.frontchannel_logout_uri,
+            client.subject_type,
+            client.sector_identifier_uri,
+            client.id_token_encrypted_response_alg,
+            client.id_token_encrypted_response_enc,
+            client.userinfo_encrypted_response_alg,
+            client.userinfo_encrypted_response_enc,
+            client.userinfo_signed_response_alg,
+            client.authorization_details_types,
+            client.tls_client_auth_method,
+            client.tls_client_auth_subject_dn,
+            client.tls_client_certificate_bound_access_tokens,
+            client.require_consent,
+            client.allow_email_login,
+            client.token_endpoint_auth_method,
+            client.request_uris,
         )
             .execute(&mut *txn)
             .await?;
@@ -215,6 +341,8 @@ impl Client {
         sqlx::query!("delete from clients where id = $1", self.id,)
             .execute(&data.db)
             .await?;
+        // consents have no foreign key, because ephemeral clients can have them as well
+        UserConsent::delete_by_client(data, &self.id).await?;
 
         // let clients = Client::find_all(data)
         //     .await?
@@ -438,7 +566,20 @@ impl Client {
             secret_kid = $5, redirect_uris = $6, post_logout_redirect_uris = $7, allowed_origins = $8,
             flows_enabled = $9, access_token_alg = $10, id_token_alg = $11, refresh_token = $12,
             auth_code_lifetime = $13, access_token_lifetime = $14, scopes = $15, default_scopes = $16,
-            challenge = $17, force_mfa= $18 where id = $19"#,
+            challenge = $17, force_mfa= $18, require_par = $19, jwks = $20, jwks_uri = $21,
+            require_request_object = $22, token_exchange_audiences = $23, token_exchange_scopes = $24,
+            token_exchange_subject_types = $25,
+            backchannel_logout_uri = $26,
+            frontchannel_logout_uri = $27,
+            subject_type = $28, sector_identifier_uri = $29,
+            id_token_encrypted_response_alg = $30, id_token_encrypted_response_enc = $31,
+            userinfo_encrypted_response_alg = $32, userinfo_encrypted_response_enc = $33,
+            userinfo_signed_response_alg = $34,
+            authorization_details_types = $35,
+            tls_client_auth_method = $36, tls_client_auth_subject_dn = $37,
+            tls_client_certificate_bound_access_tokens = $38, require_consent = $39,
+            allow_email_login = $40, token_endpoint_auth_method = $41,
+            request_uris = $42 where id = $43"#,
             self.name,
             self.enabled,
             self.confidential,
@@ -457,6 +598,30 @@ impl Client {
             self.default_scopes,
             self.challenge,
             self.force_mfa,
+            self.require_par,
+            self.jwks,
+            self.jwks_uri,
+            self.require_request_object,
+            self.token_exchange_audiences,
+            self.token_exchange_scopes,
+            self.token_exchange_subject_types,
+            self.backchannel_logout_uri,
+            self.frontchannel_logout_uri,
+            self.subject_type,
+            self.sector_identifier_uri,
+            self.id_token_encrypted_response_alg,
+            self.id_token_encrypted_response_enc,
+            self.userinfo_encrypted_response_alg,
+            self.userinfo_encrypted_response_enc,
+            self.userinfo_signed_response_alg,
+            self.authorization_details_types,
+            self.tls_client_auth_method,
+            self.tls_client_auth_subject_dn,
+            self.tls_client_certificate_bound_access_tokens,
+            self.require_consent,
+            self.allow_email_login,
+            self.token_endpoint_auth_method,
+            self.request_uris,
             self.id,
         );
 
@@ -512,6 +677,10 @@ impl Client {
             .unwrap_or_else(|| "client_secret_basic".to_string());
 
         let mut new_client = Self::try_from_dyn_reg(client_req)?;
+        new_client.validate_subject_type().await?;
+        new_client.validate_encryption().await?;
+        new_client.validate_tls_client_auth()?;
+        new_client.validate_token_endpoint_auth()?;
         let current = Self::find(data, client_dyn.id.clone()).await?;
         if !current.is_dynamic() {
             return Err(ErrorResponse::new(
@@ -523,6 +692,13 @@ impl Client {
         // we need to keep some old and possibly user-modified values
         new_client.id = current.id;
         new_client.force_mfa = current.force_mfa;
+        new_client.require_par = current.require_par;
+        new_client.jwks = current.jwks;
+        new_client.require_request_object = current.require_request_object;
+        new_client.request_uris = current.request_uris;
+        new_client.token_exchange_audiences = current.token_exchange_audiences;
+        new_client.token_exchange_scopes = current.token_exchange_scopes;
+        new_client.token_exchange_subject_types = current.token_exchange_subject_types;
         new_client.scopes = current.scopes;
         new_client.default_scopes = current.default_scopes;
 
@@ -677,6 +853,12 @@ impl Client {
         )
     }
 
+    pub fn get_request_uris(&self) -> Option<Vec<String>> {
+        self.request_uris
+            .as_ref()
+            .map(|uris| uris.split(',').map(|i| i.trim().to_string()).collect())
+    }
+
     pub fn get_redirect_uris(&self) -> Vec<String> {
         self.redirect_uris
             .split(',')
@@ -696,6 +878,28 @@ impl Client {
         self.scopes.replace(',', " ")
     }
 
+    pub fn get_token_exchange_audiences(&self) -> Option<Vec<String>> {
+        Self::csv_to_vec(&self.token_exchange_audiences)
+    }
+
+    pub fn get_token_exchange_scopes(&self) -> Option<Vec<String>> {
+        Self::csv_to_vec(&self.token_exchange_scopes)
+    }
+
+    pub fn get_token_exchange_subject_types(&self) -> Option<Vec<String>> {
+        Self::csv_to_vec(&self.token_exchange_subject_types)
+    }
+
+    pub fn get_authorization_details_types(&self) -> Option<Vec<String>> {
+        Self::csv_to_vec(&self.authorization_details_types)
+    }
+
+    #[inline]
+    fn csv_to_vec(csv: &Option<String>) -> Option<Vec<String>> {
+        csv.as_ref()
+            .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+    }
+
     pub fn is_dynamic(&self) -> bool {
         self.id.starts_with("dyn$")
     }
@@ -804,7 +1008,7 @@ impl Client {
     /// possible without MFA. The force MFA for the Rauthy admin UI is done in
     /// Principal::validate_admin_session() depending on the `ADMIN_FORCE_MFA` config variable.
     pub fn validate_mfa(&self, user: &User) -> Result<(), ErrorResponse> {
-        if &self.id != "rauthy" && self.force_mfa && !user.has_webauthn_enabled() {
+        if &self.id != "rauthy" && self.force_mfa && !user.has_mfa_enabled() {
             Err(ErrorResponse::new(
                 ErrorResponseType::MfaRequired,
                 "MFA is required for this client".to_string(),
@@ -1006,23 +1210,75 @@ impl Client {
     }
 }
 
+/// Resolves host names for the `Client::http_client()` and only returns public IPs. This is done
+/// while connecting to not be fooled by a DNS record, which changes after a validation.
+struct PublicIpResolver;
+
+impl Resolve for PublicIpResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| *DEV_MODE || Client::is_public_ip(&addr.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addrs.is_empty() {
+                return Err(format!("'{}' does not resolve to a public IP", name.as_str()).into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
 impl Client {
-    async fn ephemeral_from_url(value: &str) -> Result<Self, ErrorResponse> {
-        let client = HTTP_CLIENT.get_or_init(|| {
+    /// The HTTP client for all URIs, which are provided by clients themselves. It does not follow
+    /// any redirects and only connects to public IPs.
+    pub(crate) fn http_client() -> &'static reqwest::Client {
+        HTTP_CLIENT.get_or_init(|| {
             reqwest::Client::builder()
                 .connect_timeout(Duration::from_secs(10))
                 .timeout(Duration::from_secs(10))
-                .user_agent(format!(
-                    "Rauthy v{} Ephemeral Client Resolver",
-                    RAUTHY_VERSION
-                ))
+                .user_agent(format!("Rauthy v{} Client Resolver", RAUTHY_VERSION))
                 .min_tls_version(tls::Version::TLS_1_2)
                 .pool_idle_timeout(Duration::from_secs(600))
+                .redirect(Policy::none())
+                .dns_resolver(Arc::new(PublicIpResolver))
                 .build()
                 .unwrap()
-        });
+        })
+    }
 
-        let res = client
+    /// Reads the body of a response from a client provided URI with a max size of
+    /// `CLIENT_FETCH_MAX_BYTES`.
+    pub(crate) async fn read_body_limited(
+        mut res: reqwest::Response,
+    ) -> Result<Vec<u8>, ErrorResponse> {
+        let url = res.url().to_string();
+        let err = || {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!(
+                    "The response from {} exceeds {} bytes",
+                    url, CLIENT_FETCH_MAX_BYTES
+                ),
+            )
+        };
+        if res.content_length().unwrap_or_default() > CLIENT_FETCH_MAX_BYTES as u64 {
+            return Err(err());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk().await? {
+            if body.len() + chunk.len() > CLIENT_FETCH_MAX_BYTES {
+                return Err(err());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    async fn ephemeral_from_url(value: &str) -> Result<Self, ErrorResponse> {
+        let res = Self::http_client()
             .get(value)
             .header(CONTENT_TYPE, APPLICATION_JSON)
             .send()
@@ -1071,6 +1327,443 @@ impl Client {
     }
 }
 
+impl Client {
+    /// Returns the public keys of this client, either from the directly registered `jwks` or
+    /// fetched from its `jwks_uri`. Keys with unsupported types are skipped.
+    pub async fn find_jwks(&self) -> Result<Vec<JWKSPublicKey>, ErrorResponse> {
+        Ok(self
+            .find_jwks_raw()
+            .await?
+            .into_iter()
+            .filter_map(|key| serde_json::from_value::<JWKSPublicKey>(key).ok())
+            .collect())
+    }
+
+    pub(crate) async fn find_jwks_raw(&self) -> Result<Vec<serde_json::Value>, ErrorResponse> {
+        let jwks = if let Some(jwks) = &self.jwks {
+            serde_json::from_str::<ClientJwks>(jwks)?
+        } else if let Some(jwks_uri) = &self.jwks_uri {
+            return Self::fetch_jwks(jwks_uri).await;
+        } else {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!(
+                    "Client '{}' has no 'jwks' or 'jwks_uri' registered",
+                    self.id
+                ),
+            ));
+        };
+
+        Ok(jwks.keys)
+    }
+
+    /// Fetches the keys from a `jwks_uri`. They are cached in memory for
+    /// `CLIENT_JWKS_CACHE_LIFETIME` to not do a remote request for each client authentication or
+    /// encryption.
+    async fn fetch_jwks(jwks_uri: &str) -> Result<Vec<serde_json::Value>, ErrorResponse> {
+        let cache = CLIENT_JWKS_CACHE.get_or_init(Default::default);
+        let now = Instant::now();
+        let lifetime = Duration::from_secs(CLIENT_JWKS_CACHE_LIFETIME);
+        if let Some((fetched, keys)) = cache.lock().unwrap().get(jwks_uri) {
+            if now.duration_since(*fetched) < lifetime {
+                return Ok(keys.clone());
+            }
+        }
+
+        Self::validate_public_uri(jwks_uri, "jwks_uri")?;
+        let res = Self::http_client()
+            .get(jwks_uri)
+            .header(ACCEPT, APPLICATION_JSON)
+            .send()
+            .await
+            .map_err(|err| {
+                ErrorResponse::new(
+                    ErrorResponseType::Connection,
+                    format!("Cannot fetch the client JWKS from {}: {:?}", jwks_uri, err),
+                )
+            })?;
+        if !res.status().is_success() {
+            let msg = format!("Cannot fetch the client JWKS from {}", jwks_uri);
+            error!("{}", msg);
+            return Err(ErrorResponse::new(ErrorResponseType::Connection, msg));
+        }
+        let body = Self::read_body_limited(res).await?;
+        let jwks = serde_json::from_slice::<ClientJwks>(&body)?;
+
+        let mut cache = cache.lock().unwrap();
+        cache.retain(|_, (fetched, _)| now.duration_since(*fetched) < lifetime);
+        cache.insert(jwks_uri.to_string(), (now, jwks.keys.clone()));
+
+        Ok(jwks.keys)
+    }
+
+    /// Makes sure, that the client can actually authenticate with its `tls_client_auth_method`.
+    pub fn validate_tls_client_auth(&self) -> Result<(), ErrorResponse> {
+        let err = match self.tls_client_auth_method.as_deref() {
+            None => return Ok(()),
+            Some(_) if !self.confidential => {
+                "mutual TLS client authentication is only allowed for confidential clients"
+            }
+            Some("tls_client_auth") if self.tls_client_auth_subject_dn.is_none() => {
+                "'tls_client_auth' requires a 'tls_client_auth_subject_dn'"
+            }
+            Some("self_signed_tls_client_auth")
+                if self.jwks.is_none() && self.jwks_uri.is_none() =>
+            {
+                "'self_signed_tls_client_auth' requires a 'jwks' or 'jwks_uri'"
+            }
+            Some(_) => return Ok(()),
+        };
+        Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            err.to_string(),
+        ))
+    }
+
+    /// Makes sure, that the client can actually authenticate with its
+    /// `token_endpoint_auth_method`.
+    pub fn validate_token_endpoint_auth(&self) -> Result<(), ErrorResponse> {
+        let err = match self.token_endpoint_auth_method.as_deref() {
+            None => return Ok(()),
+            Some(_) if self.tls_client_auth_method.is_some() => {
+                "'token_endpoint_auth_method' cannot be combined with mutual TLS client authentication"
+            }
+            Some("client_secret_jwt" | "private_key_jwt") if !self.confidential => {
+                "client assertions are only allowed for confidential clients"
+            }
+            Some("private_key_jwt") if self.jwks.is_none() && self.jwks_uri.is_none() => {
+                "'private_key_jwt' requires a 'jwks' or 'jwks_uri'"
+            }
+            Some(_) => return Ok(()),
+        };
+        Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            err.to_string(),
+        ))
+    }
+
+    /// Returns `true` if the client authenticates with a signed JWT instead of its secret.
+    pub fn uses_client_assertion(&self) -> bool {
+        matches!(
+            self.token_endpoint_auth_method.as_deref(),
+            Some("client_secret_jwt" | "private_key_jwt")
+        )
+    }
+
+    /// Validates a URI, which has been provided by a client and will be requested by Rauthy
+    /// itself. It must use https and must not point to a loopback or private network address.
+    /// The `http_client()` checks the resolved IPs of host names again while connecting.
+    pub fn validate_public_uri(uri: &str, param: &str) -> Result<(), ErrorResponse> {
+        if *DEV_MODE {
+            return Ok(());
+        }
+
+        let err = || {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("The '{}' must be a public https URI", param),
+            )
+        };
+        let url = Url::parse(uri).map_err(|_| err())?;
+        if url.scheme() != "https" {
+            return Err(err());
+        }
+        let host = url.host_str().ok_or_else(err)?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match host.parse::<IpAddr>() {
+            Ok(ip) => {
+                if !Self::is_public_ip(&ip) {
+                    return Err(err());
+                }
+            }
+            Err(_) => {
+                let domain = host.trim_end_matches('.').to_lowercase();
+                if domain == "localhost" || domain.ends_with(".localhost") {
+                    return Err(err());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `false` for all loopback, private, link local and other non-routable IPs.
+    pub(crate) fn is_public_ip(ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => {
+                // shared address space 100.64.0.0/10
+                let [first, second, ..] = ip.octets();
+                !(ip.is_loopback()
+                    || ip.is_private()
+                    || ip.is_link_local()
+                    || ip.is_unspecified()
+                    || ip.is_broadcast()
+                    || ip.is_multicast()
+                    || first == 0
+                    || (first == 100 && (second & 0xc0) == 64))
+            }
+            IpAddr::V6(ip) => {
+                if let Some(mapped) = ip.to_ipv4_mapped() {
+                    return Self::is_public_ip(&IpAddr::V4(mapped));
+                }
+                // unique local fc00::/7 and link local fe80::/10
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        }
+    }
+
+    /// Validates a JSON encoded JWKS before it will be saved for a client
+    pub fn validate_jwks(jwks: &str) -> Result<(), ErrorResponse> {
+        let jwks = serde_json::from_str::<ClientJwks>(jwks).map_err(|_| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "'jwks' is not a valid JSON Web Key Set".to_string(),
+            )
+        })?;
+        if jwks.keys.is_empty() {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "'jwks' does not contain any keys".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Encrypts a signed ID token for this client, if `id_token_encrypted_response_alg` is set.
+    /// The result is a nested JWT.
+    pub async fn encrypt_id_token(&self, id_token: String) -> Result<String, ErrorResponse> {
+        if let Some(alg) = &self.id_token_encrypted_response_alg {
+            self.encrypt_jwe(
+                alg,
+                self.id_token_encrypted_response_enc.as_deref(),
+                Some("JWT"),
+                id_token.as_bytes(),
+            )
+            .await
+        } else {
+            Ok(id_token)
+        }
+    }
+
+    pub fn is_userinfo_encrypted(&self) -> bool {
+        self.userinfo_encrypted_response_alg.is_some()
+    }
+
+    /// Encrypts the userinfo response for this client. Must only be called if
+    /// `is_userinfo_encrypted()` is `true`. A signed userinfo will become a nested JWT.
+    pub async fn encrypt_userinfo(
+        &self,
+        payload: &[u8],
+        is_signed: bool,
+    ) -> Result<String, ErrorResponse> {
+        let alg = self
+            .userinfo_encrypted_response_alg
+            .as_deref()
+            .unwrap_or_default();
+        self.encrypt_jwe(
+            alg,
+            self.userinfo_encrypted_response_enc.as_deref(),
+            is_signed.then_some("JWT"),
+            payload,
+        )
+        .await
+    }
+
+    async fn encrypt_jwe(
+        &self,
+        alg: &str,
+        enc: Option<&str>,
+        cty: Option<&str>,
+        payload: &[u8],
+    ) -> Result<String, ErrorResponse> {
+        let alg = JweAlg::from_str(alg)?;
+        let enc = match enc {
+            Some(enc) => JweEnc::from_str(enc)?,
+            None => JweEnc::default(),
+        };
+        let key = JweRecipientKey::find(self.find_jwks_raw().await?, &alg)?;
+        key.encrypt(&alg, &enc, cty, payload)
+    }
+
+    /// Validates the JWE config before it will be saved. An `enc` is only allowed together with
+    /// its `alg` and the client must have a registered public key, which can be used with it.
+    pub async fn validate_encryption(&self) -> Result<(), ErrorResponse> {
+        let settings = [
+            (
+                "id_token",
+                &self.id_token_encrypted_response_alg,
+                &self.id_token_encrypted_response_enc,
+            ),
+            (
+                "userinfo",
+                &self.userinfo_encrypted_response_alg,
+                &self.userinfo_encrypted_response_enc,
+            ),
+        ];
+
+        for (typ, alg, enc) in settings {
+            match (alg, enc) {
+                (None, None) => {}
+                (None, Some(_)) => {
+                    return Err(ErrorResponse::new(
+                        ErrorResponseType::BadRequest,
+                        format!(
+                            "'{}_encrypted_response_enc' needs '{}_encrypted_response_alg'",
+                            typ, typ
+                        ),
+                    ));
+                }
+                (Some(alg), enc) => {
+                    let alg = JweAlg::from_str(alg)?;
+                    if let Some(enc) = enc {
+                        JweEnc::from_str(enc)?;
+                    }
+                    JweRecipientKey::find(self.find_jwks_raw().await?, &alg)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Client {
+    pub fn is_pairwise(&self) -> bool {
+        self.subject_type.as_deref() == Some("pairwise")
+    }
+
+    /// Returns the `sub` for the given user as seen by this client. Pairwise subjects are
+    /// remembered to be able to map them back to the user later on.
+    pub async fn subject_for(
+        &self,
+        data: &web::Data<AppState>,
+        user_id: &str,
+    ) -> Result<String, ErrorResponse> {
+        if !self.is_pairwise() {
+            return Ok(user_id.to_string());
+        }
+
+        let secret = PAIRWISE_SUBJECT_SECRET.as_deref().ok_or_else(|| {
+            ErrorResponse::new(
+                ErrorResponseType::Internal,
+                "Pairwise subjects need PAIRWISE_SUBJECT_SECRET to be set".to_string(),
+            )
+        })?;
+        let ps = PairwiseSubject::new(self.sector_identifier()?, user_id.to_string(), secret);
+        ps.upsert(data).await?;
+        Ok(ps.sub)
+    }
+
+    /// The sector identifier for pairwise subjects is the host of the `sector_identifier_uri`.
+    /// Without one, all `redirect_uris` must share the same host, which will be used then.
+    pub fn sector_identifier(&self) -> Result<String, ErrorResponse> {
+        if let Some(uri) = &self.sector_identifier_uri {
+            return Self::uri_host(uri);
+        }
+
+        let mut hosts = HashSet::new();
+        for uri in self.get_redirect_uris() {
+            hosts.insert(Self::uri_host(&uri)?);
+        }
+        if hosts.len() != 1 {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "The 'redirect_uris' use different hosts - pairwise subjects need a \
+                'sector_identifier_uri' in this case"
+                    .to_string(),
+            ));
+        }
+        Ok(hosts.into_iter().next().unwrap())
+    }
+
+    fn uri_host(uri: &str) -> Result<String, ErrorResponse> {
+        Url::parse(uri)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .ok_or_else(|| {
+                ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    format!("Cannot extract the host from '{}'", uri),
+                )
+            })
+    }
+
+    /// Validates the pairwise subject config before it will be saved. A `sector_identifier_uri`
+    /// must return a JSON array, which contains all `redirect_uris` of this client.
+    pub async fn validate_subject_type(&self) -> Result<(), ErrorResponse> {
+        if !self.is_pairwise() {
+            return Ok(());
+        }
+
+        if PAIRWISE_SUBJECT_SECRET.is_none() {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Pairwise subjects need PAIRWISE_SUBJECT_SECRET to be set".to_string(),
+            ));
+        }
+        self.sector_identifier()?;
+
+        if let Some(uri) = &self.sector_identifier_uri {
+            Self::validate_public_uri(uri, "sector_identifier_uri")?;
+
+            let res = Self::http_client()
+                .get(uri)
+                .header(ACCEPT, APPLICATION_JSON)
+                .send()
+                .await
+                .map_err(|err| {
+                    ErrorResponse::new(
+                        ErrorResponseType::Connection,
+                        format!(
+                            "Cannot fetch the 'sector_identifier_uri' {}: {:?}",
+                            uri, err
+                        ),
+                    )
+                })?;
+            if !res.status().is_success() {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::Connection,
+                    format!("Cannot fetch the 'sector_identifier_uri' {}", uri),
+                ));
+            }
+            let body = Self::read_body_limited(res).await?;
+            let uris = serde_json::from_slice::<Vec<String>>(&body).map_err(|_| {
+                ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    "The 'sector_identifier_uri' must return a JSON array of URIs".to_string(),
+                )
+            })?;
+
+            for redirect_uri in self.get_redirect_uris() {
+                if !uris.contains(&redirect_uri) {
+                    return Err(ErrorResponse::new(
+                        ErrorResponseType::BadRequest,
+                        format!(
+                            "'{}' is missing in the 'sector_identifier_uri' contents",
+                            redirect_uri
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The raw JWKS of a client. The keys are deserialized one by one later on to not reject the
+// whole set, if a client publishes keys with types that are not supported.
+#[derive(Debug, Deserialize)]
+struct ClientJwks {
+    keys: Vec<serde_json::Value>,
+}
+
 impl From<EphemeralClientRequest> for Client {
     fn from(value: EphemeralClientRequest) -> Self {
         let scopes = EPHEMERAL_CLIENTS_ALLOWED_SCOPES.clone();
@@ -1101,6 +1794,30 @@ impl From<EphemeralClientRequest> for Client {
             default_scopes: scopes,
             challenge: Some("S256".to_string()),
             force_mfa: *EPHEMERAL_CLIENTS_FORCE_MFA,
+            require_par: false,
+            jwks: None,
+            jwks_uri: None,
+            require_request_object: false,
+            token_exchange_audiences: None,
+            token_exchange_scopes: None,
+            token_exchange_subject_types: None,
+            backchannel_logout_uri: None,
+            frontchannel_logout_uri: None,
+            subject_type: None,
+            sector_identifier_uri: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+            userinfo_signed_response_alg: None,
+            authorization_details_types: None,
+            tls_client_auth_method: None,
+            tls_client_auth_subject_dn: None,
+            tls_client_certificate_bound_access_tokens: false,
+            require_consent: *EPHEMERAL_CLIENTS_REQUIRE_CONSENT,
+            allow_email_login: false,
+            token_endpoint_auth_method: None,
+            request_uris: None,
         }
     }
 }
@@ -1137,6 +1854,30 @@ impl Default for Client {
             default_scopes: "openid".to_string(),
             challenge: Some("S256".to_string()),
             force_mfa: false,
+            require_par: false,
+            jwks: None,
+            jwks_uri: None,
+            require_request_object: false,
+            token_exchange_audiences: None,
+            token_exchange_scopes: None,
+            token_exchange_subject_types: None,
+            backchannel_logout_uri: None,
+            frontchannel_logout_uri: None,
+            subject_type: None,
+            sector_identifier_uri: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+            userinfo_signed_response_alg: None,
+            authorization_details_types: None,
+            tls_client_auth_method: None,
+            tls_client_auth_subject_dn: None,
+            tls_client_certificate_bound_access_tokens: false,
+            require_consent: false,
+            allow_email_login: false,
+            token_endpoint_auth_method: None,
+            request_uris: None,
         }
     }
 }
@@ -1162,8 +1903,34 @@ impl Client {
     fn try_from_dyn_reg(req: DynamicClientRequest) -> Result<Self, ErrorResponse> {
         let id = format!("dyn${}", get_rand(16));
 
+        if req.token_endpoint_auth_method.as_deref() == Some("private_key_jwt")
+            && req.jwks_uri.is_none()
+        {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "'private_key_jwt' requires a 'jwks_uri'".to_string(),
+            ));
+        }
+        if let Some(uri) = &req.jwks_uri {
+            Self::validate_public_uri(uri, "jwks_uri")?;
+        }
+        if let Some(uri) = &req.backchannel_logout_uri {
+            Self::validate_public_uri(uri, "backchannel_logout_uri")?;
+        }
+
         let confidential = req.token_endpoint_auth_method.as_deref() != Some("none");
-        let (secret, secret_kid, _secret_plain) = if confidential {
+        let tls_client_auth_method = req
+            .token_endpoint_auth_method
+            .clone()
+            .filter(|method| RE_TLS_CLIENT_AUTH_METHOD.is_match(method));
+        let token_endpoint_auth_method = req
+            .token_endpoint_auth_method
+            .clone()
+            .filter(|method| RE_CLIENT_AUTH_METHOD.is_match(method));
+        // a `private_key_jwt` client never authenticates with a secret
+        let needs_secret =
+            confidential && token_endpoint_auth_method.as_deref() != Some("private_key_jwt");
+        let (secret, secret_kid, _secret_plain) = if needs_secret {
             let (plain, enc) = Self::generate_new_secret()?;
             (
                 Some(enc),
@@ -1206,6 +1973,33 @@ impl Client {
             access_token_lifetime: *DYN_CLIENT_DEFAULT_TOKEN_LIFETIME,
             challenge: confidential.then_some("S256".to_string()),
             force_mfa: false,
+            require_par: false,
+            jwks: None,
+            jwks_uri: req.jwks_uri,
+            require_request_object: false,
+            token_exchange_audiences: None,
+            token_exchange_scopes: None,
+            token_exchange_subject_types: None,
+            backchannel_logout_uri: req.backchannel_logout_uri,
+            frontchannel_logout_uri: req.frontchannel_logout_uri,
+            subject_type: req.subject_type.filter(|typ| typ != "public"),
+            sector_identifier_uri: req.sector_identifier_uri,
+            id_token_encrypted_response_alg: req.id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc: req.id_token_encrypted_response_enc,
+            userinfo_encrypted_response_alg: req.userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc: req.userinfo_encrypted_response_enc,
+            userinfo_signed_response_alg: req
+                .userinfo_signed_response_alg
+                .map(|alg| alg.to_string()),
+            authorization_details_types: req
+                .authorization_details_types
+                .filter(|types| !types.is_empty())
+                .map(|types| types.join(",")),
+            tls_client_auth_method,
+            tls_client_auth_subject_dn: req.tls_client_auth_subject_dn,
+            tls_client_certificate_bound_access_tokens: req
+                .tls_client_certificate_bound_access_tokens,
+            token_endpoint_auth_method,
             ..Default::default()
         })
     }
@@ -1298,6 +2092,30 @@ mod tests {
             default_scopes: "openid,email,profile,groups".to_string(),
             challenge: Some("S256,plain".to_string()),
             force_mfa: false,
+            require_par: false,
+            jwks: None,
+            jwks_uri: None,
+            require_request_object: false,
+            token_exchange_audiences: None,
+            token_exchange_scopes: None,
+            token_exchange_subject_types: None,
+            backchannel_logout_uri: None,
+            frontchannel_logout_uri: None,
+            subject_type: None,
+            sector_identifier_uri: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+            userinfo_signed_response_alg: None,
+            authorization_details_types: None,
+            tls_client_auth_method: None,
+            tls_client_auth_subject_dn: None,
+            tls_client_certificate_bound_access_tokens: false,
+            require_consent: false,
+            allow_email_login: false,
+            token_endpoint_auth_method: None,
+            request_uris: None,
         };
 
         assert_eq!(client.get_access_token_alg().unwrap(), JwkKeyPairAlg::EdDSA);
@@ -0,0 +1,204 @@
+use crate::app_state::AppState;
+use actix_web::web;
+use rand::Rng;
+use rauthy_common::constants::{
+    CACHE_NAME_DEVICE_CODES, DEVICE_GRANT_CODE_LIFETIME, DEVICE_GRANT_POLL_INTERVAL,
+    DEVICE_GRANT_USER_CODE_LENGTH, IDX_DEVICE_USER_CODE,
+};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::utils::get_rand;
+use redhac::{cache_del, cache_get, cache_get_from, cache_get_value, cache_put};
+use serde::{Deserialize, Serialize};
+use std::ops::Add;
+use time::OffsetDateTime;
+
+// Only uppercase consonants as suggested in RFC 8628 to make the user code easy to type
+// and to avoid ambiguous characters and words.
+const USER_CODE_CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+// Struct for the codes from the 'urn:ietf:params:oauth:grant-type:device_code' flow
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeviceAuthCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub client_id: String,
+    pub scopes: Vec<String>,
+    pub exp: i64,
+    pub last_poll: i64,
+    // the current polling interval in seconds, increased with each `slow_down`
+    pub interval: i64,
+    // will be set as soon as a user has verified the `user_code`
+    pub user_id: Option<String>,
+    pub is_mfa: bool,
+    pub denied: bool,
+}
+
+// CRUD
+impl DeviceAuthCode {
+    // Deletes a Device Code and its `user_code` index from the cache
+    pub async fn delete(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_del(
+            CACHE_NAME_DEVICE_CODES.to_string(),
+            Self::idx_user_code(&self.user_code),
+            &data.caches.ha_cache_config,
+        )
+        .await?;
+        cache_del(
+            CACHE_NAME_DEVICE_CODES.to_string(),
+            self.device_code.clone(),
+            &data.caches.ha_cache_config,
+        )
+        .await
+        .map_err(ErrorResponse::from)
+    }
+
+    // Returns a Device Code from the cache
+    pub async fn find(
+        data: &web::Data<AppState>,
+        device_code: String,
+    ) -> Result<Option<Self>, ErrorResponse> {
+        cache_get!(
+            DeviceAuthCode,
+            CACHE_NAME_DEVICE_CODES.to_string(),
+            device_code,
+            &data.caches.ha_cache_config,
+            true
+        )
+        .await
+        .map_err(ErrorResponse::from)
+    }
+
+    // Returns a Device Code from the cache by its `user_code`
+    pub async fn find_by_user_code(
+        data: &web::Data<AppState>,
+        user_code: &str,
+    ) -> Result<Self, ErrorResponse> {
+        let device_code = cache_get!(
+            String,
+            CACHE_NAME_DEVICE_CODES.to_string(),
+            Self::idx_user_code(user_code),
+            &data.caches.ha_cache_config,
+            true
+        )
+        .await?;
+
+        let slf = match device_code {
+            None => None,
+            Some(device_code) => Self::find(data, device_code).await?,
+        };
+        slf.ok_or_else(|| {
+            ErrorResponse::new(
+                ErrorResponseType::NotFound,
+                "The 'user_code' does not exist or has expired".to_string(),
+            )
+        })
+    }
+
+    // Saves a Device Code and its `user_code` index
+    pub async fn save(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_put(
+            CACHE_NAME_DEVICE_CODES.to_string(),
+            self.device_code.clone(),
+            &data.caches.ha_cache_config,
+            self,
+        )
+        .await?;
+        cache_put(
+            CACHE_NAME_DEVICE_CODES.to_string(),
+            Self::idx_user_code(&self.user_code),
+            &data.caches.ha_cache_config,
+            &self.device_code,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl DeviceAuthCode {
+    pub fn new(client_id: String, scopes: Vec<String>) -> Self {
+        let now = OffsetDateTime::now_utc();
+        let exp = now
+            .add(time::Duration::seconds(*DEVICE_GRANT_CODE_LIFETIME as i64))
+            .unix_timestamp();
+
+        Self {
+            device_code: get_rand(64),
+            user_code: Self::new_user_code(),
+            client_id,
+            scopes,
+            exp,
+            // the first poll is allowed after the interval at the earliest
+            last_poll: now.unix_timestamp(),
+            interval: *DEVICE_GRANT_POLL_INTERVAL as i64,
+            user_id: None,
+            is_mfa: false,
+            denied: false,
+        }
+    }
+
+    #[inline(always)]
+    fn idx_user_code(user_code: &str) -> String {
+        format!("{}{}", IDX_DEVICE_USER_CODE, user_code)
+    }
+
+    /// Users may type the `user_code` in lowercase or grouped with dashes and spaces.
+    pub fn normalize_user_code(user_code: &str) -> String {
+        user_code
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    }
+
+    fn new_user_code() -> String {
+        let mut rng = rand::thread_rng();
+        (0..*DEVICE_GRANT_USER_CODE_LENGTH)
+            .map(|_| {
+                let idx = rng.gen_range(0..USER_CODE_CHARSET.len());
+                USER_CODE_CHARSET[idx] as char
+            })
+            .collect()
+    }
+
+    /// Checks and updates the polling timestamp. Returns an error with `slow_down` if the
+    /// client polls faster than the current interval, which is increased by 5 seconds each time
+    /// (RFC 8628 section 3.5).
+    pub async fn validate_poll(&mut self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let too_fast = now - self.last_poll < self.interval;
+        self.last_poll = now;
+        if too_fast {
+            self.interval += 5;
+        }
+        self.save(data).await?;
+
+        if too_fast {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::SlowDown,
+                format!("Polling is only allowed every {} seconds", self.interval),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rauthy_common::constants::RE_USER_CODE;
+
+    #[test]
+    fn test_new_user_code() {
+        let code = DeviceAuthCode::new_user_code();
+        assert_eq!(code.len(), *DEVICE_GRANT_USER_CODE_LENGTH as usize);
+        assert!(RE_USER_CODE.is_match(&code));
+        assert!(code.bytes().all(|b| USER_CODE_CHARSET.contains(&b)));
+
+        assert!(RE_USER_CODE.is_match("bcdf-ghjk"));
+        assert!(RE_USER_CODE.is_match("BCDF GHJK"));
+        assert!(!RE_USER_CODE.is_match("ABCD1234"));
+        assert!(!RE_USER_CODE.is_match("-BCDF"));
+        assert_eq!(DeviceAuthCode::normalize_user_code("bcdf-ghjk"), "BCDFGHJK");
+        assert_eq!(DeviceAuthCode::normalize_user_code("Bcdf Ghjk"), "BCDFGHJK");
+    }
+}
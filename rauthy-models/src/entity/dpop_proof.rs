@@ -371,6 +371,7 @@ mod tests {
     use ed25519_compact::Noise;
     use rauthy_common::constants::DPOP_TOKEN_ENDPOINT;
     use rauthy_common::utils::{base64_url_encode, base64_url_no_pad_encode};
+    use ring::signature::{EcdsaKeyPair, KeyPair};
     use rsa::sha2::Sha256;
     use rsa::traits::PublicKeyParts;
     use std::fmt::Write;
@@ -395,6 +396,7 @@ mod tests {
                 n: None,
                 e: None,
                 x: Some(base64_url_encode(kp.pk.as_slice())),
+                y: None,
             },
             kid: None,
         };
@@ -448,6 +450,7 @@ mod tests {
                 n: Some(base64_url_encode(&n)),
                 e: Some(base64_url_encode(&e)),
                 x: None,
+                y: None,
             },
             kid: None,
         };
@@ -483,4 +486,55 @@ mod tests {
         // jwk::test_signature_validation
         // -> no need to test it again here
     }
+
+    #[test]
+    fn test_dpop_validation_ecdsa() {
+        // manually build up a dpop token
+        let rng = ring::rand::SystemRandom::new();
+        let alg = &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING;
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(alg, &rng).unwrap();
+        let kp = EcdsaKeyPair::from_pkcs8(alg, pkcs8.as_ref(), &rng).unwrap();
+        // uncompressed point: 0x04 || x || y
+        let (x, y) = kp.public_key().as_ref()[1..].split_at(32);
+
+        let header = DPoPHeader {
+            typ: "dpop+jwt".to_string(),
+            alg: JwkKeyPairAlg::ES256,
+            jwk: JWKSPublicKey {
+                kty: JwkKeyPairType::EC,
+                // DPoP request will not have the 'alg' here but one level higher
+                alg: None,
+                crv: Some("P-256".to_string()),
+                kid: None,
+                n: None,
+                e: None,
+                x: Some(base64_url_encode(x)),
+                y: Some(base64_url_encode(y)),
+            },
+            kid: None,
+        };
+
+        let claims = DPoPClaims {
+            jti: "-BwC3ESc6acc2lTc".to_string(),
+            htm: http::Method::POST,
+            htu: DPOP_TOKEN_ENDPOINT.clone(),
+            iat: Utc::now().timestamp(),
+            nonce: None,
+        };
+
+        // build and sign the raw token string
+        let header_json = serde_json::to_string(&header).unwrap();
+        let header_b64 = base64_url_no_pad_encode(header_json.as_bytes());
+        let claims_json = serde_json::to_string(&claims).unwrap();
+        let claims_b64 = base64_url_no_pad_encode(claims_json.as_bytes());
+        let mut token_raw = format!("{}.{}", header_b64, claims_b64);
+
+        let sig = kp.sign(&rng, token_raw.as_bytes()).unwrap();
+        let sig_b64 = base64_url_no_pad_encode(sig.as_ref());
+        write!(token_raw, ".{}", sig_b64).unwrap();
+
+        // now we have our token like it should come in with the DPoP header -> try to verify it
+        let dpop = DPoPProof::try_from_str(None, token_raw.as_str()).unwrap();
+        dpop.validate(&token_raw).unwrap();
+    }
 }
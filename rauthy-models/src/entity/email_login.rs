@@ -0,0 +1,140 @@
+use crate::app_state::AppState;
+use crate::request::LoginRequest;
+use actix_web::web;
+use rauthy_common::constants::{CACHE_NAME_EMAIL_LOGIN, EMAIL_LOGIN_RATE_LIMIT_SEC};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use redhac::{cache_del, cache_get, cache_get_from, cache_get_value, cache_put};
+use ring::constant_time;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+// each pending login accepts only a few tries for the code before it must be requested again
+const EMAIL_LOGIN_MAX_TRIES: u8 = 3;
+
+/// A pending passwordless login, waiting for the user to click the magic link or to enter the
+/// one-time code from the E-Mail. It is bound to the session, which has requested it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailLogin {
+    pub session_id: String,
+    pub user_id: String,
+    pub magic_link_id: String,
+    pub code: String,
+    pub tries: u8,
+    // the original request from the authorize page to continue the flow afterward
+    pub req: LoginRequest,
+}
+
+// CRUD
+impl EmailLogin {
+    pub async fn delete(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_del(
+            CACHE_NAME_EMAIL_LOGIN.to_string(),
+            Self::cache_idx(&self.session_id),
+            &data.caches.ha_cache_config,
+        )
+        .await
+        .map_err(ErrorResponse::from)
+    }
+
+    pub async fn find(data: &web::Data<AppState>, session_id: &str) -> Result<Self, ErrorResponse> {
+        let slf = cache_get!(
+            EmailLogin,
+            CACHE_NAME_EMAIL_LOGIN.to_string(),
+            Self::cache_idx(session_id),
+            &data.caches.ha_cache_config,
+            false
+        )
+        .await?;
+
+        slf.ok_or_else(|| {
+            ErrorResponse::new(
+                ErrorResponseType::NotFound,
+                "The E-Mail login does not exist or has expired".to_string(),
+            )
+        })
+    }
+
+    pub async fn save(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_put(
+            CACHE_NAME_EMAIL_LOGIN.to_string(),
+            Self::cache_idx(&self.session_id),
+            &data.caches.ha_cache_config,
+            self,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl EmailLogin {
+    /// Creates a new random 6-digit one-time code
+    pub fn new_code() -> String {
+        format!("{:06}", rand::random::<u32>() % 1_000_000)
+    }
+
+    /// Validates the one-time code from the E-Mail. Invalid codes count against the maximum
+    /// amount of tries, after which the pending login is deleted.
+    pub async fn validate_code(
+        &mut self,
+        data: &web::Data<AppState>,
+        code: &str,
+    ) -> Result<(), ErrorResponse> {
+        // constant time to not leak the code with timing differences
+        if constant_time::verify_slices_are_equal(self.code.as_bytes(), code.as_bytes()).is_ok() {
+            return Ok(());
+        }
+
+        self.tries += 1;
+        if self.tries >= EMAIL_LOGIN_MAX_TRIES {
+            self.delete(data).await?;
+        } else {
+            self.save(data).await?;
+        }
+
+        Err(ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            "Invalid login code".to_string(),
+        ))
+    }
+
+    /// Returns an Err(_) if a login E-Mail has been sent to this address recently.
+    /// If not, the rate limit for the address will be started with an Ok(()).
+    pub async fn rate_limit_email(
+        data: &web::Data<AppState>,
+        email: &str,
+    ) -> Result<(), ErrorResponse> {
+        let idx = format!("rl_{}", email.to_lowercase());
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        if let Some(nbf) = cache_get!(
+            i64,
+            CACHE_NAME_EMAIL_LOGIN.to_string(),
+            idx.clone(),
+            &data.caches.ha_cache_config,
+            false
+        )
+        .await?
+        {
+            if nbf > now {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::TooManyRequests(nbf),
+                    format!("You hit a rate limit. You may try again at: {}", nbf),
+                ));
+            }
+        }
+
+        cache_put(
+            CACHE_NAME_EMAIL_LOGIN.to_string(),
+            idx,
+            &data.caches.ha_cache_config,
+            &(now + *EMAIL_LOGIN_RATE_LIMIT_SEC),
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn cache_idx(session_id: &str) -> String {
+        format!("login_{}", session_id)
+    }
+}
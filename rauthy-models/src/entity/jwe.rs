@@ -0,0 +1,478 @@
+use aes::{Aes128, Aes256};
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockEncryptMut, KeyIvInit};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::utils::{base64_url_no_pad_decode, base64_url_no_pad_encode};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{aead, agreement, digest, hmac};
+use rsa::sha2::Sha256;
+use rsa::{BigUint, Oaep, RsaPublicKey};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Supported key management algorithms for encrypted ID tokens and userinfo responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JweAlg {
+    RsaOaep256,
+    EcdhEs,
+}
+
+impl JweAlg {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::RsaOaep256 => "RSA-OAEP-256",
+            Self::EcdhEs => "ECDH-ES",
+        }
+    }
+
+    pub fn all_str() -> Vec<String> {
+        vec![
+            Self::RsaOaep256.as_str().to_string(),
+            Self::EcdhEs.as_str().to_string(),
+        ]
+    }
+}
+
+impl FromStr for JweAlg {
+    type Err = ErrorResponse;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "RSA-OAEP-256" => Ok(Self::RsaOaep256),
+            "ECDH-ES" => Ok(Self::EcdhEs),
+            _ => Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("Unsupported JWE 'alg': {}", s),
+            )),
+        }
+    }
+}
+
+/// Supported content encryption algorithms for encrypted ID tokens and userinfo responses.
+/// `A128CBC-HS256` is the default from OpenID Connect Dynamic Client Registration, if only the
+/// `alg` has been given.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum JweEnc {
+    #[default]
+    A128CbcHs256,
+    A256CbcHs512,
+    A128Gcm,
+    A256Gcm,
+}
+
+impl JweEnc {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::A128CbcHs256 => "A128CBC-HS256",
+            Self::A256CbcHs512 => "A256CBC-HS512",
+            Self::A128Gcm => "A128GCM",
+            Self::A256Gcm => "A256GCM",
+        }
+    }
+
+    pub fn all_str() -> Vec<String> {
+        vec![
+            Self::A128CbcHs256.as_str().to_string(),
+            Self::A256CbcHs512.as_str().to_string(),
+            Self::A128Gcm.as_str().to_string(),
+            Self::A256Gcm.as_str().to_string(),
+        ]
+    }
+
+    /// The length of the content encryption key in bytes
+    fn key_len(&self) -> usize {
+        match self {
+            Self::A128CbcHs256 => 32,
+            Self::A256CbcHs512 => 64,
+            Self::A128Gcm => 16,
+            Self::A256Gcm => 32,
+        }
+    }
+
+    /// Encrypts the plaintext and returns `(iv, ciphertext, tag)`
+    fn encrypt(
+        &self,
+        cek: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), ErrorResponse> {
+        match self {
+            Self::A128CbcHs256 | Self::A256CbcHs512 => {
+                // RFC 7518, Section 5.2.2.1: MAC_KEY || ENC_KEY
+                let (mac_key, enc_key) = cek.split_at(cek.len() / 2);
+                let iv = random_bytes(16)?;
+                let ciphertext = if self == &Self::A128CbcHs256 {
+                    cbc::Encryptor::<Aes128>::new_from_slices(enc_key, &iv)
+                        .map_err(|_| err_encryption())?
+                        .encrypt_padded_vec_mut::<Pkcs7>(plaintext)
+                } else {
+                    cbc::Encryptor::<Aes256>::new_from_slices(enc_key, &iv)
+                        .map_err(|_| err_encryption())?
+                        .encrypt_padded_vec_mut::<Pkcs7>(plaintext)
+                };
+
+                let alg = if self == &Self::A128CbcHs256 {
+                    hmac::HMAC_SHA256
+                } else {
+                    hmac::HMAC_SHA512
+                };
+                let al = (aad.len() as u64 * 8).to_be_bytes();
+                let key = hmac::Key::new(alg, mac_key);
+                let mut ctx = hmac::Context::with_key(&key);
+                ctx.update(aad);
+                ctx.update(&iv);
+                ctx.update(&ciphertext);
+                ctx.update(&al);
+                let tag = ctx.sign().as_ref()[..mac_key.len()].to_vec();
+
+                Ok((iv, ciphertext, tag))
+            }
+
+            Self::A128Gcm | Self::A256Gcm => {
+                let alg = if self == &Self::A128Gcm {
+                    &aead::AES_128_GCM
+                } else {
+                    &aead::AES_256_GCM
+                };
+                let key = aead::UnboundKey::new(alg, cek).map_err(|_| err_encryption())?;
+                let key = aead::LessSafeKey::new(key);
+
+                let iv = random_bytes(aead::NONCE_LEN)?;
+                let nonce =
+                    aead::Nonce::try_assume_unique_for_key(&iv).map_err(|_| err_encryption())?;
+                let mut ciphertext = plaintext.to_vec();
+                let tag = key
+                    .seal_in_place_separate_tag(nonce, aead::Aad::from(aad), &mut ciphertext)
+                    .map_err(|_| err_encryption())?;
+
+                Ok((iv, ciphertext, tag.as_ref().to_vec()))
+            }
+        }
+    }
+}
+
+impl FromStr for JweEnc {
+    type Err = ErrorResponse;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "A128CBC-HS256" => Ok(Self::A128CbcHs256),
+            "A256CBC-HS512" => Ok(Self::A256CbcHs512),
+            "A128GCM" => Ok(Self::A128Gcm),
+            "A256GCM" => Ok(Self::A256Gcm),
+            _ => Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("Unsupported JWE 'enc': {}", s),
+            )),
+        }
+    }
+}
+
+/// A public encryption key from a client's JWKS. This is deserialized on its own, because
+/// encryption keys may have an `alg`, which does not exist for signing keys.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JweRecipientKey {
+    pub kty: String,
+    #[serde(rename = "use")]
+    pub key_use: Option<String>,
+    pub alg: Option<String>,
+    pub kid: Option<String>,
+    pub crv: Option<String>,
+    pub n: Option<String>,
+    pub e: Option<String>,
+    pub x: Option<String>,
+    pub y: Option<String>,
+}
+
+impl JweRecipientKey {
+    /// Finds the first key in a client's raw JWKS, which can be used with the given `alg`.
+    pub fn find(keys: Vec<serde_json::Value>, alg: &JweAlg) -> Result<Self, ErrorResponse> {
+        keys.into_iter()
+            .filter_map(|key| serde_json::from_value::<Self>(key).ok())
+            .find(|key| key.matches(alg))
+            .ok_or_else(|| {
+                ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    format!("No client encryption key found for '{}'", alg.as_str()),
+                )
+            })
+    }
+
+    fn matches(&self, alg: &JweAlg) -> bool {
+        if self.key_use.as_deref() == Some("sig") {
+            return false;
+        }
+        if let Some(key_alg) = &self.alg {
+            if key_alg != alg.as_str() {
+                return false;
+            }
+        }
+
+        match alg {
+            JweAlg::RsaOaep256 => self.kty == "RSA" && self.n.is_some() && self.e.is_some(),
+            JweAlg::EcdhEs => {
+                self.kty == "EC"
+                    && matches!(self.crv.as_deref(), Some("P-256") | Some("P-384"))
+                    && self.x.is_some()
+                    && self.y.is_some()
+            }
+        }
+    }
+
+    /// Encrypts the `plaintext` to this key and returns the JWE in compact serialization.
+    /// `cty` should be `JWT` for nested tokens.
+    pub fn encrypt(
+        &self,
+        alg: &JweAlg,
+        enc: &JweEnc,
+        cty: Option<&str>,
+        plaintext: &[u8],
+    ) -> Result<String, ErrorResponse> {
+        let mut header = serde_json::Map::with_capacity(5);
+        header.insert("alg".to_string(), alg.as_str().into());
+        header.insert("enc".to_string(), enc.as_str().into());
+        if let Some(kid) = &self.kid {
+            header.insert("kid".to_string(), kid.as_str().into());
+        }
+        if let Some(cty) = cty {
+            header.insert("cty".to_string(), cty.into());
+        }
+
+        let (cek, encrypted_key) = match alg {
+            JweAlg::RsaOaep256 => {
+                let cek = random_bytes(enc.key_len())?;
+                let n = BigUint::from_bytes_be(&base64_url_no_pad_decode(
+                    self.n.as_deref().unwrap_or_default(),
+                )?);
+                let e = BigUint::from_bytes_be(&base64_url_no_pad_decode(
+                    self.e.as_deref().unwrap_or_default(),
+                )?);
+                let pk = RsaPublicKey::new(n, e).map_err(|_| {
+                    ErrorResponse::new(
+                        ErrorResponseType::BadRequest,
+                        "Invalid client RSA encryption key".to_string(),
+                    )
+                })?;
+                let encrypted_key = pk
+                    .encrypt(&mut rand::thread_rng(), Oaep::new::<Sha256>(), &cek)
+                    .map_err(|_| err_encryption())?;
+                (cek, encrypted_key)
+            }
+
+            JweAlg::EcdhEs => {
+                let (agreement_alg, crv) = match self.crv.as_deref() {
+                    Some("P-384") => (&agreement::ECDH_P384, "P-384"),
+                    _ => (&agreement::ECDH_P256, "P-256"),
+                };
+                let mut point = vec![0x04];
+                point.extend(base64_url_no_pad_decode(
+                    self.x.as_deref().unwrap_or_default(),
+                )?);
+                point.extend(base64_url_no_pad_decode(
+                    self.y.as_deref().unwrap_or_default(),
+                )?);
+
+                let rng = SystemRandom::new();
+                let epk = agreement::EphemeralPrivateKey::generate(agreement_alg, &rng)
+                    .map_err(|_| err_encryption())?;
+                let epk_pub = epk.compute_public_key().map_err(|_| err_encryption())?;
+                let (x, y) = epk_pub.as_ref()[1..].split_at((epk_pub.as_ref().len() - 1) / 2);
+                header.insert(
+                    "epk".to_string(),
+                    serde_json::json!({
+                        "kty": "EC",
+                        "crv": crv,
+                        "x": base64_url_no_pad_encode(x),
+                        "y": base64_url_no_pad_encode(y),
+                    }),
+                );
+
+                let peer = agreement::UnparsedPublicKey::new(agreement_alg, point);
+                // with direct key agreement, the `enc` is the algorithm id for the KDF
+                let cek = agreement::agree_ephemeral(epk, &peer, |z| {
+                    concat_kdf(z, enc.as_str(), enc.key_len())
+                })
+                .map_err(|_| {
+                    ErrorResponse::new(
+                        ErrorResponseType::BadRequest,
+                        "Invalid client EC encryption key".to_string(),
+                    )
+                })?;
+                (cek, Vec::new())
+            }
+        };
+
+        let header_b64 =
+            base64_url_no_pad_encode(serde_json::Value::Object(header).to_string().as_bytes());
+        let (iv, ciphertext, tag) = enc.encrypt(&cek, header_b64.as_bytes(), plaintext)?;
+
+        Ok(format!(
+            "{}.{}.{}.{}.{}",
+            header_b64,
+            base64_url_no_pad_encode(&encrypted_key),
+            base64_url_no_pad_encode(&iv),
+            base64_url_no_pad_encode(&ciphertext),
+            base64_url_no_pad_encode(&tag),
+        ))
+    }
+}
+
+/// The Concat KDF with SHA-256 from RFC 7518, Section 4.6.2 with empty `apu` and `apv`
+fn concat_kdf(z: &[u8], alg_id: &str, key_len: usize) -> Vec<u8> {
+    let mut other_info = Vec::with_capacity(16 + alg_id.len());
+    other_info.extend_from_slice(&(alg_id.len() as u32).to_be_bytes());
+    other_info.extend_from_slice(alg_id.as_bytes());
+    other_info.extend_from_slice(&0u32.to_be_bytes());
+    other_info.extend_from_slice(&0u32.to_be_bytes());
+    other_info.extend_from_slice(&(key_len as u32 * 8).to_be_bytes());
+
+    let mut key = Vec::with_capacity(key_len + digest::SHA256_OUTPUT_LEN);
+    let mut counter = 1u32;
+    while key.len() < key_len {
+        let mut ctx = digest::Context::new(&digest::SHA256);
+        ctx.update(&counter.to_be_bytes());
+        ctx.update(z);
+        ctx.update(&other_info);
+        key.extend_from_slice(ctx.finish().as_ref());
+        counter += 1;
+    }
+    key.truncate(key_len);
+    key
+}
+
+fn random_bytes(len: usize) -> Result<Vec<u8>, ErrorResponse> {
+    let mut buf = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut buf)
+        .map_err(|_| err_encryption())?;
+    Ok(buf)
+}
+
+fn err_encryption() -> ErrorResponse {
+    ErrorResponse::new(
+        ErrorResponseType::Internal,
+        "Error encrypting the JWE".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cbc::cipher::BlockDecryptMut;
+    use rsa::traits::PublicKeyParts;
+    use rsa::RsaPrivateKey;
+
+    fn parts(jwe: &str) -> Vec<Vec<u8>> {
+        jwe.split('.')
+            .map(|part| base64_url_no_pad_decode(part).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_concat_kdf() {
+        // RFC 7518, Appendix C with apu = "Alice" and apv = "Bob" cannot be used here, since we
+        // never set them -> only check the length and determinism
+        let z = [1u8; 32];
+        let key = concat_kdf(&z, "A256CBC-HS512", 64);
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, concat_kdf(&z, "A256CBC-HS512", 64));
+        assert_ne!(key[..32], concat_kdf(&z, "A256GCM", 32)[..]);
+    }
+
+    #[test]
+    fn test_jwe_ecdh_es_a128gcm() {
+        let rng = SystemRandom::new();
+        let sk = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng).unwrap();
+        let pk = sk.compute_public_key().unwrap();
+        let (x, y) = pk.as_ref()[1..].split_at(32);
+        let key = JweRecipientKey {
+            kty: "EC".to_string(),
+            key_use: Some("enc".to_string()),
+            alg: None,
+            kid: Some("enc_key".to_string()),
+            crv: Some("P-256".to_string()),
+            n: None,
+            e: None,
+            x: Some(base64_url_no_pad_encode(x)),
+            y: Some(base64_url_no_pad_encode(y)),
+        };
+        assert!(key.matches(&JweAlg::EcdhEs));
+        assert!(!key.matches(&JweAlg::RsaOaep256));
+
+        let jwe = key
+            .encrypt(&JweAlg::EcdhEs, &JweEnc::A128Gcm, None, b"secret payload")
+            .unwrap();
+        let header_b64 = jwe.split('.').next().unwrap();
+        let parts = parts(&jwe);
+        let header = serde_json::from_slice::<serde_json::Value>(&parts[0]).unwrap();
+        assert_eq!(header["alg"], "ECDH-ES");
+        assert_eq!(header["enc"], "A128GCM");
+        assert_eq!(header["kid"], "enc_key");
+        assert!(parts[1].is_empty());
+
+        // decrypt with the recipient key
+        let epk = &header["epk"];
+        let mut point = vec![0x04];
+        point.extend(base64_url_no_pad_decode(epk["x"].as_str().unwrap()).unwrap());
+        point.extend(base64_url_no_pad_decode(epk["y"].as_str().unwrap()).unwrap());
+        let peer = agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, point);
+        let cek = agreement::agree_ephemeral(sk, &peer, |z| concat_kdf(z, "A128GCM", 16)).unwrap();
+
+        let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_128_GCM, &cek).unwrap());
+        let nonce = aead::Nonce::try_assume_unique_for_key(&parts[2]).unwrap();
+        let mut in_out = parts[3].clone();
+        in_out.extend_from_slice(&parts[4]);
+        let plain = key
+            .open_in_place(nonce, aead::Aad::from(header_b64.as_bytes()), &mut in_out)
+            .unwrap();
+        assert_eq!(plain, b"secret payload");
+    }
+
+    #[test]
+    fn test_jwe_rsa_oaep_256_a128cbc_hs256() {
+        let sk = RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap();
+        let key = JweRecipientKey {
+            kty: "RSA".to_string(),
+            key_use: None,
+            alg: Some("RSA-OAEP-256".to_string()),
+            kid: None,
+            crv: None,
+            n: Some(base64_url_no_pad_encode(&sk.n().to_bytes_be())),
+            e: Some(base64_url_no_pad_encode(&sk.e().to_bytes_be())),
+            x: None,
+            y: None,
+        };
+        assert!(key.matches(&JweAlg::RsaOaep256));
+        assert!(!key.matches(&JweAlg::EcdhEs));
+
+        let jwe = key
+            .encrypt(
+                &JweAlg::RsaOaep256,
+                &JweEnc::A128CbcHs256,
+                Some("JWT"),
+                b"nested.jwt.token",
+            )
+            .unwrap();
+        let header_b64 = jwe.split('.').next().unwrap();
+        let parts = parts(&jwe);
+        let header = serde_json::from_slice::<serde_json::Value>(&parts[0]).unwrap();
+        assert_eq!(header["cty"], "JWT");
+
+        let cek = sk.decrypt(Oaep::new::<Sha256>(), &parts[1]).unwrap();
+        let (mac_key, enc_key) = cek.split_at(16);
+
+        let al = (header_b64.len() as u64 * 8).to_be_bytes();
+        let mut ctx = hmac::Context::with_key(&hmac::Key::new(hmac::HMAC_SHA256, mac_key));
+        ctx.update(header_b64.as_bytes());
+        ctx.update(&parts[2]);
+        ctx.update(&parts[3]);
+        ctx.update(&al);
+        assert_eq!(&ctx.sign().as_ref()[..16], parts[4].as_slice());
+
+        let plain = cbc::Decryptor::<Aes128>::new_from_slices(enc_key, &parts[2])
+            .unwrap()
+            .decrypt_padded_vec_mut::<Pkcs7>(&parts[3])
+            .unwrap();
+        assert_eq!(plain, b"nested.jwt.token");
+    }
+}
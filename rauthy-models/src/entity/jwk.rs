@@ -19,30 +19,56 @@ use utoipa::ToSchema;
 #[macro_export]
 macro_rules! sign_jwt {
     ($key_pair:expr, $claims:expr) => {
+        $crate::sign_jwt!(
+            $key_pair,
+            $claims,
+            &jwt_simple::prelude::HeaderOptions::default()
+        )
+    };
+
+    ($key_pair:expr, $claims:expr, $header_opts:expr) => {
         match $key_pair.typ {
             JwkKeyPairAlg::RS256 => {
                 let key =
                     jwt_simple::algorithms::RS256KeyPair::from_der($key_pair.bytes.as_slice())
                         .unwrap();
-                key.with_key_id(&$key_pair.kid).sign($claims)
+                key.with_key_id(&$key_pair.kid)
+                    .sign_with_options($claims, $header_opts)
             }
             JwkKeyPairAlg::RS384 => {
                 let key =
                     jwt_simple::algorithms::RS384KeyPair::from_der($key_pair.bytes.as_slice())
                         .unwrap();
-                key.with_key_id(&$key_pair.kid).sign($claims)
+                key.with_key_id(&$key_pair.kid)
+                    .sign_with_options($claims, $header_opts)
             }
             JwkKeyPairAlg::RS512 => {
                 let key =
                     jwt_simple::algorithms::RS512KeyPair::from_der($key_pair.bytes.as_slice())
                         .unwrap();
-                key.with_key_id(&$key_pair.kid).sign($claims)
+                key.with_key_id(&$key_pair.kid)
+                    .sign_with_options($claims, $header_opts)
             }
             JwkKeyPairAlg::EdDSA => {
                 let key =
                     jwt_simple::algorithms::Ed25519KeyPair::from_der($key_pair.bytes.as_slice())
                         .unwrap();
-                key.with_key_id(&$key_pair.kid).sign($claims)
+                key.with_key_id(&$key_pair.kid)
+                    .sign_with_options($claims, $header_opts)
+            }
+            JwkKeyPairAlg::ES256 => {
+                let key =
+                    jwt_simple::algorithms::ES256KeyPair::from_der($key_pair.bytes.as_slice())
+                        .unwrap();
+                key.with_key_id(&$key_pair.kid)
+                    .sign_with_options($claims, $header_opts)
+            }
+            JwkKeyPairAlg::ES384 => {
+                let key =
+                    jwt_simple::algorithms::ES384KeyPair::from_der($key_pair.bytes.as_slice())
+                        .unwrap();
+                key.with_key_id(&$key_pair.kid)
+                    .sign_with_options($claims, $header_opts)
             }
         }
         .map_err(|_| {
@@ -86,6 +112,20 @@ macro_rules! validate_jwt {
                 key.public_key()
                     .verify_token::<$type>($token, Some($options))
             }
+            JwkKeyPairAlg::ES256 => {
+                let key =
+                    jwt_simple::algorithms::ES256KeyPair::from_der($key_pair.bytes.as_slice())
+                        .unwrap();
+                key.public_key()
+                    .verify_token::<$type>($token, Some($options))
+            }
+            JwkKeyPairAlg::ES384 => {
+                let key =
+                    jwt_simple::algorithms::ES384KeyPair::from_der($key_pair.bytes.as_slice())
+                        .unwrap();
+                key.public_key()
+                    .verify_token::<$type>($token, Some($options))
+            }
         }
         .map_err(|_| {
             ErrorResponse::new(ErrorResponseType::Unauthorized, "Invalid Token".to_string())
@@ -207,11 +247,12 @@ impl JWKS {
 pub struct JWKSPublicKey {
     pub kty: JwkKeyPairType,
     pub alg: Option<JwkKeyPairAlg>,
-    pub crv: Option<String>, // Ed25519
+    pub crv: Option<String>, // Ed25519, P-256, P-384
     pub kid: Option<String>,
     pub n: Option<String>, // RSA
     pub e: Option<String>, // RSA
-    pub x: Option<String>, // OKP
+    pub x: Option<String>, // OKP, EC
+    pub y: Option<String>, // EC
 }
 
 impl JWKSPublicKey {
@@ -261,6 +302,58 @@ impl JWKSPublicKey {
         }
     }
 
+    pub fn y(&self) -> Result<Vec<u8>, ErrorResponse> {
+        if let Some(y) = &self.y {
+            Ok(base64_url_no_pad_decode(y)?)
+        } else {
+            Err(ErrorResponse::new(
+                ErrorResponseType::Internal,
+                "No 'y' in JwkKeyPublicKey".to_string(),
+            ))
+        }
+    }
+
+    /// Returns the EC public key as an uncompressed SEC1 point: `0x04 || x || y`
+    pub fn ec_point(&self) -> Result<Vec<u8>, ErrorResponse> {
+        let x = self.x()?;
+        let y = self.y()?;
+        let mut point = Vec::with_capacity(1 + x.len() + y.len());
+        point.push(0x04);
+        point.extend_from_slice(&x);
+        point.extend_from_slice(&y);
+        Ok(point)
+    }
+
+    /// Finds the key matching the given `kid` and `alg` from a client's JWKS.
+    /// Keys inside a JWKS do not always contain an 'alg'. In that case, the given one will be
+    /// set, if it matches the key type.
+    pub fn find_for_alg(
+        keys: Vec<Self>,
+        kid: &Option<String>,
+        alg: JwkKeyPairAlg,
+    ) -> Result<Self, ErrorResponse> {
+        let mut key = keys
+            .into_iter()
+            .filter(|key| kid.is_none() || &key.kid == kid)
+            .filter(|key| key.alg.is_none() || key.alg.as_ref() == Some(&alg))
+            .find(|key| match key.kty {
+                JwkKeyPairType::RSA => alg.is_rsa(),
+                JwkKeyPairType::OKP => alg == JwkKeyPairAlg::EdDSA,
+                JwkKeyPairType::EC => alg.is_ec(),
+            })
+            .ok_or_else(|| {
+                ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    "No matching client key found for the token".to_string(),
+                )
+            })?;
+
+        if key.alg.is_none() {
+            key.alg = Some(alg);
+        }
+        Ok(key)
+    }
+
     pub fn from_key_pair(key_pair: &JwkKeyPair) -> Self {
         let get_rsa = |kid: String, comp: algorithms::RSAPublicKeyComponents| JWKSPublicKey {
             kty: JwkKeyPairType::RSA,
@@ -270,6 +363,7 @@ impl JWKSPublicKey {
             n: Some(base64_url_encode(&comp.n)),
             e: Some(base64_url_encode(&comp.e)),
             x: None,
+            y: None,
         };
 
         let get_ed25519 = |kid: String, x: String| JWKSPublicKey {
@@ -280,6 +374,22 @@ impl JWKSPublicKey {
             n: None,
             e: None,
             x: Some(x),
+            y: None,
+        };
+
+        // `point` is the uncompressed SEC1 encoding: 0x04 || x || y
+        let get_ec = |kid: String, crv: &str, point: Vec<u8>| {
+            let (x, y) = point[1..].split_at((point.len() - 1) / 2);
+            JWKSPublicKey {
+                kty: JwkKeyPairType::EC,
+                alg: Some(key_pair.typ.clone()),
+                crv: Some(crv.to_string()),
+                kid: Some(kid),
+                n: None,
+                e: None,
+                x: Some(base64_url_encode(x)),
+                y: Some(base64_url_encode(y)),
+            }
         };
 
         match key_pair.typ {
@@ -303,6 +413,16 @@ impl JWKSPublicKey {
                 let x = base64_url_encode(&kp.public_key().to_bytes());
                 get_ed25519(key_pair.kid.clone(), x)
             }
+            JwkKeyPairAlg::ES256 => {
+                let kp = algorithms::ES256KeyPair::from_der(&key_pair.bytes).unwrap();
+                let point = kp.public_key().to_bytes_uncompressed();
+                get_ec(key_pair.kid.clone(), "P-256", point)
+            }
+            JwkKeyPairAlg::ES384 => {
+                let kp = algorithms::ES384KeyPair::from_der(&key_pair.bytes).unwrap();
+                let point = kp.public_key().to_bytes_uncompressed();
+                get_ec(key_pair.kid.clone(), "P-384", point)
+            }
         }
     }
 
@@ -346,6 +466,27 @@ impl JWKSPublicKey {
                     x
                 )
             }
+
+            JwkKeyPairType::EC => {
+                if self.crv.is_none() || self.x.is_none() || self.y.is_none() {
+                    return Err(ErrorResponse::new(
+                        ErrorResponseType::Internal,
+                        "Incorrect format for EC JWK: crv / x / y missing".to_string(),
+                    ));
+                }
+
+                // mandatory keys for EC are in order: crv, kty, x, y
+                let crv = self.crv.as_deref().unwrap();
+                let x = self.x.as_deref().unwrap();
+                let y = self.y.as_deref().unwrap();
+                format!(
+                    "{{\"crv\":\"{}\",\"kty\":\"{}\",\"x\":\"{}\",\"y\":\"{}\"}}",
+                    crv,
+                    self.kty.as_str(),
+                    x,
+                    y
+                )
+            }
         };
 
         let hash = hmac_sha256::Hash::hash(s.as_bytes());
@@ -363,10 +504,10 @@ impl JWKSPublicKey {
             Some(alg) => {
                 match self.kty {
                     JwkKeyPairType::RSA => {
-                        if alg == &JwkKeyPairAlg::EdDSA {
+                        if !alg.is_rsa() {
                             return Err(ErrorResponse::new(
                                 ErrorResponseType::BadRequest,
-                                "RSA kty cannot have EdDSA alg".to_string(),
+                                format!("RSA kty cannot have {} alg", alg.as_str()),
                             ));
                         }
 
@@ -377,10 +518,10 @@ impl JWKSPublicKey {
                             ));
                         }
 
-                        if self.x.is_some() {
+                        if self.x.is_some() || self.y.is_some() {
                             return Err(ErrorResponse::new(
                                 ErrorResponseType::BadRequest,
-                                "RSA key cannot have 'x' public key component".to_string(),
+                                "RSA key cannot have 'x' or 'y' public key components".to_string(),
                             ));
                         }
                     }
@@ -422,6 +563,46 @@ impl JWKSPublicKey {
                                 "OKP key must have 'x' public key component".to_string(),
                             ));
                         }
+
+                        if self.y.is_some() {
+                            return Err(ErrorResponse::new(
+                                ErrorResponseType::BadRequest,
+                                "OKP key cannot have 'y' public key component".to_string(),
+                            ));
+                        }
+                    }
+
+                    JwkKeyPairType::EC => {
+                        let expected_crv = match alg {
+                            JwkKeyPairAlg::ES256 => "P-256",
+                            JwkKeyPairAlg::ES384 => "P-384",
+                            _ => {
+                                return Err(ErrorResponse::new(
+                                    ErrorResponseType::BadRequest,
+                                    "EC kty must have ES256 or ES384 alg".to_string(),
+                                ));
+                            }
+                        };
+                        if self.crv.as_deref() != Some(expected_crv) {
+                            return Err(ErrorResponse::new(
+                                ErrorResponseType::BadRequest,
+                                format!("{} needs '{}' for 'crv'", alg.as_str(), expected_crv),
+                            ));
+                        }
+
+                        if self.n.is_some() || self.e.is_some() {
+                            return Err(ErrorResponse::new(
+                                ErrorResponseType::BadRequest,
+                                "EC key cannot have 'n' or 'e' public key components".to_string(),
+                            ));
+                        }
+
+                        if self.x.is_none() || self.y.is_none() {
+                            return Err(ErrorResponse::new(
+                                ErrorResponseType::BadRequest,
+                                "EC key must have 'x' and 'y' public key components".to_string(),
+                            ));
+                        }
                     }
                 }
 
@@ -467,6 +648,16 @@ impl JwkKeyPair {
                 typ: JwkKeyPairAlg::EdDSA,
                 bytes: jwk_decrypted,
             },
+            JwkKeyPairAlg::ES256 => JwkKeyPair {
+                kid,
+                typ: JwkKeyPairAlg::ES256,
+                bytes: jwk_decrypted,
+            },
+            JwkKeyPairAlg::ES384 => JwkKeyPair {
+                kid,
+                typ: JwkKeyPairAlg::ES384,
+                bytes: jwk_decrypted,
+            },
         };
 
         Ok(res)
@@ -577,6 +768,7 @@ impl JwkKeyPair {
 pub enum JwkKeyPairType {
     RSA,
     OKP,
+    EC,
 }
 
 impl Default for JwkKeyPairType {
@@ -590,16 +782,20 @@ impl JwkKeyPairType {
         match self {
             JwkKeyPairType::RSA => "RSA",
             JwkKeyPairType::OKP => "OKP",
+            JwkKeyPairType::EC => "EC",
         }
     }
 }
 
+// New variants must only be appended - the pre-computed DEV JWKs are bincode-serialized.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum JwkKeyPairAlg {
     RS256,
     RS384,
     RS512,
     EdDSA,
+    ES256,
+    ES384,
 }
 
 impl Default for JwkKeyPairAlg {
@@ -615,6 +811,8 @@ impl From<String> for JwkKeyPairAlg {
             "RS384" => JwkKeyPairAlg::RS384,
             "RS512" => JwkKeyPairAlg::RS512,
             "EdDSA" => JwkKeyPairAlg::EdDSA,
+            "ES256" => JwkKeyPairAlg::ES256,
+            "ES384" => JwkKeyPairAlg::ES384,
             _ => unreachable!(),
         }
     }
@@ -643,8 +841,21 @@ impl JwkKeyPairAlg {
             JwkKeyPairAlg::RS384 => "RS384",
             JwkKeyPairAlg::RS512 => "RS512",
             JwkKeyPairAlg::EdDSA => "EdDSA",
+            JwkKeyPairAlg::ES256 => "ES256",
+            JwkKeyPairAlg::ES384 => "ES384",
         }
     }
+
+    pub fn is_rsa(&self) -> bool {
+        matches!(
+            self,
+            JwkKeyPairAlg::RS256 | JwkKeyPairAlg::RS384 | JwkKeyPairAlg::RS512
+        )
+    }
+
+    pub fn is_ec(&self) -> bool {
+        matches!(self, JwkKeyPairAlg::ES256 | JwkKeyPairAlg::ES384)
+    }
 }
 
 impl ToString for JwkKeyPairAlg {
@@ -662,6 +873,8 @@ impl FromStr for JwkKeyPairAlg {
             "RS384" => Ok(JwkKeyPairAlg::RS384),
             "RS512" => Ok(JwkKeyPairAlg::RS512),
             "EdDSA" => Ok(JwkKeyPairAlg::EdDSA),
+            "ES256" => Ok(JwkKeyPairAlg::ES256),
+            "ES384" => Ok(JwkKeyPairAlg::ES384),
             _ => Err(ErrorResponse::new(
                 ErrorResponseType::BadRequest,
                 "Invalid JWT Token algorithm".to_string(),
@@ -688,6 +901,7 @@ mod tests {
             n: Some("0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.fingerprint().unwrap();
         assert_eq!(tp.as_str(), "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
 
@@ -699,6 +913,7 @@ mod tests {
             n: Some("0OJuIbD0k90-Xod2cnqcGWu0xP4Z3Eyfi3CXBxdzlEwFHSNat6Vjts2g5Uzbdvmgm2ys-UWUaCcw2zPEbn25dtcv0MVK26J71OV0Q38yB701SniEJqLXf3OehSR7lfd9HNasZF_-2u6oJMwvKLe10qlSGYLzeUCWIV4LDPDv7lxsWFx0WntgLlHpKfVmYuvW_AQ1Q8XSO53K4Xk3n84zzAXvCUyW8Z4tmE4tc3ibriHH63AYpKbB8oDR-zhbIoGHtZnDdRo02JvS11KNINLdmMOE2zre7hPgXVbgnYS9qbpz4nsc4sPCiGclM2c2faSkwyxI60Ng6272e3fIEkBTKtYidoaG00tM1j42kD-b7bNjWJIsY92F15SdRA4stpic2KcAnyphNrLeDMKd_c-h3PC22eR-a8pb5nE1VvDSagn9g8WE3TSMEJxEmAgVcOcldSV9EDpSz4uk2CqRdytwAZOnRDEwehnRQiLNiwgyNEygLAcaVWDR8ym8ARRLWCRL".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.fingerprint().unwrap();
         assert_eq!(tp.as_str(), "EunK2QL42BZ2Eb4urUxXiFFomdjus4UtGB9qJ8Vnjtw");
 
@@ -710,6 +925,7 @@ mod tests {
             n: Some("1UjNug4a3OEo8saHbM14jhEqpgRHvjMaQ0lB_1rRuK4yMNPLxhdes8PcMXfEuCOYrC4jxkeVb31QgM5OFwxRtyBT-T1SmiWCtXX2beFtRrvZcGYQrd_LooKLrcjww-P8atQBBYKgf82e9aqb5I-4BFYTBdDQ5lQKQtZDwiU-lUVYP103SphHQMkkWLKsC7oFcthN2m8IliQnJ3-XeqgYt9dc6AszDEjNTDZMeC-HWwRXI9JGYjIgNIZj_u0n6UgaqhdjR1sEHxRGI_t6xQX_L9zRecdDM6-e_lNxIaeROZJ2FU-t9GmZZWyyDWUHk7tk4dS1cU5CdtwvL75dXMHsmwyTs8QK9YUvCWmLeCp6JNPOpCalwyW8YcqJphINhKgonsMinxWLPlO4jtSXKzrpGDLxOF_8xVMW3gNmnIWuUY0_29p7-DzdVm44GEYhQRNNX7yh850uYpwoi42fFvXa5wXm6Hy5QHh_Aqv3tTZgG2f20xCKOzzGzWB28BdJJa9EPu2WLrxaPbn8Qi536979UvMhlZsnUc4fW3TSy20coMb1NIatZaJCDu-uQuGFz7FHBFWjJV6fjF7gqiNqu8cZTeOedGjMitdCnMtOjCz8SASphF12_opWTvtFjq0IMNo4kR8zgZQ24Kt2o2qDhH7fYJI1cLj0RBGDCUU3AlozG_U".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.fingerprint().unwrap();
         assert_eq!(tp.as_str(), "rSJa_34h-WFCVMoSG7ORvEvxhF45iCvcm1FRZlxSRio");
 
@@ -721,10 +937,26 @@ mod tests {
             n: None,
             e: None,
             x: Some("suwfa9fyMHqS0yOh9T-Bsdkji0naFVRRGZFBNrGX_RQ".to_string()),
+            y: None,
         }
         .fingerprint()
         .unwrap();
         assert_eq!(tp.as_str(), "lVstH-NNQsIRpUp1nMmxD3cUoDS_dUbi4Or5awQ34EQ");
+
+        // example key from RFC7517 https://datatracker.ietf.org/doc/html/rfc7517#appendix-A.1
+        let tp = JWKSPublicKey {
+            kty: JwkKeyPairType::EC,
+            alg: Some(JwkKeyPairAlg::ES256),
+            crv: Some("P-256".to_string()),
+            kid: None,
+            n: None,
+            e: None,
+            x: Some("MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4".to_string()),
+            y: Some("4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM".to_string()),
+        }
+        .fingerprint()
+        .unwrap();
+        assert_eq!(tp.as_str(), "cn-I_WNMClehiVp51i_0VpOENW1upEerA8sEam5hn-s");
     }
 
     #[test]
@@ -738,6 +970,7 @@ mod tests {
             n: Some("r5Xn8yuwc7ekL5NLFnBw76cRUiYbIQqNgPq6XYw6_Mgle3BSJ-UTKTWjGLDoTSlFC7k2xCZNOt8pqix2R_qoGwlNo8kYXlgMpAEo00rSKoG1RO1PMj1M_--swijR8l1bnb-VfIPgT_kM3zv7RLPLEEjYHMuT7N5liFVq1Xh-So8i3X1UeWGHyJPHjF5koB_XO1vleYQCZQeGFaomJgrFJsxdmtFueJaMEMQ1-mPwuPjvSwOtMMAu0nO9DJm3-xwkygPqGmEbbDHLeEO1dEOlDdEYlYle5Pa70FGinCBqaAl7lDaJ1umAvpcLBUHtFOM7VBmt-xUjzOU7VDPareR6Ww".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.validate_self().unwrap();
 
         JWKSPublicKey {
@@ -748,6 +981,7 @@ mod tests {
             n: Some("0OJuIbD0k90-Xod2cnqcGWu0xP4Z3Eyfi3CXBxdzlEwFHSNat6Vjts2g5Uzbdvmgm2ys-UWUaCcw2zPEbn25dtcv0MVK26J71OV0Q38yB701SniEJqLXf3OehSR7lfd9HNasZF_-2u6oJMwvKLe10qlSGYLzeUCWIV4LDPDv7lxsWFx0WntgLlHpKfVmYuvW_AQ1Q8XSO53K4Xk3n84zzAXvCUyW8Z4tmE4tc3ibriHH63AYpKbB8oDR-zhbIoGHtZnDdRo02JvS11KNINLdmMOE2zre7hPgXVbgnYS9qbpz4nsc4sPCiGclM2c2faSkwyxI60Ng6272e3fIEkBTKtYidoaG00tM1j42kD-b7bNjWJIsY92F15SdRA4stpic2KcAnyphNrLeDMKd_c-h3PC22eR-a8pb5nE1VvDSagn9g8WE3TSMEJxEmAgVcOcldSV9EDpSz4uk2CqRdytwAZOnRDEwehnRQiLNiwgyNEygLAcaVWDR8ym8ARRLWCRL".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.validate_self().unwrap();
 
         JWKSPublicKey {
@@ -758,6 +992,7 @@ mod tests {
             n: Some("1UjNug4a3OEo8saHbM14jhEqpgRHvjMaQ0lB_1rRuK4yMNPLxhdes8PcMXfEuCOYrC4jxkeVb31QgM5OFwxRtyBT-T1SmiWCtXX2beFtRrvZcGYQrd_LooKLrcjww-P8atQBBYKgf82e9aqb5I-4BFYTBdDQ5lQKQtZDwiU-lUVYP103SphHQMkkWLKsC7oFcthN2m8IliQnJ3-XeqgYt9dc6AszDEjNTDZMeC-HWwRXI9JGYjIgNIZj_u0n6UgaqhdjR1sEHxRGI_t6xQX_L9zRecdDM6-e_lNxIaeROZJ2FU-t9GmZZWyyDWUHk7tk4dS1cU5CdtwvL75dXMHsmwyTs8QK9YUvCWmLeCp6JNPOpCalwyW8YcqJphINhKgonsMinxWLPlO4jtSXKzrpGDLxOF_8xVMW3gNmnIWuUY0_29p7-DzdVm44GEYhQRNNX7yh850uYpwoi42fFvXa5wXm6Hy5QHh_Aqv3tTZgG2f20xCKOzzGzWB28BdJJa9EPu2WLrxaPbn8Qi536979UvMhlZsnUc4fW3TSy20coMb1NIatZaJCDu-uQuGFz7FHBFWjJV6fjF7gqiNqu8cZTeOedGjMitdCnMtOjCz8SASphF12_opWTvtFjq0IMNo4kR8zgZQ24Kt2o2qDhH7fYJI1cLj0RBGDCUU3AlozG_U".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.validate_self().unwrap();
 
         JWKSPublicKey {
@@ -768,6 +1003,20 @@ mod tests {
             n: None,
             e: None,
             x: Some("suwfa9fyMHqS0yOh9T-Bsdkji0naFVRRGZFBNrGX_RQ".to_string()),
+            y: None,
+        }
+        .validate_self()
+        .unwrap();
+
+        JWKSPublicKey {
+            kty: JwkKeyPairType::EC,
+            alg: Some(JwkKeyPairAlg::ES256),
+            crv: Some("P-256".to_string()),
+            kid: None,
+            n: None,
+            e: None,
+            x: Some("MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4".to_string()),
+            y: Some("4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM".to_string()),
         }
         .validate_self()
         .unwrap();
@@ -781,6 +1030,7 @@ mod tests {
             n: Some("r5Xn8yuwc7ekL5NLFnBw76cRUiYbIQqNgPq6XYw6_Mgle3BSJ-UTKTWjGLDoTSlFC7k2xCZNOt8pqix2R_qoGwlNo8kYXlgMpAEo00rSKoG1RO1PMj1M_--swijR8l1bnb-VfIPgT_kM3zv7RLPLEEjYHMuT7N5liFVq1Xh-So8i3X1UeWGHyJPHjF5koB_XO1vleYQCZQeGFaomJgrFJsxdmtFueJaMEMQ1-mPwuPjvSwOtMMAu0nO9DJm3-xwkygPqGmEbbDHLeEO1dEOlDdEYlYle5Pa70FGinCBqaAl7lDaJ1umAvpcLBUHtFOM7VBmt-xUjzOU7VDPareR6Ww".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.validate_self();
         assert!(key.is_err());
 
@@ -792,6 +1042,7 @@ mod tests {
             n: Some("r5Xn8yuwc7ekL5NLFnBw76cRUiYbIQqNgPq6XYw6_Mgle3BSJ-UTKTWjGLDoTSlFC7k2xCZNOt8pqix2R_qoGwlNo8kYXlgMpAEo00rSKoG1RO1PMj1M_--swijR8l1bnb-VfIPgT_kM3zv7RLPLEEjYHMuT7N5liFVq1Xh-So8i3X1UeWGHyJPHjF5koB_XO1vleYQCZQeGFaomJgrFJsxdmtFueJaMEMQ1-mPwuPjvSwOtMMAu0nO9DJm3-xwkygPqGmEbbDHLeEO1dEOlDdEYlYle5Pa70FGinCBqaAl7lDaJ1umAvpcLBUHtFOM7VBmt-xUjzOU7VDPareR6Ww".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.validate_self();
         assert!(key.is_err());
 
@@ -803,6 +1054,7 @@ mod tests {
             n: Some("r5Xn8yuwc7ekL5NLFnBw76cRUiYbIQqNgPq6XYw6_Mgle3BSJ-UTKTWjGLDoTSlFC7k2xCZNOt8pqix2R_qoGwlNo8kYXlgMpAEo00rSKoG1RO1PMj1M_--swijR8l1bnb-VfIPgT_kM3zv7RLPLEEjYHMuT7N5liFVq1Xh-So8i3X1UeWGHyJPHjF5koB_XO1vleYQCZQeGFaomJgrFJsxdmtFueJaMEMQ1-mPwuPjvSwOtMMAu0nO9DJm3-xwkygPqGmEbbDHLeEO1dEOlDdEYlYle5Pa70FGinCBqaAl7lDaJ1umAvpcLBUHtFOM7VBmt-xUjzOU7VDPareR6Ww".to_string()),
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }.validate_self();
         assert!(key.is_err());
 
@@ -814,6 +1066,7 @@ mod tests {
             n: None,
             e: Some("AQAB".to_string()),
             x: None,
+            y: None,
         }
         .validate_self();
         assert!(key.is_err());
@@ -826,6 +1079,7 @@ mod tests {
             n: Some("r5Xn8yuwc7ekL5NLFnBw76cRUiYbIQqNgPq6XYw6_Mgle3BSJ-UTKTWjGLDoTSlFC7k2xCZNOt8pqix2R_qoGwlNo8kYXlgMpAEo00rSKoG1RO1PMj1M_--swijR8l1bnb-VfIPgT_kM3zv7RLPLEEjYHMuT7N5liFVq1Xh-So8i3X1UeWGHyJPHjF5koB_XO1vleYQCZQeGFaomJgrFJsxdmtFueJaMEMQ1-mPwuPjvSwOtMMAu0nO9DJm3-xwkygPqGmEbbDHLeEO1dEOlDdEYlYle5Pa70FGinCBqaAl7lDaJ1umAvpcLBUHtFOM7VBmt-xUjzOU7VDPareR6Ww".to_string()),
             e: None,
             x: None,
+            y: None,
         }
             .validate_self();
         assert!(key.is_err());
@@ -838,6 +1092,7 @@ mod tests {
             n: None,
             e: None,
             x: None,
+            y: None,
         }
         .validate_self();
         assert!(key.is_err());
@@ -850,6 +1105,7 @@ mod tests {
             n: None,
             e: None,
             x: None,
+            y: None,
         }
         .validate_self();
         assert!(key.is_err());
@@ -862,6 +1118,7 @@ mod tests {
             n: Some("n".to_string()),
             e: None,
             x: None,
+            y: None,
         }
         .validate_self();
         assert!(key.is_err());
@@ -874,6 +1131,7 @@ mod tests {
             n: Some("n".to_string()),
             e: None,
             x: Some("suwfa9fyMHqS0yOh9T-Bsdkji0naFVRRGZFBNrGX_RQ".to_string()),
+            y: None,
         }
         .validate_self();
         assert!(key.is_err());
@@ -886,6 +1144,46 @@ mod tests {
             n: None,
             e: Some("e".to_string()),
             x: Some("suwfa9fyMHqS0yOh9T-Bsdkji0naFVRRGZFBNrGX_RQ".to_string()),
+            y: None,
+        }
+        .validate_self();
+        assert!(key.is_err());
+
+        let key = JWKSPublicKey {
+            kty: JwkKeyPairType::EC,
+            alg: Some(JwkKeyPairAlg::ES384),
+            crv: Some("P-256".to_string()),
+            kid: None,
+            n: None,
+            e: None,
+            x: Some("MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4".to_string()),
+            y: Some("4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM".to_string()),
+        }
+        .validate_self();
+        assert!(key.is_err());
+
+        let key = JWKSPublicKey {
+            kty: JwkKeyPairType::EC,
+            alg: Some(JwkKeyPairAlg::ES256),
+            crv: Some("P-256".to_string()),
+            kid: None,
+            n: None,
+            e: None,
+            x: Some("MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4".to_string()),
+            y: None,
+        }
+        .validate_self();
+        assert!(key.is_err());
+
+        let key = JWKSPublicKey {
+            kty: JwkKeyPairType::EC,
+            alg: Some(JwkKeyPairAlg::EdDSA),
+            crv: Some("Ed25519".to_string()),
+            kid: None,
+            n: None,
+            e: None,
+            x: Some("suwfa9fyMHqS0yOh9T-Bsdkji0naFVRRGZFBNrGX_RQ".to_string()),
+            y: None,
         }
         .validate_self();
         assert!(key.is_err());
@@ -905,6 +1203,8 @@ mod tests {
                 typ: JwtTokenType::Refresh,
                 uid: "user_id_13337".to_string(),
                 cnf: None,
+                sid: None,
+                authorization_details: None,
             },
             coarsetime::Duration::from_secs(300),
         );
@@ -921,6 +1221,41 @@ mod tests {
             n: None,
             e: None,
             x: Some(x),
+            y: None,
+        };
+        jwk.validate_token_signature(&signed_token).unwrap();
+
+        // ES256
+        let kp = ES256KeyPair::generate();
+        let signed_token = kp.sign(claims.clone()).unwrap();
+        let point = kp.public_key().to_bytes_uncompressed();
+        let (x, y) = point[1..].split_at(32);
+        let jwk = JWKSPublicKey {
+            kty: JwkKeyPairType::EC,
+            alg: Some(JwkKeyPairAlg::ES256),
+            crv: Some("P-256".to_string()),
+            kid: None,
+            n: None,
+            e: None,
+            x: Some(base64_url_encode(x)),
+            y: Some(base64_url_encode(y)),
+        };
+        jwk.validate_token_signature(&signed_token).unwrap();
+
+        // ES384
+        let kp = ES384KeyPair::generate();
+        let signed_token = kp.sign(claims.clone()).unwrap();
+        let point = kp.public_key().to_bytes_uncompressed();
+        let (x, y) = point[1..].split_at(48);
+        let jwk = JWKSPublicKey {
+            kty: JwkKeyPairType::EC,
+            alg: Some(JwkKeyPairAlg::ES384),
+            crv: Some("P-384".to_string()),
+            kid: None,
+            n: None,
+            e: None,
+            x: Some(base64_url_encode(x)),
+            y: Some(base64_url_encode(y)),
         };
         jwk.validate_token_signature(&signed_token).unwrap();
 
@@ -938,6 +1273,7 @@ mod tests {
             n: Some(n),
             e: Some(e),
             x: None,
+            y: None,
         };
         jwk.validate_token_signature(&signed_token).unwrap();
 
@@ -955,6 +1291,7 @@ mod tests {
             n: Some(n),
             e: Some(e),
             x: None,
+            y: None,
         };
         jwk.validate_token_signature(&signed_token).unwrap();
 
@@ -972,6 +1309,7 @@ mod tests {
             n: Some(n),
             e: Some(e),
             x: None,
+            y: None,
         };
         jwk.validate_token_signature(&signed_token).unwrap();
     }
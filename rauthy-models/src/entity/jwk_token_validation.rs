@@ -4,6 +4,7 @@
 use crate::entity::jwk::{JWKSPublicKey, JwkKeyPairAlg};
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
 use rauthy_common::utils::base64_url_no_pad_decode;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED, ECDSA_P384_SHA384_FIXED};
 use rsa::sha2::{Sha256, Sha384, Sha512};
 use rsa::{Pkcs1v15Sign, RsaPublicKey};
 use tracing::warn;
@@ -23,7 +24,7 @@ impl JWKSPublicKey {
         })?;
         // TODO this can be made more efficient without creating a new String -> only &[u8] needed
         let message = format!("{}.{}", header, claims);
-        let sig_bytes = base64_url_no_pad_decode(sig_str).unwrap();
+        let sig_bytes = base64_url_no_pad_decode(sig_str)?;
 
         match self.alg()? {
             JwkKeyPairAlg::RS256 => {
@@ -64,14 +65,28 @@ impl JWKSPublicKey {
 
             JwkKeyPairAlg::EdDSA => {
                 let x = self.x()?;
-                if let Ok(pubkey) = ed25519_compact::PublicKey::from_slice(x.as_slice()) {
-                    let signature =
-                        ed25519_compact::Signature::from_slice(sig_bytes.as_slice()).unwrap();
+                let pubkey = ed25519_compact::PublicKey::from_slice(x.as_slice());
+                let signature = ed25519_compact::Signature::from_slice(sig_bytes.as_slice());
+                if let (Ok(pubkey), Ok(signature)) = (pubkey, signature) {
                     if pubkey.verify(message, &signature).is_ok() {
                         return Ok(());
                     }
                 }
             }
+
+            JwkKeyPairAlg::ES256 => {
+                let pubkey = UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, self.ec_point()?);
+                if pubkey.verify(message.as_bytes(), &sig_bytes).is_ok() {
+                    return Ok(());
+                }
+            }
+
+            JwkKeyPairAlg::ES384 => {
+                let pubkey = UnparsedPublicKey::new(&ECDSA_P384_SHA384_FIXED, self.ec_point()?);
+                if pubkey.verify(message.as_bytes(), &sig_bytes).is_ok() {
+                    return Ok(());
+                }
+            }
         };
 
         warn!("JWT Token validation error");
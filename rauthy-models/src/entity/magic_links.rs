@@ -12,6 +12,8 @@ use tracing::warn;
 #[serde(rename_all = "snake_case")]
 pub enum MagicLinkUsage {
     EmailChange(String),
+    // the value is the ID of the session, which has requested the login
+    Login(String),
     PasswordReset,
     NewUser,
 }
@@ -33,6 +35,7 @@ impl TryFrom<&str> for MagicLinkUsage {
             "password_reset" => MagicLinkUsage::PasswordReset,
             "new_user" => MagicLinkUsage::NewUser,
             "email_change" => MagicLinkUsage::EmailChange(v.to_string()),
+            "login" => MagicLinkUsage::Login(v.to_string()),
             _ => {
                 return Err(ErrorResponse::new(
                     ErrorResponseType::BadRequest,
@@ -53,6 +56,7 @@ impl ToString for MagicLinkUsage {
             MagicLinkUsage::PasswordReset => "password_reset".to_string(),
             MagicLinkUsage::NewUser => "new_user".to_string(),
             MagicLinkUsage::EmailChange(email) => format!("email_change${}", email),
+            MagicLinkUsage::Login(sid) => format!("login${}", sid),
         }
     }
 }
@@ -276,5 +280,10 @@ mod tests {
         let s = ml.to_string();
         let ml_from = MagicLinkUsage::try_from(&s).unwrap();
         assert_eq!(ml, ml_from);
+
+        let ml = MagicLinkUsage::Login("sQw4eWm2Ry3LQh0K8Xz1".to_string());
+        let s = ml.to_string();
+        let ml_from = MagicLinkUsage::try_from(&s).unwrap();
+        assert_eq!(ml, ml_from);
     }
 }
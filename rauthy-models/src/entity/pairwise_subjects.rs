@@ -0,0 +1,104 @@
+use crate::app_state::AppState;
+use actix_web::web;
+use rauthy_common::error_response::ErrorResponse;
+use rauthy_common::utils::base64_url_no_pad_encode;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Maps a pairwise subject identifier back to the user it has been computed for. Pairwise
+/// subjects cannot be reversed, but incoming access tokens only contain them.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct PairwiseSubject {
+    pub sub: String,
+    pub sector_identifier: String,
+    pub user_id: String,
+}
+
+// CRUD
+impl PairwiseSubject {
+    pub async fn upsert(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        #[cfg(feature = "sqlite")]
+        let q = sqlx::query!(
+            r#"insert or ignore into pairwise_subjects (sub, sector_identifier, user_id)
+            values ($1, $2, $3)"#,
+            self.sub,
+            self.sector_identifier,
+            self.user_id,
+        );
+
+        #[cfg(not(feature = "sqlite"))]
+        let q = sqlx::query!(
+            r#"insert into pairwise_subjects (sub, sector_identifier, user_id)
+            values ($1, $2, $3)
+            on conflict(sub) do nothing"#,
+            self.sub,
+            self.sector_identifier,
+            self.user_id,
+        );
+
+        q.execute(&data.db).await?;
+        Ok(())
+    }
+
+    pub async fn find(data: &web::Data<AppState>, sub: &str) -> Result<Self, ErrorResponse> {
+        let res = sqlx::query_as!(Self, "select * from pairwise_subjects where sub = $1", sub)
+            .fetch_one(&data.db)
+            .await?;
+        Ok(res)
+    }
+}
+
+impl PairwiseSubject {
+    /// Computes the pairwise subject for a user inside the given sector as described in
+    /// OpenID Connect Core 1.0, Section 8.1
+    pub fn new(sector_identifier: String, user_id: String, secret: &str) -> Self {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let mut ctx = hmac::Context::with_key(&key);
+        ctx.update(sector_identifier.as_bytes());
+        ctx.update(b"|");
+        ctx.update(user_id.as_bytes());
+        let sub = base64_url_no_pad_encode(ctx.sign().as_ref());
+
+        Self {
+            sub,
+            sector_identifier,
+            user_id,
+        }
+    }
+
+    /// Resolves the user id from a token `sub`. Public subjects are the user id already.
+    pub async fn resolve_user_id(
+        data: &web::Data<AppState>,
+        sub: String,
+    ) -> Result<String, ErrorResponse> {
+        let res = sqlx::query_as!(Self, "select * from pairwise_subjects where sub = $1", sub)
+            .fetch_optional(&data.db)
+            .await?;
+        Ok(res.map(|ps| ps.user_id).unwrap_or(sub))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairwise_subject() {
+        let a = PairwiseSubject::new("app.example.com".to_string(), "user1".to_string(), "s3cr3t");
+        let b = PairwiseSubject::new("app.example.com".to_string(), "user1".to_string(), "s3cr3t");
+        assert_eq!(a.sub, b.sub);
+        assert_ne!(a.sub, "user1");
+
+        let other_sector = PairwiseSubject::new(
+            "other.example.com".to_string(),
+            "user1".to_string(),
+            "s3cr3t",
+        );
+        assert_ne!(a.sub, other_sector.sub);
+
+        let other_secret =
+            PairwiseSubject::new("app.example.com".to_string(), "user1".to_string(), "other");
+        assert_ne!(a.sub, other_secret.sub);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::app_state::AppState;
+use crate::request::AuthRequest;
+use actix_web::web;
+use rauthy_common::constants::{CACHE_NAME_PAR, PAR_EXP, PAR_REQUEST_URI_PREFIX};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::utils::get_rand;
+use redhac::{cache_del, cache_get, cache_get_from, cache_get_value, cache_put};
+use serde::{Deserialize, Serialize};
+use std::ops::Add;
+use time::OffsetDateTime;
+
+// Struct for the pushed authorization requests from RFC 9126
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PushedAuthRequest {
+    pub request_uri: String,
+    pub exp: i64,
+    pub auth_request: AuthRequest,
+}
+
+// CRUD
+impl PushedAuthRequest {
+    // Returns a Pushed Authorization Request from the cache
+    pub async fn find(
+        data: &web::Data<AppState>,
+        request_uri: String,
+    ) -> Result<Self, ErrorResponse> {
+        let par = cache_get!(
+            PushedAuthRequest,
+            CACHE_NAME_PAR.to_string(),
+            request_uri,
+            &data.caches.ha_cache_config,
+            true
+        )
+        .await?;
+
+        match par {
+            Some(par) if par.exp > OffsetDateTime::now_utc().unix_timestamp() => Ok(par),
+            _ => Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "The 'request_uri' does not exist or has expired".to_string(),
+            )),
+        }
+    }
+
+    // A `request_uri` must only ever be used once
+    pub async fn delete(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_del(
+            CACHE_NAME_PAR.to_string(),
+            self.request_uri.clone(),
+            &data.caches.ha_cache_config,
+        )
+        .await?;
+        Ok(())
+    }
+
+    // Saves a Pushed Authorization Request
+    pub async fn save(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_put(
+            CACHE_NAME_PAR.to_string(),
+            self.request_uri.clone(),
+            &data.caches.ha_cache_config,
+            self,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl PushedAuthRequest {
+    pub fn new(auth_request: AuthRequest) -> Self {
+        let exp = OffsetDateTime::now_utc()
+            .add(time::Duration::seconds(*PAR_EXP as i64))
+            .unix_timestamp();
+
+        Self {
+            request_uri: format!("{}{}", PAR_REQUEST_URI_PREFIX, get_rand(48)),
+            exp,
+            auth_request,
+        }
+    }
+}
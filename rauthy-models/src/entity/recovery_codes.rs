@@ -0,0 +1,247 @@
+use crate::app_state::AppState;
+use crate::entity::sessions::MfaMethod;
+use crate::entity::users::{AccountType, User};
+use crate::entity::webauthn::{WebauthnAdditionalData, WebauthnLoginReq};
+use crate::events::event::Event;
+use crate::request::RecoveryCodeAuthRequest;
+use actix_web::web;
+use data_encoding::BASE32_NOPAD;
+use futures_util::future::try_join_all;
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::password_hasher::{ComparePasswords, HashPassword};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+const RECOVERY_CODES_COUNT: usize = 10;
+// 10 random bytes -> 16 base32 characters, shown in groups of 4
+const RECOVERY_CODE_BYTES: usize = 10;
+const RECOVERY_CODE_LEN: usize = 16;
+// The first group of each code is its public ID. This makes it possible to only compare a
+// single hash during the login instead of all existing ones for the user.
+const RECOVERY_CODE_ID_LEN: usize = 4;
+
+/// A single-use recovery code, which can be used in place of the second factor during the login.
+/// Only the argon2id hash is saved and the plain text is shown to the user a single time.
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct RecoveryCode {
+    pub user_id: String,
+    pub id: String,
+    pub hash: String,
+    pub created: i64,
+}
+
+// CRUD
+impl RecoveryCode {
+    pub async fn delete_all_for_user(
+        data: &web::Data<AppState>,
+        user_id: &str,
+    ) -> Result<(), ErrorResponse> {
+        sqlx::query!("delete from recovery_codes where user_id = $1", user_id)
+            .execute(&data.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find(
+        data: &web::Data<AppState>,
+        user_id: &str,
+        id: &str,
+    ) -> Result<Option<Self>, ErrorResponse> {
+        let res = sqlx::query_as!(
+            Self,
+            "select * from recovery_codes where user_id = $1 and id = $2",
+            user_id,
+            id,
+        )
+        .fetch_optional(&data.db)
+        .await?;
+        Ok(res)
+    }
+
+    pub async fn find_for_user(
+        data: &web::Data<AppState>,
+        user_id: &str,
+    ) -> Result<Vec<Self>, ErrorResponse> {
+        let res = sqlx::query_as!(
+            Self,
+            "select * from recovery_codes where user_id = $1",
+            user_id
+        )
+        .fetch_all(&data.db)
+        .await?;
+        Ok(res)
+    }
+}
+
+impl RecoveryCode {
+    /// Replaces all existing recovery codes for the user and returns the new ones in plain text.
+    pub async fn generate(
+        data: &web::Data<AppState>,
+        user_id: &str,
+    ) -> Result<Vec<String>, ErrorResponse> {
+        let mut codes: Vec<String> = Vec::with_capacity(RECOVERY_CODES_COUNT);
+        while codes.len() < RECOVERY_CODES_COUNT {
+            let code = BASE32_NOPAD.encode(&rand::random::<[u8; RECOVERY_CODE_BYTES]>());
+            let id = &code[..RECOVERY_CODE_ID_LEN];
+            if !codes.iter().any(|c| c.starts_with(id)) {
+                codes.push(code);
+            }
+        }
+
+        let hashes = try_join_all(
+            codes
+                .iter()
+                .map(|code| HashPassword::hash_password(code.clone())),
+        )
+        .await?;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        let mut txn = data.db.begin().await?;
+        sqlx::query!("delete from recovery_codes where user_id = $1", user_id)
+            .execute(&mut *txn)
+            .await?;
+        for (code, hash) in codes.iter().zip(hashes) {
+            let id = &code[..RECOVERY_CODE_ID_LEN];
+            sqlx::query!(
+                r#"insert into recovery_codes (user_id, id, hash, created)
+                values ($1, $2, $3, $4)"#,
+                user_id,
+                id,
+                hash,
+                now,
+            )
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+
+        info!("New recovery codes generated for user {}", user_id);
+        Ok(codes.iter().map(|c| Self::format(c)).collect())
+    }
+
+    /// Generates new recovery codes during an MFA enrollment, if the user does not have any yet.
+    /// Returns an empty `Vec` otherwise.
+    pub async fn generate_on_enrollment(
+        data: &web::Data<AppState>,
+        user: &User,
+    ) -> Result<Vec<String>, ErrorResponse> {
+        if !Self::is_applicable(user) || !Self::find_for_user(data, &user.id).await?.is_empty() {
+            return Ok(Vec::default());
+        }
+        Self::generate(data, &user.id).await
+    }
+
+    /// Recovery codes only replace the second factor. Passkey only accounts do not have any
+    /// first factor, which could be combined with them.
+    #[inline]
+    pub fn is_applicable(user: &User) -> bool {
+        user.has_mfa_enabled() && user.account_type() != AccountType::Passkey
+    }
+
+    /// Validates the given `code` and deletes it afterward. Each code can only be used once.
+    pub async fn consume(
+        data: &web::Data<AppState>,
+        user_id: &str,
+        code: &str,
+    ) -> Result<(), ErrorResponse> {
+        let err = || {
+            ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                "Invalid recovery code".to_string(),
+            )
+        };
+
+        let code = Self::normalize(code);
+        if code.len() != RECOVERY_CODE_LEN {
+            return Err(err());
+        }
+        let id = &code[..RECOVERY_CODE_ID_LEN];
+
+        let slf = Self::find(data, user_id, id).await?.ok_or_else(err)?;
+        if !ComparePasswords::is_match(code.clone(), slf.hash).await? {
+            return Err(err());
+        }
+
+        // only the request which actually deleted the code may use it
+        let res = sqlx::query!(
+            "delete from recovery_codes where user_id = $1 and id = $2",
+            user_id,
+            id,
+        )
+        .execute(&data.db)
+        .await?;
+        if res.rows_affected() == 0 {
+            return Err(err());
+        }
+
+        Ok(())
+    }
+
+    // ABCDEFGHIJKLMNOP -> ABCD-EFGH-IJKL-MNOP
+    fn format(code: &str) -> String {
+        code.as_bytes()
+            .chunks(4)
+            .map(String::from_utf8_lossy)
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    // Users may type the codes in lowercase and with or without the dashes.
+    fn normalize(code: &str) -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    }
+}
+
+/// Finishes a login, which is waiting for the second factor, with a recovery code instead
+pub async fn login_finish(
+    data: &web::Data<AppState>,
+    user_id: String,
+    req: RecoveryCodeAuthRequest,
+    ip: Option<String>,
+) -> Result<WebauthnAdditionalData, ErrorResponse> {
+    let login_req = WebauthnLoginReq::find(data, req.code).await?;
+    // just like for TOTP, each pending login only gets a single try
+    login_req.delete(data).await?;
+    if login_req.user_id != user_id {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::Forbidden,
+            "User ID does not match".to_string(),
+        ));
+    }
+
+    let user = User::find(data, user_id).await?;
+    RecoveryCode::consume(data, &user.id, &req.recovery_code).await?;
+    login_req
+        .set_session_mfa_method(data, MfaMethod::RecoveryCode)
+        .await?;
+
+    warn!("Recovery code used for the login of user {}", user.id);
+    data.tx_events
+        .send_async(Event::recovery_code_used(user.email, ip))
+        .await
+        .unwrap();
+
+    Ok(WebauthnAdditionalData::Login(login_req))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_code_format() {
+        let code = "ABCDEFGHIJKLMNOP";
+        let formatted = RecoveryCode::format(code);
+        assert_eq!(formatted, "ABCD-EFGH-IJKL-MNOP");
+        assert_eq!(RecoveryCode::normalize(&formatted), code);
+        assert_eq!(RecoveryCode::normalize(" abcd-efgh ijkl-mnop"), code);
+
+        let code = BASE32_NOPAD.encode(&rand::random::<[u8; RECOVERY_CODE_BYTES]>());
+        assert_eq!(code.len(), RECOVERY_CODE_LEN);
+    }
+}
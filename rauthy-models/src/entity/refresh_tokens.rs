@@ -14,10 +14,18 @@ pub struct RefreshToken {
     pub exp: i64,
     pub scope: Option<String>,
     pub is_mfa: bool,
+    pub client_id: Option<String>,
+    // the `id` of the first token of a rotation chain - all tokens issued via refreshing share it
+    pub family_id: Option<String>,
+    pub rotated_at: Option<i64>,
+    // the single accepted usage after the rotation within the grace time
+    pub replayed_at: Option<i64>,
 }
 
 // CRUD
 impl RefreshToken {
+    /// If no `family_id` is given, the new token starts a new rotation family.
+    #[allow(clippy::too_many_arguments)]
     pub async fn create(
         data: &web::Data<AppState>,
         id: String,
@@ -26,7 +34,10 @@ impl RefreshToken {
         exp: OffsetDateTime,
         scope: Option<String>,
         is_mfa: bool,
+        client_id: String,
+        family_id: Option<String>,
     ) -> Result<Self, ErrorResponse> {
+        let family_id = family_id.unwrap_or_else(|| id.clone());
         let rt = Self {
             id,
             user_id,
@@ -34,6 +45,10 @@ impl RefreshToken {
             exp: exp.unix_timestamp(),
             scope,
             is_mfa,
+            client_id: Some(client_id),
+            family_id: Some(family_id),
+            rotated_at: None,
+            replayed_at: None,
         };
 
         rt.save(data).await?;
@@ -47,6 +62,17 @@ impl RefreshToken {
         Ok(())
     }
 
+    /// Deletes all tokens of the given rotation family
+    pub async fn delete_family(
+        data: &web::Data<AppState>,
+        family_id: &str,
+    ) -> Result<(), ErrorResponse> {
+        sqlx::query!("delete from refresh_tokens where family_id = $1", family_id)
+            .execute(&data.db)
+            .await?;
+        Ok(())
+    }
+
     pub async fn find_all(data: &web::Data<AppState>) -> Result<Vec<Self>, ErrorResponse> {
         let res = sqlx::query_as!(Self, "select * from refresh_tokens")
             .fetch_all(&data.db)
@@ -103,26 +129,39 @@ impl RefreshToken {
     pub async fn save(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
         #[cfg(feature = "sqlite")]
         let q = sqlx::query!(
-            r#"insert or replace into refresh_tokens (id, user_id, nbf, exp, scope, is_mfa)
-                values ($1, $2, $3, $4, $5, $6)"#,
+            r#"insert or replace into refresh_tokens
+                (id, user_id, nbf, exp, scope, is_mfa, client_id, family_id, rotated_at,
+                replayed_at)
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"#,
             self.id,
             self.user_id,
             self.nbf,
             self.exp,
             self.scope,
             self.is_mfa,
+            self.client_id,
+            self.family_id,
+            self.rotated_at,
+            self.replayed_at,
         );
         #[cfg(not(feature = "sqlite"))]
         let q = sqlx::query!(
-            r#"insert into refresh_tokens (id, user_id, nbf, exp, scope, is_mfa)
-                values ($1, $2, $3, $4, $5, $6)
-                on conflict(id) do update set user_id = $2, nbf = $3, exp = $4, scope = $5"#,
+            r#"insert into refresh_tokens
+                (id, user_id, nbf, exp, scope, is_mfa, client_id, family_id, rotated_at,
+                replayed_at)
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                on conflict(id) do update set user_id = $2, nbf = $3, exp = $4, scope = $5,
+                client_id = $7, family_id = $8, rotated_at = $9, replayed_at = $10"#,
             self.id,
             self.user_id,
             self.nbf,
             self.exp,
             self.scope,
             self.is_mfa,
+            self.client_id,
+            self.family_id,
+            self.rotated_at,
+            self.replayed_at,
         );
 
         q.execute(&data.db).await?;
@@ -132,6 +171,60 @@ impl RefreshToken {
 }
 
 impl RefreshToken {
+    /// Marks the rotation of the token with its first usage. Returns `false`, if the token has
+    /// been rotated already, even when requests are racing.
+    pub async fn mark_rotated(
+        &mut self,
+        data: &web::Data<AppState>,
+        now: i64,
+        client_id: &str,
+    ) -> Result<bool, ErrorResponse> {
+        let res = sqlx::query!(
+            r#"update refresh_tokens set rotated_at = $1, client_id = $2,
+            family_id = coalesce(family_id, id)
+            where id = $3 and rotated_at is null"#,
+            now,
+            client_id,
+            self.id,
+        )
+        .execute(&data.db)
+        .await?;
+
+        if res.rows_affected() == 1 {
+            self.rotated_at = Some(now);
+            self.client_id = Some(client_id.to_string());
+            if self.family_id.is_none() {
+                self.family_id = Some(self.id.clone());
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Marks the single usage of an already rotated token within the grace time. Returns `false`,
+    /// if the token has been replayed already, even when requests are racing.
+    pub async fn mark_replayed(
+        &mut self,
+        data: &web::Data<AppState>,
+        now: i64,
+    ) -> Result<bool, ErrorResponse> {
+        let res = sqlx::query!(
+            "update refresh_tokens set replayed_at = $1 where id = $2 and replayed_at is null",
+            now,
+            self.id,
+        )
+        .execute(&data.db)
+        .await?;
+
+        if res.rows_affected() == 1 {
+            self.replayed_at = Some(now);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     pub async fn invalidate_all_for_user(
         data: &web::Data<AppState>,
         id: &str,
@@ -142,4 +235,20 @@ impl RefreshToken {
 
         Ok(())
     }
+
+    pub async fn delete_for_user_client(
+        data: &web::Data<AppState>,
+        user_id: &str,
+        client_id: &str,
+    ) -> Result<(), ErrorResponse> {
+        sqlx::query!(
+            "delete from refresh_tokens where user_id = $1 and client_id = $2",
+            user_id,
+            client_id,
+        )
+        .execute(&data.db)
+        .await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,213 @@
+use crate::app_state::AppState;
+use crate::entity::clients::Client;
+use crate::entity::jwk::{JWKSPublicKey, JwkKeyPairAlg};
+use crate::request::AuthRequest;
+use crate::JwtAudience;
+use actix_web::web;
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::utils::base64_url_no_pad_decode;
+use serde::Deserialize;
+use std::str::FromStr;
+use time::OffsetDateTime;
+use tracing::{error, warn};
+use validator::Validate;
+
+/// Signed request object for the authorization endpoint from RFC 9101
+#[derive(Debug)]
+pub struct RequestObject {
+    pub header: RequestObjectHeader,
+    pub claims: RequestObjectClaims,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RequestObjectHeader {
+    /// Must be an asymmetric algorithm - 'none' and symmetric ones are rejected
+    pub alg: String,
+    pub kid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RequestObjectClaims {
+    /// If given, must match the `client_id`
+    pub iss: Option<String>,
+    /// If given, must contain the issuer of this Rauthy instance
+    pub aud: Option<JwtAudience>,
+    pub exp: Option<i64>,
+    pub nbf: Option<i64>,
+    /// All other claims are the usual authorization request parameters
+    #[serde(flatten)]
+    pub auth_request: AuthRequest,
+}
+
+impl RequestObject {
+    /// Fetches a request object by reference from the given `request_uri`.
+    /// It must exactly match one of the registered `request_uris` of the client and only
+    /// public `https` URIs are accepted.
+    pub async fn fetch(client: &Client, request_uri: &str) -> Result<String, ErrorResponse> {
+        let is_registered = client
+            .get_request_uris()
+            .map(|uris| uris.iter().any(|uri| uri == request_uri))
+            .unwrap_or(false);
+        if !is_registered {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "The 'request_uri' is not registered for this client".to_string(),
+            ));
+        }
+        Client::validate_public_uri(request_uri, "request_uri")?;
+
+        let res = Client::http_client()
+            .get(request_uri)
+            .send()
+            .await
+            .map_err(|err| {
+                ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    format!(
+                        "Cannot fetch the request object from {}: {:?}",
+                        request_uri, err
+                    ),
+                )
+            })?;
+
+        if !res.status().is_success() {
+            let msg = format!("Cannot fetch the request object from {}", request_uri);
+            error!("{}", msg);
+            return Err(ErrorResponse::new(ErrorResponseType::BadRequest, msg));
+        }
+
+        let body = Client::read_body_limited(res).await?;
+        let token = String::from_utf8(body).map_err(|_| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Malformed request object".to_string(),
+            )
+        })?;
+        Ok(token.trim().to_string())
+    }
+
+    /// Parses the given request object and validates its signature against the public keys
+    /// of the given client and all its claims.
+    pub async fn validated_from(
+        data: &web::Data<AppState>,
+        client: &Client,
+        token: &str,
+    ) -> Result<Self, ErrorResponse> {
+        let slf = Self::try_from_str(token)?;
+
+        let alg = JwkKeyPairAlg::from_str(&slf.header.alg).map_err(|_| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("Request object 'alg' {} is not supported", slf.header.alg),
+            )
+        })?;
+        let key = JWKSPublicKey::find_for_alg(client.find_jwks().await?, &slf.header.kid, alg)?;
+        if key.validate_token_signature(token).is_err() {
+            warn!(
+                "Invalid request object signature for client '{}'",
+                client.id
+            );
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Invalid request object signature".to_string(),
+            ));
+        }
+
+        slf.validate_claims(data, client)?;
+        Ok(slf)
+    }
+
+    pub fn into_auth_request(self) -> AuthRequest {
+        let mut auth_request = self.claims.auth_request;
+        // request objects must not be nested
+        auth_request.request = None;
+        auth_request
+    }
+
+    fn try_from_str(token: &str) -> Result<Self, ErrorResponse> {
+        let err = || {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Malformed request object".to_string(),
+            )
+        };
+
+        let (header, rest) = token.split_once('.').ok_or_else(err)?;
+        let (claims, _signature) = rest.split_once('.').ok_or_else(err)?;
+
+        let bytes = base64_url_no_pad_decode(header)?;
+        let header = serde_json::from_slice::<RequestObjectHeader>(&bytes).map_err(|_| err())?;
+
+        let bytes = base64_url_no_pad_decode(claims)?;
+        let claims = serde_json::from_slice::<RequestObjectClaims>(&bytes).map_err(|_| err())?;
+
+        Ok(Self { header, claims })
+    }
+
+    fn validate_claims(
+        &self,
+        data: &web::Data<AppState>,
+        client: &Client,
+    ) -> Result<(), ErrorResponse> {
+        let claims = &self.claims;
+
+        if claims.auth_request.client_id != client.id {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "'client_id' does not match the request object".to_string(),
+            ));
+        }
+        if let Some(iss) = &claims.iss {
+            if iss != &client.id {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    "Request object 'iss' must be the 'client_id'".to_string(),
+                ));
+            }
+        }
+        if let Some(aud) = &claims.aud {
+            if !aud.contains(&data.issuer) {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    "Request object 'aud' does not contain this issuer".to_string(),
+                ));
+            }
+        }
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        if claims.exp.map(|exp| exp < now).unwrap_or(false) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Request object has expired".to_string(),
+            ));
+        }
+        if claims.nbf.map(|nbf| nbf > now).unwrap_or(false) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Request object is not valid yet".to_string(),
+            ));
+        }
+
+        claims.auth_request.validate()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jwt_audience() {
+        let aud = serde_json::from_str::<JwtAudience>(r#""https://iam.example.com""#).unwrap();
+        assert!(aud.contains("https://iam.example.com"));
+        assert!(!aud.contains("https://example.com"));
+
+        let aud = serde_json::from_str::<JwtAudience>(
+            r#"["https://example.com", "https://iam.example.com"]"#,
+        )
+        .unwrap();
+        assert!(aud.contains("https://iam.example.com"));
+        assert!(!aud.contains("https://other.example.com"));
+    }
+}
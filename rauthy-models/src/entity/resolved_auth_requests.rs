@@ -0,0 +1,89 @@
+use crate::app_state::AppState;
+use crate::entity::clients::Client;
+use crate::request::AuthRequest;
+use actix_web::web;
+use rauthy_common::constants::CACHE_NAME_AUTH_REQUESTS;
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use redhac::{cache_get, cache_get_from, cache_get_value, cache_put};
+use serde::{Deserialize, Serialize};
+
+/// An authorization request, which has been resolved server-side from a Pushed Authorization
+/// Request or a signed request object during GET /oidc/authorize. It is bound to the session,
+/// which does the login, because the params sent by the browser afterward cannot be trusted for
+/// clients with `require_par` or `require_request_object`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolvedAuthRequest {
+    pub session_id: String,
+    pub is_par: bool,
+    pub is_request_object: bool,
+    pub auth_request: AuthRequest,
+}
+
+// CRUD
+impl ResolvedAuthRequest {
+    pub async fn find(
+        data: &web::Data<AppState>,
+        session_id: &str,
+        client_id: &str,
+    ) -> Result<Option<Self>, ErrorResponse> {
+        let res = cache_get!(
+            ResolvedAuthRequest,
+            CACHE_NAME_AUTH_REQUESTS.to_string(),
+            Self::cache_idx(session_id, client_id),
+            &data.caches.ha_cache_config,
+            true
+        )
+        .await?;
+        Ok(res)
+    }
+
+    pub async fn save(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_put(
+            CACHE_NAME_AUTH_REQUESTS.to_string(),
+            Self::cache_idx(&self.session_id, &self.auth_request.client_id),
+            &data.caches.ha_cache_config,
+            self,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl ResolvedAuthRequest {
+    /// Returns the resolved request, if the client requires a PAR or a signed request object.
+    /// The login must fail without one in this case. For all other clients, `None` is returned
+    /// and the params from the browser will be used.
+    pub async fn find_required(
+        data: &web::Data<AppState>,
+        session_id: &str,
+        client: &Client,
+    ) -> Result<Option<AuthRequest>, ErrorResponse> {
+        if !client.require_par && !client.require_request_object {
+            return Ok(None);
+        }
+
+        match Self::find(data, session_id, &client.id).await? {
+            Some(resolved)
+                if (resolved.is_par || !client.require_par)
+                    && (resolved.is_request_object || !client.require_request_object) =>
+            {
+                Ok(Some(resolved.auth_request))
+            }
+            _ => {
+                let msg = if client.require_par {
+                    "This client only accepts Pushed Authorization Requests"
+                } else {
+                    "This client only accepts signed request objects"
+                };
+                Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    msg.to_string(),
+                ))
+            }
+        }
+    }
+
+    fn cache_idx(session_id: &str, client_id: &str) -> String {
+        format!("{}_{}", session_id, client_id)
+    }
+}
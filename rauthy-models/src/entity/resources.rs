@@ -0,0 +1,259 @@
+use crate::app_state::AppState;
+use crate::request::ResourceRequest;
+use actix_web::web;
+use rauthy_common::constants::{CACHE_NAME_12HR, IDX_RESOURCES};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::utils::new_store_id;
+use redhac::{cache_get, cache_get_from, cache_get_value, cache_insert, AckLevel};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// A protected resource, which can be requested via the `resource` parameter (RFC 8707).
+/// Access tokens for a resource have its `uri` as the audience and only contain the scopes,
+/// which are allowed for it.
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize, ToSchema)]
+pub struct Resource {
+    pub id: String,
+    pub name: String,
+    pub uri: String,
+    // the allowed scopes as CSV
+    pub scopes: String,
+}
+
+// CRUD
+impl Resource {
+    // Inserts a new resource into the database
+    pub async fn create(
+        data: &web::Data<AppState>,
+        resource_req: ResourceRequest,
+    ) -> Result<Self, ErrorResponse> {
+        let mut resources = Resource::find_all(data).await?;
+        if resources.iter().any(|r| r.uri == resource_req.uri) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Resource already exists".to_string(),
+            ));
+        }
+
+        let new_resource = Resource {
+            id: new_store_id(),
+            name: resource_req.name,
+            uri: resource_req.uri,
+            scopes: resource_req.scopes.join(","),
+        };
+
+        sqlx::query!(
+            "insert into resources (id, name, uri, scopes) values ($1, $2, $3, $4)",
+            new_resource.id,
+            new_resource.name,
+            new_resource.uri,
+            new_resource.scopes,
+        )
+        .execute(&data.db)
+        .await?;
+
+        resources.push(new_resource.clone());
+        cache_insert(
+            CACHE_NAME_12HR.to_string(),
+            IDX_RESOURCES.to_string(),
+            &data.caches.ha_cache_config,
+            &resources,
+            AckLevel::Quorum,
+        )
+        .await?;
+
+        Ok(new_resource)
+    }
+
+    // Deletes a resource
+    pub async fn delete(data: &web::Data<AppState>, id: &str) -> Result<(), ErrorResponse> {
+        let resource = Resource::find(data, id).await?;
+
+        sqlx::query!("delete from resources where id = $1", resource.id)
+            .execute(&data.db)
+            .await?;
+
+        let resources = Resource::find_all(data)
+            .await?
+            .into_iter()
+            .filter(|r| r.id != resource.id)
+            .collect::<Vec<Resource>>();
+        cache_insert(
+            CACHE_NAME_12HR.to_string(),
+            IDX_RESOURCES.to_string(),
+            &data.caches.ha_cache_config,
+            &resources,
+            AckLevel::Quorum,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    // Returns a single resource by id
+    pub async fn find(data: &web::Data<AppState>, id: &str) -> Result<Self, ErrorResponse> {
+        let res = sqlx::query_as!(Self, "select * from resources where id = $1", id)
+            .fetch_one(&data.db)
+            .await?;
+
+        Ok(res)
+    }
+
+    // Returns all existing resources
+    pub async fn find_all(data: &web::Data<AppState>) -> Result<Vec<Self>, ErrorResponse> {
+        let resources = cache_get!(
+            Vec<Resource>,
+            CACHE_NAME_12HR.to_string(),
+            IDX_RESOURCES.to_string(),
+            &data.caches.ha_cache_config,
+            false
+        )
+        .await?;
+        if let Some(resources) = resources {
+            return Ok(resources);
+        }
+
+        let res = sqlx::query_as!(Self, "select * from resources")
+            .fetch_all(&data.db)
+            .await?;
+
+        cache_insert(
+            CACHE_NAME_12HR.to_string(),
+            IDX_RESOURCES.to_string(),
+            &data.caches.ha_cache_config,
+            &res,
+            AckLevel::Leader,
+        )
+        .await?;
+        Ok(res)
+    }
+
+    // Updates a resource
+    pub async fn update(
+        data: &web::Data<AppState>,
+        id: &str,
+        resource_req: ResourceRequest,
+    ) -> Result<Self, ErrorResponse> {
+        let resource = Resource::find(data, id).await?;
+        let mut resources = Resource::find_all(data).await?;
+        if resources
+            .iter()
+            .any(|r| r.id != resource.id && r.uri == resource_req.uri)
+        {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Resource already exists".to_string(),
+            ));
+        }
+
+        let new_resource = Resource {
+            id: resource.id,
+            name: resource_req.name,
+            uri: resource_req.uri,
+            scopes: resource_req.scopes.join(","),
+        };
+
+        sqlx::query!(
+            "update resources set name = $1, uri = $2, scopes = $3 where id = $4",
+            new_resource.name,
+            new_resource.uri,
+            new_resource.scopes,
+            new_resource.id,
+        )
+        .execute(&data.db)
+        .await?;
+
+        resources.retain(|r| r.id != new_resource.id);
+        resources.push(new_resource.clone());
+        cache_insert(
+            CACHE_NAME_12HR.to_string(),
+            IDX_RESOURCES.to_string(),
+            &data.caches.ha_cache_config,
+            &resources,
+            AckLevel::Quorum,
+        )
+        .await?;
+
+        Ok(new_resource)
+    }
+}
+
+impl Resource {
+    /// Looks up the registered resource for the optional `resource` request parameter.
+    /// Unknown resources are rejected with `invalid_target`.
+    pub async fn find_for_request(
+        data: &web::Data<AppState>,
+        uri: Option<&str>,
+    ) -> Result<Option<Self>, ErrorResponse> {
+        let uri = match uri {
+            None => return Ok(None),
+            Some(uri) => uri,
+        };
+
+        Resource::find_all(data)
+            .await?
+            .into_iter()
+            .find(|r| r.uri == uri)
+            .map(Some)
+            .ok_or_else(|| {
+                warn!("Requested resource '{}' is not registered", uri);
+                ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    format!("invalid_target: resource '{}' is unknown", uri),
+                )
+            })
+    }
+
+    pub fn get_scopes(&self) -> Vec<&str> {
+        self.scopes.split(',').filter(|s| !s.is_empty()).collect()
+    }
+
+    /// Narrows down the space separated `scope` to the ones allowed for this resource.
+    /// If nothing remains, the client is not allowed to request a token for it.
+    pub fn narrow_scope(&self, scope: &str) -> Result<String, ErrorResponse> {
+        let allowed = self.get_scopes();
+        let narrowed = scope
+            .split(' ')
+            .filter(|s| allowed.contains(s))
+            .collect::<Vec<&str>>();
+
+        if narrowed.is_empty() {
+            Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!(
+                    "invalid_target: no granted scope is allowed for resource '{}'",
+                    self.uri
+                ),
+            ))
+        } else {
+            Ok(narrowed.join(" "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_narrow_scope() {
+        let resource = Resource {
+            id: new_store_id(),
+            name: "Orders API".to_string(),
+            uri: "https://api.example.com/orders".to_string(),
+            scopes: "orders_read,orders_write".to_string(),
+        };
+
+        assert_eq!(
+            resource.narrow_scope("openid orders_read email").unwrap(),
+            "orders_read"
+        );
+        assert_eq!(
+            resource.narrow_scope("orders_write orders_read").unwrap(),
+            "orders_write orders_read"
+        );
+        assert!(resource.narrow_scope("openid email").is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::app_state::AppState;
+use actix_web::web;
+use rauthy_common::constants::CACHE_NAME_REVOKED_TOKENS;
+use rauthy_common::error_response::ErrorResponse;
+use redhac::{cache_get, cache_get_from, cache_get_value, cache_put};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// The `jti` of an access token, which has been revoked before its expiry. The database is the
+/// source of truth, while the cache only speeds up the lookup for already known revocations.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct RevokedToken {
+    pub jti: String,
+    pub exp: i64,
+}
+
+// CRUD
+impl RevokedToken {
+    pub async fn insert(
+        data: &web::Data<AppState>,
+        jti: String,
+        exp: i64,
+    ) -> Result<(), ErrorResponse> {
+        sqlx::query!(
+            r#"insert into revoked_tokens (jti, exp) values ($1, $2)
+            on conflict(jti) do nothing"#,
+            jti,
+            exp,
+        )
+        .execute(&data.db)
+        .await?;
+
+        cache_put(
+            CACHE_NAME_REVOKED_TOKENS.to_string(),
+            jti,
+            &data.caches.ha_cache_config,
+            &true,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn is_revoked(data: &web::Data<AppState>, jti: &str) -> Result<bool, ErrorResponse> {
+        let revoked = cache_get!(
+            bool,
+            CACHE_NAME_REVOKED_TOKENS.to_string(),
+            jti.to_string(),
+            &data.caches.ha_cache_config,
+            true
+        )
+        .await?;
+        if revoked.is_some() {
+            return Ok(true);
+        }
+
+        let res = sqlx::query_as!(Self, "select * from revoked_tokens where jti = $1", jti)
+            .fetch_optional(&data.db)
+            .await?;
+        if res.is_none() {
+            return Ok(false);
+        }
+
+        cache_put(
+            CACHE_NAME_REVOKED_TOKENS.to_string(),
+            jti.to_string(),
+            &data.caches.ha_cache_config,
+            &true,
+        )
+        .await?;
+        Ok(true)
+    }
+}
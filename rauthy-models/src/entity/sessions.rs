@@ -1,4 +1,5 @@
 use crate::app_state::AppState;
+use crate::entity::backchannel_logout::BackchannelLogout;
 use crate::entity::users::User;
 use actix_web::cookie::{time, Cookie, SameSite};
 use actix_web::http::header::{HeaderName, HeaderValue};
@@ -7,8 +8,9 @@ use rauthy_common::constants::{
     CACHE_NAME_12HR, CACHE_NAME_SESSIONS, COOKIE_SESSION, CSRF_HEADER, IDX_SESSION,
 };
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
-use rauthy_common::utils::get_rand;
+use rauthy_common::utils::{base64_url_no_pad_encode, get_rand};
 use redhac::{cache_get, cache_get_from, cache_get_value, cache_insert, cache_remove, AckLevel};
+use ring::digest;
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgRow;
 use sqlx::sqlite::SqliteRow;
@@ -27,6 +29,8 @@ pub struct Session {
     pub roles: Option<String>,
     pub groups: Option<String>,
     pub is_mfa: bool,
+    /// The second factor, which has actually been used for the login
+    pub mfa_method: Option<String>,
     #[sqlx(flatten)]
     pub state: SessionState,
     pub exp: i64,
@@ -90,6 +94,23 @@ impl SessionState {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MfaMethod {
+    Webauthn,
+    Totp,
+    RecoveryCode,
+}
+
+impl MfaMethod {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MfaMethod::Webauthn => "webauthn",
+            MfaMethod::Totp => "totp",
+            MfaMethod::RecoveryCode => "recovery_code",
+        }
+    }
+}
+
 // CRUD
 impl Session {
     pub async fn delete(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
@@ -112,6 +133,8 @@ impl Session {
         data: &web::Data<AppState>,
         user_id: &str,
     ) -> Result<(), ErrorResponse> {
+        BackchannelLogout::notify_user(data, user_id).await;
+
         let sessions: Vec<Self> =
             sqlx::query_as!(Self, "SELECT * FROM sessions WHERE user_id = $1", user_id)
                 .fetch_all(&data.db)
@@ -192,6 +215,8 @@ impl Session {
         }
 
         for id in removed {
+            BackchannelLogout::notify_session(data, &id).await;
+
             cache_remove(
                 CACHE_NAME_SESSIONS.to_string(),
                 Session::cache_idx(&id),
@@ -225,6 +250,8 @@ impl Session {
             }
         }
 
+        BackchannelLogout::notify_user(data, uid).await;
+
         for id in removed {
             cache_remove(
                 CACHE_NAME_SESSIONS.to_string(),
@@ -245,8 +272,9 @@ impl Session {
         #[cfg(feature = "sqlite")]
         let q = sqlx::query!(
             r#"insert or replace into
-            sessions (id, csrf_token, user_id, roles, groups, is_mfa, state, exp, last_seen, remote_ip)
-            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"#,
+            sessions (id, csrf_token, user_id, roles, groups, is_mfa, state, exp, last_seen, remote_ip,
+            mfa_method)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"#,
             self.id,
             self.csrf_token,
             self.user_id,
@@ -257,15 +285,17 @@ impl Session {
             self.exp,
             self.last_seen,
             self.remote_ip,
+            self.mfa_method,
         );
 
         #[cfg(not(feature = "sqlite"))]
         let q = sqlx::query!(
             r#"insert into
-            sessions (id, csrf_token, user_id, roles, groups, is_mfa, state, exp, last_seen, remote_ip)
-            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            sessions (id, csrf_token, user_id, roles, groups, is_mfa, state, exp, last_seen, remote_ip,
+            mfa_method)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             on conflict(id) do update set user_id = $3, roles = $4, groups = $5, is_mfa = $6,
-            state = $7, exp = $8, last_seen = $9, remote_ip = $10"#,
+            state = $7, exp = $8, last_seen = $9, remote_ip = $10, mfa_method = $11"#,
             self.id,
             self.csrf_token,
             self.user_id,
@@ -276,6 +306,7 @@ impl Session {
             self.exp,
             self.last_seen,
             self.remote_ip,
+            self.mfa_method,
         );
 
         q.execute(&data.db).await?;
@@ -307,6 +338,7 @@ impl Session {
             roles: None,
             groups: None,
             is_mfa: false, // cannot be known at the creation stage
+            mfa_method: None,
             state: SessionState::Init,
             exp: now
                 .add(time::Duration::seconds(exp_in as i64))
@@ -320,6 +352,13 @@ impl Session {
         format!("{}{}", IDX_SESSION, id)
     }
 
+    /// Returns the `sid` claim for a session. The session id itself is the value of the session
+    /// cookie, which must never leave Rauthy, so only its hash is handed out to clients.
+    pub fn sid_from_id(id: &str) -> String {
+        let hash = digest::digest(&digest::SHA256, id.as_bytes());
+        base64_url_no_pad_encode(hash.as_ref())
+    }
+
     /// exp_in will be the time in seconds when the session will expire
     pub fn try_new(
         user: &User,
@@ -363,6 +402,7 @@ impl Session {
             roles,
             groups,
             is_mfa: false, // cannot be known at the creation stage
+            mfa_method: None,
             state: SessionState::Init,
             exp,
             last_seen: now.unix_timestamp(),
@@ -430,6 +470,8 @@ impl Session {
             .execute(&data.db)
             .await?;
 
+        BackchannelLogout::notify_session(data, &self.id).await;
+
         cache_remove(
             CACHE_NAME_12HR.to_string(),
             idx,
@@ -511,6 +553,19 @@ impl Session {
         value: bool,
     ) -> Result<(), ErrorResponse> {
         self.is_mfa = value;
+        // the factor is only known after the MFA step has been finished
+        self.mfa_method = None;
+        self.save(data).await
+    }
+
+    /// Records the second factor, which has been used for the MFA login of this session
+    #[inline]
+    pub async fn set_mfa_method(
+        &mut self,
+        data: &web::Data<AppState>,
+        method: MfaMethod,
+    ) -> Result<(), ErrorResponse> {
+        self.mfa_method = Some(method.as_str().to_string());
         self.save(data).await
     }
 
@@ -0,0 +1,290 @@
+use crate::app_state::AppState;
+use crate::entity::sessions::MfaMethod;
+use crate::entity::users::User;
+use crate::entity::webauthn::{WebauthnAdditionalData, WebauthnLoginReq};
+use crate::request::TotpAuthRequest;
+use crate::response::TotpRegistrationResponse;
+use actix_web::web;
+use cryptr::EncValue;
+use data_encoding::BASE32_NOPAD;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rauthy_common::constants::{CACHE_NAME_TOTP, TOTP_ISSUER};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use redhac::{cache_del, cache_get, cache_get_from, cache_get_value, cache_put};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+// RFC 6238 defaults, which are the only values most authenticator apps support
+const TOTP_DIGITS: u32 = 6;
+const TOTP_PERIOD: u64 = 30;
+// the accepted clock drift in time steps in each direction
+const TOTP_SKEW: u64 = 1;
+// 160 bit, as recommended for HMAC-SHA1 in RFC 4226
+const TOTP_SECRET_LEN: usize = 20;
+
+/// A pending registration of a TOTP authenticator app. The encrypted secret is only saved for
+/// the user after the first valid code has been provided.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpRegistration {
+    pub user_id: String,
+    pub secret: Vec<u8>,
+}
+
+// CRUD
+impl TotpRegistration {
+    pub async fn delete(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_del(
+            CACHE_NAME_TOTP.to_string(),
+            Self::cache_idx(&self.user_id),
+            &data.caches.ha_cache_config,
+        )
+        .await
+        .map_err(ErrorResponse::from)
+    }
+
+    pub async fn find(data: &web::Data<AppState>, user_id: &str) -> Result<Self, ErrorResponse> {
+        let slf = cache_get!(
+            TotpRegistration,
+            CACHE_NAME_TOTP.to_string(),
+            Self::cache_idx(user_id),
+            &data.caches.ha_cache_config,
+            false
+        )
+        .await?;
+
+        slf.ok_or_else(|| {
+            ErrorResponse::new(
+                ErrorResponseType::NotFound,
+                "The TOTP registration does not exist or has expired".to_string(),
+            )
+        })
+    }
+
+    pub async fn save(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_put(
+            CACHE_NAME_TOTP.to_string(),
+            Self::cache_idx(&self.user_id),
+            &data.caches.ha_cache_config,
+            self,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl TotpRegistration {
+    /// Creates a new random secret for the user
+    pub fn new(user_id: String) -> Result<Self, ErrorResponse> {
+        let secret = rand::random::<[u8; TOTP_SECRET_LEN]>();
+        Ok(Self {
+            user_id,
+            secret: EncValue::encrypt(&secret)?.into_bytes().to_vec(),
+        })
+    }
+
+    /// Validates the first code from the authenticator app and saves the secret for the user
+    pub async fn finish(self, data: &web::Data<AppState>, code: &str) -> Result<(), ErrorResponse> {
+        let mut user = User::find(data, self.user_id.clone()).await?;
+        validate_code(data, &user.id, &self.secret, code).await?;
+
+        self.delete(data).await?;
+        user.totp_secret = Some(self.secret);
+        user.save(data, None, None).await?;
+
+        info!("TOTP authenticator app registered for user {}", user.id);
+        Ok(())
+    }
+
+    pub fn as_response(&self, email: &str) -> Result<TotpRegistrationResponse, ErrorResponse> {
+        let secret = EncValue::try_from(self.secret.clone())?.decrypt()?;
+        let secret = BASE32_NOPAD.encode(secret.as_ref());
+        let uri = otpauth_uri(&secret, email);
+
+        let qr_svg = QrCode::new(uri.as_bytes())
+            .map_err(|err| {
+                ErrorResponse::new(
+                    ErrorResponseType::Internal,
+                    format!("Cannot build the TOTP QR code: {}", err),
+                )
+            })?
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build();
+
+        Ok(TotpRegistrationResponse {
+            secret,
+            uri,
+            qr_svg,
+        })
+    }
+
+    #[inline(always)]
+    fn cache_idx(user_id: &str) -> String {
+        format!("reg_{}", user_id)
+    }
+}
+
+/// Finishes a login, which is waiting for a TOTP as the second factor
+pub async fn login_finish(
+    data: &web::Data<AppState>,
+    user_id: String,
+    req: TotpAuthRequest,
+) -> Result<WebauthnAdditionalData, ErrorResponse> {
+    let login_req = WebauthnLoginReq::find(data, req.code).await?;
+    // each pending login only gets a single try, which makes guessing codes useless
+    login_req.delete(data).await?;
+    if login_req.user_id != user_id {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::Forbidden,
+            "User ID does not match".to_string(),
+        ));
+    }
+
+    let user = User::find(data, user_id).await?;
+    validate_user_code(data, &user, &req.totp).await?;
+    login_req
+        .set_session_mfa_method(data, MfaMethod::Totp)
+        .await?;
+
+    info!("TOTP Authentication successful for user {}", user.id);
+    Ok(WebauthnAdditionalData::Login(login_req))
+}
+
+/// Validates a `code` against the registered authenticator app of the `user`
+pub async fn validate_user_code(
+    data: &web::Data<AppState>,
+    user: &User,
+    code: &str,
+) -> Result<(), ErrorResponse> {
+    let secret = user.totp_secret.as_ref().ok_or_else(|| {
+        ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            "The user has no TOTP authenticator app registered".to_string(),
+        )
+    })?;
+    validate_code(data, &user.id, secret, code).await
+}
+
+// Validates the `code` against the encrypted `secret`. Each code is only accepted once.
+async fn validate_code(
+    data: &web::Data<AppState>,
+    user_id: &str,
+    secret: &[u8],
+    code: &str,
+) -> Result<(), ErrorResponse> {
+    let secret = EncValue::try_from(secret.to_vec())?.decrypt()?;
+    let now = OffsetDateTime::now_utc().unix_timestamp() as u64;
+    let err = || {
+        ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            "Invalid TOTP code".to_string(),
+        )
+    };
+
+    let step = matching_step(secret.as_ref(), code, now).ok_or_else(err)?;
+
+    // The insert is atomic, even for concurrent requests -> each code can only be used once.
+    // Steps outside the accepted window cannot match anymore and can be cleaned up.
+    let step = step as i64;
+    let res = sqlx::query!(
+        "insert into totp_used_steps (user_id, step) values ($1, $2) on conflict do nothing",
+        user_id,
+        step,
+    )
+    .execute(&data.db)
+    .await?;
+    if res.rows_affected() == 0 {
+        warn!("Replayed TOTP code for user {}", user_id);
+        return Err(err());
+    }
+
+    let outdated = step - 2 * TOTP_SKEW as i64;
+    sqlx::query!(
+        "delete from totp_used_steps where user_id = $1 and step < $2",
+        user_id,
+        outdated,
+    )
+    .execute(&data.db)
+    .await?;
+
+    Ok(())
+}
+
+/// The `otpauth://` URI for the registration inside an authenticator app
+fn otpauth_uri(secret_b32: &str, email: &str) -> String {
+    let issuer = urlencoding::encode(&TOTP_ISSUER);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        urlencoding::encode(email),
+        secret_b32,
+        issuer,
+        TOTP_DIGITS,
+        TOTP_PERIOD,
+    )
+}
+
+// Returns the time step for which the `code` is valid, if any
+fn matching_step(secret: &[u8], code: &str, unix_ts: u64) -> Option<u64> {
+    if code.len() != TOTP_DIGITS as usize {
+        return None;
+    }
+    let code = code.parse::<u32>().ok()?;
+
+    let current = unix_ts / TOTP_PERIOD;
+    (current.saturating_sub(TOTP_SKEW)..=current + TOTP_SKEW)
+        .find(|step| hotp(secret, *step) == code)
+}
+
+// HOTP value for the given counter with dynamic truncation (RFC 4226)
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let tag = hmac::sign(&key, &counter.to_be_bytes());
+    let hash = tag.as_ref();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let bin = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    bin % 10u32.pow(TOTP_DIGITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_rfc6238_vectors() {
+        // the SHA1 test vectors from RFC 6238, truncated to 6 digits
+        let secret = b"12345678901234567890";
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+        for (ts, code) in vectors {
+            assert_eq!(format!("{:06}", hotp(secret, ts / TOTP_PERIOD)), code);
+            assert_eq!(matching_step(secret, code, ts), Some(ts / TOTP_PERIOD));
+        }
+
+        // one step of clock drift is accepted in each direction
+        assert_eq!(matching_step(secret, "287082", 59 + 30), Some(1));
+        assert_eq!(matching_step(secret, "287082", 59 - 30), Some(1));
+        assert_eq!(matching_step(secret, "287082", 59 + 60), None);
+        assert_eq!(matching_step(secret, "28708", 59), None);
+
+        assert_eq!(
+            otpauth_uri("GEZDGNBVGY3TQOJQ", "admin@localhost.de"),
+            "otpauth://totp/Rauthy:admin%40localhost.de?secret=GEZDGNBVGY3TQOJQ&issuer=Rauthy&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -9,7 +9,7 @@ use redhac::{cache_get, cache_get_from, cache_get_value, cache_insert, cache_rem
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::FromRow;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use utoipa::ToSchema;
 
 // Additional custom attributes for users. These can be set for every user and then mapped to a
@@ -406,6 +406,15 @@ impl UserAttrValueEntity {
         Ok(())
     }
 
+    /// Returns the values for all users. This is only used for the bulk export and skips the
+    /// cache on purpose.
+    pub async fn find_all(data: &web::Data<AppState>) -> Result<Vec<Self>, ErrorResponse> {
+        let res = sqlx::query_as!(Self, "select * from user_attr_values")
+            .fetch_all(&data.db)
+            .await?;
+        Ok(res)
+    }
+
     pub async fn find_for_user(
         data: &web::Data<AppState>,
         user_id: &str,
@@ -443,6 +452,34 @@ impl UserAttrValueEntity {
         Ok(res)
     }
 
+    /// Inserts the values for a new user from the bulk import inside the given transaction.
+    /// The keys must have been checked against the existing attributes already.
+    pub async fn insert_for_new_user(
+        txn: &mut DbTxn<'_>,
+        user_id: &str,
+        values: HashMap<String, Value>,
+    ) -> Result<(), ErrorResponse> {
+        for (key, value) in values {
+            let is_empty =
+                value == Value::Null || value.as_str().map(|s| s.is_empty()) == Some(true);
+            if is_empty {
+                continue;
+            }
+
+            let v = serde_json::to_vec(&value).unwrap();
+            sqlx::query!(
+                "insert into user_attr_values (user_id, key, value) values ($1, $2, $3)",
+                user_id,
+                key,
+                v,
+            )
+            .execute(&mut **txn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn update_for_user(
         data: &web::Data<AppState>,
         user_id: &str,
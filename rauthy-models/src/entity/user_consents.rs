@@ -0,0 +1,309 @@
+use crate::app_state::AppState;
+use crate::entity::auth_codes::AuthCode;
+use actix_web::web;
+use rauthy_common::constants::CACHE_NAME_CONSENT_REQ;
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::utils::get_rand;
+use redhac::{cache_del, cache_get, cache_get_from, cache_get_value, cache_put};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+/// The scopes a user has granted to a client on the consent screen
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct UserConsent {
+    pub user_id: String,
+    pub client_id: String,
+    // the granted scopes as CSV
+    pub scopes: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// CRUD
+impl UserConsent {
+    // Revokes a grant
+    pub async fn delete(
+        data: &web::Data<AppState>,
+        user_id: &str,
+        client_id: &str,
+    ) -> Result<(), ErrorResponse> {
+        let res = sqlx::query!(
+            "delete from user_consents where user_id = $1 and client_id = $2",
+            user_id,
+            client_id,
+        )
+        .execute(&data.db)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::NotFound,
+                "No consent exists for this client".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Deletes all grants for a client
+    pub async fn delete_by_client(
+        data: &web::Data<AppState>,
+        client_id: &str,
+    ) -> Result<(), ErrorResponse> {
+        sqlx::query!("delete from user_consents where client_id = $1", client_id)
+            .execute(&data.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find(
+        data: &web::Data<AppState>,
+        user_id: &str,
+        client_id: &str,
+    ) -> Result<Option<Self>, ErrorResponse> {
+        let res = sqlx::query_as!(
+            Self,
+            "select * from user_consents where user_id = $1 and client_id = $2",
+            user_id,
+            client_id,
+        )
+        .fetch_optional(&data.db)
+        .await?;
+        Ok(res)
+    }
+
+    pub async fn find_for_user(
+        data: &web::Data<AppState>,
+        user_id: &str,
+    ) -> Result<Vec<Self>, ErrorResponse> {
+        let res = sqlx::query_as!(
+            Self,
+            "select * from user_consents where user_id = $1",
+            user_id
+        )
+        .fetch_all(&data.db)
+        .await?;
+        Ok(res)
+    }
+
+    pub async fn save(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        #[cfg(feature = "sqlite")]
+        let q = sqlx::query!(
+            r#"insert or replace into user_consents
+            (user_id, client_id, scopes, created_at, updated_at)
+            values ($1, $2, $3, $4, $5)"#,
+            self.user_id,
+            self.client_id,
+            self.scopes,
+            self.created_at,
+            self.updated_at,
+        );
+
+        #[cfg(not(feature = "sqlite"))]
+        let q = sqlx::query!(
+            r#"insert into user_consents (user_id, client_id, scopes, created_at, updated_at)
+            values ($1, $2, $3, $4, $5)
+            on conflict(user_id, client_id) do update set scopes = $3, updated_at = $5"#,
+            self.user_id,
+            self.client_id,
+            self.scopes,
+            self.created_at,
+            self.updated_at,
+        );
+
+        q.execute(&data.db).await?;
+        Ok(())
+    }
+}
+
+impl UserConsent {
+    /// Adds the `scopes` to an already existing grant or creates a new one
+    pub async fn grant(
+        data: &web::Data<AppState>,
+        user_id: &str,
+        client_id: &str,
+        scopes: &[String],
+    ) -> Result<(), ErrorResponse> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut slf = Self::find(data, user_id, client_id)
+            .await?
+            .unwrap_or_else(|| Self {
+                user_id: user_id.to_string(),
+                client_id: client_id.to_string(),
+                scopes: String::default(),
+                created_at: now,
+                updated_at: now,
+            });
+
+        let mut granted = slf
+            .get_scopes()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        for scope in scopes {
+            if !granted.contains(scope) {
+                granted.push(scope.clone());
+            }
+        }
+        slf.scopes = granted.join(",");
+        slf.updated_at = now;
+
+        slf.save(data).await
+    }
+
+    /// Returns `true` if all the requested `scopes` have been granted already
+    pub fn covers(&self, scopes: &[String]) -> bool {
+        let granted = self.get_scopes();
+        scopes.iter().all(|s| granted.contains(&s.as_str()))
+    }
+
+    pub fn get_scopes(&self) -> Vec<&str> {
+        self.scopes.split(',').filter(|s| !s.is_empty()).collect()
+    }
+}
+
+/// An authorization code, which is held back until the user has consented to the requested
+/// scopes. The user agent is redirected to the consent page instead of the client meanwhile.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingConsent {
+    pub id: String,
+    pub code: AuthCode,
+    pub redirect_uri: String,
+    pub state: Option<String>,
+}
+
+// CRUD
+impl PendingConsent {
+    pub async fn delete(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_del(
+            CACHE_NAME_CONSENT_REQ.to_string(),
+            self.id.clone(),
+            &data.caches.ha_cache_config,
+        )
+        .await
+        .map_err(ErrorResponse::from)
+    }
+
+    pub async fn find(data: &web::Data<AppState>, id: String) -> Result<Self, ErrorResponse> {
+        let slf = cache_get!(
+            PendingConsent,
+            CACHE_NAME_CONSENT_REQ.to_string(),
+            id,
+            &data.caches.ha_cache_config,
+            true
+        )
+        .await?;
+
+        slf.ok_or_else(|| {
+            ErrorResponse::new(
+                ErrorResponseType::NotFound,
+                "The consent request does not exist or has expired".to_string(),
+            )
+        })
+    }
+
+    pub async fn save(&self, data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
+        cache_put(
+            CACHE_NAME_CONSENT_REQ.to_string(),
+            self.id.clone(),
+            &data.caches.ha_cache_config,
+            self,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl PendingConsent {
+    /// Saves the `code` and returns the redirect location for the client. If the user has not
+    /// granted all requested scopes yet, the code is held back and the location points to the
+    /// consent page instead. `force` shows the consent page in any case (`prompt=consent`).
+    pub async fn code_location(
+        data: &web::Data<AppState>,
+        code: AuthCode,
+        redirect_uri: String,
+        state: Option<String>,
+        require_consent: bool,
+        force: bool,
+    ) -> Result<String, ErrorResponse> {
+        if require_consent || force {
+            let is_granted = !force
+                && UserConsent::find(data, &code.user_id, &code.client_id)
+                    .await?
+                    .map(|consent| consent.covers(&code.scopes))
+                    .unwrap_or(false);
+
+            if !is_granted {
+                let slf = Self {
+                    id: get_rand(48),
+                    code,
+                    redirect_uri,
+                    state,
+                };
+                slf.save(data).await?;
+                return Ok(format!("{}/oidc/consent?id={}", data.issuer, slf.id));
+            }
+        }
+
+        code.save(data).await?;
+        Self::client_location(&redirect_uri, ("code", &code.id), state.as_deref())
+    }
+
+    /// The redirect location back to the client with the given query `param` and `state`
+    pub fn client_location(
+        redirect_uri: &str,
+        param: (&str, &str),
+        state: Option<&str>,
+    ) -> Result<String, ErrorResponse> {
+        let mut url = Url::parse(redirect_uri).map_err(|_| {
+            ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Invalid 'redirect_uri'".to_string(),
+            )
+        })?;
+        {
+            let mut pairs = url.query_pairs_mut();
+            pairs.append_pair(param.0, param.1);
+            if let Some(state) = state {
+                pairs.append_pair("state", state);
+            }
+        }
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consent_covers() {
+        let consent = UserConsent {
+            user_id: "user".to_string(),
+            client_id: "client".to_string(),
+            scopes: "openid,email".to_string(),
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        assert!(consent.covers(&["openid".to_string()]));
+        assert!(consent.covers(&["email".to_string(), "openid".to_string()]));
+        assert!(!consent.covers(&["openid".to_string(), "profile".to_string()]));
+
+        assert_eq!(
+            PendingConsent::client_location("http://localhost/cb", ("code", "123"), Some("abc"))
+                .unwrap(),
+            "http://localhost/cb?code=123&state=abc"
+        );
+        assert_eq!(
+            PendingConsent::client_location(
+                "http://localhost/cb?app=1",
+                ("error", "access_denied"),
+                Some("a b&c=d"),
+            )
+            .unwrap(),
+            "http://localhost/cb?app=1&error=access_denied&state=a+b%26c%3Dd"
+        );
+    }
+}
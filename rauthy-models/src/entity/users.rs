@@ -1,4 +1,4 @@
-use crate::app_state::{AppState, Argon2Params, DbTxn};
+use crate::app_state::{AppState, Argon2Params, DbTxn, DbType};
 use crate::email::{send_email_change_info_new, send_email_confirm_change, send_pwd_reset};
 use crate::entity::colors::ColorEntity;
 use crate::entity::groups::Group;
@@ -8,18 +8,22 @@ use crate::entity::password::RecentPasswordsEntity;
 use crate::entity::refresh_tokens::RefreshToken;
 use crate::entity::roles::Role;
 use crate::entity::sessions::Session;
+use crate::entity::totp;
+use crate::entity::user_attr::UserAttrValueEntity;
 use crate::entity::users_values::UserValues;
 use crate::entity::webauthn::{PasskeyEntity, WebauthnServiceReq};
 use crate::events::event::Event;
 use crate::language::Language;
 use crate::request::{
     NewUserRegistrationRequest, NewUserRequest, UpdateUserRequest, UpdateUserSelfRequest,
+    UsersQueryParams, UsersSortBy,
 };
 use crate::templates::UserEmailChangeConfirmHtml;
 use actix_web::{web, HttpRequest};
 use argon2::PasswordHash;
 use rauthy_common::constants::{
-    CACHE_NAME_USERS, IDX_USERS, RAUTHY_ADMIN_ROLE, WEBAUTHN_NO_PASSWORD_EXPIRY,
+    CACHE_NAME_USERS, IDX_USERS, RAUTHY_ADMIN_ROLE, USERS_PAGE_SIZE_DEFAULT, USERS_PAGE_SIZE_MAX,
+    WEBAUTHN_NO_PASSWORD_EXPIRY,
 };
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
 use rauthy_common::password_hasher::{ComparePasswords, HashPassword};
@@ -28,10 +32,11 @@ use redhac::{
     cache_del, cache_get, cache_get_from, cache_get_value, cache_insert, cache_remove, AckLevel,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, QueryBuilder};
+use std::collections::HashMap;
 use std::ops::Add;
 use time::OffsetDateTime;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AccountType {
@@ -67,13 +72,14 @@ pub struct User {
     pub user_expires: Option<i64>,
     pub auth_provider_id: Option<String>,
     pub federation_uid: Option<String>,
+    pub totp_secret: Option<Vec<u8>>,
 }
 
 // CRUD
 impl User {
     // Inserts a user into the database
     pub async fn create(data: &web::Data<AppState>, new_user: User) -> Result<Self, ErrorResponse> {
-        let slf = Self::insert(data, new_user).await?;
+        let slf = Self::insert(data, new_user, None).await?;
 
         let magic_link = MagicLink::create(
             data,
@@ -91,7 +97,25 @@ impl User {
         data: &web::Data<AppState>,
         new_user: User,
     ) -> Result<Self, ErrorResponse> {
-        Self::insert(data, new_user).await
+        Self::insert(data, new_user, None).await
+    }
+
+    /// Inserts a user from the bulk import into the database together with its custom
+    /// attributes in a single transaction. In contrast to `create()`, this does not send out any
+    /// E-Mails and keeps a possibly given password hash.
+    pub async fn create_imported(
+        data: &web::Data<AppState>,
+        new_user: User,
+        attributes: HashMap<String, serde_json::Value>,
+    ) -> Result<Self, ErrorResponse> {
+        Self::is_email_free(data, new_user.email.clone()).await?;
+
+        let mut txn = data.db.begin().await?;
+        let slf = Self::insert(data, new_user, Some(&mut txn)).await?;
+        UserAttrValueEntity::insert_for_new_user(&mut txn, &slf.id, attributes).await?;
+        txn.commit().await?;
+
+        Ok(slf)
     }
 
     // Inserts a user into the database
@@ -257,6 +281,51 @@ impl User {
         Ok(res)
     }
 
+    /// Returns the users matching the given filters, sorted and paginated, together with the
+    /// total count of all matching users.
+    pub async fn find_paginated(
+        data: &web::Data<AppState>,
+        params: &UsersQueryParams,
+    ) -> Result<(Vec<Self>, i64), ErrorResponse> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        let mut qb = QueryBuilder::<DbType>::new("select count(*) from users");
+        Self::push_query_filters(&mut qb, params, now);
+        let total = qb.build_query_scalar::<i64>().fetch_one(&data.db).await?;
+
+        let mut qb = QueryBuilder::<DbType>::new("select * from users");
+        Self::push_query_filters(&mut qb, params, now);
+
+        // the column comes from a fixed set of values and is safe to be used inside the query
+        let sort_by = params.sort_by.unwrap_or_default();
+        let order = params.order.unwrap_or_default();
+        qb.push(format!(
+            " order by {} {}",
+            sort_by.as_column(),
+            order.as_sql()
+        ));
+        if sort_by == UsersSortBy::LastLogin {
+            // Postgres and SQLite sort NULLs differently by default
+            qb.push(" nulls last");
+        }
+        // the id makes the order stable for the pagination
+        qb.push(", id asc");
+
+        // never return all users at once for big deployments
+        let page_size = params
+            .page_size
+            .unwrap_or(USERS_PAGE_SIZE_DEFAULT)
+            .min(USERS_PAGE_SIZE_MAX) as i64;
+        let offset = (params.page.unwrap_or(1) as i64 - 1) * page_size;
+        qb.push(" limit ")
+            .push_bind(page_size)
+            .push(" offset ")
+            .push_bind(offset);
+
+        let users = qb.build_query_as::<Self>().fetch_all(&data.db).await?;
+        Ok((users, total))
+    }
+
     pub async fn find_expired(data: &web::Data<AppState>) -> Result<Vec<Self>, ErrorResponse> {
         let now = OffsetDateTime::now_utc()
             .add(time::Duration::seconds(10))
@@ -268,13 +337,17 @@ impl User {
         Ok(res)
     }
 
-    async fn insert(data: &web::Data<AppState>, new_user: User) -> Result<Self, ErrorResponse> {
+    async fn insert(
+        data: &web::Data<AppState>,
+        new_user: User,
+        txn: Option<&mut DbTxn<'_>>,
+    ) -> Result<Self, ErrorResponse> {
         let lang = new_user.language.as_str();
-        sqlx::query!(
+        let q = sqlx::query!(
             r#"INSERT INTO USERS
             (id, email, given_name, family_name, roles, groups, enabled, email_verified, created_at,
-            last_login, language, user_expires, auth_provider_id, federation_uid)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"#,
+            last_login, language, user_expires, auth_provider_id, federation_uid, password)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)"#,
             new_user.id,
             new_user.email,
             new_user.given_name,
@@ -289,9 +362,14 @@ impl User {
             new_user.user_expires,
             new_user.auth_provider_id,
             new_user.federation_uid,
-        )
-        .execute(&data.db)
-        .await?;
+            new_user.password,
+        );
+
+        if let Some(txn) = txn {
+            q.execute(&mut **txn).await?;
+        } else {
+            q.execute(&data.db).await?;
+        }
 
         Ok(new_user)
     }
@@ -312,8 +390,9 @@ impl User {
             email = $1, given_name = $2, family_name = $3, password = $4, roles = $5, groups = $6,
             enabled = $7, email_verified = $8, password_expires = $9, last_login = $10,
             last_failed_login = $11, failed_login_attempts = $12, language = $13,
-            webauthn_user_id = $14, user_expires = $15, auth_provider_id = $16, federation_uid = $17
-            where id = $18"#,
+            webauthn_user_id = $14, user_expires = $15, auth_provider_id = $16, federation_uid = $17,
+            totp_secret = $18
+            where id = $19"#,
         )
         .bind(&self.email)
         .bind(&self.given_name)
@@ -332,6 +411,7 @@ impl User {
         .bind(self.user_expires)
         .bind(&self.auth_provider_id)
         .bind(&self.federation_uid)
+        .bind(&self.totp_secret)
         .bind(&self.id);
 
         if let Some(txn) = txn {
@@ -475,7 +555,7 @@ impl User {
         id: String,
         upd_user: UpdateUserSelfRequest,
     ) -> Result<(User, Option<UserValues>, bool), ErrorResponse> {
-        let user = User::find(data, id.clone()).await?;
+        let mut user = User::find(data, id.clone()).await?;
 
         let mut password = None;
         if let Some(pwd_new) = upd_user.password_new {
@@ -792,7 +872,7 @@ impl User {
 
         let usage = MagicLinkUsage::try_from(&ml.usage)?;
         let new_email = match usage {
-            MagicLinkUsage::NewUser | MagicLinkUsage::PasswordReset => {
+            MagicLinkUsage::NewUser | MagicLinkUsage::PasswordReset | MagicLinkUsage::Login(_) => {
                 return Err(ErrorResponse::new(
                     ErrorResponseType::BadRequest,
                     "The Magic Link is not meant to be used to confirm an E-Mail address"
@@ -942,6 +1022,17 @@ impl User {
         res
     }
 
+    /// `true`, if the user has either a passkey or a TOTP authenticator app registered
+    #[inline(always)]
+    pub fn has_mfa_enabled(&self) -> bool {
+        self.has_webauthn_enabled() || self.has_totp_enabled()
+    }
+
+    #[inline(always)]
+    pub fn has_totp_enabled(&self) -> bool {
+        self.totp_secret.is_some()
+    }
+
     #[inline(always)]
     pub fn has_webauthn_enabled(&self) -> bool {
         self.webauthn_user_id.is_some()
@@ -958,8 +1049,11 @@ impl User {
                 String::from("Cannot validate argon2 param - password is not set"),
             ));
         }
-        let hash = PasswordHash::new(self.password.as_ref().unwrap())
-            .expect("Could not build Hash from password string");
+        // imported users may have any other supported hash, which must be upgraded
+        let hash = match PasswordHash::new(self.password.as_ref().unwrap()) {
+            Ok(hash) if hash.algorithm == argon2::ARGON2ID_IDENT => hash,
+            _ => return Ok(false),
+        };
         let curr_params =
             argon2::Params::try_from(&hash).expect("Could not extract params from hash");
 
@@ -1085,8 +1179,11 @@ impl User {
     //     Ok(())
     // }
 
+    /// Validates the given password against the saved hash. A hash in any other format than
+    /// Argon2ID or with outdated params will be upgraded in place on success. The caller must
+    /// save the user afterward.
     pub async fn validate_password(
-        &self,
+        &mut self,
         data: &web::Data<AppState>,
         plain_password: String,
     ) -> Result<(), ErrorResponse> {
@@ -1131,7 +1228,11 @@ impl User {
             }
         }
 
-        if self.match_passwords(plain_password).await? {
+        if self.match_passwords(plain_password.clone()).await? {
+            if !self.is_argon2_uptodate(&data.argon2_params)? {
+                info!("Upgrading the password hash for user '{}'", self.email);
+                self.password = Some(HashPassword::hash_password(plain_password).await?);
+            }
             Ok(())
         } else {
             Err(ErrorResponse::new(
@@ -1140,6 +1241,134 @@ impl User {
             ))
         }
     }
+
+    /// Validates a re-authentication of the user before changes to the second factors.
+    /// Accepts the current password, the `mfa_code` from a finished Webauthn request or a code
+    /// from the already registered TOTP authenticator app. Failed password and TOTP attempts
+    /// are counted just like failed logins.
+    pub async fn validate_reauth(
+        &mut self,
+        data: &web::Data<AppState>,
+        password_current: Option<String>,
+        mfa_code: Option<String>,
+        totp_current: Option<&str>,
+    ) -> Result<(), ErrorResponse> {
+        if let Some(pwd_curr) = password_current {
+            let res = self.validate_password(data, pwd_curr).await;
+            self.count_failed_reauth(data, res).await
+        } else if let Some(mfa_code) = mfa_code {
+            let svc_req = WebauthnServiceReq::find(data, mfa_code).await?;
+            if svc_req.user_id != self.id {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::Forbidden,
+                    "User ID does not match".to_string(),
+                ));
+            }
+            svc_req.delete(data).await
+        } else if let Some(code) = totp_current {
+            let res = totp::validate_user_code(data, self, code).await;
+            self.count_failed_reauth(data, res).await
+        } else {
+            Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "The current password or a second factor is needed for this change".to_string(),
+            ))
+        }
+    }
+
+    async fn count_failed_reauth(
+        &mut self,
+        data: &web::Data<AppState>,
+        res: Result<(), ErrorResponse>,
+    ) -> Result<(), ErrorResponse> {
+        if res.is_err() {
+            self.last_failed_login = Some(OffsetDateTime::now_utc().unix_timestamp());
+            self.failed_login_attempts = Some(self.failed_login_attempts.unwrap_or(0) + 1);
+            self.save(data, None, None).await?;
+        }
+        res
+    }
+
+    /// Pushes the `where` clause for the given filters. Each value is added as a bind parameter.
+    fn push_query_filters(qb: &mut QueryBuilder<'_, DbType>, params: &UsersQueryParams, now: i64) {
+        // `_` is allowed in the search and names but would be a wildcard for `like`
+        fn escape_like(value: &str) -> String {
+            value.replace('_', "\\_")
+        }
+
+        // Only prefix matches can use the indexes for the search. SQLite's `like` is
+        // case-insensitive already and only uses the `nocase` indexes on plain columns.
+        #[cfg(feature = "sqlite")]
+        const SEARCH_COLUMNS: [&str; 3] = ["email", "given_name", "family_name"];
+        #[cfg(not(feature = "sqlite"))]
+        const SEARCH_COLUMNS: [&str; 3] =
+            ["lower(email)", "lower(given_name)", "lower(family_name)"];
+
+        let mut sep = " where ";
+
+        if let Some(search) = &params.search {
+            let pattern = format!("{}%", escape_like(&search.to_lowercase()));
+            qb.push(sep).push("(");
+            for col in SEARCH_COLUMNS {
+                qb.push(col)
+                    .push(" like ")
+                    .push_bind(pattern.clone())
+                    .push(" escape '\\' or ");
+            }
+            qb.push("id = ").push_bind(search.clone()).push(")");
+            sep = " and ";
+        }
+
+        if let Some(enabled) = params.enabled {
+            qb.push(sep).push("enabled = ").push_bind(enabled);
+            sep = " and ";
+        }
+
+        if let Some(email_verified) = params.email_verified {
+            qb.push(sep)
+                .push("email_verified = ")
+                .push_bind(email_verified);
+            sep = " and ";
+        }
+
+        if let Some(expired) = params.expired {
+            if expired {
+                qb.push(sep)
+                    .push("(user_expires is not null and user_expires < ")
+                    .push_bind(now)
+                    .push(")");
+            } else {
+                qb.push(sep)
+                    .push("(user_expires is null or user_expires >= ")
+                    .push_bind(now)
+                    .push(")");
+            }
+            sep = " and ";
+        }
+
+        // roles and groups are saved as a comma separated list
+        if let Some(role) = &params.role {
+            qb.push(sep)
+                .push("(',' || roles || ',') like ")
+                .push_bind(format!("%,{},%", escape_like(role)))
+                .push(" escape '\\'");
+            sep = " and ";
+        }
+
+        if let Some(group) = &params.group {
+            qb.push(sep)
+                .push("(',' || coalesce(groups, '') || ',') like ")
+                .push_bind(format!("%,{},%", escape_like(group)))
+                .push(" escape '\\'");
+            sep = " and ";
+        }
+
+        if let Some(provider_id) = &params.auth_provider_id {
+            qb.push(sep)
+                .push("auth_provider_id = ")
+                .push_bind(provider_id.clone());
+        }
+    }
 }
 
 impl Default for User {
@@ -1164,6 +1393,7 @@ impl Default for User {
             user_expires: None,
             auth_provider_id: None,
             federation_uid: None,
+            totp_secret: None,
         }
     }
 }
@@ -1201,6 +1431,7 @@ mod tests {
             ),
             auth_provider_id: None,
             federation_uid: None,
+            totp_secret: None,
         };
         let session = Session::try_new(&user, 1, None);
         assert!(session.is_err());
@@ -1259,6 +1490,7 @@ mod tests {
             user_expires: None,
             auth_provider_id: None,
             federation_uid: None,
+            totp_secret: None,
         };
 
         // enabled
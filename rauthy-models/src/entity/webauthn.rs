@@ -1,4 +1,5 @@
 use crate::app_state::{AppState, DbTxn};
+use crate::entity::sessions::{MfaMethod, Session};
 use crate::entity::users::{AccountType, User};
 use crate::request::{
     MfaPurpose, WebauthnAuthFinishRequest, WebauthnRegFinishRequest, WebauthnRegStartRequest,
@@ -511,6 +512,8 @@ impl WebauthnAdditionalData {
 pub struct WebauthnLoginReq {
     pub code: String,
     pub user_id: String,
+    // the session, which is waiting for this second factor
+    pub session_id: Option<String>,
     pub header_loc: String,
     pub header_origin: Option<String>,
 }
@@ -561,6 +564,22 @@ impl WebauthnLoginReq {
     }
 }
 
+impl WebauthnLoginReq {
+    /// Records the second factor, which has finished this login, for the waiting session.
+    /// The `amr` of the ID tokens is derived from it later on.
+    pub async fn set_session_mfa_method(
+        &self,
+        data: &web::Data<AppState>,
+        method: MfaMethod,
+    ) -> Result<(), ErrorResponse> {
+        if let Some(session_id) = &self.session_id {
+            let mut session = Session::find(data, session_id.clone()).await?;
+            session.set_mfa_method(data, method).await?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct WebauthnServiceReq {
     pub code: String,
@@ -747,6 +766,12 @@ pub async fn auth_finish(
                 }
             }
 
+            if let WebauthnAdditionalData::Login(login_req) = &auth_data.data {
+                login_req
+                    .set_session_mfa_method(data, MfaMethod::Webauthn)
+                    .await?;
+            }
+
             info!("Webauthn Authentication successful for user {}", user.id);
 
             Ok(auth_data.data)
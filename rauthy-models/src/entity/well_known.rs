@@ -1,19 +1,34 @@
 use crate::app_state::AppState;
+use crate::entity::jwe::{JweAlg, JweEnc};
+use crate::entity::jwk::{JwkKeyPair, JwkKeyPairAlg};
 use crate::entity::scopes::Scope;
+use crate::sign_jwt;
 use actix_web::web;
-use rauthy_common::constants::{CACHE_NAME_12HR, ENABLE_DYN_CLIENT_REG};
-use rauthy_common::error_response::ErrorResponse;
+use jwt_simple::algorithms::{
+    ECDSAP256KeyPairLike, ECDSAP384KeyPairLike, EdDSAKeyPairLike, RSAKeyPairLike,
+};
+use jwt_simple::claims::Claims;
+use jwt_simple::prelude::coarsetime;
+use rauthy_common::constants::{
+    CACHE_NAME_12HR, ENABLE_DYN_CLIENT_REG, ENABLE_SIGNED_METADATA, GRANT_TYPE_DEVICE_CODE,
+    GRANT_TYPE_TOKEN_EXCHANGE, PAIRWISE_SUBJECT_SECRET,
+};
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
 use redhac::{cache_get, cache_get_from, cache_get_value, cache_put};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-/// The struct for the `.well-known` endpoint for automatic OIDC discovery
+/// The struct for the `.well-known` endpoints for automatic OIDC discovery and the
+/// OAuth 2.0 Authorization Server Metadata (RFC 8414)
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct WellKnown {
     pub issuer: String,
     pub authorization_endpoint: String,
     pub token_endpoint: String,
     pub introspection_endpoint: String,
+    pub device_authorization_endpoint: String,
+    pub revocation_endpoint: String,
+    pub pushed_authorization_request_endpoint: String,
     pub userinfo_endpoint: String,
     pub end_session_endpoint: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -21,10 +36,19 @@ pub struct WellKnown {
     pub jwks_uri: String,
     pub grant_types_supported: Vec<String>,
     pub response_types_supported: Vec<String>,
+    pub response_modes_supported: Vec<String>,
     pub subject_types_supported: Vec<String>,
     pub id_token_signing_alg_values_supported: Vec<String>,
+    pub id_token_encryption_alg_values_supported: Vec<String>,
+    pub id_token_encryption_enc_values_supported: Vec<String>,
+    pub userinfo_signing_alg_values_supported: Vec<String>,
+    pub userinfo_encryption_alg_values_supported: Vec<String>,
+    pub userinfo_encryption_enc_values_supported: Vec<String>,
     pub token_endpoint_auth_methods_supported: Vec<String>,
     pub token_endpoint_auth_signing_alg_values_supported: Vec<String>,
+    pub revocation_endpoint_auth_methods_supported: Vec<String>,
+    pub introspection_endpoint_auth_methods_supported: Vec<String>,
+    pub acr_values_supported: Vec<String>,
     pub claims_supported: Vec<String>,
     pub claim_types_supported: Vec<String>,
     pub scopes_supported: Vec<String>,
@@ -33,6 +57,19 @@ pub struct WellKnown {
     pub service_documentation: String,
     pub ui_locales_supported: Vec<String>,
     pub claims_parameter_supported: bool,
+    pub require_pushed_authorization_requests: bool,
+    pub request_parameter_supported: bool,
+    pub request_uri_parameter_supported: bool,
+    pub require_request_uri_registration: bool,
+    pub request_object_signing_alg_values_supported: Vec<String>,
+    pub backchannel_logout_supported: bool,
+    pub backchannel_logout_session_supported: bool,
+    pub frontchannel_logout_supported: bool,
+    pub frontchannel_logout_session_supported: bool,
+    pub tls_client_certificate_bound_access_tokens: bool,
+    /// All other metadata values as a signed JWT, if `ENABLE_SIGNED_METADATA` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed_metadata: Option<String>,
 }
 
 const IDX: &str = ".well-known";
@@ -50,13 +87,7 @@ impl WellKnown {
         {
             Ok(wk)
         } else {
-            let scopes = Scope::find_all(data)
-                .await?
-                .into_iter()
-                .map(|s| s.name)
-                .collect::<Vec<String>>();
-            let slf = Self::new(&data.issuer, scopes);
-            let json = serde_json::to_string(&slf).unwrap();
+            let json = Self::build_json(data).await?;
 
             cache_put(
                 CACHE_NAME_12HR.to_string(),
@@ -73,13 +104,7 @@ impl WellKnown {
     /// Rebuilds the WellKnown, serializes it as json and updates it inside the cache.
     /// Should be called after any update on the Scopes.
     pub async fn rebuild(data: &web::Data<AppState>) -> Result<(), ErrorResponse> {
-        let scopes = Scope::find_all(data)
-            .await?
-            .into_iter()
-            .map(|s| s.name)
-            .collect::<Vec<String>>();
-        let slf = Self::new(&data.issuer, scopes);
-        let json = serde_json::to_string(&slf).unwrap();
+        let json = Self::build_json(data).await?;
 
         cache_put(
             CACHE_NAME_12HR.to_string(),
@@ -91,6 +116,32 @@ impl WellKnown {
 
         Ok(())
     }
+
+    async fn build_json(data: &web::Data<AppState>) -> Result<String, ErrorResponse> {
+        let scopes = Scope::find_all(data)
+            .await?
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<String>>();
+        let mut slf = Self::new(&data.issuer, scopes);
+        if *ENABLE_SIGNED_METADATA {
+            slf.signed_metadata = Some(slf.sign(data).await?);
+        }
+
+        Ok(serde_json::to_string(&slf).unwrap())
+    }
+
+    // Signs all metadata values with the latest RS256 key, which every client can validate.
+    // The JWT has no expiry, because it is rebuilt after each key rotation.
+    async fn sign(&self, data: &web::Data<AppState>) -> Result<String, ErrorResponse> {
+        let kp = JwkKeyPair::find_latest(data, "RS256", JwkKeyPairAlg::RS256).await?;
+        let mut claims =
+            Claims::with_custom_claims(self.clone(), coarsetime::Duration::from_secs(0))
+                .with_issuer(self.issuer.clone());
+        claims.expires_at = None;
+
+        sign_jwt!(kp, claims)
+    }
 }
 
 impl WellKnown {
@@ -98,6 +149,9 @@ impl WellKnown {
         let authorization_endpoint = format!("{}/oidc/authorize", issuer);
         let token_endpoint = format!("{}/oidc/token", issuer);
         let introspection_endpoint = format!("{}/oidc/tokenInfo", issuer);
+        let device_authorization_endpoint = format!("{}/oidc/device", issuer);
+        let revocation_endpoint = format!("{}/oidc/revoke", issuer);
+        let pushed_authorization_request_endpoint = format!("{}/oidc/par", issuer);
         let userinfo_endpoint = format!("{}/oidc/userinfo", issuer);
         let registration_endpoint =
             ENABLE_DYN_CLIENT_REG.then_some(format!("{}/clients_dyn", issuer));
@@ -108,29 +162,63 @@ impl WellKnown {
             "client_credentials".to_string(),
             "password".to_string(),
             "refresh_token".to_string(),
+            GRANT_TYPE_DEVICE_CODE.to_string(),
+            GRANT_TYPE_TOKEN_EXCHANGE.to_string(),
         ];
         let response_types_supported = vec!["code".to_string()];
-        let subject_types_supported = vec!["public".to_string()];
+        let response_modes_supported = vec!["query".to_string()];
+        // pairwise subjects can only be computed with a configured secret
+        let subject_types_supported = if PAIRWISE_SUBJECT_SECRET.is_some() {
+            vec!["public".to_string(), "pairwise".to_string()]
+        } else {
+            vec!["public".to_string()]
+        };
         let id_token_signing_alg_values_supported = vec![
             "RS256".to_string(),
             "RS384".to_string(),
             "RS512".to_string(),
             "EdDSA".to_string(),
+            "ES256".to_string(),
+            "ES384".to_string(),
         ];
+        let id_token_encryption_alg_values_supported = JweAlg::all_str();
+        let id_token_encryption_enc_values_supported = JweEnc::all_str();
+        let userinfo_signing_alg_values_supported = id_token_signing_alg_values_supported.clone();
+        let userinfo_encryption_alg_values_supported = JweAlg::all_str();
+        let userinfo_encryption_enc_values_supported = JweEnc::all_str();
         let token_endpoint_auth_methods_supported = vec![
             "client_secret_post".to_string(),
             "client_secret_basic".to_string(),
+            "client_secret_jwt".to_string(),
+            "private_key_jwt".to_string(),
+            "tls_client_auth".to_string(),
+            "self_signed_tls_client_auth".to_string(),
         ];
         let token_endpoint_auth_signing_alg_values_supported = vec![
             "RS256".to_string(),
             "RS384".to_string(),
             "RS512".to_string(),
             "EdDSA".to_string(),
+            "ES256".to_string(),
+            "ES384".to_string(),
+            "HS256".to_string(),
+            "HS384".to_string(),
+            "HS512".to_string(),
         ];
+        let revocation_endpoint_auth_methods_supported = vec![
+            "client_secret_post".to_string(),
+            "client_secret_basic".to_string(),
+            "tls_client_auth".to_string(),
+            "self_signed_tls_client_auth".to_string(),
+        ];
+        // the introspection endpoint does not need any client authentication
+        let introspection_endpoint_auth_methods_supported = vec!["none".to_string()];
+        let acr_values_supported = vec!["pwd".to_string(), "mfa".to_string()];
         let claims_supported = vec![
             "iss".to_string(),
             "azp".to_string(),
             "amr".to_string(),
+            "acr".to_string(),
             "sub".to_string(),
             "preferred_username".to_string(),
             "email".to_string(),
@@ -140,6 +228,7 @@ impl WellKnown {
             "roles".to_string(),
             "groups".to_string(),
             "custom".to_string(),
+            "sid".to_string(),
         ];
         let claim_types_supported = vec![
             "normal".to_string(),
@@ -158,6 +247,16 @@ impl WellKnown {
             "RS384".to_string(),
             "RS512".to_string(),
             "EdDSA".to_string(),
+            "ES256".to_string(),
+            "ES384".to_string(),
+        ];
+        let request_object_signing_alg_values_supported = vec![
+            "RS256".to_string(),
+            "RS384".to_string(),
+            "RS512".to_string(),
+            "EdDSA".to_string(),
+            "ES256".to_string(),
+            "ES384".to_string(),
         ];
 
         let service_documentation = "https://sebadob.github.io/rauthy/".to_string();
@@ -168,16 +267,28 @@ impl WellKnown {
             authorization_endpoint,
             token_endpoint,
             introspection_endpoint,
+            device_authorization_endpoint,
+            revocation_endpoint,
+            pushed_authorization_request_endpoint,
             userinfo_endpoint,
             end_session_endpoint,
             registration_endpoint,
             jwks_uri,
             grant_types_supported,
             response_types_supported,
+            response_modes_supported,
             subject_types_supported,
             id_token_signing_alg_values_supported,
+            id_token_encryption_alg_values_supported,
+            id_token_encryption_enc_values_supported,
+            userinfo_signing_alg_values_supported,
+            userinfo_encryption_alg_values_supported,
+            userinfo_encryption_enc_values_supported,
             token_endpoint_auth_methods_supported,
             token_endpoint_auth_signing_alg_values_supported,
+            revocation_endpoint_auth_methods_supported,
+            introspection_endpoint_auth_methods_supported,
+            acr_values_supported,
             claims_supported,
             claim_types_supported,
             scopes_supported,
@@ -186,6 +297,18 @@ impl WellKnown {
             service_documentation,
             ui_locales_supported,
             claims_parameter_supported: true,
+            // can be enforced on a per-client basis
+            require_pushed_authorization_requests: false,
+            request_parameter_supported: true,
+            request_uri_parameter_supported: true,
+            require_request_uri_registration: true,
+            request_object_signing_alg_values_supported,
+            backchannel_logout_supported: true,
+            backchannel_logout_session_supported: true,
+            frontchannel_logout_supported: true,
+            frontchannel_logout_session_supported: true,
+            tls_client_certificate_bound_access_tokens: true,
+            signed_metadata: None,
         }
     }
 }
@@ -4,8 +4,9 @@ use crate::events::{
     EVENT_LEVEL_FAILED_LOGINS_20, EVENT_LEVEL_FAILED_LOGINS_25, EVENT_LEVEL_FAILED_LOGINS_7,
     EVENT_LEVEL_IP_BLACKLISTED, EVENT_LEVEL_JWKS_ROTATE, EVENT_LEVEL_NEW_RAUTHY_ADMIN,
     EVENT_LEVEL_NEW_RAUTHY_VERSION, EVENT_LEVEL_NEW_USER, EVENT_LEVEL_RAUTHY_HEALTHY,
-    EVENT_LEVEL_RAUTHY_START, EVENT_LEVEL_RAUTHY_UNHEALTHY, EVENT_LEVEL_SECRETS_MIGRATED,
-    EVENT_LEVEL_USER_EMAIL_CHANGE, EVENT_LEVEL_USER_PASSWORD_RESET,
+    EVENT_LEVEL_RAUTHY_START, EVENT_LEVEL_RAUTHY_UNHEALTHY, EVENT_LEVEL_RECOVERY_CODE_USED,
+    EVENT_LEVEL_REFRESH_TOKEN_REUSE, EVENT_LEVEL_SECRETS_MIGRATED, EVENT_LEVEL_USER_EMAIL_CHANGE,
+    EVENT_LEVEL_USER_PASSWORD_RESET,
 };
 use chrono::{DateTime, Timelike, Utc};
 use rauthy_common::constants::EMAIL_SUB_PREFIX;
@@ -134,6 +135,8 @@ pub enum EventType {
     RauthyStarted,
     RauthyHealthy,
     RauthyUnhealthy,
+    RecoveryCodeUsed,
+    RefreshTokenReuse,
     SecretsMigrated,
     UserEmailChange,
     UserPasswordReset,
@@ -161,6 +164,8 @@ impl Display for EventType {
             EventType::RauthyStarted => write!(f, "Rauthy has been restarted"),
             EventType::RauthyHealthy => write!(f, "Rauthy is healthy"),
             EventType::RauthyUnhealthy => write!(f, "Rauthy is unhealthy"),
+            EventType::RecoveryCodeUsed => write!(f, "MFA recovery code used"),
+            EventType::RefreshTokenReuse => write!(f, "Refresh Token reused"),
             EventType::SecretsMigrated => write!(f, "Secrets have been migrated"),
             EventType::UserEmailChange => write!(f, "User's E-Mail has been changed"),
             EventType::UserPasswordReset => write!(f, "User has reset its password"),
@@ -183,6 +188,8 @@ impl EventType {
             Self::RauthyStarted => "RauthyRestarted",
             Self::RauthyHealthy => "RauthyHealthy",
             Self::RauthyUnhealthy => "RauthyUnhealthy",
+            Self::RecoveryCodeUsed => "RecoveryCodeUsed",
+            Self::RefreshTokenReuse => "RefreshTokenReuse",
             Self::SecretsMigrated => "SecretsMigrated",
             Self::UserEmailChange => "UserEmailChange",
             Self::UserPasswordReset => "UserPasswordReset",
@@ -207,6 +214,8 @@ impl EventType {
             EventType::UserEmailChange => 12,
             EventType::UserPasswordReset => 13,
             EventType::Test => 14,
+            EventType::RefreshTokenReuse => 15,
+            EventType::RecoveryCodeUsed => 16,
         }
     }
 }
@@ -225,6 +234,8 @@ impl From<String> for EventType {
             "RauthyRestarted" => Self::RauthyStarted,
             "RauthyHealthy" => Self::RauthyHealthy,
             "RauthyUnhealthy" => Self::RauthyUnhealthy,
+            "RecoveryCodeUsed" => Self::RecoveryCodeUsed,
+            "RefreshTokenReuse" => Self::RefreshTokenReuse,
             "SecretsMigrated" => Self::SecretsMigrated,
             "UserEmailChange" => Self::UserEmailChange,
             "UserPasswordReset" => Self::UserPasswordReset,
@@ -259,6 +270,8 @@ impl From<i64> for EventType {
             12 => EventType::UserEmailChange,
             13 => EventType::UserPasswordReset,
             14 => EventType::Test,
+            15 => EventType::RefreshTokenReuse,
+            16 => EventType::RecoveryCodeUsed,
             _ => EventType::Test,
         }
     }
@@ -326,6 +339,8 @@ impl From<&Event> for Notification {
             EventType::RauthyStarted => value.text.clone(),
             EventType::RauthyHealthy => value.text.clone(),
             EventType::RauthyUnhealthy => value.text.clone(),
+            EventType::RecoveryCodeUsed => value.text.clone(),
+            EventType::RefreshTokenReuse => value.text.clone(),
             EventType::SecretsMigrated => value.ip.clone(),
             EventType::UserEmailChange => value.text.clone(),
             EventType::UserPasswordReset => value.text.clone(),
@@ -598,6 +613,26 @@ impl Event {
         )
     }
 
+    pub fn recovery_code_used(text: String, ip: Option<String>) -> Self {
+        Self::new(
+            EVENT_LEVEL_RECOVERY_CODE_USED.get().cloned().unwrap(),
+            EventType::RecoveryCodeUsed,
+            ip,
+            None,
+            Some(text),
+        )
+    }
+
+    pub fn refresh_token_reuse(text: String, ip: Option<String>) -> Self {
+        Self::new(
+            EVENT_LEVEL_REFRESH_TOKEN_REUSE.get().cloned().unwrap(),
+            EventType::RefreshTokenReuse,
+            ip,
+            None,
+            Some(text),
+        )
+    }
+
     pub fn secrets_migrated(ip: Option<String>) -> Self {
         Self::new(
             EVENT_LEVEL_SECRETS_MIGRATED.get().cloned().unwrap(),
@@ -665,6 +700,13 @@ impl Event {
             EventType::RauthyStarted => self.text.clone().unwrap(),
             EventType::RauthyHealthy => self.text.clone().unwrap(),
             EventType::RauthyUnhealthy => self.text.clone().unwrap(),
+            EventType::RecoveryCodeUsed => {
+                format!(
+                    "User {} has used a recovery code",
+                    self.text.as_deref().unwrap_or_default()
+                )
+            }
+            EventType::RefreshTokenReuse => self.text.clone().unwrap_or_default(),
             EventType::SecretsMigrated => String::default(),
             EventType::UserEmailChange => {
                 format!("User E-Mail: {}", self.text.as_deref().unwrap_or_default())
@@ -225,6 +225,8 @@ impl EventListener {
                         EventType::RauthyStarted => {}
                         EventType::RauthyHealthy => {}
                         EventType::RauthyUnhealthy => {}
+                        EventType::RecoveryCodeUsed => {}
+                        EventType::RefreshTokenReuse => {}
                         EventType::SecretsMigrated => {}
                         EventType::UserEmailChange => {}
                         EventType::UserPasswordReset => {}
@@ -21,6 +21,8 @@ pub static EVENT_LEVEL_SECRETS_MIGRATED: OnceLock<EventLevel> = OnceLock::new();
 pub static EVENT_LEVEL_RAUTHY_START: OnceLock<EventLevel> = OnceLock::new();
 pub static EVENT_LEVEL_RAUTHY_HEALTHY: OnceLock<EventLevel> = OnceLock::new();
 pub static EVENT_LEVEL_RAUTHY_UNHEALTHY: OnceLock<EventLevel> = OnceLock::new();
+pub static EVENT_LEVEL_RECOVERY_CODE_USED: OnceLock<EventLevel> = OnceLock::new();
+pub static EVENT_LEVEL_REFRESH_TOKEN_REUSE: OnceLock<EventLevel> = OnceLock::new();
 pub static EVENT_LEVEL_IP_BLACKLISTED: OnceLock<EventLevel> = OnceLock::new();
 pub static EVENT_LEVEL_FAILED_LOGINS_25: OnceLock<EventLevel> = OnceLock::new();
 pub static EVENT_LEVEL_FAILED_LOGINS_20: OnceLock<EventLevel> = OnceLock::new();
@@ -91,6 +93,18 @@ pub fn init_event_vars() -> Result<(), ErrorResponse> {
             EventLevel::Critical,
         ))
         .unwrap();
+    EVENT_LEVEL_RECOVERY_CODE_USED
+        .set(map_env_var_level(
+            "EVENT_LEVEL_RECOVERY_CODE_USED",
+            EventLevel::Warning,
+        ))
+        .unwrap();
+    EVENT_LEVEL_REFRESH_TOKEN_REUSE
+        .set(map_env_var_level(
+            "EVENT_LEVEL_REFRESH_TOKEN_REUSE",
+            EventLevel::Critical,
+        ))
+        .unwrap();
     EVENT_LEVEL_IP_BLACKLISTED
         .set(map_env_var_level(
             "EVENT_LEVEL_IP_BLACKLISTED",
@@ -260,9 +260,20 @@ pub struct I18nAccountMfa<'a> {
     registerd_keys: &'a str,
     passkey_name: &'a str,
     passkey_name_err: &'a str,
+    recovery_codes: &'a str,
+    recovery_codes_desc: &'a str,
+    recovery_codes_generate: &'a str,
+    recovery_codes_new: &'a str,
     test: &'a str,
     test_error: &'a str,
     test_success: &'a str,
+    totp: &'a str,
+    totp_code: &'a str,
+    totp_desc: &'a str,
+    totp_registered: &'a str,
+    totp_register: &'a str,
+    totp_scan: &'a str,
+    totp_secret: &'a str,
 }
 
 impl SsrJson for I18nAccountMfa<'_> {
@@ -298,9 +309,25 @@ impl I18nAccountMfa<'_> {
             registerd_keys: "Registered Keys",
             passkey_name: "Passkey Name",
             passkey_name_err: "2 - 32 non-special characters",
+            recovery_codes: "Recovery Codes",
+            recovery_codes_desc: "If you lose access to your second factor, you can use one of \
+            your recovery codes during the login instead. Each code can only be used once. \
+            Generating new codes invalidates all existing ones.",
+            recovery_codes_generate: "Generate New Codes",
+            recovery_codes_new: "Store these codes in a safe place. They will only be shown \
+            this single time.",
             test: "Test",
             test_error: "Error starting the Test",
             test_success: "Test successful",
+            totp: "Authenticator App",
+            totp_code: "Code",
+            totp_desc: "Instead of a Passkey, you can use an Authenticator App, which generates \
+            one-time codes (TOTP). Passkeys are always preferred during the login, if you have both.",
+            totp_registered: "An Authenticator App is registered",
+            totp_register: "Register App",
+            totp_scan: "Scan the QR code with your Authenticator App or enter the secret manually. \
+            Confirm the registration with the current code afterward.",
+            totp_secret: "Secret",
         }
     }
 
@@ -323,9 +350,27 @@ impl I18nAccountMfa<'_> {
             registerd_keys: "Registrierte Keys",
             passkey_name: "Passkey Name",
             passkey_name_err: "2 - 32 Buchstaben, keine Sonderzeichen",
+            recovery_codes: "Wiederherstellungscodes",
+            recovery_codes_desc: "Sollten Sie den Zugang zu Ihrem zweiten Faktor verlieren, \
+            können Sie beim Login stattdessen einen Ihrer Wiederherstellungscodes nutzen. Jeder \
+            Code kann nur einmal genutzt werden. Neu generierte Codes machen alle bestehenden \
+            ungültig.",
+            recovery_codes_generate: "Neue Codes Generieren",
+            recovery_codes_new: "Bewahren Sie diese Codes an einem sicheren Ort auf. Sie werden \
+            nur dieses eine Mal angezeigt.",
             test: "Test",
             test_error: "Fehler beim Starten des Tests",
             test_success: "Test erfolgreich",
+            totp: "Authenticator App",
+            totp_code: "Code",
+            totp_desc: "Anstelle eines Passkeys können Sie eine Authenticator App nutzen, welche \
+            Einmal-Codes (TOTP) generiert. Sind beide vorhanden, wird beim Login immer der Passkey \
+            bevorzugt.",
+            totp_registered: "Eine Authenticator App ist registriert",
+            totp_register: "App Registrieren",
+            totp_scan: "Scannen Sie den QR Code mit Ihrer Authenticator App oder geben Sie das \
+            Secret manuell ein. Bestätigen Sie die Registrierung danach mit dem aktuellen Code.",
+            totp_secret: "Secret",
         }
     }
 }
@@ -5,9 +5,15 @@ use serde::Serialize;
 #[derive(Debug, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct I18nAuthorize<'a> {
+    authorization_details: &'a str,
     client_force_mfa: &'a str,
     email: &'a str,
     email_bad_format: &'a str,
+    email_login: &'a str,
+    email_login_code: &'a str,
+    email_login_desc: &'a str,
+    email_login_invalid: &'a str,
+    email_login_progress: &'a str,
     email_required: &'a str,
     email_sent_msg: &'a str,
     http_429: &'a str,
@@ -20,7 +26,13 @@ pub struct I18nAuthorize<'a> {
     password_request: &'a str,
     password_required: &'a str,
     provide_mfa: &'a str,
+    provide_totp: &'a str,
+    recovery_code: &'a str,
+    recovery_code_invalid: &'a str,
+    recovery_code_use: &'a str,
     request_expires: &'a str,
+    totp_code: &'a str,
+    totp_invalid: &'a str,
 }
 
 impl SsrJson for I18nAuthorize<'_> {
@@ -39,10 +51,18 @@ impl SsrJson for I18nAuthorize<'_> {
 impl I18nAuthorize<'_> {
     fn build_en() -> Self {
         Self {
+            authorization_details: "Requested authorization",
             client_force_mfa: r#"This login forces MFA to achieve higher security.
-To get access, you need to log in to your account and add at least one additional Passkey"#,
+To get access, you need to log in to your account and add at least one additional Passkey
+or Authenticator App"#,
             email: "E-Mail",
             email_bad_format: "Bad E-Mail format",
+            email_login: "Login via E-Mail",
+            email_login_code: "Login Code",
+            email_login_desc: "If your E-Mail exists, a login link has been sent. Click the link \
+            or enter the code from the E-Mail below.",
+            email_login_invalid: "Invalid code",
+            email_login_progress: "Logging in...",
             email_required: "E-Mail is required",
             email_sent_msg: "If your E-Mail exists, a request has been sent",
             http_429: "Too many invalid inputs. Locked until:",
@@ -55,17 +75,31 @@ To get access, you need to log in to your account and add at least one additiona
             password_request: "Request",
             password_required: "Password is required",
             provide_mfa: "Please login with your MFA device",
+            provide_totp: "Please enter the code from your Authenticator App",
+            recovery_code: "Recovery Code",
+            recovery_code_invalid: "Invalid recovery code - please log in again",
+            recovery_code_use: "Use a recovery code instead",
             request_expires: "Request expires",
+            totp_code: "Code",
+            totp_invalid: "Invalid code - please log in again",
         }
     }
 
     fn build_de() -> Self {
         Self {
+            authorization_details: "Angeforderte Berechtigung",
             client_force_mfa: r#"Dieser Login setzt MFA voraus für eine erhöhte Sicherheit.
 Um Zugang zu bekommen, müssen Sie sie in Ihren Account einloggen und mindestens einen Passkey
-hinzufügen."#,
+oder eine Authenticator App hinzufügen."#,
             email: "E-Mail",
             email_bad_format: "Inkorrektes E-Mail Format",
+            email_login: "Login per E-Mail",
+            email_login_code: "Login Code",
+            email_login_desc:
+                "Sollte Ihre Adresse registriert sein, wurde ein Login Link versandt. \
+            Klicken Sie auf den Link oder geben Sie den Code aus der E-Mail unten ein.",
+            email_login_invalid: "Ungültiger Code",
+            email_login_progress: "Login wird durchgeführt...",
             email_required: "E-Mail ist notwendig",
             email_sent_msg: "Sollte Ihre Adresse registriert sein, wurde eine Nachricht versandt",
             http_429: "Zu viele ungültige Versuche. Gesperrt bis:",
@@ -78,7 +112,14 @@ hinzufügen."#,
             password_request: "Anfordern",
             password_required: "Password ist notwendig",
             provide_mfa: "Bitte stellen Sie Ihr MFA Gerät zur Verfügung",
+            provide_totp: "Bitte geben Sie den Code aus Ihrer Authenticator App ein",
+            recovery_code: "Wiederherstellungscode",
+            recovery_code_invalid: "Ungültiger Wiederherstellungscode - bitte loggen Sie sich \
+            erneut ein",
+            recovery_code_use: "Stattdessen einen Wiederherstellungscode nutzen",
             request_expires: "Anfrage läuft ab",
+            totp_code: "Code",
+            totp_invalid: "Ungültiger Code - bitte loggen Sie sich erneut ein",
         }
     }
 }
@@ -0,0 +1,54 @@
+use crate::i18n::SsrJson;
+use crate::language::Language;
+use serde::Serialize;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct I18nConsent<'a> {
+    title: &'a str,
+    desc: &'a str,
+    scopes: &'a str,
+    attributes: &'a str,
+    revoke_info: &'a str,
+    accept: &'a str,
+    decline: &'a str,
+}
+
+impl SsrJson for I18nConsent<'_> {
+    fn build(lang: &Language) -> Self {
+        match lang {
+            Language::En => Self::build_en(),
+            Language::De => Self::build_de(),
+        }
+    }
+
+    fn as_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl I18nConsent<'_> {
+    fn build_en() -> Self {
+        Self {
+            title: "Consent",
+            desc: "The following application requests access to your account:",
+            scopes: "Requested Scopes",
+            attributes: "Shared Attributes",
+            revoke_info: "You can revoke the access anytime in your account.",
+            accept: "Accept",
+            decline: "Decline",
+        }
+    }
+
+    fn build_de() -> Self {
+        Self {
+            title: "Zustimmung",
+            desc: "Die folgende Anwendung fordert Zugriff auf Ihren Account an:",
+            scopes: "Angeforderte Scopes",
+            attributes: "Geteilte Attribute",
+            revoke_info: "Sie können den Zugriff jederzeit in Ihrem Account widerrufen.",
+            accept: "Akzeptieren",
+            decline: "Ablehnen",
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::i18n::SsrJson;
+use crate::language::Language;
+use serde::Serialize;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct I18nDevice<'a> {
+    title: &'a str,
+    desc: &'a str,
+    user_code: &'a str,
+    accept: &'a str,
+    decline: &'a str,
+    accepted: &'a str,
+    declined: &'a str,
+    invalid_code: &'a str,
+}
+
+impl SsrJson for I18nDevice<'_> {
+    fn build(lang: &Language) -> Self {
+        match lang {
+            Language::En => Self::build_en(),
+            Language::De => Self::build_de(),
+        }
+    }
+
+    fn as_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl I18nDevice<'_> {
+    fn build_en() -> Self {
+        Self {
+            title: "Device Authorization",
+            desc: "Please enter the code shown on your device. Only accept the request, if you \
+            started the login on this device yourself.",
+            user_code: "User Code",
+            accept: "Accept",
+            decline: "Decline",
+            accepted: "The device has been authorized. You can close this window now.",
+            declined: "The device authorization has been declined.",
+            invalid_code: "Invalid or expired code",
+        }
+    }
+
+    fn build_de() -> Self {
+        Self {
+            title: "Geräte Autorisierung",
+            desc: "Bitte geben Sie den Code ein, welcher auf Ihrem Gerät angezeigt wird. \
+            Akzeptieren Sie die Anfrage nur, wenn Sie den Login auf diesem Gerät selbst gestartet \
+            haben.",
+            user_code: "Benutzer Code",
+            accept: "Akzeptieren",
+            decline: "Ablehnen",
+            accepted: "Das Gerät wurde autorisiert. Sie können dieses Fenster jetzt schließen.",
+            declined: "Die Autorisierung des Geräts wurde abgelehnt.",
+            invalid_code: "Ungültiger oder abgelaufener Code",
+        }
+    }
+}
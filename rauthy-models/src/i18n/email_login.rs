@@ -0,0 +1,56 @@
+use crate::i18n::SsrJson;
+use crate::language::Language;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct I18nEmailLogin<'a> {
+    pub subject: &'a str,
+    pub header: &'a str,
+    pub click_link: &'a str,
+    pub enter_code: &'a str,
+    pub validity: &'a str,
+    pub expires: &'a str,
+    pub button_text: &'a str,
+}
+
+impl SsrJson for I18nEmailLogin<'_> {
+    fn build(lang: &Language) -> Self {
+        match lang {
+            Language::En => Self::build_en(),
+            Language::De => Self::build_de(),
+        }
+    }
+
+    fn as_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl I18nEmailLogin<'_> {
+    fn build_en() -> Self {
+        Self {
+            subject: "Login Request",
+            header: "Login request for",
+            click_link:
+                "Click the link below to log in, in the same browser you requested it from.",
+            enter_code: "Alternatively, enter this code on the login page:",
+            validity: "This link is only valid for a short period of time for security reasons.",
+            expires: "Link expires:",
+            button_text: "Login",
+        }
+    }
+
+    fn build_de() -> Self {
+        Self {
+            subject: "Login angefordert",
+            header: "Login angefordert für",
+            click_link:
+                "Klicken Sie auf den unten stehenden Link für den Login, im selben Browser, \
+            in dem er angefordert wurde.",
+            enter_code: "Alternativ geben Sie diesen Code auf der Login Seite ein:",
+            validity: "Dieser Link ist aus Sicherheitsgründen nur für kurze Zeit gültig.",
+            expires: "Link gültig bis:",
+            button_text: "Login",
+        }
+    }
+}
@@ -2,10 +2,13 @@ use crate::language::Language;
 
 pub mod account;
 pub mod authorize;
+pub mod consent;
+pub mod device;
 pub mod email_change_info_new;
 pub mod email_change_info_old;
 pub mod email_confirm_change;
 pub mod email_confirm_change_html;
+pub mod email_login;
 pub mod email_reset;
 pub mod email_reset_info;
 pub mod error;
@@ -2,6 +2,7 @@
 
 #![forbid(unsafe_code)]
 
+use crate::entity::authorization_details::AuthorizationDetails;
 use crate::entity::sessions::Session;
 use crate::entity::users::User;
 use crate::entity::users_values::UserValues;
@@ -28,6 +29,7 @@ pub mod templates;
 pub enum AuthStep {
     LoggedIn(AuthStepLoggedIn),
     AwaitWebauthn(AuthStepAwaitWebauthn),
+    AwaitTotp(AuthStepAwaitTotp),
 }
 
 pub struct AuthStepLoggedIn {
@@ -49,6 +51,15 @@ pub struct AuthStepAwaitWebauthn {
     pub session: Session,
 }
 
+pub struct AuthStepAwaitTotp {
+    pub has_password_been_hashed: bool,
+    pub code: String,
+    pub header_csrf: (HeaderName, HeaderValue),
+    pub header_origin: Option<(HeaderName, HeaderValue)>,
+    pub user_id: String,
+    pub exp: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RequestId {
     pub id: String,
@@ -79,11 +90,54 @@ pub struct JwtCommonClaims {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scope: Option<String>,
     pub cnf: Option<JktClaim>,
+    pub act: Option<JwtActClaim>,
+    pub authorization_details: Option<AuthorizationDetails>,
 }
 
+/// The `cnf` claim, which binds a token to a DPoP key (RFC 9449) or to the client certificate
+/// of a mutual TLS connection (RFC 8705).
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct JktClaim {
-    pub jkt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jkt: Option<String>,
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+}
+
+impl JktClaim {
+    pub fn opt_from(jkt: Option<String>, x5t_s256: Option<String>) -> Option<Self> {
+        if jkt.is_none() && x5t_s256.is_none() {
+            None
+        } else {
+            Some(Self { jkt, x5t_s256 })
+        }
+    }
+}
+
+/// The `act` claim from RFC 8693 for delegated tokens, which identifies the acting party.
+/// A prior actor of a delegation chain is nested inside.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtActClaim {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<JwtActClaim>>,
+}
+
+/// The `aud` claim of a foreign JWT, which may be a single value or an array
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum JwtAudience {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl JwtAudience {
+    pub fn contains(&self, value: &str) -> bool {
+        match self {
+            Self::Single(aud) => aud == value,
+            Self::Multiple(auds) => auds.iter().any(|aud| aud == value),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -148,6 +202,13 @@ pub struct JwtAccessClaims {
     pub typ: JwtTokenType,
     pub azp: String,
     pub scope: String,
+    // RFC 9068 part, only added with `ACCESS_TOKEN_RFC9068`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allowed_origins: Option<Vec<String>>,
     // user part
@@ -162,6 +223,10 @@ pub struct JwtAccessClaims {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cnf: Option<JktClaim>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<JwtActClaim>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_details: Option<AuthorizationDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub custom: Option<HashMap<String, serde_json::Value>>,
 }
 
@@ -170,6 +235,9 @@ pub struct JwtIdClaims {
     pub azp: String,
     pub typ: JwtTokenType,
     pub amr: Vec<String>,
+    /// Mirrors the `amr`. Clients can demand `mfa` with the `acr_values` during authorization.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
     pub auth_time: i64,
     pub preferred_username: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -197,6 +265,16 @@ pub struct JwtIdClaims {
     pub custom: Option<HashMap<String, serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub webid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+/// The custom claims of a `logout_token` for OIDC Back-Channel Logout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtLogoutClaims {
+    pub events: HashMap<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -206,6 +284,11 @@ pub struct JwtRefreshClaims {
     pub uid: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cnf: Option<JktClaim>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// Carried over into the access tokens of a refresh
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_details: Option<AuthorizationDetails>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
@@ -232,6 +315,7 @@ impl JwtTokenType {
 pub enum JwtAmrValue {
     Pwd,
     Mfa,
+    Otp,
 }
 
 impl FromStr for JwtAmrValue {
@@ -241,6 +325,7 @@ impl FromStr for JwtAmrValue {
         let slf = match s {
             "pwd" => Self::Pwd,
             "mfa" => Self::Mfa,
+            "otp" => Self::Otp,
             _ => {
                 return Err(ErrorResponse::new(
                     ErrorResponseType::BadRequest,
@@ -257,6 +342,7 @@ impl ToString for JwtAmrValue {
         let s = match self {
             Self::Pwd => "pwd",
             Self::Mfa => "mfa",
+            Self::Otp => "otp",
         };
         s.to_string()
     }
@@ -3,7 +3,8 @@ use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
 use cryptr::{EncKeys, EncValue};
 use jwt_simple::algorithms::{
-    Ed25519KeyPair, EdDSAKeyPairLike, RS256KeyPair, RS384KeyPair, RS512KeyPair, RSAKeyPairLike,
+    ES256KeyPair, ES384KeyPair, Ed25519KeyPair, EdDSAKeyPairLike, RS256KeyPair, RS384KeyPair,
+    RS512KeyPair, RSAKeyPairLike,
 };
 use rand_core::OsRng;
 use sqlx::Row;
@@ -24,11 +25,15 @@ use crate::entity::groups::Group;
 use crate::entity::jwk::{Jwk, JwkKeyPairAlg};
 use crate::entity::magic_links::MagicLink;
 use crate::entity::password::RecentPasswordsEntity;
+use crate::entity::recovery_codes::RecoveryCode;
 use crate::entity::refresh_tokens::RefreshToken;
+use crate::entity::resources::Resource;
+use crate::entity::revoked_tokens::RevokedToken;
 use crate::entity::roles::Role;
 use crate::entity::scopes::Scope;
 use crate::entity::sessions::Session;
 use crate::entity::user_attr::{UserAttrConfigEntity, UserAttrValueEntity};
+use crate::entity::user_consents::UserConsent;
 use crate::entity::users::User;
 use crate::entity::webauthn::PasskeyEntity;
 
@@ -74,6 +79,30 @@ pub async fn anti_lockout(db: &DbPool, issuer: &str) -> Result<(), ErrorResponse
         default_scopes: "openid".to_string(),
         challenge: Some("S256".to_string()),
         force_mfa: *ADMIN_FORCE_MFA,
+        require_par: false,
+        jwks: None,
+        jwks_uri: None,
+        require_request_object: false,
+        token_exchange_audiences: None,
+        token_exchange_scopes: None,
+        token_exchange_subject_types: None,
+        backchannel_logout_uri: None,
+        frontchannel_logout_uri: None,
+        subject_type: None,
+        sector_identifier_uri: None,
+        id_token_encrypted_response_alg: None,
+        id_token_encrypted_response_enc: None,
+        userinfo_encrypted_response_alg: None,
+        userinfo_encrypted_response_enc: None,
+        userinfo_signed_response_alg: None,
+        authorization_details_types: None,
+        tls_client_auth_method: None,
+        tls_client_auth_subject_dn: None,
+        tls_client_certificate_bound_access_tokens: false,
+        require_consent: false,
+        allow_email_login: false,
+        token_endpoint_auth_method: None,
+        request_uris: None,
     };
 
     #[cfg(feature = "sqlite")]
@@ -81,8 +110,18 @@ pub async fn anti_lockout(db: &DbPool, issuer: &str) -> Result<(), ErrorResponse
         r#"insert or replace into clients (id, name, enabled, confidential,
         secret, secret_kid, redirect_uris, post_logout_redirect_uris, allowed_origins,
         flows_enabled, access_token_alg, id_token_alg, refresh_token, auth_code_lifetime,
-        access_token_lifetime, scopes, default_scopes, challenge, force_mfa)
-        values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)"#,
+        access_token_lifetime, scopes, default_scopes, challenge, force_mfa, require_par, jwks,
+        jwks_uri, require_request_object,
+        token_exchange_audiences, token_exchange_scopes, token_exchange_subject_types,
+        backchannel_logout_uri, frontchannel_logout_uri, subject_type, sector_identifier_uri,
+        id_token_encrypted_response_alg, id_token_encrypted_response_enc, userinfo_encrypted_response_alg,
+        userinfo_encrypted_response_enc, userinfo_signed_response_alg,
+        authorization_details_types, tls_client_auth_method, tls_client_auth_subject_dn,
+        tls_client_certificate_bound_access_tokens, require_consent, allow_email_login,
+        token_endpoint_auth_method, request_uris)
+        values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+        $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37, $38,
+        $39, $40, $41, $42, $43)"#,
         rauthy.id,
         rauthy.name,
         rauthy.enabled,
@@ -102,6 +141,30 @@ pub async fn anti_lockout(db: &DbPool, issuer: &str) -> Result<(), ErrorResponse
         rauthy.default_scopes,
         rauthy.challenge,
         rauthy.force_mfa,
+        rauthy.require_par,
+        rauthy.jwks,
+        rauthy.jwks_uri,
+        rauthy.require_request_object,
+        rauthy.token_exchange_audiences,
+        rauthy.token_exchange_scopes,
+        rauthy.token_exchange_subject_types,
+        rauthy.backchannel_logout_uri,
+        rauthy.frontchannel_logout_uri,
+        rauthy.subject_type,
+        rauthy.sector_identifier_uri,
+        rauthy.id_token_encrypted_response_alg,
+        rauthy.id_token_encrypted_response_enc,
+        rauthy.userinfo_encrypted_response_alg,
+        rauthy.userinfo_encrypted_response_enc,
+        rauthy.userinfo_signed_response_alg,
+        rauthy.authorization_details_types,
+        rauthy.tls_client_auth_method,
+        rauthy.tls_client_auth_subject_dn,
+        rauthy.tls_client_certificate_bound_access_tokens,
+        rauthy.require_consent,
+        rauthy.allow_email_login,
+        rauthy.token_endpoint_auth_method,
+        rauthy.request_uris,
     );
 
     #[cfg(not(feature = "sqlite"))]
@@ -109,13 +172,35 @@ pub async fn anti_lockout(db: &DbPool, issuer: &str) -> Result<(), ErrorResponse
         r#"insert into clients (id, name, enabled, confidential, secret, secret_kid,
         redirect_uris, post_logout_redirect_uris, allowed_origins, flows_enabled,
         access_token_alg, id_token_alg, refresh_token, auth_code_lifetime,
-        access_token_lifetime, scopes, default_scopes, challenge, force_mfa)
-        values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
+        access_token_lifetime, scopes, default_scopes, challenge, force_mfa, require_par, jwks,
+        jwks_uri, require_request_object,
+        token_exchange_audiences, token_exchange_scopes, token_exchange_subject_types,
+        backchannel_logout_uri, frontchannel_logout_uri, subject_type, sector_identifier_uri,
+        id_token_encrypted_response_alg, id_token_encrypted_response_enc, userinfo_encrypted_response_alg,
+        userinfo_encrypted_response_enc, userinfo_signed_response_alg,
+        authorization_details_types, tls_client_auth_method, tls_client_auth_subject_dn,
+        tls_client_certificate_bound_access_tokens, require_consent, allow_email_login,
+        token_endpoint_auth_method, request_uris)
+        values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+        $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37, $38,
+        $39, $40, $41, $42, $43)
         on conflict(id) do update set name = $2, enabled = $3, confidential = $4, secret = $5,
         secret_kid = $6, redirect_uris = $7, post_logout_redirect_uris = $8, allowed_origins = $9,
         flows_enabled = $10, access_token_alg = $11, id_token_alg = $12, refresh_token = $13,
         auth_code_lifetime = $14, access_token_lifetime = $15, scopes = $16, default_scopes = $17,
-        challenge = $18, force_mfa = $19"#,
+        challenge = $18, force_mfa = $19, require_par = $20, jwks = $21, jwks_uri = $22,
+        require_request_object = $23,
+        token_exchange_audiences = $24, token_exchange_scopes = $25, token_exchange_subject_types = $26,
+        backchannel_logout_uri = $27,
+        frontchannel_logout_uri = $28,
+        subject_type = $29, sector_identifier_uri = $30,
+        id_token_encrypted_response_alg = $31, id_token_encrypted_response_enc = $32,
+        userinfo_encrypted_response_alg = $33, userinfo_encrypted_response_enc = $34,
+        userinfo_signed_response_alg = $35,
+        authorization_details_types = $36,
+        tls_client_auth_method = $37, tls_client_auth_subject_dn = $38,
+        tls_client_certificate_bound_access_tokens = $39, require_consent = $40,
+        allow_email_login = $41, token_endpoint_auth_method = $42, request_uris = $43"#,
         rauthy.id,
         rauthy.name,
         rauthy.enabled,
@@ -135,6 +220,30 @@ pub async fn anti_lockout(db: &DbPool, issuer: &str) -> Result<(), ErrorResponse
         rauthy.default_scopes,
         rauthy.challenge,
         rauthy.force_mfa,
+        rauthy.require_par,
+        rauthy.jwks,
+        rauthy.jwks_uri,
+        rauthy.require_request_object,
+        rauthy.token_exchange_audiences,
+        rauthy.token_exchange_scopes,
+        rauthy.token_exchange_subject_types,
+        rauthy.backchannel_logout_uri,
+        rauthy.frontchannel_logout_uri,
+        rauthy.subject_type,
+        rauthy.sector_identifier_uri,
+        rauthy.id_token_encrypted_response_alg,
+        rauthy.id_token_encrypted_response_enc,
+        rauthy.userinfo_encrypted_response_alg,
+        rauthy.userinfo_encrypted_response_enc,
+        rauthy.userinfo_signed_response_alg,
+        rauthy.authorization_details_types,
+        rauthy.tls_client_auth_method,
+        rauthy.tls_client_auth_subject_dn,
+        rauthy.tls_client_certificate_bound_access_tokens,
+        rauthy.require_consent,
+        rauthy.allow_email_login,
+        rauthy.token_endpoint_auth_method,
+        rauthy.request_uris,
     );
 
     q.execute(db).await?;
@@ -283,6 +392,51 @@ pub async fn migrate_init_prod(
     Ok(())
 }
 
+/// Generates the ECDSA JWKs, if they do not exist yet. This applies to databases, which have been
+/// initialized before ES256 / ES384 were supported, as well as to the pre-computed DEV JWKs.
+pub async fn migrate_ecdsa_jwks(db: &DbPool) -> Result<(), ErrorResponse> {
+    let jwks = sqlx::query_as::<_, Jwk>("select * from jwks")
+        .fetch_all(db)
+        .await?;
+    let enc_key_active = &EncKeys::get_static().enc_key_active;
+
+    if !jwks.iter().any(|jwk| jwk.signature == JwkKeyPairAlg::ES256) {
+        info!("Generating missing ES256 JWK");
+        let jwk_plain = ES256KeyPair::generate().with_key_id(&get_rand(24));
+        let jwk = EncValue::encrypt(jwk_plain.to_der().unwrap().as_slice())?
+            .into_bytes()
+            .to_vec();
+        Jwk {
+            kid: jwk_plain.key_id().as_ref().unwrap().clone(),
+            created_at: OffsetDateTime::now_utc().unix_timestamp(),
+            signature: JwkKeyPairAlg::ES256,
+            enc_key_id: enc_key_active.clone(),
+            jwk,
+        }
+        .save(db)
+        .await?;
+    }
+
+    if !jwks.iter().any(|jwk| jwk.signature == JwkKeyPairAlg::ES384) {
+        info!("Generating missing ES384 JWK");
+        let jwk_plain = ES384KeyPair::generate().with_key_id(&get_rand(24));
+        let jwk = EncValue::encrypt(jwk_plain.to_der().unwrap().as_slice())?
+            .into_bytes()
+            .to_vec();
+        Jwk {
+            kid: jwk_plain.key_id().as_ref().unwrap().clone(),
+            created_at: OffsetDateTime::now_utc().unix_timestamp(),
+            signature: JwkKeyPairAlg::ES384,
+            enc_key_id: enc_key_active.clone(),
+            jwk,
+        }
+        .save(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
 /// Migrates `MIGRATE_DB_FROM` to `DATABASE_URL`
 pub async fn migrate_from_sqlite(
     db_from: sqlx::SqlitePool,
@@ -336,8 +490,9 @@ pub async fn migrate_from_sqlite(
             r#"insert into users
             (id, email, given_name, family_name, password, roles, groups, enabled, email_verified,
             password_expires, created_at, last_login, last_failed_login, failed_login_attempts,
-            language, webauthn_user_id, user_expires)
-            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)"#,
+            language, webauthn_user_id, user_expires, totp_secret)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+            $18)"#,
         )
         .bind(b.id)
         .bind(b.email)
@@ -356,6 +511,7 @@ pub async fn migrate_from_sqlite(
         .bind(b.language)
         .bind(b.webauthn_user_id)
         .bind(b.user_expires)
+        .bind(b.totp_secret)
         .execute(db_to)
         .await?;
     }
@@ -393,8 +549,17 @@ pub async fn migrate_from_sqlite(
             r#"insert into clients (id, name, enabled, confidential, secret, secret_kid,
             redirect_uris, post_logout_redirect_uris, allowed_origins, flows_enabled, access_token_alg,
             id_token_alg, refresh_token, auth_code_lifetime, access_token_lifetime, scopes, default_scopes,
-            challenge, force_mfa)
-            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)"#)
+            challenge, force_mfa, require_par, jwks, jwks_uri, require_request_object,
+            token_exchange_audiences, token_exchange_scopes, token_exchange_subject_types,
+            backchannel_logout_uri, frontchannel_logout_uri, subject_type, sector_identifier_uri,
+            id_token_encrypted_response_alg, id_token_encrypted_response_enc, userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc, userinfo_signed_response_alg,
+            authorization_details_types, tls_client_auth_method, tls_client_auth_subject_dn,
+            tls_client_certificate_bound_access_tokens, require_consent, allow_email_login,
+            token_endpoint_auth_method, request_uris)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37, $38, $39, $40,
+            $41, $42, $43)"#)
             .bind(&b.id)
             .bind(&b.name)
             .bind(b.enabled)
@@ -414,6 +579,30 @@ pub async fn migrate_from_sqlite(
             .bind(&b.default_scopes)
             .bind(&b.challenge)
             .bind(b.force_mfa)
+            .bind(b.require_par)
+            .bind(&b.jwks)
+            .bind(&b.jwks_uri)
+            .bind(b.require_request_object)
+            .bind(&b.token_exchange_audiences)
+            .bind(&b.token_exchange_scopes)
+            .bind(&b.token_exchange_subject_types)
+            .bind(&b.backchannel_logout_uri)
+            .bind(&b.frontchannel_logout_uri)
+            .bind(&b.subject_type)
+            .bind(&b.sector_identifier_uri)
+            .bind(&b.id_token_encrypted_response_alg)
+            .bind(&b.id_token_encrypted_response_enc)
+            .bind(&b.userinfo_encrypted_response_alg)
+            .bind(&b.userinfo_encrypted_response_enc)
+            .bind(&b.userinfo_signed_response_alg)
+            .bind(b.authorization_details_types)
+            .bind(b.tls_client_auth_method)
+            .bind(b.tls_client_auth_subject_dn)
+            .bind(b.tls_client_certificate_bound_access_tokens)
+            .bind(b.require_consent)
+            .bind(b.allow_email_login)
+            .bind(b.token_endpoint_auth_method)
+            .bind(b.request_uris)
             .execute(db_to)
             .await?;
     }
@@ -533,6 +722,21 @@ pub async fn migrate_from_sqlite(
         .await?;
     }
 
+    // REVOKED TOKENS
+    let before = sqlx::query_as::<_, RevokedToken>("select * from revoked_tokens")
+        .fetch_all(&db_from)
+        .await?;
+    sqlx::query("delete from revoked_tokens")
+        .execute(db_to)
+        .await?;
+    for b in before {
+        sqlx::query("insert into revoked_tokens (jti, exp) values ($1, $2)")
+            .bind(&b.jti)
+            .bind(b.exp)
+            .execute(db_to)
+            .await?;
+    }
+
     // ROLES
     let before = sqlx::query_as::<_, Role>("select * from roles")
         .fetch_all(&db_from)
@@ -562,6 +766,21 @@ pub async fn migrate_from_sqlite(
         .await?;
     }
 
+    // RESOURCES
+    let before = sqlx::query_as::<_, Resource>("select * from resources")
+        .fetch_all(&db_from)
+        .await?;
+    sqlx::query("delete from resources").execute(db_to).await?;
+    for b in before {
+        sqlx::query("insert into resources (id, name, uri, scopes) values ($1, $2, $3, $4)")
+            .bind(b.id)
+            .bind(b.name)
+            .bind(b.uri)
+            .bind(b.scopes)
+            .execute(db_to)
+            .await?;
+    }
+
     // USER ATTR CONFIG
     let before = sqlx::query_as::<_, UserAttrConfigEntity>("select * from user_attr_config")
         .fetch_all(&db_from)
@@ -600,6 +819,46 @@ pub async fn migrate_from_sqlite(
             .await?;
     }
 
+    // USER CONSENTS
+    let before = sqlx::query_as::<_, UserConsent>("select * from user_consents")
+        .fetch_all(&db_from)
+        .await?;
+    sqlx::query("delete from user_consents")
+        .execute(db_to)
+        .await?;
+    for b in before {
+        sqlx::query(
+            r#"insert into user_consents (user_id, client_id, scopes, created_at, updated_at)
+            values ($1, $2, $3, $4, $5)"#,
+        )
+        .bind(b.user_id)
+        .bind(b.client_id)
+        .bind(b.scopes)
+        .bind(b.created_at)
+        .bind(b.updated_at)
+        .execute(db_to)
+        .await?;
+    }
+
+    // RECOVERY CODES
+    let before = sqlx::query_as::<_, RecoveryCode>("select * from recovery_codes")
+        .fetch_all(&db_from)
+        .await?;
+    sqlx::query("delete from recovery_codes")
+        .execute(db_to)
+        .await?;
+    for b in before {
+        sqlx::query(
+            "insert into recovery_codes (user_id, id, hash, created) values ($1, $2, $3, $4)",
+        )
+        .bind(b.user_id)
+        .bind(b.id)
+        .bind(b.hash)
+        .bind(b.created)
+        .execute(db_to)
+        .await?;
+    }
+
     // SESSIONS
     let before = sqlx::query_as::<_, Session>("select * from sessions")
         .fetch_all(&db_from)
@@ -694,8 +953,9 @@ pub async fn migrate_from_postgres(
             r#"insert into users
             (id, email, given_name, family_name, password, roles, groups, enabled, email_verified,
             password_expires, created_at, last_login, last_failed_login, failed_login_attempts,
-            language, webauthn_user_id, user_expires)
-            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)"#,
+            language, webauthn_user_id, user_expires, totp_secret)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+            $18)"#,
         )
         .bind(b.id)
         .bind(b.email)
@@ -714,6 +974,7 @@ pub async fn migrate_from_postgres(
         .bind(b.language)
         .bind(b.webauthn_user_id)
         .bind(b.user_expires)
+        .bind(b.totp_secret)
         .execute(db_to)
         .await?;
     }
@@ -751,8 +1012,17 @@ pub async fn migrate_from_postgres(
             r#"insert into clients (id, name, enabled, confidential, secret, secret_kid,
             redirect_uris, post_logout_redirect_uris, allowed_origins, flows_enabled, access_token_alg,
             id_token_alg, refresh_token, auth_code_lifetime, access_token_lifetime, scopes, default_scopes,
-            challenge, force_mfa)
-            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)"#)
+            challenge, force_mfa, require_par, jwks, jwks_uri, require_request_object,
+            token_exchange_audiences, token_exchange_scopes, token_exchange_subject_types,
+            backchannel_logout_uri, frontchannel_logout_uri, subject_type, sector_identifier_uri,
+            id_token_encrypted_response_alg, id_token_encrypted_response_enc, userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc, userinfo_signed_response_alg,
+            authorization_details_types, tls_client_auth_method, tls_client_auth_subject_dn,
+            tls_client_certificate_bound_access_tokens, require_consent, allow_email_login,
+            token_endpoint_auth_method, request_uris)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37, $38, $39, $40,
+            $41, $42, $43)"#)
             .bind(&b.id)
             .bind(&b.name)
             .bind(b.enabled)
@@ -772,6 +1042,30 @@ pub async fn migrate_from_postgres(
             .bind(&b.default_scopes)
             .bind(&b.challenge)
             .bind(b.force_mfa)
+            .bind(b.require_par)
+            .bind(&b.jwks)
+            .bind(&b.jwks_uri)
+            .bind(b.require_request_object)
+            .bind(&b.token_exchange_audiences)
+            .bind(&b.token_exchange_scopes)
+            .bind(&b.token_exchange_subject_types)
+            .bind(&b.backchannel_logout_uri)
+            .bind(&b.frontchannel_logout_uri)
+            .bind(&b.subject_type)
+            .bind(&b.sector_identifier_uri)
+            .bind(&b.id_token_encrypted_response_alg)
+            .bind(&b.id_token_encrypted_response_enc)
+            .bind(&b.userinfo_encrypted_response_alg)
+            .bind(&b.userinfo_encrypted_response_enc)
+            .bind(&b.userinfo_signed_response_alg)
+            .bind(b.authorization_details_types)
+            .bind(b.tls_client_auth_method)
+            .bind(b.tls_client_auth_subject_dn)
+            .bind(b.tls_client_certificate_bound_access_tokens)
+            .bind(b.require_consent)
+            .bind(b.allow_email_login)
+            .bind(b.token_endpoint_auth_method)
+            .bind(b.request_uris)
             .execute(db_to)
             .await?;
     }
@@ -891,6 +1185,21 @@ pub async fn migrate_from_postgres(
         .await?;
     }
 
+    // REVOKED TOKENS
+    let before = sqlx::query_as::<_, RevokedToken>("select * from rauthy.revoked_tokens")
+        .fetch_all(&db_from)
+        .await?;
+    sqlx::query("delete from revoked_tokens")
+        .execute(db_to)
+        .await?;
+    for b in before {
+        sqlx::query("insert into revoked_tokens (jti, exp) values ($1, $2)")
+            .bind(&b.jti)
+            .bind(b.exp)
+            .execute(db_to)
+            .await?;
+    }
+
     // ROLES
     let before = sqlx::query_as::<_, Role>("select * from rauthy.roles")
         .fetch_all(&db_from)
@@ -920,6 +1229,21 @@ pub async fn migrate_from_postgres(
         .await?;
     }
 
+    // RESOURCES
+    let before = sqlx::query_as::<_, Resource>("select * from rauthy.resources")
+        .fetch_all(&db_from)
+        .await?;
+    sqlx::query("delete from resources").execute(db_to).await?;
+    for b in before {
+        sqlx::query("insert into resources (id, name, uri, scopes) values ($1, $2, $3, $4)")
+            .bind(b.id)
+            .bind(b.name)
+            .bind(b.uri)
+            .bind(b.scopes)
+            .execute(db_to)
+            .await?;
+    }
+
     // USER ATTR CONFIG
     let before = sqlx::query_as::<_, UserAttrConfigEntity>("select * from rauthy.user_attr_config")
         .fetch_all(&db_from)
@@ -958,6 +1282,46 @@ pub async fn migrate_from_postgres(
             .await?;
     }
 
+    // USER CONSENTS
+    let before = sqlx::query_as::<_, UserConsent>("select * from rauthy.user_consents")
+        .fetch_all(&db_from)
+        .await?;
+    sqlx::query("delete from user_consents")
+        .execute(db_to)
+        .await?;
+    for b in before {
+        sqlx::query(
+            r#"insert into user_consents (user_id, client_id, scopes, created_at, updated_at)
+            values ($1, $2, $3, $4, $5)"#,
+        )
+        .bind(b.user_id)
+        .bind(b.client_id)
+        .bind(b.scopes)
+        .bind(b.created_at)
+        .bind(b.updated_at)
+        .execute(db_to)
+        .await?;
+    }
+
+    // RECOVERY CODES
+    let before = sqlx::query_as::<_, RecoveryCode>("select * from rauthy.recovery_codes")
+        .fetch_all(&db_from)
+        .await?;
+    sqlx::query("delete from recovery_codes")
+        .execute(db_to)
+        .await?;
+    for b in before {
+        sqlx::query(
+            "insert into recovery_codes (user_id, id, hash, created) values ($1, $2, $3, $4)",
+        )
+        .bind(b.user_id)
+        .bind(b.id)
+        .bind(b.hash)
+        .bind(b.created)
+        .execute(db_to)
+        .await?;
+    }
+
     // SESSIONS
     let before = sqlx::query_as::<_, Session>("select * from rauthy.sessions")
         .fetch_all(&db_from)
@@ -1,5 +1,7 @@
 use crate::entity::api_keys::ApiKeyAccess;
 use crate::entity::auth_providers::AuthProviderType;
+use crate::entity::authorization_details::deserialize_authorization_details;
+use crate::entity::client_assertions::ClientAssertion;
 use crate::entity::jwk::JwkKeyPairAlg;
 use crate::events::event::{EventLevel, EventType};
 use crate::language::Language;
@@ -7,15 +9,21 @@ use actix_web::http::header;
 use actix_web::HttpRequest;
 use css_color::Srgb;
 use rauthy_common::constants::{
-    RE_ALNUM, RE_ALNUM_48, RE_ALNUM_64, RE_ALNUM_SPACE, RE_API_KEY, RE_APP_ID, RE_ATTR,
-    RE_ATTR_DESC, RE_AUTH_PROVIDER_SCOPE, RE_CHALLENGE, RE_CITY, RE_CLIENT_ID_EPHEMERAL,
-    RE_CLIENT_NAME, RE_CODE_CHALLENGE, RE_CODE_VERIFIER, RE_DATE_STR, RE_FLOWS, RE_GRANT_TYPES,
-    RE_GROUPS, RE_LOWERCASE, RE_LOWERCASE_SPACE, RE_MFA_CODE, RE_PEM, RE_PHONE, RE_STREET,
-    RE_TOKEN_ENDPOINT_AUTH_METHOD, RE_URI, RE_USER_NAME,
+    DEV_MODE, RE_ACR_VALUES, RE_ALNUM, RE_ALNUM_48, RE_ALNUM_64, RE_ALNUM_SPACE, RE_API_KEY,
+    RE_APP_ID, RE_ATTR, RE_ATTR_DESC, RE_AUTHORIZATION_DETAILS_TYPE, RE_AUTH_PROVIDER_SCOPE,
+    RE_CHALLENGE, RE_CITY, RE_CLIENT_ASSERTION_TYPE, RE_CLIENT_AUTH_METHOD, RE_CLIENT_ID_EPHEMERAL,
+    RE_CLIENT_NAME, RE_CODE_CHALLENGE, RE_CODE_VERIFIER, RE_DATE_STR, RE_FLOWS, RE_GRANT_TYPE,
+    RE_GRANT_TYPES, RE_GROUPS, RE_JWE_ALG, RE_JWE_ENC, RE_LOWERCASE, RE_LOWERCASE_SPACE,
+    RE_MFA_CODE, RE_PEM, RE_PHONE, RE_PROMPT, RE_RECOVERY_CODE, RE_RESOURCE, RE_SEARCH, RE_STREET,
+    RE_SUBJECT_TYPE, RE_TLS_CLIENT_AUTH_METHOD, RE_TLS_SUBJECT_DN, RE_TOKEN_68,
+    RE_TOKEN_ENDPOINT_AUTH_METHOD, RE_TOKEN_EXCHANGE_SUBJECT_TYPE, RE_TOKEN_TYPE,
+    RE_TOKEN_TYPE_HINT, RE_TOTP, RE_URI, RE_USER_CODE, RE_USER_NAME,
 };
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_common::password_hasher;
 use rauthy_common::utils::base64_decode;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::Ipv4Addr;
 use std::str::FromStr;
 use utoipa::{IntoParams, ToSchema};
@@ -62,7 +70,7 @@ pub struct IpBlacklistRequest {
     pub exp: i64,
 }
 
-#[derive(Debug, Deserialize, Validate, ToSchema, IntoParams)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema, IntoParams)]
 pub struct AuthRequest {
     /// Validation: `^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%]{2,128}$`
     #[validate(regex(
@@ -91,9 +99,104 @@ pub struct AuthRequest {
     pub code_challenge_method: Option<String>,
     #[validate(range(min = 0))]
     pub max_age: Option<i64>,
-    /// Validation: `[a-zA-Z0-9]`
-    #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
+    /// Space separated, `none` must not be combined with other values
+    ///
+    /// Validation: `^(none|login|consent|select_account)( (none|login|consent|select_account))*$`
+    #[validate(regex(
+        path = "RE_PROMPT",
+        code = "^(none|login|consent|select_account)( (none|login|consent|select_account))*$"
+    ))]
     pub prompt: Option<String>,
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub nonce: Option<String>,
+    /// Pre-fills the email on the login form
+    ///
+    /// Validation: `email`
+    #[validate(email)]
+    pub login_hint: Option<String>,
+    /// Space separated. Only `pwd` and `mfa` are known, `mfa` alone demands an MFA login.
+    ///
+    /// Validation: `^[a-zA-Z0-9\-_.:/ ]{1,256}$`
+    #[validate(regex(path = "RE_ACR_VALUES", code = "^[a-zA-Z0-9\\-_.:/ ]{1,256}$"))]
+    pub acr_values: Option<String>,
+    /// A JSON array of Rich Authorization Request details (RFC 9396)
+    #[serde(default, deserialize_with = "deserialize_authorization_details")]
+    pub authorization_details: Option<String>,
+    /// The protected resource the access token is requested for (RFC 8707)
+    ///
+    /// Validation: `^[a-zA-Z][a-zA-Z0-9+.\-]*:[a-zA-Z0-9,.:/_\-&?=~!$'()*+%]+$`
+    #[validate(regex(
+        path = "RE_RESOURCE",
+        code = "^[a-zA-Z][a-zA-Z0-9+.\\-]*:[a-zA-Z0-9,.:/_\\-&?=~!$'()*+%]+$"
+    ))]
+    pub resource: Option<String>,
+    /// A signed request object (RFC 9101). If given, all other parameters are taken from its
+    /// claims.
+    ///
+    /// Validation: `^[a-zA-Z0-9-._~+/]+=*$`
+    #[validate(regex(path = "RE_TOKEN_68", code = "^[a-zA-Z0-9-._~+/]+=*$"))]
+    pub request: Option<String>,
+}
+
+impl AuthRequest {
+    /// Returns `true` if the space separated `prompt` contains the given value
+    pub fn has_prompt(&self, value: &str) -> bool {
+        prompt_contains(&self.prompt, value)
+    }
+
+    /// `prompt=none` must not be combined with any other value
+    pub fn validate_prompt(&self) -> Result<(), ErrorResponse> {
+        if self.has_prompt("none") && self.prompt.as_deref() != Some("none") {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "'prompt=none' must not be combined with other values".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn requires_mfa(&self) -> bool {
+        acr_values_require_mfa(&self.acr_values)
+    }
+}
+
+/// Returns `true` if the space separated `prompt` contains the given value
+pub fn prompt_contains(prompt: &Option<String>, value: &str) -> bool {
+    prompt
+        .as_deref()
+        .map(|p| p.split(' ').any(|v| v == value))
+        .unwrap_or(false)
+}
+
+/// The `acr_values` are a list of acceptable values in order of preference. MFA is only demanded,
+/// if `mfa` is given without `pwd` as an alternative.
+pub fn acr_values_require_mfa(acr_values: &Option<String>) -> bool {
+    acr_values
+        .as_deref()
+        .map(|values| {
+            let mut values = values.split(' ');
+            values.clone().any(|v| v == "mfa") && !values.any(|v| v == "pwd")
+        })
+        .unwrap_or(false)
+}
+
+/// An authorization request referencing a former Pushed Authorization Request, or carrying a
+/// signed request object (RFC 9101) either by value or by reference.
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+pub struct AuthRequestUri {
+    /// Validation: `^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%]{2,128}$`
+    #[validate(regex(
+        path = "RE_CLIENT_ID_EPHEMERAL",
+        code = "^[a-zA-Z0-9,.:/_\\-&?=~#!$'()*+%]{2,128}$"
+    ))]
+    pub client_id: String,
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub request_uri: Option<String>,
+    /// Validation: `^[a-zA-Z0-9-._~+/]+=*$`
+    #[validate(regex(path = "RE_TOKEN_68", code = "^[a-zA-Z0-9-._~+/]+=*$"))]
+    pub request: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -145,6 +248,97 @@ impl ColorsRequest {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct DeviceRequest {
+    /// Validation: `^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%]{2,128}$`
+    #[validate(regex(
+        path = "RE_CLIENT_ID_EPHEMERAL",
+        code = "^[a-zA-Z0-9,.:/_\\-&?=~#!$'()*+%]{2,128}$"
+    ))]
+    pub client_id: Option<String>,
+    /// Validation: `[a-zA-Z0-9]`
+    #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
+    pub client_secret: Option<String>,
+    /// Validation: `^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$`
+    #[validate(regex(
+        path = "RE_CLIENT_ASSERTION_TYPE",
+        code = "^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$"
+    ))]
+    pub client_assertion_type: Option<String>,
+    /// Client authentication with a signed JWT from RFC 7523 instead of the `client_secret`
+    ///
+    /// Validation: `^[a-zA-Z0-9-._~+/]+=*$`
+    #[validate(regex(path = "RE_TOKEN_68", code = "^[a-zA-Z0-9-._~+/]+=*$"))]
+    pub client_assertion: Option<String>,
+    /// Validation: `[a-zA-Z0-9À-ÿ-s]{2,128}`
+    #[validate(regex(path = "RE_CLIENT_NAME", code = "[a-zA-Z0-9À-ÿ-s]{2,128}"))]
+    pub scope: Option<String>,
+}
+
+impl DeviceRequest {
+    pub fn try_get_client_id_secret(
+        &self,
+        req: &HttpRequest,
+    ) -> Result<(String, Option<String>), ErrorResponse> {
+        client_id_secret_from_req(req, &self.client_id, &self.client_secret)
+            .and_then(|creds| client_id_from_assertion(creds, &self.client_assertion))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ConsentRequest {
+    /// Validation: `^[a-zA-Z0-9]{48}$`
+    #[validate(regex(path = "RE_ALNUM_48", code = "^[a-zA-Z0-9]{48}$"))]
+    pub id: String,
+    /// If `false`, the client will receive an `access_denied` error
+    pub accept: bool,
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+pub struct ConsentParams {
+    /// Validation: `^[a-zA-Z0-9]{48}$`
+    #[validate(regex(path = "RE_ALNUM_48", code = "^[a-zA-Z0-9]{48}$"))]
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct DeviceVerifyRequest {
+    /// The `user_code` is case-insensitive and may be grouped with dashes or spaces.
+    ///
+    /// Validation: `^[BCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz]+([\-\s][BCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz]+)*$`
+    #[validate(
+        length(min = 4, max = 32),
+        regex(
+            path = "RE_USER_CODE",
+            code = "^[BCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz]+([\\-\\s][BCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz]+)*$"
+        )
+    )]
+    pub user_code: String,
+    /// If `false`, the device authorization will be denied
+    pub accept: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct EmailLoginFinishRequest {
+    /// The one-time code from the E-Mail
+    ///
+    /// Validation: `[0-9]{6}`
+    #[validate(regex(path = "RE_TOTP", code = "[0-9]{6}"))]
+    pub code: Option<String>,
+    /// The ID of the magic link from the E-Mail, if the link has been clicked instead
+    ///
+    /// Validation: `^[a-zA-Z0-9]{64}$`
+    #[validate(regex(path = "RE_ALNUM_64", code = "^[a-zA-Z0-9]{64}$"))]
+    pub magic_link_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+pub struct EmailLoginParams {
+    /// Validation: `^[a-zA-Z0-9]{64}$`
+    #[validate(regex(path = "RE_ALNUM_64", code = "^[a-zA-Z0-9]{64}$"))]
+    pub id: String,
+}
+
 #[derive(Debug, Deserialize, Validate, ToSchema, IntoParams)]
 pub struct EncKeyMigrateRequest {
     #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
@@ -166,7 +360,7 @@ pub struct EphemeralClientRequest {
     /// Validation: `Vec<^[a-zA-Z0-9,.:/_\\-&?=~#!$'()*+%]+$>`
     #[validate(custom(function = "validate_vec_uri"))]
     pub post_logout_redirect_uris: Option<Vec<String>>,
-    /// Validation: `Vec<^(authorization_code|client_credentials|password|refresh_token)$>`
+    /// Validation: `Vec<^(authorization_code|client_credentials|password|refresh_token|urn:ietf:params:oauth:grant-type:device_code)$>`
     #[validate(custom(function = "validate_vec_grant_type"))]
     pub grant_types: Vec<String>,
     /// Validation: `60 <= access_token_lifetime <= 86400`
@@ -177,9 +371,9 @@ pub struct EphemeralClientRequest {
     pub scope: Option<String>,
     pub require_auth_time: Option<bool>,
 
-    /// Validation: `^(RS256|RS384|RS512|EdDSA)$`
+    /// Validation: `^(RS256|RS384|RS512|EdDSA|ES256|ES384)$`
     pub access_token_signed_response_alg: Option<JwkKeyPairAlg>,
-    /// Validation: `^(RS256|RS384|RS512|EdDSA)$`
+    /// Validation: `^(RS256|RS384|RS512|EdDSA|ES256|ES384)$`
     pub id_token_signed_response_alg: Option<JwkKeyPairAlg>,
 }
 
@@ -217,6 +411,8 @@ pub struct I18nRequest {
 pub enum I18nContent {
     Account,
     Authorize,
+    Consent,
+    Device,
     EmailChangeConfirm,
     Error,
     Index,
@@ -257,6 +453,47 @@ pub struct LoginRequest {
     /// Validation: `[a-zA-Z0-9]`
     #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
     pub code_challenge_method: Option<String>,
+    /// The `acr_values` from the authorization request
+    ///
+    /// Validation: `^[a-zA-Z0-9\-_.:/ ]{1,256}$`
+    #[validate(regex(path = "RE_ACR_VALUES", code = "^[a-zA-Z0-9\\-_.:/ ]{1,256}$"))]
+    pub acr_values: Option<String>,
+    /// The `authorization_details` from the authorization request
+    pub authorization_details: Option<String>,
+    /// The `resource` from the authorization request
+    ///
+    /// Validation: `^[a-zA-Z][a-zA-Z0-9+.\-]*:[a-zA-Z0-9,.:/_\-&?=~!$'()*+%]+$`
+    #[validate(regex(
+        path = "RE_RESOURCE",
+        code = "^[a-zA-Z][a-zA-Z0-9+.\\-]*:[a-zA-Z0-9,.:/_\\-&?=~!$'()*+%]+$"
+    ))]
+    pub resource: Option<String>,
+    /// The `prompt` from the authorization request. Only `consent` is relevant at this point.
+    ///
+    /// Validation: `^(none|login|consent|select_account)( (none|login|consent|select_account))*$`
+    #[validate(regex(
+        path = "RE_PROMPT",
+        code = "^(none|login|consent|select_account)( (none|login|consent|select_account))*$"
+    ))]
+    pub prompt: Option<String>,
+}
+
+impl LoginRequest {
+    /// Replaces all params of the authorization request with the ones from a request, which
+    /// has been resolved server-side from a PAR or a signed request object.
+    pub fn apply_auth_request(&mut self, auth_request: AuthRequest) {
+        self.client_id = auth_request.client_id;
+        self.redirect_uri = auth_request.redirect_uri;
+        self.scopes = Some(auth_request.scope.split(' ').map(String::from).collect());
+        self.state = auth_request.state;
+        self.nonce = auth_request.nonce;
+        self.code_challenge = auth_request.code_challenge;
+        self.code_challenge_method = auth_request.code_challenge_method;
+        self.acr_values = auth_request.acr_values;
+        self.authorization_details = auth_request.authorization_details;
+        self.resource = auth_request.resource;
+        self.prompt = auth_request.prompt;
+    }
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -285,6 +522,46 @@ pub struct LoginRefreshRequest {
     /// Validation: `[a-zA-Z0-9]`
     #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
     pub code_challenge_method: Option<String>,
+    /// The `acr_values` from the authorization request
+    ///
+    /// Validation: `^[a-zA-Z0-9\-_.:/ ]{1,256}$`
+    #[validate(regex(path = "RE_ACR_VALUES", code = "^[a-zA-Z0-9\\-_.:/ ]{1,256}$"))]
+    pub acr_values: Option<String>,
+    /// The `authorization_details` from the authorization request
+    pub authorization_details: Option<String>,
+    /// The `resource` from the authorization request
+    ///
+    /// Validation: `^[a-zA-Z][a-zA-Z0-9+.\-]*:[a-zA-Z0-9,.:/_\-&?=~!$'()*+%]+$`
+    #[validate(regex(
+        path = "RE_RESOURCE",
+        code = "^[a-zA-Z][a-zA-Z0-9+.\\-]*:[a-zA-Z0-9,.:/_\\-&?=~!$'()*+%]+$"
+    ))]
+    pub resource: Option<String>,
+    /// The `prompt` from the authorization request. Only `consent` is relevant at this point.
+    ///
+    /// Validation: `^(none|login|consent|select_account)( (none|login|consent|select_account))*$`
+    #[validate(regex(
+        path = "RE_PROMPT",
+        code = "^(none|login|consent|select_account)( (none|login|consent|select_account))*$"
+    ))]
+    pub prompt: Option<String>,
+}
+
+impl LoginRefreshRequest {
+    /// Same as [LoginRequest::apply_auth_request](LoginRequest::apply_auth_request)
+    pub fn apply_auth_request(&mut self, auth_request: AuthRequest) {
+        self.client_id = auth_request.client_id;
+        self.redirect_uri = auth_request.redirect_uri;
+        self.scopes = Some(auth_request.scope.split(' ').map(String::from).collect());
+        self.state = auth_request.state;
+        self.nonce = auth_request.nonce;
+        self.code_challenge = auth_request.code_challenge;
+        self.code_challenge_method = auth_request.code_challenge_method;
+        self.acr_values = auth_request.acr_values;
+        self.authorization_details = auth_request.authorization_details;
+        self.resource = auth_request.resource;
+        self.prompt = auth_request.prompt;
+    }
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema, IntoParams)]
@@ -345,25 +622,101 @@ pub struct DynamicClientRequest {
     /// Validation: `Vec<^[a-zA-Z0-9,.:/_\\-&?=~#!$'()*+%]+$>`
     #[validate(custom(function = "validate_vec_uri"))]
     pub redirect_uris: Vec<String>,
-    /// Validation: `Vec<^(authorization_code|client_credentials|password|refresh_token)$>`
+    /// Validation: `Vec<^(authorization_code|client_credentials|password|refresh_token|urn:ietf:params:oauth:grant-type:device_code|urn:ietf:params:oauth:grant-type:token-exchange)$>`
     #[validate(custom(function = "validate_vec_flows"))]
     pub grant_types: Vec<String>,
     /// Validation: `[a-zA-Z0-9À-ÿ-\\s]{2,128}`
     #[validate(regex(path = "RE_CLIENT_NAME", code = "[a-zA-Z0-9À-ÿ-\\s]{2,128}"))]
     pub client_name: Option<String>,
-    /// Validation: `^(RS256|RS384|RS512|EdDSA)$`
+    /// Validation: `^(RS256|RS384|RS512|EdDSA|ES256|ES384)$`
     pub id_token_signed_response_alg: Option<JwkKeyPairAlg>,
-    /// Validation: `^(client_secret_post|client_secret_basic|none)$`
+    /// Validation: `^(client_secret_post|client_secret_basic|client_secret_jwt|private_key_jwt|tls_client_auth|self_signed_tls_client_auth|none)$`
     #[validate(regex(
         path = "RE_TOKEN_ENDPOINT_AUTH_METHOD",
-        code = "client_secret_post|client_secret_basic|none"
+        code = "client_secret_post|client_secret_basic|client_secret_jwt|private_key_jwt|tls_client_auth|self_signed_tls_client_auth|none"
     ))]
     pub token_endpoint_auth_method: Option<String>,
-    /// Validation: `^(RS256|RS384|RS512|EdDSA)$`
+    /// Validation: `^(RS256|RS384|RS512|EdDSA|ES256|ES384)$`
     pub token_endpoint_auth_signing_alg: Option<JwkKeyPairAlg>,
+    /// The public keys for `private_key_jwt` client authentication
+    ///
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub jwks_uri: Option<String>,
+    /// The URI which receives the `logout_token` for OIDC Back-Channel Logout
+    ///
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`, `https` only
+    #[validate(
+        regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"),
+        custom(function = "validate_logout_uri")
+    )]
+    pub backchannel_logout_uri: Option<String>,
+    /// The URI which will be loaded inside a hidden iframe for OIDC Front-Channel Logout
+    ///
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`, `https` only
+    #[validate(
+        regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"),
+        custom(function = "validate_logout_uri")
+    )]
+    pub frontchannel_logout_uri: Option<String>,
+    /// `pairwise` hands out a different `sub` for each sector. Defaults to `public`.
+    ///
+    /// Validation: `^(public|pairwise)$`
+    #[validate(regex(path = "RE_SUBJECT_TYPE", code = "^(public|pairwise)$"))]
+    pub subject_type: Option<String>,
+    /// The URI of a JSON array with all redirect URIs of the sector for pairwise subjects
+    ///
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub sector_identifier_uri: Option<String>,
+    /// The JWE `alg` for encrypted ID tokens. The client's public key is taken from its JWKS.
+    ///
+    /// Validation: `^(RSA-OAEP-256|ECDH-ES)$`
+    #[validate(regex(path = "RE_JWE_ALG", code = "^(RSA-OAEP-256|ECDH-ES)$"))]
+    pub id_token_encrypted_response_alg: Option<String>,
+    /// The JWE `enc` for encrypted ID tokens. Defaults to `A128CBC-HS256`.
+    ///
+    /// Validation: `^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$`
+    #[validate(regex(
+        path = "RE_JWE_ENC",
+        code = "^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$"
+    ))]
+    pub id_token_encrypted_response_enc: Option<String>,
+    /// The JWE `alg` for encrypted userinfo responses
+    ///
+    /// Validation: `^(RSA-OAEP-256|ECDH-ES)$`
+    #[validate(regex(path = "RE_JWE_ALG", code = "^(RSA-OAEP-256|ECDH-ES)$"))]
+    pub userinfo_encrypted_response_alg: Option<String>,
+    /// The JWE `enc` for encrypted userinfo responses. Defaults to `A128CBC-HS256`.
+    ///
+    /// Validation: `^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$`
+    #[validate(regex(
+        path = "RE_JWE_ENC",
+        code = "^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$"
+    ))]
+    pub userinfo_encrypted_response_enc: Option<String>,
+    /// Returns the userinfo as a signed JWT, if set
+    ///
+    /// Validation: `^(RS256|RS384|RS512|EdDSA|ES256|ES384)$`
+    pub userinfo_signed_response_alg: Option<JwkKeyPairAlg>,
+    /// The `type`s of the `authorization_details` this client may request (RFC 9396)
+    ///
+    /// Validation: `Vec<^[a-zA-Z0-9\-_.:/]{1,128}$>`
+    #[validate(custom(function = "validate_vec_authorization_details_types"))]
+    pub authorization_details_types: Option<Vec<String>>,
+    /// The expected subject DN of the client certificate for `tls_client_auth`
+    ///
+    /// Validation: `^[a-zA-Z0-9À-ÿ\\s,.:/_\\-=+@*'()]{1,256}$`
+    #[validate(regex(
+        path = "RE_TLS_SUBJECT_DN",
+        code = "^[a-zA-Z0-9À-ÿ\\s,.:/_\\-=+@*'()]{1,256}$"
+    ))]
+    pub tls_client_auth_subject_dn: Option<String>,
+    /// Binds the issued access tokens to the client certificate
+    #[serde(default)]
+    pub tls_client_certificate_bound_access_tokens: bool,
     // Rauthy will only accept the following defaults
     // `response_type=code`
-    // `subject_type=public`
     // `require_auth_time=true` (always added by Rauthy anyway)
     //
     // The following must never be accepted for security reasons,
@@ -376,14 +729,7 @@ pub struct DynamicClientRequest {
     // Unsupported values:
     // - application_type (may come in the future)
     // - contacts (may come in the future)
-    // - jwks_uri
     // - jwks
-    // - sector_identifier_uri
-    // - id_token_encrypted_response_alg
-    // - id_token_encrypted_response_enc
-    // - userinfo_signed_response_alg
-    // - userinfo_encrypted_response_alg
-    // - userinfo_encrypted_response_enc
     // - request_object_signing_alg
     // - request_object_encryption_alg
     // - request_object_encryption_enc
@@ -403,6 +749,117 @@ pub struct NewGroupRequest {
     pub group: String,
 }
 
+/// Pushed Authorization Request as defined in RFC 9126. Accepts the same parameters as the
+/// [AuthRequest](AuthRequest) plus the client authentication.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ParRequest {
+    /// Validation: `^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%]{2,128}$`
+    #[validate(regex(
+        path = "RE_CLIENT_ID_EPHEMERAL",
+        code = "^[a-zA-Z0-9,.:/_\\-&?=~#!$'()*+%]{2,128}$"
+    ))]
+    pub client_id: Option<String>,
+    /// Validation: `[a-zA-Z0-9]`
+    #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
+    pub client_secret: Option<String>,
+    /// Validation: `^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$`
+    #[validate(regex(
+        path = "RE_CLIENT_ASSERTION_TYPE",
+        code = "^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$"
+    ))]
+    pub client_assertion_type: Option<String>,
+    /// Client authentication with a signed JWT from RFC 7523 instead of the `client_secret`
+    ///
+    /// Validation: `^[a-zA-Z0-9-._~+/]+=*$`
+    #[validate(regex(path = "RE_TOKEN_68", code = "^[a-zA-Z0-9-._~+/]+=*$"))]
+    pub client_assertion: Option<String>,
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub redirect_uri: String,
+    /// Validation: `[a-z0-9-_/]{2,128}`
+    #[validate(regex(path = "RE_LOWERCASE", code = "[a-z0-9-_/]{2,128}"))]
+    pub response_type: String,
+    /// Validation: `[a-zA-Z0-9À-ÿ-s]{2,128}`
+    #[validate(regex(path = "RE_CLIENT_NAME", code = "[a-zA-Z0-9À-ÿ-s]{2,128}"))]
+    #[serde(default = "default_scope")]
+    pub scope: String,
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub state: Option<String>,
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub code_challenge: Option<String>,
+    /// Validation: `[a-zA-Z0-9]`
+    #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
+    pub code_challenge_method: Option<String>,
+    #[validate(range(min = 0))]
+    pub max_age: Option<i64>,
+    /// Space separated, `none` must not be combined with other values
+    ///
+    /// Validation: `^(none|login|consent|select_account)( (none|login|consent|select_account))*$`
+    #[validate(regex(
+        path = "RE_PROMPT",
+        code = "^(none|login|consent|select_account)( (none|login|consent|select_account))*$"
+    ))]
+    pub prompt: Option<String>,
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub nonce: Option<String>,
+    /// Pre-fills the email on the login form
+    ///
+    /// Validation: `email`
+    #[validate(email)]
+    pub login_hint: Option<String>,
+    /// Space separated. Only `pwd` and `mfa` are known, `mfa` alone demands an MFA login.
+    ///
+    /// Validation: `^[a-zA-Z0-9\-_.:/ ]{1,256}$`
+    #[validate(regex(path = "RE_ACR_VALUES", code = "^[a-zA-Z0-9\\-_.:/ ]{1,256}$"))]
+    pub acr_values: Option<String>,
+    /// A JSON array of Rich Authorization Request details (RFC 9396)
+    #[serde(default, deserialize_with = "deserialize_authorization_details")]
+    pub authorization_details: Option<String>,
+    /// The protected resource the access token is requested for (RFC 8707)
+    ///
+    /// Validation: `^[a-zA-Z][a-zA-Z0-9+.\-]*:[a-zA-Z0-9,.:/_\-&?=~!$'()*+%]+$`
+    #[validate(regex(
+        path = "RE_RESOURCE",
+        code = "^[a-zA-Z][a-zA-Z0-9+.\\-]*:[a-zA-Z0-9,.:/_\\-&?=~!$'()*+%]+$"
+    ))]
+    pub resource: Option<String>,
+}
+
+impl ParRequest {
+    pub fn try_get_client_id_secret(
+        &self,
+        req: &HttpRequest,
+    ) -> Result<(String, Option<String>), ErrorResponse> {
+        client_id_secret_from_req(req, &self.client_id, &self.client_secret)
+            .and_then(|creds| client_id_from_assertion(creds, &self.client_assertion))
+    }
+
+    /// Converts this request into an [AuthRequest](AuthRequest) for the already authenticated
+    /// client.
+    pub fn into_auth_request(self, client_id: String) -> AuthRequest {
+        AuthRequest {
+            client_id,
+            redirect_uri: self.redirect_uri,
+            response_type: self.response_type,
+            scope: self.scope,
+            state: self.state,
+            code_challenge: self.code_challenge,
+            code_challenge_method: self.code_challenge_method,
+            max_age: self.max_age,
+            prompt: self.prompt,
+            nonce: self.nonce,
+            login_hint: self.login_hint,
+            acr_values: self.acr_values,
+            authorization_details: self.authorization_details,
+            resource: self.resource,
+            request: None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct PasswordHashTimesRequest {
     #[validate(range(min = 500))]
@@ -639,6 +1096,57 @@ pub struct PasskeyRequest {
     pub name: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct RecoveryCodeAuthRequest {
+    /// The code from the login response
+    ///
+    /// Validation: `[a-zA-Z0-9]{48}`
+    #[validate(regex(path = "RE_ALNUM_48", code = "[a-zA-Z0-9]{48}"))]
+    pub code: String,
+    /// The recovery code with or without the dashes
+    ///
+    /// Validation: `[a-zA-Z2-7]{4}(-?[a-zA-Z2-7]{4}){3}`
+    #[validate(regex(
+        path = "RE_RECOVERY_CODE",
+        code = "[a-zA-Z2-7]{4}(-?[a-zA-Z2-7]{4}){3}"
+    ))]
+    pub recovery_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct RecoveryCodesRequest {
+    /// Re-authentication with the current password
+    pub password_current: Option<String>,
+    /// Re-authentication with the code from a finished Webauthn request
+    pub mfa_code: Option<String>,
+    /// Re-authentication with a code from the registered authenticator app
+    ///
+    /// Validation: `[0-9]{6}`
+    #[validate(regex(path = "RE_TOTP", code = "[0-9]{6}"))]
+    pub totp_current: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ResourceRequest {
+    /// Validation: `^[a-zA-Z0-9À-ÿ-\\s]{2,128}$`
+    #[validate(regex(path = "RE_CLIENT_NAME", code = "^[a-zA-Z0-9À-ÿ-\\s]{2,128}$"))]
+    pub name: String,
+    /// The absolute URI of the resource, which will be the `aud` of its access tokens. It must
+    /// not contain a fragment.
+    ///
+    /// Validation: `^[a-zA-Z][a-zA-Z0-9+.\-]*:[a-zA-Z0-9,.:/_\-&?=~!$'()*+%]+$`
+    #[validate(regex(
+        path = "RE_RESOURCE",
+        code = "^[a-zA-Z][a-zA-Z0-9+.\\-]*:[a-zA-Z0-9,.:/_\\-&?=~!$'()*+%]+$"
+    ))]
+    pub uri: String,
+    /// The scopes, which are allowed in access tokens for this resource
+    ///
+    /// Validation: `Vec<^[a-z0-9-_/]{2,128}$>`
+    #[validate(custom(function = "validate_vec_lowercase"), length(min = 1))]
+    pub scopes: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 pub struct ScopeRequest {
     /// Validation: `^[a-z0-9-_/,]{2,32}$`
@@ -654,8 +1162,8 @@ pub struct ScopeRequest {
 
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct TokenRequest {
-    /// Validation: `^[a-z0-9-_/]{2,128}$`
-    #[validate(regex(path = "RE_LOWERCASE", code = "^[a-z0-9-_/]{2,128}$"))]
+    /// Validation: `^[a-z0-9-_/:.]{2,128}$`
+    #[validate(regex(path = "RE_GRANT_TYPE", code = "^[a-z0-9-_/:.]{2,128}$"))]
     pub grant_type: String,
     /// Validation: `[a-zA-Z0-9]`
     #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
@@ -682,6 +1190,66 @@ pub struct TokenRequest {
     /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
     #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
     pub refresh_token: Option<String>,
+    /// Validation: `[a-zA-Z0-9]`
+    #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
+    pub device_code: Option<String>,
+    /// Validation: `^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$`
+    #[validate(regex(
+        path = "RE_CLIENT_ASSERTION_TYPE",
+        code = "^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$"
+    ))]
+    pub client_assertion_type: Option<String>,
+    /// Client authentication with a signed JWT from RFC 7523 instead of the `client_secret`
+    ///
+    /// Validation: `^[a-zA-Z0-9-._~+/]+=*$`
+    #[validate(regex(path = "RE_TOKEN_68", code = "^[a-zA-Z0-9-._~+/]+=*$"))]
+    pub client_assertion: Option<String>,
+    /// The token to exchange with the `urn:ietf:params:oauth:grant-type:token-exchange` grant
+    ///
+    /// Validation: `^[a-zA-Z0-9-._~+/]+=*$`
+    #[validate(regex(path = "RE_TOKEN_68", code = "^[a-zA-Z0-9-._~+/]+=*$"))]
+    pub subject_token: Option<String>,
+    /// Validation: `^urn:ietf:params:oauth:token-type:(access_token|id_token|jwt)$`
+    #[validate(regex(
+        path = "RE_TOKEN_TYPE",
+        code = "^urn:ietf:params:oauth:token-type:(access_token|id_token|jwt)$"
+    ))]
+    pub subject_token_type: Option<String>,
+    /// Optional token of the acting party for a delegation Token Exchange
+    ///
+    /// Validation: `^[a-zA-Z0-9-._~+/]+=*$`
+    #[validate(regex(path = "RE_TOKEN_68", code = "^[a-zA-Z0-9-._~+/]+=*$"))]
+    pub actor_token: Option<String>,
+    /// Validation: `^urn:ietf:params:oauth:token-type:(access_token|id_token|jwt)$`
+    #[validate(regex(
+        path = "RE_TOKEN_TYPE",
+        code = "^urn:ietf:params:oauth:token-type:(access_token|id_token|jwt)$"
+    ))]
+    pub actor_token_type: Option<String>,
+    /// Validation: `^urn:ietf:params:oauth:token-type:(access_token|id_token|jwt)$`
+    #[validate(regex(
+        path = "RE_TOKEN_TYPE",
+        code = "^urn:ietf:params:oauth:token-type:(access_token|id_token|jwt)$"
+    ))]
+    pub requested_token_type: Option<String>,
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub audience: Option<String>,
+    /// Validation: `^[a-z0-9-_/\s]{2,128}$`
+    #[validate(regex(path = "RE_LOWERCASE_SPACE", code = "^[a-z0-9-_/\\s]{2,128}$"))]
+    pub scope: Option<String>,
+    /// A JSON array of Rich Authorization Request details (RFC 9396). For the
+    /// `authorization_code` flow, this may only narrow down the already granted details.
+    pub authorization_details: Option<String>,
+    /// The protected resource the access token is requested for (RFC 8707). With the
+    /// `refresh_token` grant, tokens for other resources can be requested as well.
+    ///
+    /// Validation: `^[a-zA-Z][a-zA-Z0-9+.\-]*:[a-zA-Z0-9,.:/_\-&?=~!$'()*+%]+$`
+    #[validate(regex(
+        path = "RE_RESOURCE",
+        code = "^[a-zA-Z][a-zA-Z0-9+.\\-]*:[a-zA-Z0-9,.:/_\\-&?=~!$'()*+%]+$"
+    ))]
+    pub resource: Option<String>,
 }
 
 impl TokenRequest {
@@ -690,35 +1258,138 @@ impl TokenRequest {
         &self,
         req: &HttpRequest,
     ) -> Result<(String, Option<String>), ErrorResponse> {
-        let auth_header = req.headers().get(header::AUTHORIZATION).map(|h| {
-            let (_, b64) = h
-                .to_str()
-                .unwrap_or_default()
-                .split_once(' ')
-                .unwrap_or(("", ""));
-            b64
-        });
+        client_id_secret_from_req(req, &self.client_id, &self.client_secret)
+            .and_then(|creds| client_id_from_assertion(creds, &self.client_assertion))
+    }
+}
+
+/// With a client assertion, the `client_id` is optional and taken from its `sub`.
+fn client_id_from_assertion(
+    (client_id, client_secret): (String, Option<String>),
+    client_assertion: &Option<String>,
+) -> Result<(String, Option<String>), ErrorResponse> {
+    match client_assertion {
+        Some(assertion) if client_id.is_empty() => Ok((
+            ClientAssertion::client_id_unverified(assertion)?,
+            client_secret,
+        )),
+        _ => Ok((client_id, client_secret)),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TokenRevocationRequest {
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub token: String,
+    /// Validation: `^(access_token|refresh_token)$`
+    #[validate(regex(path = "RE_TOKEN_TYPE_HINT", code = "^(access_token|refresh_token)$"))]
+    pub token_type_hint: Option<String>,
+    /// Validation: `^[a-zA-Z0-9,.:/_\-&?=~#!$'()*+%]{2,128}$`
+    #[validate(regex(
+        path = "RE_CLIENT_ID_EPHEMERAL",
+        code = "^[a-zA-Z0-9,.:/_\\-&?=~#!$'()*+%]{2,128}$"
+    ))]
+    pub client_id: Option<String>,
+    /// Validation: `[a-zA-Z0-9]`
+    #[validate(regex(path = "RE_ALNUM", code = "[a-zA-Z0-9]"))]
+    pub client_secret: Option<String>,
+    /// Validation: `^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$`
+    #[validate(regex(
+        path = "RE_CLIENT_ASSERTION_TYPE",
+        code = "^urn:ietf:params:oauth:client-assertion-type:jwt-bearer$"
+    ))]
+    pub client_assertion_type: Option<String>,
+    /// Client authentication with a signed JWT from RFC 7523 instead of the `client_secret`
+    ///
+    /// Validation: `^[a-zA-Z0-9-._~+/]+=*$`
+    #[validate(regex(path = "RE_TOKEN_68", code = "^[a-zA-Z0-9-._~+/]+=*$"))]
+    pub client_assertion: Option<String>,
+}
+
+impl TokenRevocationRequest {
+    pub fn try_get_client_id_secret(
+        &self,
+        req: &HttpRequest,
+    ) -> Result<(String, Option<String>), ErrorResponse> {
+        client_id_secret_from_req(req, &self.client_id, &self.client_secret)
+            .and_then(|creds| client_id_from_assertion(creds, &self.client_assertion))
+    }
+}
+
+/// Extracts the client credentials either from a Basic Authorization header or from the
+/// given body values, if no header exists.
+fn client_id_secret_from_req(
+    req: &HttpRequest,
+    client_id: &Option<String>,
+    client_secret: &Option<String>,
+) -> Result<(String, Option<String>), ErrorResponse> {
+    let auth_header = req.headers().get(header::AUTHORIZATION).map(|h| {
+        let (_, b64) = h
+            .to_str()
+            .unwrap_or_default()
+            .split_once(' ')
+            .unwrap_or(("", ""));
+        b64
+    });
 
-        if let Some(header) = auth_header {
-            let decoded = String::from_utf8(base64_decode(header)?)?;
-            match decoded.split_once(':') {
-                None => Err(ErrorResponse::new(
-                    ErrorResponseType::BadRequest,
-                    "Bad Authorization header".to_string(),
-                )),
-                Some((client_id, client_secret)) => {
-                    Ok((client_id.to_string(), Some(client_secret.to_string())))
-                }
+    if let Some(header) = auth_header {
+        let decoded = String::from_utf8(base64_decode(header)?)?;
+        match decoded.split_once(':') {
+            None => Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "Bad Authorization header".to_string(),
+            )),
+            Some((client_id, client_secret)) => {
+                Ok((client_id.to_string(), Some(client_secret.to_string())))
             }
-        } else {
-            Ok((
-                self.client_id.clone().unwrap_or_default(),
-                self.client_secret.clone(),
-            ))
         }
+    } else {
+        Ok((client_id.clone().unwrap_or_default(), client_secret.clone()))
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TotpAuthRequest {
+    /// The code from the login response
+    ///
+    /// Validation: `[a-zA-Z0-9]{48}`
+    #[validate(regex(path = "RE_ALNUM_48", code = "[a-zA-Z0-9]{48}"))]
+    pub code: String,
+    /// Validation: `[0-9]{6}`
+    #[validate(regex(path = "RE_TOTP", code = "[0-9]{6}"))]
+    pub totp: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TotpRequest {
+    /// Validation: `[0-9]{6}`
+    #[validate(regex(path = "RE_TOTP", code = "[0-9]{6}"))]
+    pub totp: String,
+    /// Re-authentication with the current password
+    pub password_current: Option<String>,
+    /// Re-authentication with the code from a finished Webauthn request
+    pub mfa_code: Option<String>,
+    /// Re-authentication with a code from an already registered authenticator app
+    ///
+    /// Validation: `[0-9]{6}`
+    #[validate(regex(path = "RE_TOTP", code = "[0-9]{6}"))]
+    pub totp_current: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TotpDeleteRequest {
+    /// Re-authentication with the current password
+    pub password_current: Option<String>,
+    /// Re-authentication with the code from a finished Webauthn request
+    pub mfa_code: Option<String>,
+    /// Re-authentication with a code from the registered authenticator app
+    ///
+    /// Validation: `[0-9]{6}`
+    #[validate(regex(path = "RE_TOTP", code = "[0-9]{6}"))]
+    pub totp_current: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Validate, ToSchema)]
 pub struct TokenValidationRequest {
     /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
@@ -745,12 +1416,12 @@ pub struct UpdateClientRequest {
     #[validate(custom(function = "validate_vec_uri"))]
     pub allowed_origins: Option<Vec<String>>,
     pub enabled: bool,
-    /// Validation: `Vec<^(authorization_code|client_credentials|password|refresh_token)$>`
+    /// Validation: `Vec<^(authorization_code|client_credentials|password|refresh_token|urn:ietf:params:oauth:grant-type:device_code|urn:ietf:params:oauth:grant-type:token-exchange)$>`
     #[validate(custom(function = "validate_vec_flows"))]
     pub flows_enabled: Vec<String>,
-    /// Validation: `^(RS256|RS384|RS512|EdDSA)$`
+    /// Validation: `^(RS256|RS384|RS512|EdDSA|ES256|ES384)$`
     pub access_token_alg: JwkKeyPairAlg,
-    /// Validation: `^(RS256|RS384|RS512|EdDSA)$`
+    /// Validation: `^(RS256|RS384|RS512|EdDSA|ES256|ES384)$`
     pub id_token_alg: JwkKeyPairAlg,
     pub refresh_token: bool,
     /// Validation: `10 <= auth_code_lifetime <= 300`
@@ -769,6 +1440,137 @@ pub struct UpdateClientRequest {
     #[validate(custom(function = "validate_vec_challenge"))]
     pub challenges: Option<Vec<String>>,
     pub force_mfa: bool,
+    /// If set to `true`, authorization requests are only accepted via a former Pushed
+    /// Authorization Request
+    #[serde(default)]
+    pub require_par: bool,
+    /// JSON encoded JWKS with the public keys for the validation of signed request objects
+    ///
+    /// Validation: `length <= 16384`
+    #[validate(length(max = 16384))]
+    pub jwks: Option<String>,
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub jwks_uri: Option<String>,
+    /// If set to `true`, authorization requests are only accepted with a signed request object
+    #[serde(default)]
+    pub require_request_object: bool,
+    /// The audiences, which may be requested with a Token Exchange
+    ///
+    /// Validation: `Vec<^[a-zA-Z0-9,.:/_\\-&?=~#!$'()*+%]+$>`
+    #[validate(custom(function = "validate_vec_uri"))]
+    pub token_exchange_audiences: Option<Vec<String>>,
+    /// The scopes, which may be requested with a Token Exchange
+    ///
+    /// Validation: `Vec<^[a-z0-9-_/]{2,128}$>`
+    #[validate(custom(function = "validate_vec_lowercase"))]
+    pub token_exchange_scopes: Option<Vec<String>>,
+    /// The subject token types, which are accepted for a Token Exchange
+    ///
+    /// Validation: `Vec<^(access_token|id_token|jwt)$>`
+    #[validate(custom(function = "validate_vec_token_exchange_subject_types"))]
+    pub token_exchange_subject_types: Option<Vec<String>>,
+    /// The URI which receives the `logout_token` for OIDC Back-Channel Logout
+    ///
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`, `https` only
+    #[validate(
+        regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"),
+        custom(function = "validate_logout_uri")
+    )]
+    pub backchannel_logout_uri: Option<String>,
+    /// The URI which will be loaded inside a hidden iframe for OIDC Front-Channel Logout
+    ///
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`, `https` only
+    #[validate(
+        regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"),
+        custom(function = "validate_logout_uri")
+    )]
+    pub frontchannel_logout_uri: Option<String>,
+    /// `pairwise` hands out a different `sub` for each sector. Defaults to `public`.
+    ///
+    /// Validation: `^(public|pairwise)$`
+    #[validate(regex(path = "RE_SUBJECT_TYPE", code = "^(public|pairwise)$"))]
+    pub subject_type: Option<String>,
+    /// The URI of a JSON array with all redirect URIs of the sector for pairwise subjects
+    ///
+    /// Validation: `[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$`
+    #[validate(regex(path = "RE_URI", code = "[a-zA-Z0-9,.:/_-&?=~#!$'()*+%]+$"))]
+    pub sector_identifier_uri: Option<String>,
+    /// The JWE `alg` for encrypted ID tokens. The client's public key is taken from its JWKS.
+    ///
+    /// Validation: `^(RSA-OAEP-256|ECDH-ES)$`
+    #[validate(regex(path = "RE_JWE_ALG", code = "^(RSA-OAEP-256|ECDH-ES)$"))]
+    pub id_token_encrypted_response_alg: Option<String>,
+    /// The JWE `enc` for encrypted ID tokens. Defaults to `A128CBC-HS256`.
+    ///
+    /// Validation: `^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$`
+    #[validate(regex(
+        path = "RE_JWE_ENC",
+        code = "^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$"
+    ))]
+    pub id_token_encrypted_response_enc: Option<String>,
+    /// The JWE `alg` for encrypted userinfo responses
+    ///
+    /// Validation: `^(RSA-OAEP-256|ECDH-ES)$`
+    #[validate(regex(path = "RE_JWE_ALG", code = "^(RSA-OAEP-256|ECDH-ES)$"))]
+    pub userinfo_encrypted_response_alg: Option<String>,
+    /// The JWE `enc` for encrypted userinfo responses. Defaults to `A128CBC-HS256`.
+    ///
+    /// Validation: `^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$`
+    #[validate(regex(
+        path = "RE_JWE_ENC",
+        code = "^(A128CBC-HS256|A256CBC-HS512|A128GCM|A256GCM)$"
+    ))]
+    pub userinfo_encrypted_response_enc: Option<String>,
+    /// Returns the userinfo as a signed JWT, if set
+    ///
+    /// Validation: `^(RS256|RS384|RS512|EdDSA|ES256|ES384)$`
+    pub userinfo_signed_response_alg: Option<JwkKeyPairAlg>,
+    /// The `type`s of the `authorization_details` this client may request (RFC 9396)
+    ///
+    /// Validation: `Vec<^[a-zA-Z0-9\-_.:/]{1,128}$>`
+    #[validate(custom(function = "validate_vec_authorization_details_types"))]
+    pub authorization_details_types: Option<Vec<String>>,
+    /// Authenticates the client with its certificate instead of the `client_secret` (RFC 8705)
+    ///
+    /// Validation: `^(tls_client_auth|self_signed_tls_client_auth)$`
+    #[validate(regex(
+        path = "RE_TLS_CLIENT_AUTH_METHOD",
+        code = "^(tls_client_auth|self_signed_tls_client_auth)$"
+    ))]
+    pub tls_client_auth_method: Option<String>,
+    /// The expected subject DN of the client certificate for `tls_client_auth`
+    ///
+    /// Validation: `^[a-zA-Z0-9À-ÿ\\s,.:/_\\-=+@*'()]{1,256}$`
+    #[validate(regex(
+        path = "RE_TLS_SUBJECT_DN",
+        code = "^[a-zA-Z0-9À-ÿ\\s,.:/_\\-=+@*'()]{1,256}$"
+    ))]
+    pub tls_client_auth_subject_dn: Option<String>,
+    /// Binds the issued access tokens to the client certificate
+    #[serde(default)]
+    pub tls_client_certificate_bound_access_tokens: bool,
+    /// Shows the consent screen for scopes the user has not granted to this client yet
+    #[serde(default)]
+    pub require_consent: bool,
+    /// Allows the login with a magic link or one-time code sent via email
+    #[serde(default)]
+    pub allow_email_login: bool,
+    /// The only accepted client authentication at the token endpoint. `private_key_jwt` clients
+    /// do not have a `client_secret`. Only the `client_secret` is accepted, if not set.
+    ///
+    /// Validation: `^(client_secret_basic|client_secret_post|client_secret_jwt|private_key_jwt)$`
+    #[validate(regex(
+        path = "RE_CLIENT_AUTH_METHOD",
+        code = "^(client_secret_basic|client_secret_post|client_secret_jwt|private_key_jwt)$"
+    ))]
+    pub token_endpoint_auth_method: Option<String>,
+    /// The only `request_uri`s, from which signed request objects will be fetched. Each one
+    /// must be a public `https` URI.
+    ///
+    /// Validation: `Vec<^[a-zA-Z0-9,.:/_\\-&?=~#!$'()*+%]+$>`
+    #[validate(custom(function = "validate_vec_uri"))]
+    pub request_uris: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
@@ -864,6 +1666,147 @@ pub struct UserAttrValuesUpdateRequest {
     pub values: Vec<UserAttrValueRequest>,
 }
 
+/// A single user for the bulk import. Unknown fields like `id`, `created_at` or `last_login`
+/// from an export are ignored and the user will get a new ID.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct UserImportRequest {
+    /// Validation: `email`
+    #[validate(email)]
+    pub email: String,
+    /// Validation: `[a-zA-Z0-9À-ÿ-\\s]{2,32}`
+    #[validate(regex(path = "RE_USER_NAME", code = "[a-zA-Z0-9À-ÿ-\\s]{2,32}"))]
+    pub given_name: String,
+    /// Validation: `[a-zA-Z0-9À-ÿ-\\s]{2,32}`
+    #[validate(regex(path = "RE_USER_NAME", code = "[a-zA-Z0-9À-ÿ-\\s]{2,32}"))]
+    pub family_name: String,
+    #[serde(default)]
+    pub language: Language,
+    /// Non-existing roles will be ignored
+    ///
+    /// Validation: `Vec<^[a-z0-9-_/,]{2,128}$>`
+    #[serde(default)]
+    #[validate(custom(function = "validate_vec_groups"))]
+    pub roles: Vec<String>,
+    /// Non-existing groups will be ignored
+    ///
+    /// Validation: `Vec<^[a-z0-9-_/,]{2,128}$>`
+    #[validate(custom(function = "validate_vec_groups"))]
+    pub groups: Option<Vec<String>>,
+    /// Default: `true`
+    pub enabled: Option<bool>,
+    /// Default: `false`
+    pub email_verified: Option<bool>,
+    #[validate(range(min = 1672527600, max = 4070905200))]
+    pub user_expires: Option<i64>,
+    /// A password hash from another system. Accepts Argon2, bcrypt (`$2a$` / `$2b$` / `$2y$`)
+    /// and PBKDF2-SHA256 / -SHA512 or scrypt in the PHC string format with a bounded cost.
+    /// Any other hash than Argon2ID with the configured params will be upgraded on the next
+    /// successful login.
+    #[validate(custom(function = "validate_password_hash"))]
+    pub password_hash: Option<String>,
+    /// Custom user attributes, which must exist already
+    pub attributes: Option<HashMap<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+pub struct UsersExportParams {
+    /// Default: `json`
+    pub format: Option<UsersExportFormat>,
+    /// Include the password hashes in the export. Default: `false`
+    pub password_hashes: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UsersExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct UsersImportRequest {
+    #[validate]
+    pub users: Vec<UserImportRequest>,
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+pub struct UsersQueryParams {
+    /// The page to return, starting at 1
+    ///
+    /// Validation: `1 <= page`
+    #[validate(range(min = 1))]
+    pub page: Option<u32>,
+    /// Default: `100`
+    ///
+    /// Validation: `1 <= page_size <= 1000`
+    #[validate(range(min = 1, max = 1000))]
+    pub page_size: Option<u32>,
+    /// Matches case-insensitively against the beginning of the email, given name or family name
+    /// or exactly against the ID
+    ///
+    /// Validation: `^[a-zA-Z0-9À-ÿ@.+\-_\s]{1,128}$`
+    #[validate(regex(path = "RE_SEARCH", code = "^[a-zA-Z0-9À-ÿ@.+\\-_\\s]{1,128}$"))]
+    pub search: Option<String>,
+    pub enabled: Option<bool>,
+    pub email_verified: Option<bool>,
+    /// `true` returns only users with an `user_expires` in the past
+    pub expired: Option<bool>,
+    /// Validation: `^[a-z0-9-_/]{2,128}$`
+    #[validate(regex(path = "RE_LOWERCASE", code = "^[a-z0-9-_/]{2,128}$"))]
+    pub role: Option<String>,
+    /// Validation: `^[a-z0-9-_/]{2,128}$`
+    #[validate(regex(path = "RE_LOWERCASE", code = "^[a-z0-9-_/]{2,128}$"))]
+    pub group: Option<String>,
+    /// Validation: `^[a-zA-Z0-9]+$`
+    #[validate(regex(path = "RE_ALNUM", code = "^[a-zA-Z0-9]+$"))]
+    pub auth_provider_id: Option<String>,
+    /// Default: `email`
+    pub sort_by: Option<UsersSortBy>,
+    /// Default: `asc`
+    pub order: Option<SortOrder>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UsersSortBy {
+    #[default]
+    Email,
+    GivenName,
+    FamilyName,
+    CreatedAt,
+    LastLogin,
+}
+
+impl UsersSortBy {
+    pub fn as_column(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::GivenName => "given_name",
+            Self::FamilyName => "family_name",
+            Self::CreatedAt => "created_at",
+            Self::LastLogin => "last_login",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Desc => "desc",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct WebauthnAuthStartRequest {
     pub purpose: MfaPurpose,
@@ -944,6 +1887,19 @@ fn validate_vec_attr(value: &[String]) -> Result<(), ValidationError> {
     Ok(())
 }
 
+fn validate_vec_authorization_details_types(value: &[String]) -> Result<(), ValidationError> {
+    let mut err = None;
+    value.iter().for_each(|v| {
+        if !RE_AUTHORIZATION_DETAILS_TYPE.is_match(v) {
+            err = Some("^[a-zA-Z0-9\\-_.:/]{1,128}$");
+        }
+    });
+    if let Some(e) = err {
+        return Err(ValidationError::new(e));
+    }
+    Ok(())
+}
+
 fn validate_vec_challenge(value: &[String]) -> Result<(), ValidationError> {
     let mut err = None;
 
@@ -971,7 +1927,7 @@ fn validate_vec_flows(value: &[String]) -> Result<(), ValidationError> {
     } else {
         value.iter().for_each(|v| {
             if !RE_FLOWS.is_match(v) {
-                err = Some("^(authorization_code|client_credentials|password|refresh_token)$");
+                err = Some("^(authorization_code|client_credentials|password|refresh_token|urn:ietf:params:oauth:grant-type:device_code|urn:ietf:params:oauth:grant-type:token-exchange)$");
             }
         });
     }
@@ -1012,7 +1968,7 @@ fn validate_vec_grant_type(value: &[String]) -> Result<(), ValidationError> {
     let mut err = None;
     value.iter().for_each(|v| {
         if !RE_GRANT_TYPES.is_match(v) {
-            err = Some("authorization_code|client_credentials|password|refresh_token");
+            err = Some("authorization_code|client_credentials|password|refresh_token|urn:ietf:params:oauth:grant-type:device_code");
         }
     });
     if let Some(e) = err {
@@ -1034,6 +1990,24 @@ fn validate_vec_groups(value: &[String]) -> Result<(), ValidationError> {
     Ok(())
 }
 
+// Logout URIs are loaded by the browser or requested by Rauthy itself. Allowing only `https`
+// (and `http` in `DEV_MODE`) makes sure, that they can never be a `javascript:` URI.
+fn validate_logout_uri(value: &str) -> Result<(), ValidationError> {
+    match reqwest::Url::parse(value) {
+        Ok(url) if url.scheme() == "https" || (*DEV_MODE && url.scheme() == "http") => Ok(()),
+        _ => Err(ValidationError::new("the logout URI must use https")),
+    }
+}
+
+fn validate_password_hash(value: &str) -> Result<(), ValidationError> {
+    if !password_hasher::is_supported_hash(value) {
+        return Err(ValidationError::new(
+            "unsupported password hash, only Argon2, bcrypt, PBKDF2-SHA256 / -SHA512 and scrypt with a bounded cost are allowed",
+        ));
+    }
+    Ok(())
+}
+
 fn validate_vec_scope(value: &[String]) -> Result<(), ValidationError> {
     let mut err = None;
     value.iter().for_each(|v| {
@@ -1047,6 +2021,19 @@ fn validate_vec_scope(value: &[String]) -> Result<(), ValidationError> {
     Ok(())
 }
 
+fn validate_vec_token_exchange_subject_types(value: &[String]) -> Result<(), ValidationError> {
+    let mut err = None;
+    value.iter().for_each(|v| {
+        if !RE_TOKEN_EXCHANGE_SUBJECT_TYPE.is_match(v) {
+            err = Some("^(access_token|id_token|jwt)$");
+        }
+    });
+    if let Some(e) = err {
+        return Err(ValidationError::new(e));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::request::ColorsRequest;
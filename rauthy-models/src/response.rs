@@ -1,13 +1,16 @@
 use crate::app_state::AppState;
 use crate::entity::api_keys::{ApiKey, ApiKeyAccess};
 use crate::entity::auth_providers::{AuthProvider, AuthProviderType};
+use crate::entity::authorization_details::AuthorizationDetails;
 use crate::entity::clients::Client;
 use crate::entity::clients_dyn::ClientDyn;
 use crate::entity::jwk::{JWKSPublicKey, JwkKeyPairAlg, JwkKeyPairType, JWKS};
 use crate::entity::password::PasswordPolicy;
+use crate::entity::resources::Resource;
 use crate::entity::scopes::Scope;
 use crate::entity::sessions::SessionState;
 use crate::entity::user_attr::{UserAttrConfigEntity, UserAttrValueEntity};
+use crate::entity::user_consents::UserConsent;
 use crate::entity::users::{AccountType, User};
 use crate::entity::users_values::UserValues;
 use crate::entity::webauthn::PasskeyEntity;
@@ -20,8 +23,9 @@ use rio_api::formatter::TriplesFormatter;
 use rio_api::model::{Literal, NamedNode, Subject, Term, Triple};
 use rio_turtle::TurtleFormatter;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use time::OffsetDateTime;
-use tracing::debug;
+use tracing::{debug, error};
 use utoipa::ToSchema;
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -91,6 +95,49 @@ pub struct ClientResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub challenges: Option<Vec<String>>,
     pub force_mfa: bool,
+    pub require_par: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    pub require_request_object: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_exchange_audiences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_exchange_scopes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_exchange_subject_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backchannel_logout_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frontchannel_logout_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sector_identifier_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token_encrypted_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token_encrypted_response_enc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userinfo_encrypted_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userinfo_encrypted_response_enc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userinfo_signed_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_details_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_auth_method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_auth_subject_dn: Option<String>,
+    pub tls_client_certificate_bound_access_tokens: bool,
+    pub require_consent: bool,
+    pub allow_email_login: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_uris: Option<Vec<String>>,
 }
 
 impl From<Client> for ClientResponse {
@@ -98,10 +145,15 @@ impl From<Client> for ClientResponse {
         let redirect_uris = client.get_redirect_uris();
         let post_logout_redirect_uris = client.get_post_logout_uris();
         let allowed_origins = client.get_allowed_origins();
+        let request_uris = client.get_request_uris();
         let flows_enabled = client.get_flows();
         let scopes = client.get_scopes();
         let default_scopes = client.get_default_scopes();
         let challenges = client.get_challenges();
+        let token_exchange_audiences = client.get_token_exchange_audiences();
+        let authorization_details_types = client.get_authorization_details_types();
+        let token_exchange_scopes = client.get_token_exchange_scopes();
+        let token_exchange_subject_types = client.get_token_exchange_subject_types();
 
         Self {
             id: client.id,
@@ -121,10 +173,51 @@ impl From<Client> for ClientResponse {
             default_scopes,
             challenges,
             force_mfa: client.force_mfa,
+            require_par: client.require_par,
+            jwks: client.jwks,
+            jwks_uri: client.jwks_uri,
+            require_request_object: client.require_request_object,
+            token_exchange_audiences,
+            token_exchange_scopes,
+            token_exchange_subject_types,
+            backchannel_logout_uri: client.backchannel_logout_uri,
+            frontchannel_logout_uri: client.frontchannel_logout_uri,
+            subject_type: client.subject_type,
+            sector_identifier_uri: client.sector_identifier_uri,
+            id_token_encrypted_response_alg: client.id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc: client.id_token_encrypted_response_enc,
+            userinfo_encrypted_response_alg: client.userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc: client.userinfo_encrypted_response_enc,
+            userinfo_signed_response_alg: client.userinfo_signed_response_alg,
+            authorization_details_types,
+            tls_client_auth_method: client.tls_client_auth_method,
+            tls_client_auth_subject_dn: client.tls_client_auth_subject_dn,
+            tls_client_certificate_bound_access_tokens: client
+                .tls_client_certificate_bound_access_tokens,
+            require_consent: client.require_consent,
+            allow_email_login: client.allow_email_login,
+            token_endpoint_auth_method: client.token_endpoint_auth_method,
+            request_uris,
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: u16,
+    pub interval: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct EmailLoginResponse {
+    /// Unix timestamp, until the E-Mail login can be finished
+    pub exp: i64,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct DynamicClientResponse {
     pub client_id: String,
@@ -154,6 +247,31 @@ pub struct DynamicClientResponse {
     pub id_token_signed_response_alg: String,
     pub token_endpoint_auth_method: String,
     pub token_endpoint_auth_signing_alg: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backchannel_logout_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frontchannel_logout_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sector_identifier_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token_encrypted_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token_encrypted_response_enc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userinfo_encrypted_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userinfo_encrypted_response_enc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userinfo_signed_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_details_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_auth_subject_dn: Option<String>,
+    pub tls_client_certificate_bound_access_tokens: bool,
 }
 
 impl DynamicClientResponse {
@@ -166,6 +284,7 @@ impl DynamicClientResponse {
         let redirect_uris = client.get_redirect_uris();
         let grant_types = client.get_flows();
         let post_logout_redirect_uri = client.get_redirect_uris().first().cloned();
+        let authorization_details_types = client.get_authorization_details_types();
 
         let client_secret = client.get_secret_cleartext()?;
         let (registration_access_token, registration_client_uri) = if map_registration_client_uri {
@@ -191,6 +310,20 @@ impl DynamicClientResponse {
             id_token_signed_response_alg: client.id_token_alg,
             token_endpoint_auth_method: client_dyn.token_endpoint_auth_method,
             token_endpoint_auth_signing_alg: client.access_token_alg,
+            jwks_uri: client.jwks_uri,
+            backchannel_logout_uri: client.backchannel_logout_uri,
+            frontchannel_logout_uri: client.frontchannel_logout_uri,
+            subject_type: client.subject_type,
+            sector_identifier_uri: client.sector_identifier_uri,
+            id_token_encrypted_response_alg: client.id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc: client.id_token_encrypted_response_enc,
+            userinfo_encrypted_response_alg: client.userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc: client.userinfo_encrypted_response_enc,
+            userinfo_signed_response_alg: client.userinfo_signed_response_alg,
+            authorization_details_types,
+            tls_client_auth_subject_dn: client.tls_client_auth_subject_dn,
+            tls_client_certificate_bound_access_tokens: client
+                .tls_client_certificate_bound_access_tokens,
         })
     }
 }
@@ -202,6 +335,22 @@ pub struct ClientSecretResponse {
     pub secret: Option<String>,
 }
 
+/// Passed into the consent page
+#[derive(Debug, Serialize)]
+pub struct ConsentData {
+    pub id: String,
+    pub client_id: String,
+    pub client_name: Option<String>,
+    pub scopes: Vec<ConsentScope>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsentScope {
+    pub name: String,
+    /// The custom user attributes, which will be shared with the client for this scope
+    pub attributes: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct EncKeysResponse<'a> {
     pub active: &'a str,
@@ -266,6 +415,15 @@ impl From<JWKSPublicKey> for JWKSPublicKeyCerts {
     }
 }
 
+/// Passed into the logout page to finish an RP-initiated logout
+#[derive(Debug, Default, Serialize)]
+pub struct LogoutData {
+    /// The `frontchannel_logout_uri`s, which will be loaded inside hidden iframes
+    pub frontchannel_logout_uris: Vec<String>,
+    /// The validated `post_logout_redirect_uri` including the `state`
+    pub post_logout_redirect_uri: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct LoginTimeResponse {
     pub argon2_params: Argon2ParamsResponse,
@@ -273,6 +431,12 @@ pub struct LoginTimeResponse {
     pub num_cpus: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ParResponse {
+    pub request_uri: String,
+    pub expires_in: u16,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct PasskeyResponse {
     pub name: String,
@@ -400,6 +564,33 @@ pub struct ProviderLookupResponse<'a> {
     pub danger_allow_insecure: bool,
 }
 
+/// The new recovery codes in plain text. They are only shown this single time.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ResourceResponse {
+    pub id: String,
+    pub name: String,
+    pub uri: String,
+    pub scopes: Vec<String>,
+}
+
+impl From<Resource> for ResourceResponse {
+    fn from(value: Resource) -> Self {
+        let scopes = value.get_scopes().into_iter().map(String::from).collect();
+
+        Self {
+            id: value.id,
+            name: value.name,
+            uri: value.uri,
+            scopes,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ScopeResponse {
     pub id: String,
@@ -475,6 +666,28 @@ pub struct TokenInfo {
     pub exp: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cnf: Option<JktClaim>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<Object>>)]
+    pub authorization_details: Option<AuthorizationDetails>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotpLoginResponse {
+    pub code: String,
+    pub user_id: String,
+    pub exp: u64,
+    /// always `true` to distinguish this from a `WebauthnLoginResponse`
+    pub totp: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TotpRegistrationResponse {
+    /// The base32 encoded secret for a manual setup of the authenticator app
+    pub secret: String,
+    /// The `otpauth://` URI
+    pub uri: String,
+    /// The URI as QR code in SVG format
+    pub qr_svg: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -505,7 +718,30 @@ pub struct UserAttrValuesResponse {
     pub values: Vec<UserAttrValueResponse>,
 }
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserConsentResponse {
+    pub client_id: String,
+    pub scopes: Vec<String>,
+    /// Unix timestamp in seconds
+    pub created_at: i64,
+    /// Unix timestamp in seconds
+    pub updated_at: i64,
+}
+
+impl From<UserConsent> for UserConsentResponse {
+    fn from(value: UserConsent) -> Self {
+        let scopes = value.get_scopes().into_iter().map(String::from).collect();
+
+        Self {
+            client_id: value.client_id,
+            scopes,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Userinfo {
     pub id: String,
     pub sub: String,
@@ -547,6 +783,14 @@ pub struct Userinfo {
     pub webid: Option<String>,
 }
 
+/// The userinfo is returned as plain JSON, or as a JWT in `application/jwt` format, if the
+/// client requested a signed or encrypted response.
+#[derive(Debug)]
+pub enum UserinfoResponse {
+    Json(Userinfo),
+    Jwt(String),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum UserAccountTypeResponse {
@@ -604,6 +848,7 @@ pub struct UserResponse {
     pub user_values: UserValuesResponse,
     pub auth_provider_id: Option<String>,
     pub federation_uid: Option<String>,
+    pub totp_enabled: bool,
 }
 
 impl UserResponse {
@@ -615,6 +860,7 @@ impl UserResponse {
             None
         };
         let account_type = UserAccountTypeResponse::from(u.account_type());
+        let totp_enabled = u.has_totp_enabled();
 
         Self {
             id: u.id,
@@ -637,6 +883,7 @@ impl UserResponse {
             user_values: v.map(UserValuesResponse::from).unwrap_or_default(),
             auth_provider_id: u.auth_provider_id,
             federation_uid: u.federation_uid,
+            totp_enabled,
         }
     }
 }
@@ -656,6 +903,89 @@ impl From<User> for UserResponseSimple {
     }
 }
 
+/// A single user from the bulk export. This has the same format as the import with a few
+/// additional, read-only values, which will be ignored by the import.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserExportResponse {
+    pub id: String,
+    pub email: String,
+    pub given_name: String,
+    pub family_name: String,
+    pub language: Language,
+    pub roles: Vec<String>,
+    pub groups: Option<Vec<String>>,
+    pub enabled: bool,
+    pub email_verified: bool,
+    pub user_expires: Option<i64>,
+    pub created_at: i64,
+    pub last_login: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    pub attributes: HashMap<String, serde_json::Value>,
+}
+
+impl UserExportResponse {
+    pub fn build(user: User, attrs: Vec<UserAttrValueEntity>, with_password_hash: bool) -> Self {
+        let roles = user.get_roles();
+        let groups = user.groups.as_ref().map(|_| user.get_groups());
+
+        let mut attributes = HashMap::with_capacity(attrs.len());
+        for attr in attrs {
+            match serde_json::from_slice(&attr.value) {
+                Ok(value) => {
+                    attributes.insert(attr.key, value);
+                }
+                Err(err) => {
+                    error!(
+                        "Invalid value for attribute '{}' of user '{}': {}",
+                        attr.key, user.id, err
+                    );
+                }
+            }
+        }
+
+        Self {
+            id: user.id,
+            email: user.email,
+            given_name: user.given_name,
+            family_name: user.family_name,
+            language: user.language,
+            roles,
+            groups,
+            enabled: user.enabled,
+            email_verified: user.email_verified,
+            user_expires: user.user_expires,
+            created_at: user.created_at,
+            last_login: user.last_login,
+            password_hash: if with_password_hash {
+                user.password
+            } else {
+                None
+            },
+            attributes,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UsersExportResponse {
+    pub users: Vec<UserExportResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserImportErrorResponse {
+    pub email: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UsersImportResponse {
+    /// The amount of successfully imported users
+    pub imported: usize,
+    /// All users, which could not be imported
+    pub errors: Vec<UserImportErrorResponse>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct UserValuesResponse {
     pub birthdate: Option<String>,
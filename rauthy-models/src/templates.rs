@@ -2,6 +2,8 @@ use crate::entity::colors::Colors;
 use crate::entity::password::PasswordPolicy;
 use crate::i18n::account::I18nAccount;
 use crate::i18n::authorize::I18nAuthorize;
+use crate::i18n::consent::I18nConsent;
+use crate::i18n::device::I18nDevice;
 use crate::i18n::email_confirm_change_html::I18nEmailConfirmChangeHtml;
 use crate::i18n::error::I18nError;
 use crate::i18n::index::I18nIndex;
@@ -10,6 +12,7 @@ use crate::i18n::password_reset::I18nPasswordReset;
 use crate::i18n::register::I18nRegister;
 use crate::i18n::SsrJson;
 use crate::language::Language;
+use crate::request::AuthRequest;
 use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, HttpResponseBuilder};
 use askama_actix::Template;
@@ -55,6 +58,7 @@ pub struct IndexHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl IndexHtml<'_> {
@@ -106,6 +110,7 @@ pub struct AccountHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: String,
+    pub auth_request: String,
 }
 
 impl AccountHtml<'_> {
@@ -155,6 +160,7 @@ pub struct AdminHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminHtml<'_> {
@@ -203,6 +209,7 @@ pub struct ErrorHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl ErrorHtml<'_> {
@@ -269,6 +276,7 @@ pub struct Error1Html<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl Error1Html<'_> {
@@ -323,6 +331,7 @@ pub struct Error2Html<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl Error2Html<'_> {
@@ -377,6 +386,7 @@ pub struct Error3Html<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl Error3Html<'_> {
@@ -431,6 +441,7 @@ pub struct AdminApiKeysHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminApiKeysHtml<'_> {
@@ -479,6 +490,7 @@ pub struct AdminAttributesHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminAttributesHtml<'_> {
@@ -527,6 +539,7 @@ pub struct AdminBlacklistHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminBlacklistHtml<'_> {
@@ -575,6 +588,7 @@ pub struct AdminClientsHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminClientsHtml<'_> {
@@ -623,6 +637,7 @@ pub struct AdminConfigHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminConfigHtml<'_> {
@@ -671,6 +686,7 @@ pub struct AdminDocsHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminDocsHtml<'_> {
@@ -719,6 +735,7 @@ pub struct AdminEventsHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminEventsHtml<'_> {
@@ -767,6 +784,7 @@ pub struct AdminGroupsHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminGroupsHtml<'_> {
@@ -815,6 +833,7 @@ pub struct AdminRolesHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminRolesHtml<'_> {
@@ -863,6 +882,7 @@ pub struct AdminScopesHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminScopesHtml<'_> {
@@ -911,6 +931,7 @@ pub struct AdminSessionsHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminSessionsHtml<'_> {
@@ -959,6 +980,7 @@ pub struct AdminUsersHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl AdminUsersHtml<'_> {
@@ -1007,20 +1029,31 @@ pub struct AuthorizeHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: String,
+    pub auth_request: String,
 }
 
 impl AuthorizeHtml<'_> {
     pub fn build(
         client_name: &Option<String>,
+        allow_email_login: bool,
         csrf_token: &str,
         action: FrontendAction,
         colors: &Colors,
         lang: &Language,
         auth_providers_json: Option<String>,
+        // only set, if the request has been resolved from a Pushed Authorization Request
+        auth_request: Option<&AuthRequest>,
     ) -> String {
-        let mut res = AuthorizeHtml {
+        let data = serde_json::json!({
+            "client_name": client_name,
+            "email_login": allow_email_login,
+        })
+        .to_string();
+
+        let res = AuthorizeHtml {
             lang: lang.as_str(),
             csrf_token,
+            data: &data,
             action: &action.to_string(),
             col_act1: &colors.act1,
             col_act1a: &colors.act1a,
@@ -1037,13 +1070,12 @@ impl AuthorizeHtml<'_> {
             col_bg: &colors.bg,
             i18n: I18nAuthorize::build(lang).as_json(),
             auth_providers: auth_providers_json.unwrap_or_default(),
+            auth_request: auth_request
+                .map(|r| serde_json::to_string(r).unwrap())
+                .unwrap_or_default(),
             ..Default::default()
         };
 
-        if client_name.is_some() {
-            res.data = client_name.as_ref().unwrap();
-        }
-
         res.render().unwrap()
     }
 }
@@ -1070,6 +1102,7 @@ pub struct CallbackHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl CallbackHtml<'_> {
@@ -1096,6 +1129,160 @@ impl CallbackHtml<'_> {
     }
 }
 
+#[derive(Default, Template)]
+#[template(path = "html/oidc/consent.html")]
+pub struct ConsentHtml<'a> {
+    pub lang: &'a str,
+    pub csrf_token: &'a str,
+    pub data: &'a str,
+    pub action: &'a str,
+    pub col_act1: &'a str,
+    pub col_act1a: &'a str,
+    pub col_act2: &'a str,
+    pub col_act2a: &'a str,
+    pub col_acnt: &'a str,
+    pub col_acnta: &'a str,
+    pub col_ok: &'a str,
+    pub col_err: &'a str,
+    pub col_glow: &'a str,
+    pub col_gmid: &'a str,
+    pub col_ghigh: &'a str,
+    pub col_text: &'a str,
+    pub col_bg: &'a str,
+    pub i18n: String,
+    pub auth_providers: &'a str,
+    pub auth_request: &'a str,
+}
+
+impl ConsentHtml<'_> {
+    pub fn build(csrf_token: &str, data: &str, colors: &Colors, lang: &Language) -> String {
+        let res = ConsentHtml {
+            lang: lang.as_str(),
+            csrf_token,
+            data,
+            col_act1: &colors.act1,
+            col_act1a: &colors.act1a,
+            col_act2: &colors.act2,
+            col_act2a: &colors.act2a,
+            col_acnt: &colors.acnt,
+            col_acnta: &colors.acnta,
+            col_ok: &colors.ok,
+            col_err: &colors.err,
+            col_glow: &colors.glow,
+            col_gmid: &colors.gmid,
+            col_ghigh: &colors.ghigh,
+            col_text: &colors.text,
+            col_bg: &colors.bg,
+            i18n: I18nConsent::build(lang).as_json(),
+            ..Default::default()
+        };
+
+        res.render().unwrap()
+    }
+}
+
+#[derive(Default, Template)]
+#[template(path = "html/oidc/device.html")]
+pub struct DeviceHtml<'a> {
+    pub lang: &'a str,
+    pub csrf_token: &'a str,
+    pub data: &'a str,
+    pub action: &'a str,
+    pub col_act1: &'a str,
+    pub col_act1a: &'a str,
+    pub col_act2: &'a str,
+    pub col_act2a: &'a str,
+    pub col_acnt: &'a str,
+    pub col_acnta: &'a str,
+    pub col_ok: &'a str,
+    pub col_err: &'a str,
+    pub col_glow: &'a str,
+    pub col_gmid: &'a str,
+    pub col_ghigh: &'a str,
+    pub col_text: &'a str,
+    pub col_bg: &'a str,
+    pub i18n: String,
+    pub auth_providers: &'a str,
+    pub auth_request: &'a str,
+}
+
+impl DeviceHtml<'_> {
+    pub fn build(colors: &Colors, lang: &Language) -> String {
+        let res = DeviceHtml {
+            lang: lang.as_str(),
+            col_act1: &colors.act1,
+            col_act1a: &colors.act1a,
+            col_act2: &colors.act2,
+            col_act2a: &colors.act2a,
+            col_acnt: &colors.acnt,
+            col_acnta: &colors.acnta,
+            col_ok: &colors.ok,
+            col_err: &colors.err,
+            col_glow: &colors.glow,
+            col_gmid: &colors.gmid,
+            col_ghigh: &colors.ghigh,
+            col_text: &colors.text,
+            col_bg: &colors.bg,
+            i18n: I18nDevice::build(lang).as_json(),
+            ..Default::default()
+        };
+
+        res.render().unwrap()
+    }
+}
+
+#[derive(Default, Template)]
+#[template(path = "html/oidc/email.html")]
+pub struct EmailLoginHtml<'a> {
+    pub lang: &'a str,
+    pub csrf_token: &'a str,
+    pub data: &'a str,
+    pub action: &'a str,
+    pub col_act1: &'a str,
+    pub col_act1a: &'a str,
+    pub col_act2: &'a str,
+    pub col_act2a: &'a str,
+    pub col_acnt: &'a str,
+    pub col_acnta: &'a str,
+    pub col_ok: &'a str,
+    pub col_err: &'a str,
+    pub col_glow: &'a str,
+    pub col_gmid: &'a str,
+    pub col_ghigh: &'a str,
+    pub col_text: &'a str,
+    pub col_bg: &'a str,
+    pub i18n: String,
+    pub auth_providers: &'a str,
+    pub auth_request: &'a str,
+}
+
+impl EmailLoginHtml<'_> {
+    pub fn build(csrf_token: &str, colors: &Colors, lang: &Language) -> String {
+        let res = EmailLoginHtml {
+            lang: lang.as_str(),
+            csrf_token,
+            col_act1: &colors.act1,
+            col_act1a: &colors.act1a,
+            col_act2: &colors.act2,
+            col_act2a: &colors.act2a,
+            col_acnt: &colors.acnt,
+            col_acnta: &colors.acnta,
+            col_ok: &colors.ok,
+            col_err: &colors.err,
+            col_glow: &colors.glow,
+            col_gmid: &colors.gmid,
+            col_ghigh: &colors.ghigh,
+            col_text: &colors.text,
+            col_bg: &colors.bg,
+            // the page continues the login and may need the MFA translations
+            i18n: I18nAuthorize::build(lang).as_json(),
+            ..Default::default()
+        };
+
+        res.render().unwrap()
+    }
+}
+
 #[derive(Default, Template)]
 #[template(path = "html/providers/callback.html")]
 pub struct ProviderCallbackHtml<'a> {
@@ -1118,6 +1305,7 @@ pub struct ProviderCallbackHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl ProviderCallbackHtml<'_> {
@@ -1167,13 +1355,21 @@ pub struct LogoutHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl LogoutHtml<'_> {
-    pub fn build(csrf_token: &str, set_logout: bool, colors: &Colors, lang: &Language) -> String {
+    pub fn build(
+        csrf_token: &str,
+        set_logout: bool,
+        data: &str,
+        colors: &Colors,
+        lang: &Language,
+    ) -> String {
         let res = LogoutHtml {
             lang: lang.as_str(),
             csrf_token,
+            data,
             action: set_logout,
             col_act1: &colors.act1,
             col_act1a: &colors.act1a,
@@ -1218,6 +1414,7 @@ pub struct PwdResetHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl PwdResetHtml<'_> {
@@ -1303,6 +1500,7 @@ pub struct UserEmailChangeConfirmHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl UserEmailChangeConfirmHtml<'_> {
@@ -1355,6 +1553,7 @@ pub struct UserRegisterHtml<'a> {
     pub col_bg: &'a str,
     pub i18n: String,
     pub auth_providers: &'a str,
+    pub auth_request: &'a str,
 }
 
 impl UserRegisterHtml<'_> {
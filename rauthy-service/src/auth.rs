@@ -5,42 +5,70 @@ use actix_web::{web, HttpRequest, HttpResponse};
 use chrono::Utc;
 use cryptr::{EncKeys, EncValue};
 use jwt_simple::algorithms::{
+    ECDSAP256KeyPairLike, ECDSAP256PublicKeyLike, ECDSAP384KeyPairLike, ECDSAP384PublicKeyLike,
     EdDSAKeyPairLike, EdDSAPublicKeyLike, RSAKeyPairLike, RSAPublicKeyLike,
 };
 use jwt_simple::claims;
 use jwt_simple::prelude::*;
 use rauthy_common::constants::{
-    CACHE_NAME_12HR, CACHE_NAME_LOGIN_DELAY, COOKIE_MFA, ENABLE_SOLID_AUD, ENABLE_WEB_ID,
-    HEADER_DPOP_NONCE, IDX_JWKS, IDX_JWK_LATEST, IDX_LOGIN_TIME, SESSION_LIFETIME,
-    SESSION_RENEW_MFA, TOKEN_BEARER, WEBAUTHN_REQ_EXP,
+    ACCESS_TOKEN_RFC9068, CACHE_NAME_12HR, CACHE_NAME_LOGIN_DELAY,
+    CLIENT_ASSERTION_TYPE_JWT_BEARER, COOKIE_MFA, DEVICE_GRANT_CODE_LIFETIME,
+    DEVICE_GRANT_POLL_INTERVAL, DEV_MODE, ENABLE_SIGNED_METADATA, ENABLE_SOLID_AUD, ENABLE_WEB_ID,
+    GRANT_TYPE_DEVICE_CODE, GRANT_TYPE_TOKEN_EXCHANGE, HEADER_DPOP_NONCE, IDX_JWKS, IDX_JWK_LATEST,
+    IDX_LOGIN_TIME, JWT_TYP_ACCESS_TOKEN, ML_LT_EMAIL_LOGIN, PAR_EXP, SESSION_LIFETIME,
+    SESSION_RENEW_MFA, TOKEN_BEARER, TOKEN_TYPE_ACCESS_TOKEN, TOKEN_TYPE_ID_TOKEN, TOKEN_TYPE_JWT,
+    WEBAUTHN_REQ_EXP,
 };
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
-use rauthy_common::password_hasher::HashPassword;
-use rauthy_common::utils::{base64_url_encode, get_client_ip, get_rand};
+use rauthy_common::utils::{base64_url_encode, get_client_ip, get_rand, real_ip_from_req};
 use rauthy_models::app_state::AppState;
+use rauthy_models::email::send_email_login;
 use rauthy_models::entity::auth_codes::AuthCode;
+use rauthy_models::entity::auth_providers::AuthProvider;
+use rauthy_models::entity::authorization_details::AuthorizationDetails;
+use rauthy_models::entity::backchannel_logout::SessionClient;
+use rauthy_models::entity::client_assertions::ClientAssertion;
+use rauthy_models::entity::client_certificates::ClientCertificate;
 use rauthy_models::entity::clients::Client;
 use rauthy_models::entity::clients_dyn::ClientDyn;
 use rauthy_models::entity::colors::ColorEntity;
+use rauthy_models::entity::device_codes::DeviceAuthCode;
 use rauthy_models::entity::dpop_proof::DPoPProof;
+use rauthy_models::entity::email_login::EmailLogin;
 use rauthy_models::entity::jwk::{Jwk, JwkKeyPair, JwkKeyPairAlg};
+use rauthy_models::entity::magic_links::{MagicLink, MagicLinkUsage};
+use rauthy_models::entity::pairwise_subjects::PairwiseSubject;
+use rauthy_models::entity::pushed_auth_requests::PushedAuthRequest;
 use rauthy_models::entity::refresh_tokens::RefreshToken;
+use rauthy_models::entity::request_objects::RequestObject;
+use rauthy_models::entity::resolved_auth_requests::ResolvedAuthRequest;
+use rauthy_models::entity::resources::Resource;
+use rauthy_models::entity::revoked_tokens::RevokedToken;
 use rauthy_models::entity::scopes::Scope;
-use rauthy_models::entity::sessions::{Session, SessionState};
+use rauthy_models::entity::sessions::{MfaMethod, Session, SessionState};
+use rauthy_models::entity::user_consents::{PendingConsent, UserConsent};
 use rauthy_models::entity::users::{AccountType, User};
 use rauthy_models::entity::users_values::UserValues;
 use rauthy_models::entity::webauthn::{WebauthnCookie, WebauthnLoginReq};
 use rauthy_models::entity::webids::WebId;
+use rauthy_models::entity::well_known::WellKnown;
 use rauthy_models::events::event::Event;
 use rauthy_models::events::ip_blacklist_handler::{IpBlacklistReq, IpFailedLoginCheck};
 use rauthy_models::language::Language;
-use rauthy_models::request::{LoginRefreshRequest, LoginRequest, LogoutRequest, TokenRequest};
-use rauthy_models::response::{TokenInfo, Userinfo};
+use rauthy_models::request::{
+    acr_values_require_mfa, prompt_contains, AuthRequest, ConsentRequest, DeviceRequest,
+    DeviceVerifyRequest, EmailLoginFinishRequest, LoginRefreshRequest, LoginRequest, LogoutRequest,
+    ParRequest, TokenRequest, TokenRevocationRequest,
+};
+use rauthy_models::response::{
+    ConsentData, ConsentScope, DeviceCodeResponse, EmailLoginResponse, LogoutData, ParResponse,
+    TokenInfo, Userinfo, UserinfoResponse,
+};
 use rauthy_models::templates::{LogoutHtml, TooManyRequestsHtml};
 use rauthy_models::{
-    sign_jwt, validate_jwt, AddressClaim, AuthStep, AuthStepAwaitWebauthn, AuthStepLoggedIn,
-    JktClaim, JwtAccessClaims, JwtAmrValue, JwtCommonClaims, JwtIdClaims, JwtRefreshClaims,
-    JwtTokenType,
+    sign_jwt, validate_jwt, AddressClaim, AuthStep, AuthStepAwaitTotp, AuthStepAwaitWebauthn,
+    AuthStepLoggedIn, JktClaim, JwtAccessClaims, JwtActClaim, JwtAmrValue, JwtCommonClaims,
+    JwtIdClaims, JwtRefreshClaims, JwtTokenType,
 };
 use redhac::cache_del;
 use redhac::{cache_get, cache_get_from, cache_get_value, cache_put};
@@ -52,14 +80,15 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use time::OffsetDateTime;
 use tokio::sync::oneshot;
 use tracing::{debug, error, info, warn};
+use webauthn_rs::prelude::Url;
 
 /// # Business logic for [POST /oidc/authorize](crate::handlers::post_authorize)
 #[tracing::instrument(name = "post_authorize", skip_all, fields(client_id = req_data.client_id, email = req_data.email))]
 pub async fn authorize(
     data: &web::Data<AppState>,
     req: &HttpRequest,
-    req_data: LoginRequest,
-    mut session: Session,
+    mut req_data: LoginRequest,
+    session: Session,
     // the second argument with the error will be 'true' if a login delay should be added
 ) -> Result<AuthStep, (ErrorResponse, bool)> {
     // This Error must be the same if user does not exist AND passwords do not match to prevent
@@ -123,7 +152,7 @@ pub async fn authorize(
     user.check_expired()
         .map_err(|err| (err, !user_must_provide_password))?;
 
-    let has_password_been_hashed = if let Some(pwd) = req_data.password {
+    let has_password_been_hashed = if let Some(pwd) = req_data.password.take() {
         match user.validate_password(data, pwd).await {
             Ok(_) => {
                 // update user info
@@ -144,23 +173,45 @@ pub async fn authorize(
         false
     };
 
+    authorize_user(data, req, req_data, user, session, has_password_been_hashed).await
+}
+
+// Continues the authorization for an already authenticated `user` and returns either the
+// redirect back to the client or the next step for the second factor.
+async fn authorize_user(
+    data: &web::Data<AppState>,
+    req: &HttpRequest,
+    mut req_data: LoginRequest,
+    user: User,
+    mut session: Session,
+    has_password_been_hashed: bool,
+) -> Result<AuthStep, (ErrorResponse, bool)> {
     // client validations
-    let client = Client::find_maybe_ephemeral(data, req_data.client_id)
+    let client = Client::find_maybe_ephemeral(data, req_data.client_id.clone())
         .await
-        .map_err(|err| (err, !user_must_provide_password))?;
+        .map_err(|err| (err, true))?;
+    // the params from the browser must not be trusted, if the client requires a PAR or a
+    // request object -> use the ones which have been resolved during GET /authorize
+    if let Some(auth_request) = ResolvedAuthRequest::find_required(data, &session.id, &client)
+        .await
+        .map_err(|err| (err, true))?
+    {
+        req_data.apply_auth_request(auth_request);
+    }
 
     client
         .validate_mfa(&user)
         .map_err(|err| (err, has_password_been_hashed))?;
+    validate_acr_mfa(&req_data.acr_values, &user).map_err(|err| (err, has_password_been_hashed))?;
     client
         .validate_redirect_uri(&req_data.redirect_uri)
-        .map_err(|err| (err, !user_must_provide_password))?;
+        .map_err(|err| (err, true))?;
     client
         .validate_code_challenge(&req_data.code_challenge, &req_data.code_challenge_method)
-        .map_err(|err| (err, !user_must_provide_password))?;
+        .map_err(|err| (err, true))?;
     let header_origin = client
         .validate_origin(req, &data.listen_scheme, &data.public_url)
-        .map_err(|err| (err, !user_must_provide_password))?;
+        .map_err(|err| (err, true))?;
     // let challenge: Option<String> = req_data.code_challenge.clone();
     // let mut challenge_method: Option<String> = None;
     // // TODO would it be possible to omit a code challenge and skip it, even if the client should request it?
@@ -211,14 +262,20 @@ pub async fn authorize(
     // }
 
     // build authorization code
-    let code_lifetime = if user.has_webauthn_enabled() {
+    let code_lifetime = if user.has_mfa_enabled() {
         client.auth_code_lifetime + *WEBAUTHN_REQ_EXP as i32
     } else {
         client.auth_code_lifetime
     };
     let scopes = client
         .sanitize_login_scopes(&req_data.scopes)
-        .map_err(|err| (err, !user_must_provide_password))?;
+        .map_err(|err| (err, true))?;
+    let authorization_details =
+        AuthorizationDetails::try_for_client(req_data.authorization_details.as_deref(), &client)
+            .map_err(|err| (err, true))?;
+    Resource::find_for_request(data, req_data.resource.as_deref())
+        .await
+        .map_err(|err| (err, true))?;
     let code = AuthCode::new(
         user.id.clone(),
         client.id,
@@ -227,17 +284,21 @@ pub async fn authorize(
         req_data.code_challenge_method,
         req_data.nonce,
         scopes,
+        authorization_details,
+        req_data.resource,
         code_lifetime,
     );
-    code.save(data)
-        .await
-        .map_err(|err| (err, !user_must_provide_password))?;
-
-    // build location header
-    let mut loc = format!("{}?code={}", req_data.redirect_uri, code.id);
-    if let Some(state) = req_data.state {
-        loc = format!("{}&state={}", loc, state);
-    };
+    // points to the consent page, if the user has not granted all scopes yet
+    let loc = PendingConsent::code_location(
+        data,
+        code,
+        req_data.redirect_uri,
+        req_data.state,
+        client.require_consent,
+        prompt_contains(&req_data.prompt, "consent"),
+    )
+    .await
+    .map_err(|err| (err, true))?;
 
     // TODO double check that we do not have any problems with the direct webauthn login here
     // TODO should we allow to skip this step if set so in the config?
@@ -246,7 +307,7 @@ pub async fn authorize(
         session
             .set_mfa(data, true)
             .await
-            .map_err(|err| (err, !user_must_provide_password))?;
+            .map_err(|err| (err, true))?;
 
         let step = AuthStepAwaitWebauthn {
             has_password_been_hashed,
@@ -262,6 +323,7 @@ pub async fn authorize(
         WebauthnLoginReq {
             code: step.code.clone(),
             user_id: user.id,
+            session_id: Some(step.session.id.clone()),
             header_loc: loc,
             header_origin: step
                 .header_origin
@@ -270,9 +332,39 @@ pub async fn authorize(
         }
         .save(data)
         .await
-        .map_err(|err| (err, !user_must_provide_password))?;
+        .map_err(|err| (err, true))?;
 
         Ok(AuthStep::AwaitWebauthn(step))
+    } else if user.has_totp_enabled() {
+        session
+            .set_mfa(data, true)
+            .await
+            .map_err(|err| (err, true))?;
+
+        let step = AuthStepAwaitTotp {
+            has_password_been_hashed,
+            code: get_rand(48),
+            header_csrf: Session::get_csrf_header(&session.csrf_token),
+            header_origin,
+            user_id: user.id.clone(),
+            exp: *WEBAUTHN_REQ_EXP,
+        };
+
+        WebauthnLoginReq {
+            code: step.code.clone(),
+            user_id: user.id,
+            session_id: Some(session.id.clone()),
+            header_loc: loc,
+            header_origin: step
+                .header_origin
+                .as_ref()
+                .map(|h| h.1.to_str().unwrap().to_string()),
+        }
+        .save(data)
+        .await
+        .map_err(|err| (err, true))?;
+
+        Ok(AuthStep::AwaitTotp(step))
     } else {
         Ok(AuthStep::LoggedIn(AuthStepLoggedIn {
             has_password_been_hashed,
@@ -284,14 +376,150 @@ pub async fn authorize(
     }
 }
 
+/// # Business logic for [POST /oidc/authorize/email](crate::handlers::post_authorize_email)
+#[tracing::instrument(name = "post_authorize_email", skip_all, fields(client_id = req_data.client_id, email = req_data.email))]
+pub async fn authorize_email(
+    data: &web::Data<AppState>,
+    mut req_data: LoginRequest,
+    session: &Session,
+) -> Result<EmailLoginResponse, ErrorResponse> {
+    let client = Client::find_maybe_ephemeral(data, req_data.client_id.clone()).await?;
+    if !client.allow_email_login {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            "The E-Mail login is not allowed for this client".to_string(),
+        ));
+    }
+    if let Some(auth_request) =
+        ResolvedAuthRequest::find_required(data, &session.id, &client).await?
+    {
+        req_data.apply_auth_request(auth_request);
+    }
+    client.validate_redirect_uri(&req_data.redirect_uri)?;
+    client.validate_code_challenge(&req_data.code_challenge, &req_data.code_challenge_method)?;
+
+    EmailLogin::rate_limit_email(data, &req_data.email).await?;
+
+    // a new request always replaces an already pending one for this session
+    if let Ok(login) = EmailLogin::find(data, &session.id).await {
+        if let Ok(mut ml) = MagicLink::find(data, &login.magic_link_id).await {
+            ml.invalidate(data).await?;
+        }
+        login.delete(data).await?;
+    }
+
+    // This response must be the same if the user does not exist to prevent username enumeration
+    let exp = OffsetDateTime::now_utc().unix_timestamp() + *ML_LT_EMAIL_LOGIN as i64 * 60;
+    let user = match User::find_by_email(data, req_data.email.clone()).await {
+        Ok(user) if user.check_enabled().is_ok() && user.check_expired().is_ok() => user,
+        _ => {
+            info!("E-Mail login requested for an unknown or disabled user");
+            return Ok(EmailLoginResponse { exp });
+        }
+    };
+
+    let ml = MagicLink::create(
+        data,
+        user.id.clone(),
+        *ML_LT_EMAIL_LOGIN as i64,
+        MagicLinkUsage::Login(session.id.clone()),
+    )
+    .await?;
+
+    req_data.password = None;
+    let login = EmailLogin {
+        session_id: session.id.clone(),
+        user_id: user.id.clone(),
+        magic_link_id: ml.id.clone(),
+        code: EmailLogin::new_code(),
+        tries: 0,
+        req: req_data,
+    };
+    login.save(data).await?;
+    send_email_login(data, &ml, &user, &login.code).await;
+
+    Ok(EmailLoginResponse { exp: ml.exp })
+}
+
+/// # Business logic for [POST /oidc/authorize/email/finish](crate::handlers::post_authorize_email_finish)
+pub async fn authorize_email_finish(
+    data: &web::Data<AppState>,
+    req: &HttpRequest,
+    req_data: EmailLoginFinishRequest,
+    session: Session,
+    // the second argument with the error will be 'true' if a login delay should be added
+) -> Result<AuthStep, (ErrorResponse, bool)> {
+    let mut login = EmailLogin::find(data, &session.id)
+        .await
+        .map_err(|err| (err, true))?;
+    match (req_data.code, req_data.magic_link_id) {
+        (Some(code), _) => login
+            .validate_code(data, &code)
+            .await
+            .map_err(|err| (err, true))?,
+        (None, Some(id)) if id == login.magic_link_id => {}
+        _ => {
+            return Err((
+                ErrorResponse::new(
+                    ErrorResponseType::Unauthorized,
+                    "Invalid login code or link".to_string(),
+                ),
+                true,
+            ));
+        }
+    }
+    login.delete(data).await.map_err(|err| (err, true))?;
+
+    let mut ml = MagicLink::find(data, &login.magic_link_id)
+        .await
+        .map_err(|err| (err, true))?;
+    ml.validate(&login.user_id, req, false)
+        .map_err(|err| (err, true))?;
+    let usage = MagicLinkUsage::try_from(&ml.usage).map_err(|err| (err, true))?;
+    if usage != MagicLinkUsage::Login(session.id.clone()) {
+        return Err((
+            ErrorResponse::new(
+                ErrorResponseType::Forbidden,
+                "The login link belongs to another session".to_string(),
+            ),
+            true,
+        ));
+    }
+    ml.used = true;
+    ml.invalidate(data).await.map_err(|err| (err, true))?;
+
+    let mut user = User::find(data, login.user_id)
+        .await
+        .map_err(|err| (err, true))?;
+    user.check_enabled().map_err(|err| (err, true))?;
+    user.check_expired().map_err(|err| (err, true))?;
+
+    // the user has just proven the ownership of the E-Mail address
+    user.email_verified = true;
+    user.last_login = Some(OffsetDateTime::now_utc().unix_timestamp());
+    user.last_failed_login = None;
+    user.failed_login_attempts = None;
+    user.save(data, None, None)
+        .await
+        .map_err(|err| (err, true))?;
+
+    authorize_user(data, req, login.req, user, session, false).await
+}
+
 /// # Business logic for [POST /oidc/authorize/refresh](crate::handlers::post_authorize_refresh)
 pub async fn authorize_refresh(
     data: &web::Data<AppState>,
     session: &Session,
     client: Client,
     header_origin: Option<(HeaderName, HeaderValue)>,
-    req_data: LoginRefreshRequest,
+    mut req_data: LoginRefreshRequest,
 ) -> Result<AuthStep, ErrorResponse> {
+    if let Some(auth_request) =
+        ResolvedAuthRequest::find_required(data, &session.id, &client).await?
+    {
+        req_data.apply_auth_request(auth_request);
+    }
+
     let user_id = session.user_id.as_ref().ok_or_else(|| {
         ErrorResponse::new(
             ErrorResponseType::Internal,
@@ -303,9 +531,16 @@ pub async fn authorize_refresh(
     user.check_expired()?;
 
     client.validate_mfa(&user)?;
+    validate_acr_mfa(&req_data.acr_values, &user)?;
+    // a session which has been created without MFA must be upgraded, if the client demands it
+    let needs_mfa_step =
+        *SESSION_RENEW_MFA || (acr_values_require_mfa(&req_data.acr_values) && !session.is_mfa);
 
     let scopes = client.sanitize_login_scopes(&req_data.scopes)?;
-    let code_lifetime = if user.has_webauthn_enabled() {
+    let authorization_details =
+        AuthorizationDetails::try_for_client(req_data.authorization_details.as_deref(), &client)?;
+    Resource::find_for_request(data, req_data.resource.as_deref()).await?;
+    let code_lifetime = if user.has_mfa_enabled() {
         client.auth_code_lifetime + *WEBAUTHN_REQ_EXP as i32
     } else {
         client.auth_code_lifetime
@@ -319,19 +554,23 @@ pub async fn authorize_refresh(
         req_data.code_challenge_method,
         req_data.nonce,
         scopes,
+        authorization_details,
+        req_data.resource,
         code_lifetime,
     );
-    code.save(data).await?;
-
-    // build location header
-    let header_loc = if let Some(s) = req_data.state {
-        format!("{}?code={}&state={}", req_data.redirect_uri, code.id, s)
-    } else {
-        format!("{}?code={}", req_data.redirect_uri, code.id)
-    };
+    // points to the consent page, if the user has not granted all scopes yet
+    let header_loc = PendingConsent::code_location(
+        data,
+        code,
+        req_data.redirect_uri,
+        req_data.state,
+        client.require_consent,
+        prompt_contains(&req_data.prompt, "consent"),
+    )
+    .await?;
 
     // check if we need to validate the 2nd factor
-    if user.has_webauthn_enabled() && *SESSION_RENEW_MFA {
+    if user.has_webauthn_enabled() && needs_mfa_step {
         let step = AuthStepAwaitWebauthn {
             has_password_been_hashed: false,
             code: get_rand(48),
@@ -346,6 +585,7 @@ pub async fn authorize_refresh(
         let login_req = WebauthnLoginReq {
             code: step.code.clone(),
             user_id: user.id,
+            session_id: Some(step.session.id.clone()),
             header_loc,
             header_origin: step
                 .header_origin
@@ -355,6 +595,29 @@ pub async fn authorize_refresh(
         login_req.save(data).await?;
 
         Ok(AuthStep::AwaitWebauthn(step))
+    } else if user.has_totp_enabled() && needs_mfa_step {
+        let step = AuthStepAwaitTotp {
+            has_password_been_hashed: false,
+            code: get_rand(48),
+            header_csrf: Session::get_csrf_header(&session.csrf_token),
+            header_origin,
+            user_id: user.id.clone(),
+            exp: *WEBAUTHN_REQ_EXP,
+        };
+
+        let login_req = WebauthnLoginReq {
+            code: step.code.clone(),
+            user_id: user.id,
+            session_id: Some(session.id.clone()),
+            header_loc,
+            header_origin: step
+                .header_origin
+                .as_ref()
+                .map(|h| h.1.to_str().unwrap().to_string()),
+        };
+        login_req.save(data).await?;
+
+        Ok(AuthStep::AwaitTotp(step))
     } else {
         Ok(AuthStep::LoggedIn(AuthStepLoggedIn {
             has_password_been_hashed: false,
@@ -369,27 +632,134 @@ pub async fn authorize_refresh(
     }
 }
 
-/// Builds the access token for a user after all validation has been successful
-#[allow(clippy::type_complexity)]
+/// Checks if a `prompt=none` request can be served from the existing `session` without any user
+/// interaction. Returns the OIDC error code for the client redirect otherwise.
+pub async fn prompt_none_error(
+    data: &web::Data<AppState>,
+    session: &Session,
+    client: &Client,
+    req_data: &AuthRequest,
+) -> Option<&'static str> {
+    let user = match &session.user_id {
+        Some(user_id) => match User::find(data, user_id.clone()).await {
+            Ok(user) => user,
+            Err(_) => return Some("login_required"),
+        },
+        None => return Some("login_required"),
+    };
+    if user.check_enabled().is_err() || user.check_expired().is_err() {
+        return Some("login_required");
+    }
+
+    let mfa_required = req_data.requires_mfa() && !session.is_mfa;
+    let mfa_renew = user.has_mfa_enabled() && *SESSION_RENEW_MFA;
+    if mfa_required || mfa_renew || client.validate_mfa(&user).is_err() {
+        return Some("interaction_required");
+    }
+
+    if client.require_consent {
+        let scopes = req_data
+            .scope
+            .split(' ')
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let is_granted = match client.sanitize_login_scopes(&Some(scopes)) {
+            Ok(scopes) => matches!(
+                UserConsent::find(data, &user.id, &client.id).await,
+                Ok(Some(consent)) if consent.covers(&scopes)
+            ),
+            Err(_) => false,
+        };
+        if !is_granted {
+            return Some("consent_required");
+        }
+    }
+
+    None
+}
+
+/// Returns [ErrorResponseType::MfaRequired], if the `acr_values` demand an MFA login the user
+/// is not able to provide.
+fn validate_acr_mfa(acr_values: &Option<String>, user: &User) -> Result<(), ErrorResponse> {
+    if acr_values_require_mfa(acr_values) && !user.has_mfa_enabled() {
+        Err(ErrorResponse::new(
+            ErrorResponseType::MfaRequired,
+            "MFA is required by the 'acr_values'".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the `amr` value and the `auth_time` for the tokens of a user
+fn amr_auth_time(user: &User, is_auth_code_flow: bool) -> (String, i64) {
+    let now_ts = Utc::now().timestamp();
+    match user.has_mfa_enabled() {
+        true => {
+            if is_auth_code_flow {
+                // With active MFA, the auth_time is always 'now', because it must be re-validated each time
+                (JwtAmrValue::Mfa.to_string(), now_ts)
+            } else {
+                // TODO to get this 100% correct, we would need to do a DB migration with a new version and update
+                // something like a `last_auth` field in the DB each time. Not implemented now to not being forced into
+                // a new minor version just because of this small thing -> do in the future.
+                (
+                    JwtAmrValue::Pwd.to_string(),
+                    now_ts - *SESSION_LIFETIME as i64,
+                )
+            }
+        }
+        false => {
+            if is_auth_code_flow {
+                // TODO this is a bit inaccurate at this time as well -> improve with DB migration in future minor version
+                // it might be the case, that this was initiated with a direct refresh
+                (JwtAmrValue::Pwd.to_string(), now_ts)
+            } else {
+                // TODO make more accurate with future DB migration
+                (
+                    JwtAmrValue::Pwd.to_string(),
+                    now_ts - *SESSION_LIFETIME as i64,
+                )
+            }
+        }
+    }
+}
+
+/// Builds the access token for a user after all validation has been successful.
+/// The `audience` defaults to the `client_id`.
+/// With `ACCESS_TOKEN_RFC9068`, the token conforms to the JWT profile for access tokens.
+/// The `mfa_method` is the second factor, which has been recorded for the user's session.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub async fn build_access_token(
     user: Option<&User>,
     data: &web::Data<AppState>,
     client: &Client,
     dpop_fingerprint: Option<String>,
+    cert_thumbprint: Option<String>,
     lifetime: i64,
     scope: Option<String>,
     scope_customs: Option<(Vec<&Scope>, &Option<HashMap<String, Vec<u8>>>)>,
+    audience: Option<String>,
+    act: Option<JwtActClaim>,
+    authorization_details: Option<AuthorizationDetails>,
+    is_auth_code_flow: bool,
+    mfa_method: Option<&str>,
 ) -> Result<String, ErrorResponse> {
     let mut custom_claims = JwtAccessClaims {
         typ: JwtTokenType::Bearer,
         azp: client.id.to_string(),
         scope: scope.unwrap_or_else(|| client.default_scopes.clone().replace(',', " ")),
+        client_id: ACCESS_TOKEN_RFC9068.then(|| client.id.clone()),
+        auth_time: None,
+        acr: None,
         allowed_origins: None,
         email: None,
         preferred_username: None,
         roles: None,
         groups: None,
-        cnf: dpop_fingerprint.map(|jkt| JktClaim { jkt }),
+        cnf: JktClaim::opt_from(dpop_fingerprint, cert_thumbprint),
+        act,
+        authorization_details,
         custom: None,
     };
 
@@ -406,7 +776,22 @@ pub async fn build_access_token(
             custom_claims.groups = Some(user.get_groups());
         }
 
-        Some(&user.id)
+        if *ACCESS_TOKEN_RFC9068 {
+            // the acr must only claim an MFA, if the session has actually been logged in with one
+            let (_, auth_time) = amr_auth_time(user, is_auth_code_flow);
+            let acr = if mfa_method.is_some() {
+                JwtAmrValue::Mfa
+            } else {
+                JwtAmrValue::Pwd
+            };
+            custom_claims.acr = Some(acr.to_string());
+            custom_claims.auth_time = Some(auth_time);
+        }
+
+        Some(client.subject_for(data, &user.id).await?)
+    } else if *ACCESS_TOKEN_RFC9068 {
+        // RFC 9068 requires a `sub` in any case, which is the client itself without a user
+        Some(client.id.clone())
     } else {
         None
     };
@@ -436,7 +821,9 @@ pub async fn build_access_token(
         coarsetime::Duration::from_secs(lifetime as u64),
     )
     .with_issuer(data.issuer.clone())
-    .with_audience(client.id.to_string());
+    .with_audience(audience.unwrap_or_else(|| client.id.to_string()))
+    // the 'jti' is needed to be able to revoke access tokens before their expiry
+    .with_jwt_id(get_rand(24));
 
     if let Some(sub) = sub {
         claims = claims.with_subject(sub);
@@ -457,37 +844,15 @@ pub async fn build_id_token(
     scope: &str,
     scope_customs: Option<(Vec<&Scope>, &Option<HashMap<String, Vec<u8>>>)>,
     is_auth_code_flow: bool,
+    mfa_method: Option<&str>,
+    sid: Option<String>,
 ) -> Result<String, ErrorResponse> {
-    let now_ts = Utc::now().timestamp();
-    let (amr, auth_time) = match user.has_webauthn_enabled() {
-        true => {
-            if is_auth_code_flow {
-                // With active MFA, the auth_time is always 'now', because it must be re-validated each time
-                (JwtAmrValue::Mfa.to_string(), now_ts)
-            } else {
-                // TODO to get this 100% correct, we would need to do a DB migration with a new version and update
-                // something like a `last_auth` field in the DB each time. Not implemented now to not being forced into
-                // a new minor version just because of this small thing -> do in the future.
-                (
-                    JwtAmrValue::Pwd.to_string(),
-                    now_ts - *SESSION_LIFETIME as i64,
-                )
-            }
-        }
-        false => {
-            if is_auth_code_flow {
-                // TODO this is a bit inaccurate at this time as well -> improve with DB migration in future minor version
-                // it might be the case, that this was initiated with a direct refresh
-                (JwtAmrValue::Pwd.to_string(), now_ts)
-            } else {
-                // TODO make more accurate with future DB migration
-                (
-                    JwtAmrValue::Pwd.to_string(),
-                    now_ts - *SESSION_LIFETIME as i64,
-                )
-            }
-        }
-    };
+    let (amr, auth_time) = amr_auth_time(user, is_auth_code_flow);
+    let mut amr_values = vec![amr.clone()];
+    // only report the factor, which has actually been used for the login of the session
+    if amr == JwtAmrValue::Mfa.to_string() && mfa_method == Some(MfaMethod::Totp.as_str()) {
+        amr_values.push(JwtAmrValue::Otp.to_string());
+    }
 
     let webid =
         (*ENABLE_WEB_ID && scope.contains("webid")).then(|| WebId::resolve_webid_uri(&user.id));
@@ -495,7 +860,8 @@ pub async fn build_id_token(
     let mut custom_claims = JwtIdClaims {
         azp: client.id.clone(),
         typ: JwtTokenType::Id,
-        amr: vec![amr],
+        acr: Some(amr),
+        amr: amr_values,
         auth_time,
         preferred_username: user.email.clone(),
         email: None,
@@ -508,9 +874,10 @@ pub async fn build_id_token(
         phone: None,
         roles: user.get_roles(),
         groups: None,
-        cnf: dpop_fingerprint.map(|jkt| JktClaim { jkt }),
+        cnf: JktClaim::opt_from(dpop_fingerprint, None),
         custom: None,
         webid,
+        sid,
     };
 
     let mut user_values = None;
@@ -588,7 +955,7 @@ pub async fn build_id_token(
         custom_claims,
         coarsetime::Duration::from_secs(lifetime as u64),
     )
-    .with_subject(user.id.clone())
+    .with_subject(client.subject_for(data, &user.id).await?)
     .with_issuer(data.issuer.clone());
 
     // TODO should we maybe always include the "solid" claim here depending on if a webid exists?
@@ -606,10 +973,13 @@ pub async fn build_id_token(
         claims = claims.with_nonce(nonce);
     }
 
-    sign_id_token(data, claims, client).await
+    let id_token = sign_id_token(data, claims, client).await?;
+    client.encrypt_id_token(id_token).await
 }
 
-/// Builds the refresh token for a user after all validation has been successful
+/// Builds the refresh token for a user after all validation has been successful.
+/// The `family_id` must be given when the token replaces a rotated one.
+#[allow(clippy::too_many_arguments)]
 pub async fn build_refresh_token(
     user: &User,
     data: &web::Data<AppState>,
@@ -618,12 +988,17 @@ pub async fn build_refresh_token(
     access_token_lifetime: i64,
     scope: Option<String>,
     is_mfa: bool,
+    sid: Option<String>,
+    family_id: Option<String>,
+    authorization_details: Option<AuthorizationDetails>,
 ) -> Result<String, ErrorResponse> {
     let custom_claims = JwtRefreshClaims {
         azp: client.id.clone(),
         typ: JwtTokenType::Refresh,
         uid: user.id.clone(),
-        cnf: dpop_fingerprint.map(|jkt| JktClaim { jkt }),
+        cnf: JktClaim::opt_from(dpop_fingerprint, None),
+        sid,
+        authorization_details,
     };
 
     let claims = Claims::with_custom_claims(custom_claims, coarsetime::Duration::from_hours(48))
@@ -646,69 +1021,387 @@ pub async fn build_refresh_token(
         *exp,
         scope,
         is_mfa,
+        client.id.clone(),
+        family_id,
     )
     .await?;
 
     Ok(token)
 }
 
-#[inline(always)]
-pub fn get_bearer_token_from_header(headers: &HeaderMap) -> Result<String, ErrorResponse> {
-    let bearer = headers.get("Authorization").ok_or_else(|| {
-        ErrorResponse::new(
-            ErrorResponseType::Unauthorized,
-            String::from("Bearer Token missing"),
-        )
-    });
-    if bearer.is_err() {
+/// Saves a new Pushed Authorization Request for [POST /oidc/par](crate::handlers::post_par)
+#[tracing::instrument(skip_all, fields(client_id = req_data.client_id))]
+pub async fn pushed_auth_request(
+    data: &web::Data<AppState>,
+    req: HttpRequest,
+    req_data: ParRequest,
+) -> Result<ParResponse, ErrorResponse> {
+    let (client_id, client_secret) = req_data.try_get_client_id_secret(&req)?;
+    let client = Client::find(data, client_id).await?;
+    if !client.enabled {
         return Err(ErrorResponse::new(
-            ErrorResponseType::Unauthorized,
-            String::from("Authorization header missing"),
+            ErrorResponseType::BadRequest,
+            String::from("client is disabled"),
         ));
     }
-
-    let head_val = bearer?
-        .to_str()
-        .map_err(|_| {
-            ErrorResponse::new(
-                ErrorResponseType::Unauthorized,
-                String::from("Malformed Authorization Header. Could not extract token."),
-            )
-        })?
-        .to_string();
-
-    let (p, bearer) = head_val.split_once(' ').ok_or(("ERR", "")).map_err(|_| {
-        ErrorResponse::new(
-            ErrorResponseType::Unauthorized,
-            String::from("Malformed Authorization Header. Could not extract token."),
+    if client.confidential {
+        validate_client_auth(
+            data,
+            &req,
+            &client,
+            client_secret,
+            req_data.client_assertion.as_deref(),
+            req_data.client_assertion_type.as_deref(),
         )
-    })?;
-    if p.ne(TOKEN_BEARER) || bearer.is_empty() {
-        return Err(ErrorResponse::new(
-            ErrorResponseType::Unauthorized,
-            String::from("No bearer token given"),
-        ));
+        .await?;
+    }
+
+    client.validate_redirect_uri(&req_data.redirect_uri)?;
+    client.validate_code_challenge(&req_data.code_challenge, &req_data.code_challenge_method)?;
+    AuthorizationDetails::try_for_client(req_data.authorization_details.as_deref(), &client)?;
+    Resource::find_for_request(data, req_data.resource.as_deref()).await?;
+
+    let par = PushedAuthRequest::new(req_data.into_auth_request(client.id));
+    par.save(data).await?;
+
+    Ok(ParResponse {
+        request_uri: par.request_uri,
+        expires_in: *PAR_EXP,
+    })
+}
+
+/// Resolves the `request_uri` from a former Pushed Authorization Request for
+/// [GET /oidc/authorize](crate::handlers::get_authorize)
+pub async fn resolve_pushed_auth_request(
+    data: &web::Data<AppState>,
+    client_id: &str,
+    request_uri: String,
+) -> Result<AuthRequest, ErrorResponse> {
+    let par = PushedAuthRequest::find(data, request_uri).await?;
+    par.delete(data).await?;
+    if par.auth_request.client_id != client_id {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("'client_id' does not match the pushed authorization request"),
+        ));
+    }
+    Ok(par.auth_request)
+}
+
+/// Saves an authorization request, which has been resolved from a PAR or a signed request
+/// object, for the login flow of the given session.
+pub async fn save_resolved_auth_request(
+    data: &web::Data<AppState>,
+    session_id: &str,
+    auth_request: &AuthRequest,
+    is_par: bool,
+    is_request_object: bool,
+) -> Result<(), ErrorResponse> {
+    ResolvedAuthRequest {
+        session_id: session_id.to_string(),
+        is_par,
+        is_request_object,
+        auth_request: auth_request.clone(),
+    }
+    .save(data)
+    .await
+}
+
+/// Validates a signed request object (RFC 9101) and returns the contained authorization
+/// request for [GET /oidc/authorize](crate::handlers::get_authorize)
+#[tracing::instrument(skip_all, fields(client_id = client_id))]
+pub async fn resolve_request_object(
+    data: &web::Data<AppState>,
+    client_id: &str,
+    request: &str,
+) -> Result<AuthRequest, ErrorResponse> {
+    let client = find_enabled_client(data, client_id).await?;
+    let request_object = RequestObject::validated_from(data, &client, request).await?;
+    Ok(request_object.into_auth_request())
+}
+
+/// Fetches a signed request object (RFC 9101) by reference and returns the contained
+/// authorization request for [GET /oidc/authorize](crate::handlers::get_authorize).
+/// The `request_uri` must be registered for the client.
+#[tracing::instrument(skip_all, fields(client_id = client_id))]
+pub async fn resolve_request_uri(
+    data: &web::Data<AppState>,
+    client_id: &str,
+    request_uri: &str,
+) -> Result<AuthRequest, ErrorResponse> {
+    let client = find_enabled_client(data, client_id).await?;
+    let request = RequestObject::fetch(&client, request_uri).await?;
+    let request_object = RequestObject::validated_from(data, &client, &request).await?;
+    Ok(request_object.into_auth_request())
+}
+
+async fn find_enabled_client(
+    data: &web::Data<AppState>,
+    client_id: &str,
+) -> Result<Client, ErrorResponse> {
+    let client = Client::find(data, client_id.to_string()).await?;
+    if !client.enabled {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("client is disabled"),
+        ));
+    }
+    Ok(client)
+}
+
+/// Starts a new device authorization for [POST /oidc/device](crate::handlers::post_device_auth)
+#[tracing::instrument(skip_all, fields(client_id = req_data.client_id))]
+pub async fn device_authorization(
+    data: &web::Data<AppState>,
+    req: HttpRequest,
+    req_data: DeviceRequest,
+) -> Result<DeviceCodeResponse, ErrorResponse> {
+    let (client_id, client_secret) = req_data.try_get_client_id_secret(&req)?;
+    let client = Client::find(data, client_id).await?;
+    if !client.enabled {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("client is disabled"),
+        ));
+    }
+    if client.confidential {
+        validate_client_auth(
+            data,
+            &req,
+            &client,
+            client_secret,
+            req_data.client_assertion.as_deref(),
+            req_data.client_assertion_type.as_deref(),
+        )
+        .await?;
+    }
+    client.validate_flow(GRANT_TYPE_DEVICE_CODE)?;
+
+    let scopes = req_data
+        .scope
+        .map(|s| s.split(' ').map(String::from).collect::<Vec<String>>());
+    let scopes = client.sanitize_login_scopes(&scopes)?;
+
+    let code = DeviceAuthCode::new(client.id, scopes);
+    code.save(data).await?;
+
+    let verification_uri = format!("{}/oidc/device", data.issuer);
+    let verification_uri_complete = format!("{}?user_code={}", verification_uri, code.user_code);
+    Ok(DeviceCodeResponse {
+        device_code: code.device_code,
+        user_code: code.user_code,
+        verification_uri,
+        verification_uri_complete,
+        expires_in: *DEVICE_GRANT_CODE_LIFETIME,
+        interval: *DEVICE_GRANT_POLL_INTERVAL,
+    })
+}
+
+/// Accepts or denies a pending device authorization for the currently logged in user for
+/// [POST /oidc/device/verify](crate::handlers::post_device_verify)
+pub async fn device_verify(
+    data: &web::Data<AppState>,
+    session: &Session,
+    req_data: DeviceVerifyRequest,
+) -> Result<(), ErrorResponse> {
+    let user_id = session.user_id.clone().ok_or_else(|| {
+        ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            String::from("No user linked to this session"),
+        )
+    })?;
+
+    let user_code = DeviceAuthCode::normalize_user_code(&req_data.user_code);
+    let mut code = DeviceAuthCode::find_by_user_code(data, &user_code).await?;
+    if code.user_id.is_some() || code.denied {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("The 'user_code' has been used already"),
+        ));
+    }
+
+    if req_data.accept {
+        let user = User::find(data, user_id).await?;
+        user.check_enabled()?;
+        user.check_expired()?;
+        Client::find(data, code.client_id.clone())
+            .await?
+            .validate_mfa(&user)?;
+
+        code.user_id = Some(user.id);
+        code.is_mfa = session.is_mfa;
+    } else {
+        code.denied = true;
+    }
+    code.save(data).await
+}
+
+// A pending consent must only ever be handled by the session which started the login
+async fn find_pending_consent(
+    data: &web::Data<AppState>,
+    session: &Session,
+    id: String,
+) -> Result<PendingConsent, ErrorResponse> {
+    let pending = PendingConsent::find(data, id).await?;
+    if pending.code.session_id.as_deref() != Some(session.id.as_str()) {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::Forbidden,
+            String::from("The consent request belongs to another session"),
+        ));
+    }
+    Ok(pending)
+}
+
+/// Returns the data for the consent page for [GET /oidc/consent](crate::handlers::get_consent_html)
+pub async fn consent_data(
+    data: &web::Data<AppState>,
+    session: &Session,
+    id: String,
+) -> Result<ConsentData, ErrorResponse> {
+    let pending = find_pending_consent(data, session, id).await?;
+    let client = Client::find_maybe_ephemeral(data, pending.code.client_id.clone()).await?;
+
+    let all_scopes = Scope::find_all(data).await?;
+    let scopes = pending
+        .code
+        .scopes
+        .iter()
+        .map(|name| {
+            let mut attributes = Vec::new();
+            if let Some(scope) = all_scopes.iter().find(|s| &s.name == name) {
+                let attrs = [&scope.attr_include_access, &scope.attr_include_id];
+                for attr in attrs.into_iter().flatten().flat_map(|a| a.split(',')) {
+                    let attr = attr.trim();
+                    if !attr.is_empty() && !attributes.iter().any(|a| a == attr) {
+                        attributes.push(attr.to_string());
+                    }
+                }
+            }
+
+            ConsentScope {
+                name: name.clone(),
+                attributes,
+            }
+        })
+        .collect();
+
+    Ok(ConsentData {
+        id: pending.id,
+        client_id: client.id,
+        client_name: client.name,
+        scopes,
+    })
+}
+
+/// Accepts or declines a pending consent for [POST /oidc/consent](crate::handlers::post_consent)
+/// and returns the redirect location back to the client.
+pub async fn consent(
+    data: &web::Data<AppState>,
+    session: &Session,
+    req_data: ConsentRequest,
+) -> Result<String, ErrorResponse> {
+    let pending = find_pending_consent(data, session, req_data.id).await?;
+    pending.delete(data).await?;
+
+    if !req_data.accept {
+        info!(
+            "User '{}' declined the consent for client '{}'",
+            pending.code.user_id, pending.code.client_id
+        );
+        return PendingConsent::client_location(
+            &pending.redirect_uri,
+            ("error", "access_denied"),
+            pending.state.as_deref(),
+        );
+    }
+
+    let mut code = pending.code;
+    UserConsent::grant(data, &code.user_id, &code.client_id, &code.scopes).await?;
+
+    // the user may have spent some time on the consent page
+    let client = Client::find_maybe_ephemeral(data, code.client_id.clone()).await?;
+    code.exp = OffsetDateTime::now_utc()
+        .add(::time::Duration::seconds(client.auth_code_lifetime as i64))
+        .unix_timestamp();
+    code.save(data).await?;
+
+    PendingConsent::client_location(
+        &pending.redirect_uri,
+        ("code", &code.id),
+        pending.state.as_deref(),
+    )
+}
+
+#[inline(always)]
+pub fn get_bearer_token_from_header(headers: &HeaderMap) -> Result<String, ErrorResponse> {
+    let bearer = headers.get("Authorization").ok_or_else(|| {
+        ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            String::from("Bearer Token missing"),
+        )
+    });
+    if bearer.is_err() {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            String::from("Authorization header missing"),
+        ));
+    }
+
+    let head_val = bearer?
+        .to_str()
+        .map_err(|_| {
+            ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                String::from("Malformed Authorization Header. Could not extract token."),
+            )
+        })?
+        .to_string();
+
+    let (p, bearer) = head_val.split_once(' ').ok_or(("ERR", "")).map_err(|_| {
+        ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            String::from("Malformed Authorization Header. Could not extract token."),
+        )
+    })?;
+    if p.ne(TOKEN_BEARER) || bearer.is_empty() {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            String::from("No bearer token given"),
+        ));
     }
     Ok(bearer.to_string())
 }
 
 /// Returns the 'userInfo' for the [/oidc/userinfo endpoint](crate::handlers::get_userinfo)<br>
+/// The response will be encrypted, if the client has set an `userinfo_encrypted_response_alg`.
 pub async fn get_userinfo(
     data: &web::Data<AppState>,
     req: HttpRequest,
-) -> Result<Userinfo, ErrorResponse> {
+) -> Result<UserinfoResponse, ErrorResponse> {
     // get bearer token
     let bearer = get_bearer_token_from_header(req.headers())?;
 
     let claims = validate_token::<JwtCommonClaims>(data, &bearer).await?;
+    // a certificate-bound token must be presented with the same client certificate
+    if let Some(x5t) = claims.custom.cnf.as_ref().and_then(|c| c.x5t_s256.as_ref()) {
+        let cert = ClientCertificate::from_req(&req)?;
+        if cert.map(|c| c.thumbprint() != *x5t).unwrap_or(true) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                String::from("The access token is bound to another client certificate"),
+            ));
+        }
+    }
     let scope = claims.custom.scope.unwrap_or_else(|| "openid".to_string());
 
-    let uid = claims.subject.ok_or_else(|| {
+    let sub = claims.subject.ok_or_else(|| {
         ErrorResponse::new(
             ErrorResponseType::Internal,
             String::from("Token without 'sub' - could not extract the Principal"),
         )
     })?;
+    // the `sub` may be a pairwise one, which must stay the same for the userinfo
+    let uid = PairwiseSubject::resolve_user_id(data, sub.clone()).await?;
     let user = User::find(data, uid).await.map_err(|_| {
         ErrorResponse::new(
             ErrorResponseType::WWWAuthenticate("user-not-found".to_string()),
@@ -722,8 +1415,8 @@ pub async fn get_userinfo(
         (*ENABLE_WEB_ID && scope.contains("webid")).then(|| WebId::resolve_webid_uri(&user.id));
 
     let mut userinfo = Userinfo {
-        id: user.id.clone(),
-        sub: user.id.clone(),
+        id: sub.clone(),
+        sub,
         name: format!("{} {}", &user.given_name, &user.family_name),
         roles,
 
@@ -799,7 +1492,22 @@ pub async fn get_userinfo(
         }
     }
 
-    Ok(userinfo)
+    let client = Client::find_maybe_ephemeral(data, claims.custom.azp).await?;
+    if client.userinfo_signed_response_alg.is_some() {
+        let jwt = sign_userinfo(data, userinfo, &client).await?;
+        if client.is_userinfo_encrypted() {
+            let jwe = client.encrypt_userinfo(jwt.as_bytes(), true).await?;
+            return Ok(UserinfoResponse::Jwt(jwe));
+        }
+        return Ok(UserinfoResponse::Jwt(jwt));
+    }
+    if client.is_userinfo_encrypted() {
+        let payload = serde_json::to_vec(&userinfo)?;
+        let jwe = client.encrypt_userinfo(&payload, false).await?;
+        return Ok(UserinfoResponse::Jwt(jwe));
+    }
+
+    Ok(UserinfoResponse::Json(userinfo))
 }
 
 /// Returns [TokenInfo](crate::models::response::TokenInfo) for the
@@ -817,6 +1525,7 @@ pub async fn get_token_info(
             username: None,
             exp: None,
             cnf: None,
+            authorization_details: None,
         });
     }
 
@@ -827,6 +1536,7 @@ pub async fn get_token_info(
     let username = claims.subject;
     let exp = claims.expires_at.unwrap().as_secs();
     let cnf = claims.custom.cnf;
+    let authorization_details = claims.custom.authorization_details;
 
     Ok(TokenInfo {
         active: true,
@@ -835,6 +1545,7 @@ pub async fn get_token_info(
         username,
         exp: Some(exp),
         cnf,
+        authorization_details,
     })
 }
 
@@ -847,8 +1558,10 @@ pub async fn get_token_set(
     match req_data.grant_type.as_str() {
         "authorization_code" => grant_type_code(data, req, req_data).await,
         "client_credentials" => grant_type_credentials(data, req, req_data).await,
+        GRANT_TYPE_DEVICE_CODE => grant_type_device_code(data, req, req_data).await,
         "password" => grant_type_password(data, req, req_data).await,
         "refresh_token" => grant_type_refresh(data, req, req_data).await,
+        GRANT_TYPE_TOKEN_EXCHANGE => grant_type_token_exchange(data, req, req_data).await,
         _ => Err(ErrorResponse::new(
             ErrorResponseType::BadRequest,
             String::from("Invalid 'grant_type'"),
@@ -856,6 +1569,123 @@ pub async fn get_token_set(
     }
 }
 
+/// Authenticates a confidential client at the token, revocation, PAR or device authorization
+/// endpoint, either with its `client_secret`, with a `client_assertion` (RFC 7523) for
+/// `private_key_jwt` and `client_secret_jwt` or with its certificate for mutual TLS (RFC 8705).
+async fn validate_client_auth(
+    data: &web::Data<AppState>,
+    req: &HttpRequest,
+    client: &Client,
+    client_secret: Option<String>,
+    client_assertion: Option<&str>,
+    client_assertion_type: Option<&str>,
+) -> Result<(), ErrorResponse> {
+    if client.tls_client_auth_method.is_some() {
+        return validate_client_cert(req, client).await;
+    }
+
+    if client.uses_client_assertion() && client_assertion.is_none() {
+        warn!("'client_assertion' is missing for client '{}'", client.id);
+        return Err(ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            format!(
+                "The client must authenticate with '{}'",
+                client
+                    .token_endpoint_auth_method
+                    .as_deref()
+                    .unwrap_or_default()
+            ),
+        ));
+    }
+    if !client.uses_client_assertion() && client_assertion.is_some() {
+        warn!("Unexpected 'client_assertion' for client '{}'", client.id);
+        return Err(ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            String::from("The client is not registered for client assertions"),
+        ));
+    }
+
+    if let Some(assertion) = client_assertion {
+        if client_assertion_type != Some(CLIENT_ASSERTION_TYPE_JWT_BEARER) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!(
+                    "'client_assertion_type' must be '{}'",
+                    CLIENT_ASSERTION_TYPE_JWT_BEARER
+                ),
+            ));
+        }
+        return ClientAssertion::validate(data, client, assertion).await;
+    }
+
+    let secret = client_secret.ok_or_else(|| {
+        warn!("'client_secret' is missing");
+        ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("'client_secret' is missing"),
+        )
+    })?;
+
+    let is_basic_auth = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with("Basic "))
+        .unwrap_or(false);
+    let method_matches = match client.token_endpoint_auth_method.as_deref() {
+        Some("client_secret_basic") => is_basic_auth,
+        Some("client_secret_post") => !is_basic_auth,
+        _ => true,
+    };
+    if !method_matches {
+        warn!("Wrong client secret auth method for client '{}'", client.id);
+        return Err(ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            format!(
+                "The client must authenticate with '{}'",
+                client
+                    .token_endpoint_auth_method
+                    .as_deref()
+                    .unwrap_or_default()
+            ),
+        ));
+    }
+
+    client.validate_secret(&secret, req)
+}
+
+/// Authenticates a client, which is registered for mutual TLS, with the certificate of the
+/// current connection.
+async fn validate_client_cert(req: &HttpRequest, client: &Client) -> Result<(), ErrorResponse> {
+    let cert = ClientCertificate::from_req(req)?.ok_or_else(|| {
+        warn!("Client certificate for '{}' is missing", client.id);
+        ErrorResponse::new(
+            ErrorResponseType::Unauthorized,
+            String::from("A client certificate is required"),
+        )
+    })?;
+    cert.validate_client_auth(client).await
+}
+
+/// Returns the thumbprint of the client certificate, if the client requires certificate-bound
+/// access tokens (RFC 8705).
+fn opt_cert_thumbprint(
+    req: &HttpRequest,
+    client: &Client,
+) -> Result<Option<String>, ErrorResponse> {
+    if !client.tls_client_certificate_bound_access_tokens {
+        return Ok(None);
+    }
+
+    let cert = ClientCertificate::from_req(req)?.ok_or_else(|| {
+        ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            "A client certificate is required for certificate-bound access tokens".to_string(),
+        )
+    })?;
+    Ok(Some(cert.thumbprint()))
+}
+
 /// Return a [TokenSet](crate::models::response::TokenSet) for the `authorization_code` flow
 #[tracing::instrument(skip_all, fields(client_id = req_data.client_id, username = req_data.username))]
 async fn grant_type_code(
@@ -883,14 +1713,15 @@ async fn grant_type_code(
         })?;
     let header_origin = client.validate_origin(&req, &data.listen_scheme, &data.public_url)?;
     if client.confidential {
-        let secret = client_secret.ok_or_else(|| {
-            warn!("'client_secret' is missing");
-            ErrorResponse::new(
-                ErrorResponseType::BadRequest,
-                String::from("'client_secret' is missing"),
-            )
-        })?;
-        client.validate_secret(&secret, &req)?;
+        validate_client_auth(
+            data,
+            &req,
+            &client,
+            client_secret,
+            req_data.client_assertion.as_deref(),
+            req_data.client_assertion_type.as_deref(),
+        )
+        .await?;
     }
     client.validate_flow("authorization_code")?;
 
@@ -908,6 +1739,7 @@ async fn grant_type_code(
         } else {
             None
         };
+    let cert_thumbprint = opt_cert_thumbprint(&req, &client)?;
     if let Some(h) = header_origin {
         headers.push(h);
     }
@@ -977,23 +1809,62 @@ async fn grant_type_code(
     //
     // An additional check at this point does not provide any security benefit but only uses resources.
 
+    // the client may only narrow down the details, which have been granted with the code
+    let authorization_details = match req_data.authorization_details.as_deref() {
+        Some(value) => {
+            let requested = AuthorizationDetails::try_from_str(value)?;
+            match &code.authorization_details {
+                Some(granted) => requested.validate_subset_of(granted)?,
+                None => {
+                    return Err(ErrorResponse::new(
+                        ErrorResponseType::BadRequest,
+                        "invalid_authorization_details: none have been granted".to_string(),
+                    ))
+                }
+            }
+            Some(requested)
+        }
+        None => code.authorization_details.clone(),
+    };
+
+    // a resource from the authorization request cannot be changed anymore
+    let resource = match (&code.resource, &req_data.resource) {
+        (Some(granted), Some(requested)) if granted != requested => {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                "invalid_target: the resource differs from the authorization request".to_string(),
+            ));
+        }
+        (granted, requested) => {
+            Resource::find_for_request(data, granted.as_deref().or(requested.as_deref())).await?
+        }
+    };
+
     let user = User::find(data, code.user_id.clone()).await?;
+    let session = if let Some(sid) = &code.session_id {
+        Some(Session::find(data, sid.clone()).await?)
+    } else {
+        None
+    };
     let token_set = TokenSet::from_user(
         &user,
         data,
         &client,
         dpop_fingerprint,
+        cert_thumbprint,
         code.nonce.clone(),
         Some(code.scopes.join(" ")),
         true,
+        session.as_ref().and_then(|s| s.mfa_method.as_deref()),
+        code.session_id.as_deref().map(Session::sid_from_id),
+        None,
+        authorization_details,
+        resource.as_ref(),
     )
     .await?;
 
     // update session metadata
-    if code.session_id.is_some() {
-        let sid = code.session_id.as_ref().unwrap().clone();
-        let mut session = Session::find(data, sid).await?;
-
+    if let Some(mut session) = session {
         session.last_seen = OffsetDateTime::now_utc().unix_timestamp();
         session.state = SessionState::Auth;
         if let Err(err) = session.validate_user_expiry(&user) {
@@ -1005,6 +1876,11 @@ async fn grant_type_code(
         session.roles = Some(user.roles);
         session.groups = user.groups;
         session.save(data).await?;
+
+        // remember the client to be able to notify it about a logout of this session
+        if client.backchannel_logout_uri.is_some() || client.frontchannel_logout_uri.is_some() {
+            SessionClient::upsert(data, &session.id, &client.id, &code.user_id).await?;
+        }
     }
     code.delete(data).await?;
 
@@ -1023,13 +1899,6 @@ async fn grant_type_credentials(
     req: HttpRequest,
     req_data: TokenRequest,
 ) -> Result<(TokenSet, Vec<(HeaderName, HeaderValue)>), ErrorResponse> {
-    if req_data.client_secret.is_none() {
-        return Err(ErrorResponse::new(
-            ErrorResponseType::BadRequest,
-            String::from("'client_secret' is missing"),
-        ));
-    }
-
     let (client_id, client_secret) = req_data.try_get_client_id_secret(&req)?;
     let client = Client::find(data, client_id).await?;
     if !client.confidential {
@@ -1044,13 +1913,15 @@ async fn grant_type_credentials(
             String::from("client is disabled"),
         ));
     }
-    let secret = client_secret.ok_or_else(|| {
-        ErrorResponse::new(
-            ErrorResponseType::BadRequest,
-            String::from("'client_secret' is missing"),
-        )
-    })?;
-    client.validate_secret(&secret, &req)?;
+    validate_client_auth(
+        data,
+        &req,
+        &client,
+        client_secret,
+        req_data.client_assertion.as_deref(),
+        req_data.client_assertion_type.as_deref(),
+    )
+    .await?;
     client.validate_flow("client_credentials")?;
     let header_origin = client.validate_origin(&req, &data.listen_scheme, &data.public_url)?;
 
@@ -1067,6 +1938,7 @@ async fn grant_type_credentials(
         } else {
             None
         };
+    let cert_thumbprint = opt_cert_thumbprint(&req, &client)?;
     // We do not push the origin header, because client credentials should never be used from
     // any browser at all
 
@@ -1075,7 +1947,134 @@ async fn grant_type_credentials(
         ClientDyn::update_used(data, &client.id).await?;
     }
 
-    let ts = TokenSet::for_client_credentials(data, &client, dpop_fingerprint).await?;
+    let authorization_details =
+        AuthorizationDetails::try_for_client(req_data.authorization_details.as_deref(), &client)?;
+    let resource = Resource::find_for_request(data, req_data.resource.as_deref()).await?;
+    let ts = TokenSet::for_client_credentials(
+        data,
+        &client,
+        dpop_fingerprint,
+        cert_thumbprint,
+        authorization_details,
+        resource.as_ref(),
+    )
+    .await?;
+    Ok((ts, headers))
+}
+
+/// Return a [TokenSet](crate::models::response::TokenSet) for the
+/// `urn:ietf:params:oauth:grant-type:device_code` flow
+#[tracing::instrument(skip_all, fields(client_id = req_data.client_id))]
+async fn grant_type_device_code(
+    data: &web::Data<AppState>,
+    req: HttpRequest,
+    req_data: TokenRequest,
+) -> Result<(TokenSet, Vec<(HeaderName, HeaderValue)>), ErrorResponse> {
+    if req_data.device_code.is_none() {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("'device_code' is missing"),
+        ));
+    }
+
+    let (client_id, client_secret) = req_data.try_get_client_id_secret(&req)?;
+    let client = Client::find(data, client_id).await?;
+    let header_origin = client.validate_origin(&req, &data.listen_scheme, &data.public_url)?;
+    if client.confidential {
+        validate_client_auth(
+            data,
+            &req,
+            &client,
+            client_secret,
+            req_data.client_assertion.as_deref(),
+            req_data.client_assertion_type.as_deref(),
+        )
+        .await?;
+    }
+    client.validate_flow(GRANT_TYPE_DEVICE_CODE)?;
+
+    let mut code = DeviceAuthCode::find(data, req_data.device_code.unwrap())
+        .await?
+        .ok_or_else(|| {
+            ErrorResponse::new(
+                ErrorResponseType::ExpiredToken,
+                String::from("The 'device_code' does not exist or has expired"),
+            )
+        })?;
+    if code.client_id != client.id {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("Invalid 'client_id'"),
+        ));
+    }
+    if code.exp < OffsetDateTime::now_utc().unix_timestamp() {
+        code.delete(data).await?;
+        return Err(ErrorResponse::new(
+            ErrorResponseType::ExpiredToken,
+            String::from("The 'device_code' does not exist or has expired"),
+        ));
+    }
+    if code.denied {
+        code.delete(data).await?;
+        return Err(ErrorResponse::new(
+            ErrorResponseType::AccessDenied,
+            String::from("The authorization request has been denied"),
+        ));
+    }
+    if code.user_id.is_none() {
+        code.validate_poll(data).await?;
+        return Err(ErrorResponse::new(
+            ErrorResponseType::AuthorizationPending,
+            String::from("The authorization request is still pending"),
+        ));
+    }
+
+    let mut headers = Vec::new();
+    let dpop_fingerprint =
+        if let Some(proof) = DPoPProof::opt_validated_from(data, &req, &header_origin).await? {
+            if let Some(nonce) = &proof.claims.nonce {
+                headers.push((
+                    HeaderName::from_str(HEADER_DPOP_NONCE).unwrap(),
+                    HeaderValue::from_str(nonce).unwrap(),
+                ));
+            }
+            Some(proof.jwk_fingerprint()?)
+        } else {
+            None
+        };
+    let cert_thumbprint = opt_cert_thumbprint(&req, &client)?;
+    if let Some(h) = header_origin {
+        headers.push(h);
+    }
+
+    // the device code must only be usable once
+    code.delete(data).await?;
+
+    let user = User::find(data, code.user_id.unwrap()).await?;
+    user.check_enabled()?;
+    user.check_expired()?;
+
+    // update timestamp if it is a dynamic client
+    if client.is_dynamic() {
+        ClientDyn::update_used(data, &client.id).await?;
+    }
+
+    let ts = TokenSet::from_user(
+        &user,
+        data,
+        &client,
+        dpop_fingerprint,
+        cert_thumbprint,
+        None,
+        Some(code.scopes.join(" ")),
+        code.is_mfa,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await?;
     Ok((ts, headers))
 }
 
@@ -1101,20 +2100,25 @@ async fn grant_type_password(
 
     let (client_id, client_secret) = req_data.try_get_client_id_secret(&req)?;
     let email = req_data.username.as_ref().unwrap();
-    let password = req_data.password.unwrap();
 
     let client = Client::find(data, client_id).await?;
     let header_origin = client.validate_origin(&req, &data.listen_scheme, &data.public_url)?;
     if client.confidential {
-        let secret = client_secret.ok_or_else(|| {
-            ErrorResponse::new(
-                ErrorResponseType::BadRequest,
-                String::from("Missing 'client_secret'"),
-            )
-        })?;
-        client.validate_secret(&secret, &req)?;
+        validate_client_auth(
+            data,
+            &req,
+            &client,
+            client_secret,
+            req_data.client_assertion.as_deref(),
+            req_data.client_assertion_type.as_deref(),
+        )
+        .await?;
     }
     client.validate_flow("password")?;
+    let authorization_details =
+        AuthorizationDetails::try_for_client(req_data.authorization_details.as_deref(), &client)?;
+    let resource = Resource::find_for_request(data, req_data.resource.as_deref()).await?;
+    let password = req_data.password.unwrap();
 
     let mut headers = Vec::new();
     let dpop_fingerprint =
@@ -1129,6 +2133,7 @@ async fn grant_type_password(
         } else {
             None
         };
+    let cert_thumbprint = opt_cert_thumbprint(&req, &client)?;
     if let Some(h) = header_origin {
         headers.push(h);
     }
@@ -1151,21 +2156,12 @@ async fn grant_type_password(
     user.check_enabled()?;
     user.check_expired()?;
 
-    match user.validate_password(data, password.clone()).await {
+    match user.validate_password(data, password).await {
         Ok(_) => {
             user.last_login = Some(OffsetDateTime::now_utc().unix_timestamp());
             user.last_failed_login = None;
             user.failed_login_attempts = None;
-
-            // check if the password hash should be upgraded
-            let hash_uptodate = user.is_argon2_uptodate(&data.argon2_params)?;
-            if !hash_uptodate {
-                info!("Updating Argon2ID params for user '{}'", &user.email);
-                let new_hash = HashPassword::hash_password(password).await?;
-                // let new_hash = User::new_password_hash(&password, params).await?;
-                user.password = Some(new_hash);
-            }
-
+            // a possibly upgraded password hash is saved here as well
             user.save(data, None, None).await?;
 
             // update timestamp if it is a dynamic client
@@ -1173,8 +2169,22 @@ async fn grant_type_password(
                 ClientDyn::update_used(data, &client.id).await?;
             }
 
-            let ts = TokenSet::from_user(&user, data, &client, dpop_fingerprint, None, None, false)
-                .await?;
+            let ts = TokenSet::from_user(
+                &user,
+                data,
+                &client,
+                dpop_fingerprint,
+                cert_thumbprint,
+                None,
+                None,
+                false,
+                None,
+                None,
+                None,
+                authorization_details,
+                resource.as_ref(),
+            )
+            .await?;
             Ok((ts, headers))
         }
         Err(err) => {
@@ -1213,37 +2223,380 @@ async fn grant_type_refresh(
 
     let header_origin = client.validate_origin(&req, &data.listen_scheme, &data.public_url)?;
 
-    if client.confidential {
-        let secret = client_secret.ok_or_else(|| {
+    if client.confidential {
+        validate_client_auth(
+            data,
+            &req,
+            &client,
+            client_secret,
+            req_data.client_assertion.as_deref(),
+            req_data.client_assertion_type.as_deref(),
+        )
+        .await?;
+    }
+
+    client.validate_flow("refresh_token")?;
+
+    let refresh_token = req_data.refresh_token.unwrap();
+    // tokens for any registered resource can be requested with the same refresh token
+    let resource = Resource::find_for_request(data, req_data.resource.as_deref()).await?;
+
+    // validate common refresh token claims first and get the payload
+    let (ts, dpop_none) =
+        validate_refresh_token(Some(client), &refresh_token, data, &req, resource.as_ref()).await?;
+
+    let mut headers = Vec::new();
+    if let Some(h) = header_origin {
+        headers.push(h);
+    }
+    if let Some(nonce) = dpop_none {
+        headers.push((
+            HeaderName::from_str(HEADER_DPOP_NONCE).unwrap(),
+            HeaderValue::from_str(&nonce).unwrap(),
+        ));
+    }
+
+    Ok((ts, headers))
+}
+
+/// Return a [TokenSet](crate::models::response::TokenSet) for the
+/// `urn:ietf:params:oauth:grant-type:token-exchange` grant from RFC 8693
+#[tracing::instrument(skip_all, fields(client_id = req_data.client_id))]
+async fn grant_type_token_exchange(
+    data: &web::Data<AppState>,
+    req: HttpRequest,
+    req_data: TokenRequest,
+) -> Result<(TokenSet, Vec<(HeaderName, HeaderValue)>), ErrorResponse> {
+    let (client_id, client_secret) = req_data.try_get_client_id_secret(&req)?;
+    let client = Client::find(data, client_id).await?;
+    if !client.confidential {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("'token-exchange' is allowed for confidential clients only"),
+        ));
+    }
+    if !client.enabled {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("client is disabled"),
+        ));
+    }
+    validate_client_auth(
+        data,
+        &req,
+        &client,
+        client_secret,
+        req_data.client_assertion.as_deref(),
+        req_data.client_assertion_type.as_deref(),
+    )
+    .await?;
+    client.validate_flow(GRANT_TYPE_TOKEN_EXCHANGE)?;
+    let header_origin = client.validate_origin(&req, &data.listen_scheme, &data.public_url)?;
+
+    if let Some(typ) = &req_data.requested_token_type {
+        if typ != TOKEN_TYPE_ACCESS_TOKEN {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("Only '{}' can be requested", TOKEN_TYPE_ACCESS_TOKEN),
+            ));
+        }
+    }
+
+    let (subject_token, subject_token_type) =
+        match (&req_data.subject_token, &req_data.subject_token_type) {
+            (Some(token), Some(typ)) => (token, typ),
+            _ => {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    String::from("'subject_token' and 'subject_token_type' are mandatory"),
+                ));
+            }
+        };
+    // without any configured policy, only our own access tokens are accepted
+    let subject_types = client
+        .get_token_exchange_subject_types()
+        .unwrap_or_else(|| vec!["access_token".to_string()]);
+    let subject_type = subject_token_type
+        .strip_prefix("urn:ietf:params:oauth:token-type:")
+        .unwrap_or_default();
+    if !subject_types.iter().any(|t| t == subject_type) {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            format!(
+                "'subject_token_type' {} is not allowed for this client",
+                subject_token_type
+            ),
+        ));
+    }
+    let subject = TokenExchangeSubject::validated_from(
+        data,
+        subject_token,
+        subject_token_type,
+        Some(&client.id),
+    )
+    .await?;
+
+    // without any configured policy, the audience can only be the client itself
+    let audience = match &req_data.audience {
+        Some(aud) if aud != &client.id => {
+            let allowed = client.get_token_exchange_audiences().unwrap_or_default();
+            if !allowed.contains(aud) {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    format!("'audience' {} is not allowed for this client", aud),
+                ));
+            }
+            aud.clone()
+        }
+        _ => client.id.clone(),
+    };
+
+    // Scopes can only be narrowed down from the subject token and must be allowed by the policy.
+    // Without any configured policy, the client scopes are allowed.
+    let scopes_allowed = client
+        .get_token_exchange_scopes()
+        .unwrap_or_else(|| client.get_scopes());
+    let is_allowed = |scope: &str| {
+        scopes_allowed.iter().any(|s| s == scope)
+            && subject
+                .scope
+                .as_ref()
+                .map(|scp| scp.split(' ').any(|s| s == scope))
+                .unwrap_or(true)
+    };
+    let scope = if let Some(scope) = &req_data.scope {
+        for s in scope.split(' ').filter(|s| !s.is_empty()) {
+            if !is_allowed(s) {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    format!("'scope' {} is not allowed", s),
+                ));
+            }
+        }
+        scope.clone()
+    } else {
+        subject
+            .scope
+            .clone()
+            .unwrap_or_else(|| client.default_scopes.replace(',', " "))
+            .split(' ')
+            .filter(|s| is_allowed(s))
+            .collect::<Vec<&str>>()
+            .join(" ")
+    };
+
+    let actor = if let Some(actor_token) = &req_data.actor_token {
+        let actor_token_type = req_data.actor_token_type.as_deref().ok_or_else(|| {
             ErrorResponse::new(
                 ErrorResponseType::BadRequest,
-                String::from("'client_secret' is missing"),
+                String::from("'actor_token_type' is mandatory with an 'actor_token'"),
             )
         })?;
-        client.validate_secret(&secret, &req)?;
-    }
-
-    client.validate_flow("refresh_token")?;
-
-    let refresh_token = req_data.refresh_token.unwrap();
+        Some(TokenExchangeSubject::validated_from(data, actor_token, actor_token_type, None).await?)
+    } else {
+        None
+    };
 
-    // validate common refresh token claims first and get the payload
-    let (ts, dpop_none) = validate_refresh_token(Some(client), &refresh_token, data, &req).await?;
+    // the exchanged token must never outlive the subject token
+    let mut lifetime = client.access_token_lifetime as i64;
+    if let Some(exp) = subject.exp {
+        let left = exp - OffsetDateTime::now_utc().unix_timestamp();
+        if left < 1 {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                String::from("The 'subject_token' has expired"),
+            ));
+        }
+        if left < lifetime {
+            lifetime = left;
+        }
+    }
 
     let mut headers = Vec::new();
+    let dpop_fingerprint =
+        if let Some(proof) = DPoPProof::opt_validated_from(data, &req, &header_origin).await? {
+            if let Some(nonce) = &proof.claims.nonce {
+                headers.push((
+                    HeaderName::from_str(HEADER_DPOP_NONCE).unwrap(),
+                    HeaderValue::from_str(nonce).unwrap(),
+                ));
+            }
+            Some(proof.jwk_fingerprint()?)
+        } else {
+            None
+        };
+    let cert_thumbprint = opt_cert_thumbprint(&req, &client)?;
     if let Some(h) = header_origin {
         headers.push(h);
     }
-    if let Some(nonce) = dpop_none {
-        headers.push((
-            HeaderName::from_str(HEADER_DPOP_NONCE).unwrap(),
-            HeaderValue::from_str(&nonce).unwrap(),
-        ));
+
+    subject.validate_binding(&req, dpop_fingerprint.as_deref())?;
+    if let Some(actor) = &actor {
+        actor.validate_binding(&req, dpop_fingerprint.as_deref())?;
     }
 
+    // with an actor token, this is a delegation and any former actor is nested
+    let act = match actor {
+        Some(actor) => Some(JwtActClaim {
+            sub: actor.sub,
+            act: subject.act.map(Box::new),
+        }),
+        None => subject.act,
+    };
+
+    let ts = TokenSet::for_token_exchange(
+        data,
+        &client,
+        subject.user.as_ref(),
+        dpop_fingerprint,
+        cert_thumbprint,
+        lifetime,
+        scope,
+        audience,
+        act,
+    )
+    .await?;
     Ok((ts, headers))
 }
 
+/// The validated `subject_token` or `actor_token` of a Token Exchange
+struct TokenExchangeSubject {
+    /// The user, if the token has been issued for one
+    user: Option<User>,
+    /// The user id, or the `client_id` for a token without any user
+    sub: String,
+    scope: Option<String>,
+    exp: Option<i64>,
+    act: Option<JwtActClaim>,
+    /// The DPoP key or client certificate the token is bound to
+    cnf: Option<JktClaim>,
+}
+
+impl TokenExchangeSubject {
+    /// Validates a `subject_token` or `actor_token`. With an `audience`, our own tokens must have
+    /// been issued to, or for, this client, so that a client cannot exchange any token it
+    /// gets hold of.
+    async fn validated_from(
+        data: &web::Data<AppState>,
+        token: &str,
+        token_type: &str,
+        audience: Option<&str>,
+    ) -> Result<Self, ErrorResponse> {
+        let (user, sub, scope, exp, act, cnf) = match token_type {
+            TOKEN_TYPE_ACCESS_TOKEN | TOKEN_TYPE_ID_TOKEN => {
+                let claims = validate_token::<JwtCommonClaims>(data, token).await?;
+                let typ_expected = if token_type == TOKEN_TYPE_ID_TOKEN {
+                    JwtTokenType::Id
+                } else {
+                    JwtTokenType::Bearer
+                };
+                if claims.custom.typ != typ_expected {
+                    return Err(ErrorResponse::new(
+                        ErrorResponseType::BadRequest,
+                        format!("The token is not a valid {}", token_type),
+                    ));
+                }
+
+                if let Some(aud) = audience {
+                    let is_aud = claims
+                        .audiences
+                        .as_ref()
+                        .map(|auds| auds.contains(&HashSet::from([aud.to_string()])))
+                        .unwrap_or(false);
+                    if !is_aud && claims.custom.azp != aud {
+                        warn!(
+                            "Token Exchange with a foreign 'subject_token' by client '{}'",
+                            aud
+                        );
+                        return Err(ErrorResponse::new(
+                            ErrorResponseType::Unauthorized,
+                            format!("The {} was not issued for this client", token_type),
+                        ));
+                    }
+                }
+
+                let exp = claims.expires_at.map(|exp| exp.as_secs() as i64);
+                // RFC 9068 tokens without a user have the client itself as the `sub`
+                let user = match &claims.subject {
+                    Some(sub) if sub != &claims.custom.azp => {
+                        let uid = PairwiseSubject::resolve_user_id(data, sub.clone()).await?;
+                        Some(User::find(data, uid).await?)
+                    }
+                    _ => None,
+                };
+                let sub = claims.subject.unwrap_or(claims.custom.azp);
+                (
+                    user,
+                    sub,
+                    claims.custom.scope,
+                    exp,
+                    claims.custom.act,
+                    claims.custom.cnf,
+                )
+            }
+            // upstream provider tokens are only allowed by an explicit client policy
+            TOKEN_TYPE_JWT => {
+                let (user, exp) = AuthProvider::find_user_by_token(data, token).await?;
+                let sub = user.id.clone();
+                (Some(user), sub, None, exp, None, None)
+            }
+            _ => {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    format!("Unsupported token type {}", token_type),
+                ));
+            }
+        };
+
+        if let Some(user) = &user {
+            user.check_enabled()?;
+            user.check_expired()?;
+        }
+
+        Ok(Self {
+            user,
+            sub,
+            scope,
+            exp,
+            act,
+            cnf,
+        })
+    }
+
+    /// A sender-constrained token can only be exchanged with a DPoP proof for the same key, or
+    /// over a connection with the same client certificate. The binding is kept for the new token.
+    fn validate_binding(
+        &self,
+        req: &HttpRequest,
+        dpop_fingerprint: Option<&str>,
+    ) -> Result<(), ErrorResponse> {
+        let cnf = if let Some(cnf) = &self.cnf {
+            cnf
+        } else {
+            return Ok(());
+        };
+
+        if let Some(jkt) = &cnf.jkt {
+            if dpop_fingerprint != Some(jkt.as_str()) {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::Unauthorized,
+                    String::from("The token is bound to another DPoP key"),
+                ));
+            }
+        }
+        if let Some(x5t) = &cnf.x5t_s256 {
+            let thumbprint = ClientCertificate::from_req(req)?.map(|cert| cert.thumbprint());
+            if thumbprint.as_ref() != Some(x5t) {
+                return Err(ErrorResponse::new(
+                    ErrorResponseType::Unauthorized,
+                    String::from("The token is bound to another client certificate"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 /**
 Handles the login delay.
 
@@ -1467,6 +2820,9 @@ pub async fn handle_login_delay(
 }
 
 /// Returns the Logout HTML Page for [GET /oidc/logout](crate::handlers::get_logout)
+///
+/// The page receives the `frontchannel_logout_uri`s of all clients, which have been used with
+/// this session, and the validated `post_logout_redirect_uri` including the `state`.
 pub async fn logout(
     logout_request: LogoutRequest,
     session: &Session,
@@ -1475,13 +2831,58 @@ pub async fn logout(
 ) -> Result<String, ErrorResponse> {
     let colors = ColorEntity::find_rauthy(data).await?;
 
+    let mut logout_data = LogoutData {
+        frontchannel_logout_uris: frontchannel_logout_uris(data, session).await,
+        post_logout_redirect_uri: None,
+    };
+
     if logout_request.id_token_hint.is_none() {
-        return Ok(LogoutHtml::build(&session.csrf_token, false, &colors, lang));
+        let json = serde_json::to_string(&logout_data)?;
+        return Ok(LogoutHtml::build(
+            &session.csrf_token,
+            false,
+            &json,
+            &colors,
+            lang,
+        ));
     }
 
+    // from here on, the token_hint contains a valid ID token -> skip the logout confirmation
+    logout_data.post_logout_redirect_uri = post_logout_redirect(data, &logout_request).await?;
+
+    let json = serde_json::to_string(&logout_data)?;
+    Ok(LogoutHtml::build(
+        &session.csrf_token,
+        true,
+        &json,
+        &colors,
+        lang,
+    ))
+}
+
+/// Validates the `id_token_hint` and the `post_logout_redirect_uri` of an RP-initiated logout.
+/// Returns the final redirect location including the `state`, if a `post_logout_redirect_uri`
+/// has been given.
+///
+/// A `post_logout_redirect_uri` is only accepted together with a valid `id_token_hint`, because
+/// it must be checked against the allowed URIs of the client the token was issued to.
+pub async fn post_logout_redirect(
+    data: &web::Data<AppState>,
+    logout_request: &LogoutRequest,
+) -> Result<Option<String>, ErrorResponse> {
+    let token_raw = match &logout_request.id_token_hint {
+        Some(token) => token,
+        None if logout_request.post_logout_redirect_uri.is_some() => {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                String::from("'post_logout_redirect_uri' requires an 'id_token_hint'"),
+            ));
+        }
+        None => return Ok(None),
+    };
+
     // check if the provided token hint is a valid
-    let token_raw = logout_request.id_token_hint.unwrap();
-    let claims = validate_token::<JwtIdClaims>(data, &token_raw).await?;
+    let claims = validate_token::<JwtIdClaims>(data, token_raw).await?;
 
     // check if it is an ID token
     if JwtTokenType::Id != claims.custom.typ {
@@ -1491,39 +2892,179 @@ pub async fn logout(
         ));
     }
 
-    // from here on, the token_hint contains a valid ID token -> skip the logout confirmation
-    if logout_request.post_logout_redirect_uri.is_some() {
-        // unwrap is safe since the token is valid already
-        let client_id = claims.custom.azp;
-        let client = Client::find(data, client_id).await?;
-        if client.post_logout_redirect_uris.is_none() {
+    let target = match &logout_request.post_logout_redirect_uri {
+        Some(target) => target,
+        None => return Ok(None),
+    };
+
+    let client = Client::find(data, claims.custom.azp).await?;
+    let uri_vec = match client.get_post_logout_uris() {
+        Some(uris) => uris,
+        None => {
             return Err(ErrorResponse::new(
                 ErrorResponseType::BadRequest,
                 String::from("Given 'post_logout_redirect_uri' is not allowed"),
             ));
         }
+    };
+    let is_valid = uri_vec.iter().any(|uri| {
+        if uri.ends_with('*') && target.starts_with(uri.split_once('*').unwrap().0) {
+            return true;
+        }
+        target.eq(uri)
+    });
+    if !is_valid {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("Given 'post_logout_redirect_uri' is not allowed"),
+        ));
+    }
+
+    // redirect uri is valid at this point
+    let mut uri = Url::parse(target).map_err(|_| {
+        ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("Cannot parse the 'post_logout_redirect_uri'"),
+        )
+    })?;
+    if let Some(state) = &logout_request.state {
+        uri.query_pairs_mut().append_pair("state", state);
+    }
+    Ok(Some(uri.to_string()))
+}
+
+/// Builds the `frontchannel_logout_uri`s for OIDC Front-Channel Logout of all clients, which
+/// have been used with the given session. Errors are only logged, since they must never
+/// prevent the logout itself.
+async fn frontchannel_logout_uris(data: &web::Data<AppState>, session: &Session) -> Vec<String> {
+    let session_clients = match SessionClient::find_for_session(data, &session.id).await {
+        Ok(sc) => sc,
+        Err(err) => {
+            error!("Error looking up frontchannel logout sessions: {}", err);
+            return Vec::default();
+        }
+    };
+
+    let sid = Session::sid_from_id(&session.id);
+    let mut uris = Vec::with_capacity(session_clients.len());
+    for sc in session_clients {
+        let client = match Client::find(data, sc.client_id).await {
+            Ok(client) => client,
+            Err(err) => {
+                error!("Error looking up client for frontchannel logout: {}", err);
+                continue;
+            }
+        };
+        let uri = match &client.frontchannel_logout_uri {
+            Some(uri) => uri,
+            None => continue,
+        };
 
-        let target = logout_request.post_logout_redirect_uri.unwrap();
-        let uri_vec = client.get_post_logout_uris();
-        let valid_redirect = uri_vec.as_ref().unwrap().iter().filter(|uri| {
-            if uri.ends_with('*') && target.starts_with(uri.split_once('*').unwrap().0) {
-                return true;
+        match Url::parse(uri) {
+            // the uri will be loaded inside an iframe and must never be anything else than https
+            Ok(url) if url.scheme() != "https" && !(*DEV_MODE && url.scheme() == "http") => {
+                error!(
+                    "Skipping frontchannel_logout_uri with scheme '{}' for client {}",
+                    url.scheme(),
+                    client.id
+                );
             }
-            if target.eq(*uri) {
-                return true;
+            Ok(mut url) => {
+                url.query_pairs_mut()
+                    .append_pair("iss", &data.issuer)
+                    .append_pair("sid", &sid);
+                uris.push(url.to_string());
             }
-            false
-        });
-        if valid_redirect.count() == 0 {
+            Err(err) => error!(
+                "Invalid frontchannel_logout_uri for client {}: {}",
+                client.id, err
+            ),
+        }
+    }
+    uris
+}
+
+/// Revokes a refresh token or an access token via its `jti` for
+/// [POST /oidc/revoke](crate::handlers::post_revoke) as described in RFC 7009.
+///
+/// The `token_type_hint` is only validated, since the type can be read from the token itself.
+/// Invalid or expired tokens will not produce an error, as the spec demands.
+#[tracing::instrument(skip_all, fields(client_id = req_data.client_id))]
+pub async fn revoke_token(
+    data: &web::Data<AppState>,
+    req: HttpRequest,
+    req_data: TokenRevocationRequest,
+) -> Result<Option<(HeaderName, HeaderValue)>, ErrorResponse> {
+    let (client_id, client_secret) = req_data.try_get_client_id_secret(&req)?;
+    let client = Client::find_maybe_ephemeral(data, client_id).await?;
+
+    let header_origin = client.validate_origin(&req, &data.listen_scheme, &data.public_url)?;
+
+    if client.confidential || client.tls_client_auth_method.is_some() {
+        validate_client_auth(
+            data,
+            &req,
+            &client,
+            client_secret,
+            req_data.client_assertion.as_deref(),
+            req_data.client_assertion_type.as_deref(),
+        )
+        .await?;
+    }
+
+    let claims = match validate_token::<JwtCommonClaims>(data, &req_data.token).await {
+        Ok(claims) => claims,
+        Err(err) => {
+            debug!("Ignoring revocation for an invalid token: {}", err.message);
+            return Ok(header_origin);
+        }
+    };
+
+    if client.id != claims.custom.azp {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("Invalid 'azp'"),
+        ));
+    }
+
+    match claims.custom.typ {
+        JwtTokenType::Refresh => {
+            let token = &req_data.token;
+            let (_, validation_str) = token.split_at(token.len() - 49);
+            // the token may have been deleted already by the cleanup scheduler
+            if let Ok(rt) = RefreshToken::find(data, validation_str).await {
+                // rotated successors must not stay valid
+                match &rt.family_id {
+                    Some(family_id) => RefreshToken::delete_family(data, family_id).await?,
+                    None => rt.delete(data).await?,
+                }
+            }
+        }
+
+        JwtTokenType::Bearer | JwtTokenType::DPoP => {
+            let jti = claims.jwt_id.ok_or_else(|| {
+                ErrorResponse::new(
+                    ErrorResponseType::BadRequest,
+                    String::from("The access token does not contain a 'jti' and cannot be revoked"),
+                )
+            })?;
+            // the revocation must be kept as long as the token itself could be valid
+            let exp = claims
+                .expires_at
+                .map(|exp| exp.as_secs() as i64)
+                .unwrap_or(i64::MAX);
+            RevokedToken::insert(data, jti, exp).await?;
+        }
+
+        JwtTokenType::Id => {
             return Err(ErrorResponse::new(
                 ErrorResponseType::BadRequest,
-                String::from("Given 'post_logout_redirect_uri' is not allowed"),
+                String::from("unsupported_token_type"),
             ));
         }
-        // redirect uri is valid at this point
     }
 
-    Ok(LogoutHtml::build(&session.csrf_token, true, &colors, lang))
+    Ok(header_origin)
 }
 
 // TODO move into entity
@@ -1605,6 +3146,34 @@ pub async fn rotate_jwks(data: &web::Data<AppState>) -> Result<(), ErrorResponse
     };
     entity.save(&data.db).await?;
 
+    // ES256
+    let jwk_plain = web::block(|| ES256KeyPair::generate().with_key_id(&get_rand(24))).await?;
+    let jwk = EncValue::encrypt(jwk_plain.to_der().unwrap().as_slice())?
+        .into_bytes()
+        .to_vec();
+    let entity = Jwk {
+        kid: jwk_plain.key_id().as_ref().unwrap().clone(),
+        created_at: OffsetDateTime::now_utc().unix_timestamp(),
+        signature: JwkKeyPairAlg::ES256,
+        enc_key_id: enc_key_active.to_string(),
+        jwk,
+    };
+    entity.save(&data.db).await?;
+
+    // ES384
+    let jwk_plain = web::block(|| ES384KeyPair::generate().with_key_id(&get_rand(24))).await?;
+    let jwk = EncValue::encrypt(jwk_plain.to_der().unwrap().as_slice())?
+        .into_bytes()
+        .to_vec();
+    let entity = Jwk {
+        kid: jwk_plain.key_id().as_ref().unwrap().clone(),
+        created_at: OffsetDateTime::now_utc().unix_timestamp(),
+        signature: JwkKeyPairAlg::ES384,
+        enc_key_id: enc_key_active.to_string(),
+        jwk,
+    };
+    entity.save(&data.db).await?;
+
     // clear all latest_jwk from cache
     cache_del(
         CACHE_NAME_12HR.to_string(),
@@ -1630,6 +3199,18 @@ pub async fn rotate_jwks(data: &web::Data<AppState>) -> Result<(), ErrorResponse
         &data.caches.ha_cache_config,
     )
     .await?;
+    cache_del(
+        CACHE_NAME_12HR.to_string(),
+        format!("{}{}", IDX_JWK_LATEST, JwkKeyPairAlg::ES256.to_string()),
+        &data.caches.ha_cache_config,
+    )
+    .await?;
+    cache_del(
+        CACHE_NAME_12HR.to_string(),
+        format!("{}{}", IDX_JWK_LATEST, JwkKeyPairAlg::ES384.to_string()),
+        &data.caches.ha_cache_config,
+    )
+    .await?;
 
     // clear the all_certs / JWKS cache
     cache_del(
@@ -1639,6 +3220,11 @@ pub async fn rotate_jwks(data: &web::Data<AppState>) -> Result<(), ErrorResponse
     )
     .await?;
 
+    // the signed metadata must always be signed with the latest key
+    if *ENABLE_SIGNED_METADATA {
+        WellKnown::rebuild(data).await?;
+    }
+
     info!("Finished JWKS rotation");
 
     data.tx_events
@@ -1657,7 +3243,15 @@ async fn sign_access_token(
 ) -> Result<String, ErrorResponse> {
     let key_pair_type = JwkKeyPairAlg::from_str(&client.access_token_alg)?;
     let kp = JwkKeyPair::find_latest(data, &client.access_token_alg, key_pair_type).await?;
-    sign_jwt!(kp, claims)
+    if *ACCESS_TOKEN_RFC9068 {
+        let header_opts = HeaderOptions {
+            signature_type: Some(JWT_TYP_ACCESS_TOKEN.to_string()),
+            ..Default::default()
+        };
+        sign_jwt!(kp, claims, &header_opts)
+    } else {
+        sign_jwt!(kp, claims)
+    }
 }
 
 /// Signs an id token
@@ -1671,6 +3265,28 @@ async fn sign_id_token(
     sign_jwt!(kp, claims)
 }
 
+/// Signs a userinfo response. The `sub` is already part of the userinfo itself.
+async fn sign_userinfo(
+    data: &web::Data<AppState>,
+    userinfo: Userinfo,
+    client: &Client,
+) -> Result<String, ErrorResponse> {
+    let alg = client
+        .userinfo_signed_response_alg
+        .clone()
+        .unwrap_or_else(|| client.id_token_alg.clone());
+    let key_pair_type = JwkKeyPairAlg::from_str(&alg)?;
+    let kp = JwkKeyPair::find_latest(data, &alg, key_pair_type).await?;
+
+    let claims = Claims::with_custom_claims(
+        userinfo,
+        coarsetime::Duration::from_secs(client.access_token_lifetime as u64),
+    )
+    .with_issuer(data.issuer.clone())
+    .with_audience(client.id.clone());
+    sign_jwt!(kp, claims)
+}
+
 /// Signs a refresh token
 async fn sign_refresh_token(
     data: &web::Data<AppState>,
@@ -1731,6 +3347,7 @@ pub async fn validate_refresh_token(
     refresh_token: &str,
     data: &web::Data<AppState>,
     req: &HttpRequest,
+    resource: Option<&Resource>,
 ) -> Result<(TokenSet, Option<String>), ErrorResponse> {
     let options = VerificationOptions {
         // allowed_audiences: Some(HashSet::from_strings(&[&])), // TODO change after making client non-opt
@@ -1772,11 +3389,11 @@ pub async fn validate_refresh_token(
     let header_origin = client.validate_origin(req, &data.listen_scheme, &data.public_url)?;
 
     // validate DPoP proof
-    let (dpop_fingerprint, dpop_nonce) = if let Some(cnf) = claims.custom.cnf {
+    let (dpop_fingerprint, dpop_nonce) = if let Some(jkt) = claims.custom.cnf.and_then(|c| c.jkt) {
         // if the refresh token contains the 'cnf' header, we must validate the DPoP as well
         if let Some(proof) = DPoPProof::opt_validated_from(data, req, &header_origin).await? {
             let fingerprint = proof.jwk_fingerprint()?;
-            if fingerprint != cnf.jkt {
+            if fingerprint != jkt {
                 return Err(ErrorResponse::new(
                     ErrorResponseType::Forbidden,
                     "The refresh token is bound to a missing DPoP proof".to_string(),
@@ -1793,23 +3410,84 @@ pub async fn validate_refresh_token(
     } else {
         (None, None)
     };
+    let cert_thumbprint = opt_cert_thumbprint(req, &client)?;
 
     // validate that it exists in the db
     let (_, validation_str) = refresh_token.split_at(refresh_token.len() - 49);
 
     let mut rt = RefreshToken::find(data, validation_str).await?;
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+
+    // the token may only ever be used by the client it has been issued to
+    if let Some(client_id) = &rt.client_id {
+        if client_id != &client.id {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                String::from("Refresh Token has been issued to another client"),
+            ));
+        }
+    }
+
+    // Rotate the current refresh token with its first usage. It is kept until it expires to be
+    // able to detect a possible reuse. The rotation is marked atomically, so only a single one of
+    // possibly racing requests can ever be the first usage.
+    let is_legacy = rt.family_id.is_none();
+    let is_first_usage = rt.rotated_at.is_none() && rt.mark_rotated(data, now, &client.id).await?;
+    if !is_first_usage && rt.rotated_at.is_none() {
+        // a concurrent request has rotated the token in the meantime
+        rt = RefreshToken::find(data, validation_str).await?;
+    }
+
+    // A rotated token is only accepted a single time again within the grace time to allow a
+    // concurrent request. Any other usage is a replay, which means the whole token family is
+    // compromised.
+    if let (false, Some(rotated_at)) = (is_first_usage, rt.rotated_at) {
+        let is_replay = rotated_at + (data.refresh_grace_time as i64) < now
+            || !rt.mark_replayed(data, now).await?;
+        if is_replay {
+            let family_id = rt.family_id.as_deref().unwrap_or(&rt.id);
+            RefreshToken::delete_family(data, family_id).await?;
+
+            warn!(
+                "Reuse of a rotated refresh token for user {} and client {} - revoked its family",
+                rt.user_id, client.id
+            );
+            data.tx_events
+                .send_async(Event::refresh_token_reuse(
+                    format!("User {} / Client {}", rt.user_id, client.id),
+                    real_ip_from_req(req),
+                ))
+                .await
+                .unwrap();
+
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                String::from(
+                    "Refresh Token has been used already. All tokens derived from it \
+                    have been invalidated now because of misuse.",
+                ),
+            ));
+        }
+    }
 
     // check expires_at from the db entry
-    if rt.exp < OffsetDateTime::now_utc().unix_timestamp() {
-        // if an already used refresh token was provided again, invalidate all existing ones for the
-        // user as well to prevent possible security issues
-        RefreshToken::invalidate_all_for_user(data, &rt.user_id).await?;
+    if rt.exp < now {
+        // Tokens from before the rotation tracking do not have a family. If an already used one
+        // was provided again, invalidate all existing ones for the user as well to prevent
+        // possible security issues.
+        if is_legacy {
+            RefreshToken::invalidate_all_for_user(data, &rt.user_id).await?;
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                String::from(
+                    "Refresh Token has expired already. All other refresh tokens \
+                    for this user have been invalidated now because of misuse.",
+                ),
+            ));
+        }
         return Err(ErrorResponse::new(
             ErrorResponseType::BadRequest,
-            String::from(
-                "Refresh Token has expired already. All other refresh tokens\
-                for this user have been invalidated now because of misuse.",
-            ),
+            String::from("Refresh Token has expired already"),
         ));
     }
 
@@ -1824,14 +3502,7 @@ pub async fn validate_refresh_token(
     user.last_login = Some(OffsetDateTime::now_utc().unix_timestamp());
     user.save(data, None, None).await?;
 
-    // invalidate current refresh token
-    let now = OffsetDateTime::now_utc().unix_timestamp();
-    let exp_at_secs = now + data.refresh_grace_time as i64;
-    // do not set expires_at, if we are below our refresh token grace time anyway already
-    if rt.exp > exp_at_secs + 1 {
-        rt.exp = exp_at_secs;
-        rt.save(data).await?;
-    }
+    let family_id = rt.family_id.clone();
 
     // TODO do we somehow need to be able to set ID 'nonce' here too?
     let ts = if let Some(s) = rt.scope {
@@ -1840,9 +3511,15 @@ pub async fn validate_refresh_token(
             data,
             &client,
             dpop_fingerprint,
+            cert_thumbprint,
             None,
             Some(s),
             rt.is_mfa,
+            None,
+            claims.custom.sid,
+            family_id,
+            claims.custom.authorization_details,
+            resource,
         )
         .await
     } else {
@@ -1851,9 +3528,15 @@ pub async fn validate_refresh_token(
             data,
             &client,
             dpop_fingerprint,
+            cert_thumbprint,
             None,
             None,
             rt.is_mfa,
+            None,
+            claims.custom.sid,
+            family_id,
+            claims.custom.authorization_details,
+            resource,
         )
         .await
     }?;
@@ -1876,7 +3559,19 @@ pub async fn validate_token<T: serde::Serialize + for<'de> ::serde::Deserialize<
 
     // retrieve jwk for kid
     let kp = JwkKeyPair::find(data, kid).await?;
-    validate_jwt!(T, kp, token, options)
+    let claims: claims::JWTClaims<T> = validate_jwt!(T, kp, token, options)?;
+
+    // access tokens may have been revoked before their expiry
+    if let Some(jti) = &claims.jwt_id {
+        if RevokedToken::is_revoked(data, jti).await? {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::Unauthorized,
+                String::from("The token has been revoked"),
+            ));
+        }
+    }
+
+    Ok(claims)
 
     // TODO check roles if we add more users / roles
 }
@@ -25,8 +25,8 @@ pub async fn update_client(
 
     client.name = client_req.name;
     if client_req.confidential {
-        // only set a new secret if this value has been changed
-        if !client.confidential {
+        // only set a new secret if this value has been changed or the client had none
+        if !client.confidential || client.secret.is_none() {
             let (_, enc) = Client::generate_new_secret()?;
             client.secret = Some(enc);
         }
@@ -56,6 +56,93 @@ pub async fn update_client(
 
     client.challenge = client_req.challenges.map(|c| c.join(","));
     client.force_mfa = client_req.force_mfa;
+    client.require_par = client_req.require_par;
+
+    client.jwks = client_req.jwks.filter(|jwks| !jwks.is_empty());
+    if let Some(jwks) = &client.jwks {
+        Client::validate_jwks(jwks)?;
+    }
+    client.jwks_uri = client_req.jwks_uri.filter(|uri| !uri.is_empty());
+    if client_req.require_request_object && client.jwks.is_none() && client.jwks_uri.is_none() {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            String::from("'require_request_object' needs either 'jwks' or 'jwks_uri'"),
+        ));
+    }
+    client.require_request_object = client_req.require_request_object;
+
+    client.token_exchange_audiences = client_req
+        .token_exchange_audiences
+        .filter(|aud| !aud.is_empty())
+        .map(|aud| aud.join(","));
+    client.token_exchange_scopes = match client_req.token_exchange_scopes {
+        Some(scopes) if !scopes.is_empty() => Some(Client::sanitize_scopes(data, scopes).await?),
+        _ => None,
+    };
+    client.token_exchange_subject_types = client_req
+        .token_exchange_subject_types
+        .filter(|typ| !typ.is_empty())
+        .map(|typ| typ.join(","));
+    client.backchannel_logout_uri = client_req
+        .backchannel_logout_uri
+        .filter(|uri| !uri.is_empty());
+    client.frontchannel_logout_uri = client_req
+        .frontchannel_logout_uri
+        .filter(|uri| !uri.is_empty());
+    client.subject_type = client_req.subject_type.filter(|typ| typ != "public");
+    client.sector_identifier_uri = client_req
+        .sector_identifier_uri
+        .filter(|uri| !uri.is_empty());
+    client.validate_subject_type().await?;
+    client.id_token_encrypted_response_alg = client_req
+        .id_token_encrypted_response_alg
+        .filter(|alg| !alg.is_empty());
+    client.id_token_encrypted_response_enc = client_req
+        .id_token_encrypted_response_enc
+        .filter(|enc| !enc.is_empty());
+    client.userinfo_encrypted_response_alg = client_req
+        .userinfo_encrypted_response_alg
+        .filter(|alg| !alg.is_empty());
+    client.userinfo_encrypted_response_enc = client_req
+        .userinfo_encrypted_response_enc
+        .filter(|enc| !enc.is_empty());
+    client.validate_encryption().await?;
+    client.userinfo_signed_response_alg = client_req
+        .userinfo_signed_response_alg
+        .map(|alg| alg.to_string());
+    client.authorization_details_types = client_req
+        .authorization_details_types
+        .filter(|types| !types.is_empty())
+        .map(|types| types.join(","));
+    client.tls_client_auth_method = client_req
+        .tls_client_auth_method
+        .filter(|method| !method.is_empty());
+    client.tls_client_auth_subject_dn = client_req
+        .tls_client_auth_subject_dn
+        .filter(|dn| !dn.is_empty());
+    client.tls_client_certificate_bound_access_tokens =
+        client_req.tls_client_certificate_bound_access_tokens;
+    client.validate_tls_client_auth()?;
+    client.token_endpoint_auth_method = client_req
+        .token_endpoint_auth_method
+        .filter(|method| !method.is_empty());
+    client.validate_token_endpoint_auth()?;
+    if client.token_endpoint_auth_method.as_deref() == Some("private_key_jwt") {
+        // the client authenticates with its keys only
+        client.secret = None;
+        client.secret_kid = None;
+    }
+    client.require_consent = client_req.require_consent;
+    client.allow_email_login = client_req.allow_email_login;
+    client.request_uris = match client_req.request_uris {
+        Some(uris) if !uris.is_empty() => {
+            for uri in &uris {
+                Client::validate_public_uri(uri, "request_uris")?;
+            }
+            Some(uris.join(","))
+        }
+        _ => None,
+    };
 
     client.save(data, None).await?;
     Ok(client)
@@ -90,6 +177,12 @@ pub async fn generate_new_secret(
     data: &web::Data<AppState>,
 ) -> Result<ClientSecretResponse, ErrorResponse> {
     let mut client = Client::find(data, id).await?;
+    if client.token_endpoint_auth_method.as_deref() == Some("private_key_jwt") {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            format!("'{}' authenticates with 'private_key_jwt'", &client.id),
+        ));
+    }
     let (clear, enc) = Client::generate_new_secret()?;
 
     client.confidential = true;
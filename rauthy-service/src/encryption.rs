@@ -5,6 +5,7 @@ use rauthy_models::app_state::AppState;
 use rauthy_models::entity::api_keys::ApiKeyEntity;
 use rauthy_models::entity::auth_providers::AuthProvider;
 use rauthy_models::entity::clients::Client;
+use rauthy_models::entity::users::User;
 use tracing::{error, info};
 
 /// Migrates encrypted data in the backend to a new key.
@@ -106,6 +107,25 @@ pub async fn migrate_encryption_alg(
         new_kid
     );
 
+    // migrate TOTP secrets
+    info!("Starting TOTP secrets migration to key id: {}", new_kid);
+    let users = User::find_all(data)
+        .await?
+        .into_iter()
+        .filter(|u| u.totp_secret.is_some())
+        .collect::<Vec<User>>();
+    for mut user in users {
+        let dec = EncValue::try_from(user.totp_secret.unwrap())?.decrypt()?;
+        user.totp_secret = Some(
+            EncValue::encrypt_with_key_id(dec.as_ref(), new_kid.to_string())?
+                .into_bytes()
+                .to_vec(),
+        );
+        user.save(data, None, None).await?;
+        modified += 1;
+    }
+    info!("Finished TOTP secrets migration to key id: {}", new_kid);
+
     info!(
         "Finished secrets migration to key id: {} after {} ms. Modified {} encryption's",
         new_kid,
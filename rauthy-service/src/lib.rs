@@ -8,3 +8,4 @@ pub mod client;
 pub mod encryption;
 pub mod password_reset;
 pub mod token_set;
+pub mod users_bulk;
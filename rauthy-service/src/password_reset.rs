@@ -28,6 +28,7 @@ pub async fn handle_get_pwd_reset<'a>(
 ) -> Result<(String, cookie::Cookie<'a>), ErrorResponse> {
     let mut ml = MagicLink::find(data, &reset_id).await?;
     ml.validate(&user_id, &req, false)?;
+    deny_login_link(&ml)?;
 
     // check if the user has MFA enabled
     let user = User::find(data, ml.user_id.clone()).await?;
@@ -109,6 +110,7 @@ pub async fn handle_put_user_passkey_finish<'a>(
     let ml_id = req_data.magic_link_id.as_ref().unwrap();
     let mut ml = MagicLink::find(data, ml_id).await?;
     ml.validate(&user_id, &req, true)?;
+    deny_login_link(&ml)?;
 
     // finish webauthn request -> always force UV for passkey only accounts
     debug!("ml is valid - finishing webauthn request");
@@ -194,6 +196,7 @@ pub async fn handle_put_user_password_reset<'a>(
 
     let mut ml = MagicLink::find(data, &req_data.magic_link_id).await?;
     ml.validate(&user.id, &req, true)?;
+    deny_login_link(&ml)?;
 
     // validate password
     user.apply_password_rules(data, &req_data.password).await?;
@@ -231,3 +234,17 @@ pub async fn handle_put_user_password_reset<'a>(
         .finish();
     Ok(cookie)
 }
+
+// Magic links for the passwordless login must never be usable for setting a new password.
+fn deny_login_link(ml: &MagicLink) -> Result<(), ErrorResponse> {
+    if matches!(
+        MagicLinkUsage::try_from(&ml.usage)?,
+        MagicLinkUsage::Login(_)
+    ) {
+        return Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            "The Magic Link is not meant to be used for a password reset".to_string(),
+        ));
+    }
+    Ok(())
+}
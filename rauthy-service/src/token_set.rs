@@ -1,13 +1,15 @@
 use crate::auth;
 use actix_web::web;
-use rauthy_common::constants::OFFLINE_TOKEN_LT;
+use rauthy_common::constants::{OFFLINE_TOKEN_LT, TOKEN_TYPE_ACCESS_TOKEN};
 use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
 use rauthy_models::app_state::AppState;
+use rauthy_models::entity::authorization_details::AuthorizationDetails;
 use rauthy_models::entity::clients::Client;
+use rauthy_models::entity::resources::Resource;
 use rauthy_models::entity::scopes::Scope;
 use rauthy_models::entity::user_attr::UserAttrValueEntity;
 use rauthy_models::entity::users::User;
-use rauthy_models::JwtTokenType;
+use rauthy_models::{JwtActClaim, JwtTokenType};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use time::OffsetDateTime;
@@ -22,26 +24,49 @@ pub struct TokenSet {
     pub expires_in: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refresh_token: Option<String>,
+    /// Only set for a Token Exchange
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_token_type: Option<String>,
+    /// The granted Rich Authorization Request details (RFC 9396)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<Object>>)]
+    pub authorization_details: Option<AuthorizationDetails>,
 }
 
 impl TokenSet {
+    /// With a requested `resource`, the token is issued for it with the allowed default scopes.
     pub async fn for_client_credentials(
         data: &web::Data<AppState>,
         client: &Client,
         dpop_fingerprint: Option<String>,
+        cert_thumbprint: Option<String>,
+        authorization_details: Option<AuthorizationDetails>,
+        resource: Option<&Resource>,
     ) -> Result<Self, ErrorResponse> {
         let token_type = if dpop_fingerprint.is_some() {
             JwtTokenType::DPoP
         } else {
             JwtTokenType::Bearer
         };
+        let (scope, audience) = if let Some(resource) = resource {
+            let scope = resource.narrow_scope(&client.default_scopes.replace(',', " "))?;
+            (Some(scope), Some(resource.uri.clone()))
+        } else {
+            (None, None)
+        };
         let access_token = auth::build_access_token(
             None,
             data,
             client,
             dpop_fingerprint,
+            cert_thumbprint,
             client.access_token_lifetime as i64,
+            scope,
+            None,
+            audience,
             None,
+            authorization_details.clone(),
+            false,
             None,
         )
         .await?;
@@ -52,17 +77,79 @@ impl TokenSet {
             id_token: None,
             expires_in: client.access_token_lifetime,
             refresh_token: None,
+            issued_token_type: None,
+            authorization_details,
+        })
+    }
+
+    /// Builds the access token for a Token Exchange. Custom scope attributes are not mapped into
+    /// exchanged tokens.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn for_token_exchange(
+        data: &web::Data<AppState>,
+        client: &Client,
+        user: Option<&User>,
+        dpop_fingerprint: Option<String>,
+        cert_thumbprint: Option<String>,
+        lifetime: i64,
+        scope: String,
+        audience: String,
+        act: Option<JwtActClaim>,
+    ) -> Result<Self, ErrorResponse> {
+        let token_type = if dpop_fingerprint.is_some() {
+            JwtTokenType::DPoP
+        } else {
+            JwtTokenType::Bearer
+        };
+        let access_token = auth::build_access_token(
+            user,
+            data,
+            client,
+            dpop_fingerprint,
+            cert_thumbprint,
+            lifetime,
+            Some(scope),
+            None,
+            Some(audience),
+            act,
+            None,
+            false,
+            None,
+        )
+        .await?;
+
+        Ok(Self {
+            access_token,
+            token_type,
+            id_token: None,
+            expires_in: lifetime as i32,
+            refresh_token: None,
+            issued_token_type: Some(TOKEN_TYPE_ACCESS_TOKEN.to_string()),
+            authorization_details: None,
         })
     }
 
+    /// The `sid` links the tokens to the user's session for OIDC Back-Channel Logout.
+    /// The `refresh_token_family` must be given when the refresh token replaces a rotated one.
+    /// Granted `authorization_details` are added to the access and refresh token.
+    /// With a requested `resource`, only the access token is restricted to it.
+    /// The `cert_thumbprint` binds the access token to the client certificate (RFC 8705).
+    /// The `mfa_method` is the second factor, which has been recorded for the user's session.
+    #[allow(clippy::too_many_arguments)]
     pub async fn from_user(
         user: &User,
         data: &web::Data<AppState>,
         client: &Client,
         dpop_fingerprint: Option<String>,
+        cert_thumbprint: Option<String>,
         nonce: Option<String>,
         scopes: Option<String>,
         is_auth_code_flow: bool,
+        mfa_method: Option<&str>,
+        sid: Option<String>,
+        refresh_token_family: Option<String>,
+        authorization_details: Option<AuthorizationDetails>,
+        resource: Option<&Resource>,
     ) -> Result<Self, ErrorResponse> {
         let scope = if let Some(s) = &scopes {
             s.clone()
@@ -121,6 +208,22 @@ impl TokenSet {
             (None, None)
         };
 
+        // The access token for a resource only contains the scopes allowed for it. The refresh
+        // token keeps the full scope to be able to request tokens for other resources later on.
+        let (access_scope, audience, customs_access) = if let Some(resource) = resource {
+            let access_scope = resource.narrow_scope(&scope)?;
+            let customs_access = customs_access.and_then(|(scps, attrs)| {
+                let scps = scps
+                    .into_iter()
+                    .filter(|s| access_scope.split(' ').any(|a| a == s.name))
+                    .collect::<Vec<&Scope>>();
+                (!scps.is_empty()).then_some((scps, attrs))
+            });
+            (access_scope, Some(resource.uri.clone()), customs_access)
+        } else {
+            (scope.clone(), None, customs_access)
+        };
+
         // set the correct lifetime
         let lifetime = if let Some(ts) = user.user_expires {
             let now = OffsetDateTime::now_utc().unix_timestamp();
@@ -159,6 +262,8 @@ impl TokenSet {
             &scope,
             customs_id,
             is_auth_code_flow,
+            mfa_method,
+            sid.clone(),
         )
         .await?;
         let access_token = auth::build_access_token(
@@ -166,9 +271,15 @@ impl TokenSet {
             data,
             client,
             dpop_fingerprint.clone(),
+            cert_thumbprint,
             lifetime,
-            Some(scope),
+            Some(access_scope),
             customs_access,
+            audience,
+            None,
+            authorization_details.clone(),
+            is_auth_code_flow,
+            mfa_method,
         )
         .await?;
         let refresh_token = if client.refresh_token {
@@ -181,6 +292,9 @@ impl TokenSet {
                     lifetime,
                     scopes,
                     is_auth_code_flow,
+                    sid,
+                    refresh_token_family,
+                    authorization_details.clone(),
                 )
                 .await?,
             )
@@ -194,6 +308,8 @@ impl TokenSet {
             id_token: Some(id_token),
             expires_in: client.access_token_lifetime,
             refresh_token,
+            issued_token_type: None,
+            authorization_details,
         })
     }
 }
@@ -0,0 +1,314 @@
+use actix_web::web;
+use rauthy_common::error_response::{ErrorResponse, ErrorResponseType};
+use rauthy_models::app_state::AppState;
+use rauthy_models::entity::groups::Group;
+use rauthy_models::entity::roles::Role;
+use rauthy_models::entity::user_attr::{UserAttrConfigEntity, UserAttrValueEntity};
+use rauthy_models::entity::users::User;
+use rauthy_models::language::Language;
+use rauthy_models::request::{UserImportRequest, UsersImportRequest};
+use rauthy_models::response::{
+    UserExportResponse, UserImportErrorResponse, UsersExportResponse, UsersImportResponse,
+};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+use validator::Validate;
+
+// The fixed columns for the CSV export. Each additional column will be a custom attribute.
+const CSV_COLUMNS: [&str; 13] = [
+    "id",
+    "email",
+    "given_name",
+    "family_name",
+    "language",
+    "roles",
+    "groups",
+    "enabled",
+    "email_verified",
+    "user_expires",
+    "created_at",
+    "last_login",
+    "password_hash",
+];
+
+/// Imports all given users. A single invalid user does not abort the whole import. Instead,
+/// all users, that could not be imported, will be returned with the reason.
+pub async fn import_users(
+    data: &web::Data<AppState>,
+    req: UsersImportRequest,
+) -> Result<UsersImportResponse, ErrorResponse> {
+    let attr_names = UserAttrConfigEntity::find_all_as_set(data).await?;
+
+    let mut imported = 0;
+    let mut errors = Vec::new();
+    for user in req.users {
+        let email = user.email.clone();
+        match import_user(data, user, &attr_names).await {
+            Ok(_) => imported += 1,
+            Err(err) => errors.push(UserImportErrorResponse {
+                email,
+                error: err.message,
+            }),
+        }
+    }
+
+    info!(
+        "User import finished with {} imported users and {} errors",
+        imported,
+        errors.len()
+    );
+    Ok(UsersImportResponse { imported, errors })
+}
+
+async fn import_user(
+    data: &web::Data<AppState>,
+    req: UserImportRequest,
+    attr_names: &HashSet<String>,
+) -> Result<(), ErrorResponse> {
+    // each user is validated on its own to only skip the invalid ones
+    req.validate()?;
+
+    if let Some(attrs) = &req.attributes {
+        if let Some(key) = attrs.keys().find(|key| !attr_names.contains(*key)) {
+            return Err(ErrorResponse::new(
+                ErrorResponseType::BadRequest,
+                format!("Custom attribute '{}' does not exist", key),
+            ));
+        }
+    }
+
+    let user = User {
+        email: req.email.to_lowercase(),
+        given_name: req.given_name,
+        family_name: req.family_name,
+        language: req.language,
+        roles: Role::sanitize(data, req.roles).await?,
+        groups: Group::sanitize(data, req.groups).await?,
+        enabled: req.enabled.unwrap_or(true),
+        email_verified: req.email_verified.unwrap_or(false),
+        user_expires: req.user_expires,
+        password: req.password_hash,
+        ..Default::default()
+    };
+    User::create_imported(data, user, req.attributes.unwrap_or_default()).await?;
+
+    Ok(())
+}
+
+/// Parses a CSV file with a header line into an import request. The column names match the
+/// fields of the JSON import. `roles` and `groups` are comma separated lists inside a single
+/// column. Each unknown column will be imported as a custom attribute with a string value.
+pub fn parse_csv(bytes: &[u8]) -> Result<UsersImportRequest, ErrorResponse> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(bytes);
+    let headers = rdr.headers().map_err(csv_err)?.clone();
+
+    let mut users = Vec::new();
+    for record in rdr.records() {
+        let record = record.map_err(csv_err)?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+
+        let mut user = UserImportRequest {
+            email: String::default(),
+            given_name: String::default(),
+            family_name: String::default(),
+            language: Language::default(),
+            roles: Vec::default(),
+            groups: None,
+            enabled: None,
+            email_verified: None,
+            user_expires: None,
+            password_hash: None,
+            attributes: None,
+        };
+        let mut attributes = HashMap::new();
+
+        for (column, value) in headers.iter().zip(record.iter()) {
+            if value.is_empty() {
+                continue;
+            }
+
+            match column {
+                "email" => user.email = value.to_string(),
+                "given_name" => user.given_name = value.to_string(),
+                "family_name" => user.family_name = value.to_string(),
+                "language" => user.language = Language::from(value),
+                "roles" => user.roles = split_list(value),
+                "groups" => user.groups = Some(split_list(value)),
+                "enabled" => user.enabled = Some(parse_bool(value, line)?),
+                "email_verified" => user.email_verified = Some(parse_bool(value, line)?),
+                "user_expires" => {
+                    user.user_expires = Some(value.parse::<i64>().map_err(|_| {
+                        ErrorResponse::new(
+                            ErrorResponseType::BadRequest,
+                            format!("Invalid 'user_expires' in line {}", line),
+                        )
+                    })?)
+                }
+                "password_hash" => user.password_hash = Some(value.to_string()),
+                // read-only values from an export
+                "id" | "created_at" | "last_login" => {}
+                key => {
+                    attributes.insert(key.to_string(), Value::String(value.to_string()));
+                }
+            }
+        }
+
+        if !attributes.is_empty() {
+            user.attributes = Some(attributes);
+        }
+        users.push(user);
+    }
+
+    Ok(UsersImportRequest { users })
+}
+
+/// Exports all users. The password hashes are only included on request.
+pub async fn export_users(
+    data: &web::Data<AppState>,
+    with_password_hashes: bool,
+) -> Result<UsersExportResponse, ErrorResponse> {
+    let mut attrs: HashMap<String, Vec<UserAttrValueEntity>> = HashMap::new();
+    for attr in UserAttrValueEntity::find_all(data).await? {
+        attrs.entry(attr.user_id.clone()).or_default().push(attr);
+    }
+
+    let users = User::find_all(data)
+        .await?
+        .into_iter()
+        .map(|user| {
+            let user_attrs = attrs.remove(&user.id).unwrap_or_default();
+            UserExportResponse::build(user, user_attrs, with_password_hashes)
+        })
+        .collect();
+
+    Ok(UsersExportResponse { users })
+}
+
+/// Exports all users as CSV in the same format, that is accepted by the import. Non-string
+/// values of custom attributes are written as JSON.
+pub async fn export_users_csv(
+    data: &web::Data<AppState>,
+    with_password_hashes: bool,
+) -> Result<Vec<u8>, ErrorResponse> {
+    let export = export_users(data, with_password_hashes).await?;
+
+    let mut attr_names = UserAttrConfigEntity::find_all(data)
+        .await?
+        .into_iter()
+        .map(|attr| attr.name)
+        .collect::<Vec<String>>();
+    attr_names.sort();
+
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    wtr.write_record(
+        CSV_COLUMNS
+            .iter()
+            .copied()
+            .chain(attr_names.iter().map(|n| n.as_str())),
+    )
+    .map_err(csv_err)?;
+
+    for user in export.users {
+        let mut record = vec![
+            user.id,
+            user.email,
+            user.given_name,
+            user.family_name,
+            user.language.to_string(),
+            user.roles.join(","),
+            user.groups.map(|g| g.join(",")).unwrap_or_default(),
+            user.enabled.to_string(),
+            user.email_verified.to_string(),
+            user.user_expires.map(|e| e.to_string()).unwrap_or_default(),
+            user.created_at.to_string(),
+            user.last_login.map(|l| l.to_string()).unwrap_or_default(),
+            user.password_hash.unwrap_or_default(),
+        ];
+        for name in &attr_names {
+            let value = match user.attributes.get(name) {
+                Some(Value::String(s)) => s.clone(),
+                Some(value) => value.to_string(),
+                None => String::default(),
+            };
+            record.push(value);
+        }
+        wtr.write_record(&record).map_err(csv_err)?;
+    }
+
+    wtr.into_inner().map_err(|err| {
+        ErrorResponse::new(
+            ErrorResponseType::Internal,
+            format!("Error writing the CSV export: {}", err),
+        )
+    })
+}
+
+#[inline]
+fn csv_err(err: csv::Error) -> ErrorResponse {
+    ErrorResponse::new(
+        ErrorResponseType::BadRequest,
+        format!("Invalid CSV: {}", err),
+    )
+}
+
+fn parse_bool(value: &str, line: u64) -> Result<bool, ErrorResponse> {
+    match value {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(ErrorResponse::new(
+            ErrorResponseType::BadRequest,
+            format!("Invalid boolean value '{}' in line {}", value, line),
+        )),
+    }
+}
+
+#[inline]
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = r#"id,email,given_name,family_name,language,roles,groups,enabled,email_verified,password_hash,city
+abc123,batman@batcave.io,Bruce,Wayne,de,"admin,user",,true,0,$2a$06$DCq7YPn5Rq63x1Lad4cll.TV4S6ytwfsfvkgY8jIucDrjc8deX1s.,Gotham
+,alfred@batcave.io,Alfred,Pennyworth,,,user,,,,
+"#;
+        let req = parse_csv(csv.as_bytes()).unwrap();
+        assert_eq!(req.users.len(), 2);
+
+        let batman = &req.users[0];
+        assert_eq!(batman.email, "batman@batcave.io");
+        assert_eq!(batman.language, Language::De);
+        assert_eq!(batman.roles, vec!["admin".to_string(), "user".to_string()]);
+        assert_eq!(batman.groups, None);
+        assert_eq!(batman.enabled, Some(true));
+        assert_eq!(batman.email_verified, Some(false));
+        assert!(batman.password_hash.is_some());
+        assert_eq!(
+            batman.attributes.as_ref().unwrap().get("city"),
+            Some(&Value::String("Gotham".to_string()))
+        );
+
+        let alfred = &req.users[1];
+        assert_eq!(alfred.language, Language::En);
+        assert!(alfred.roles.is_empty());
+        assert_eq!(alfred.groups, Some(vec!["user".to_string()]));
+        assert_eq!(alfred.enabled, None);
+        assert!(alfred.attributes.is_none());
+
+        let csv = "email,given_name,family_name,enabled\nbatman@batcave.io,Bruce,Wayne,yes\n";
+        assert!(parse_csv(csv.as_bytes()).is_err());
+    }
+}